@@ -0,0 +1,93 @@
+#![feature(test)]
+
+extern crate test;
+
+use bytes::BytesMut;
+use h2::hpack::{Decoder, Encoder, Field};
+use test::Bencher;
+
+fn field(name: &str, value: &str) -> Field {
+    Field::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn request() -> Vec<Field> {
+    vec![
+        field(":method", "GET"),
+        field(":scheme", "https"),
+        field(":authority", "www.example.com"),
+        field(
+            ":path",
+            "/images/top/sp2/cmn/logo-ns-130528.png?size=large&v=2",
+        ),
+        field(
+            "user-agent",
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.14; rv:68.0) Gecko/20100101 Firefox/68.0",
+        ),
+        field(
+            "accept",
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        ),
+        field("accept-language", "en-US,en;q=0.5"),
+        field("accept-encoding", "gzip, deflate, br"),
+        field("referer", "https://www.example.com/search?q=hpack+huffman"),
+        field(
+            "cookie",
+            "B=76j09a189a6h4&b=3&s=0b; session=a8f5f167f44f4964e6c998dee827110c",
+        ),
+    ]
+}
+
+// Without a dynamic table, every field is sent as a Huffman encoded literal.
+fn encode_literals(fields: Vec<Field>) -> BytesMut {
+    let mut encoder = Encoder::with_max_table_size(0);
+    let mut block = BytesMut::new();
+    encoder.encode(fields, &mut block);
+    block
+}
+
+#[bench]
+fn decode_request_literals(b: &mut Bencher) {
+    let block = encode_literals(request());
+
+    b.bytes = block.len() as u64;
+    b.iter(|| {
+        let mut decoder = Decoder::with_max_table_size(0);
+        decoder.decode(&block).unwrap()
+    });
+}
+
+#[bench]
+fn decode_large_literal(b: &mut Bencher) {
+    let value = "The quick brown fox jumps over the lazy dog. ".repeat(100);
+    let block = encode_literals(vec![field("x-large", &value)]);
+
+    b.bytes = block.len() as u64;
+    b.iter(|| {
+        let mut decoder = Decoder::with_max_table_size(0);
+        decoder.decode(&block).unwrap()
+    });
+}
+
+#[bench]
+fn decode_request_indexed(b: &mut Bencher) {
+    let mut encoder = Encoder::new();
+    let mut decoder = Decoder::new();
+
+    let mut block = BytesMut::new();
+    encoder.encode(request(), &mut block);
+    decoder.decode(&block).unwrap();
+
+    // Repeated fields are now mostly dynamic table indices.
+    let mut block = BytesMut::new();
+    encoder.encode(request(), &mut block);
+
+    b.bytes = block.len() as u64;
+    b.iter(|| decoder.decode(&block).unwrap());
+}
+
+#[bench]
+fn encode_request_literals(b: &mut Bencher) {
+    let fields = request();
+
+    b.iter(|| encode_literals(fields.clone()));
+}
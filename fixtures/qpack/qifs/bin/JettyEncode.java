@@ -0,0 +1,139 @@
+// Encodes a QIF file with the QPACK encoder of Jetty, in the format of the
+// qifs `encoded` directories.
+//
+// Usage:
+//
+//   java -cp <jetty jars> JettyEncode.java <qif> <out> <table capacity> \
+//       <blocked streams> <immediate ack>
+//
+// The class path needs jetty-http3-qpack, jetty-http, jetty-io, jetty-util
+// and slf4j-api. With an immediate ack of 1, every section is decoded by a
+// Jetty decoder right away and its instructions are fed back to the encoder.
+
+import java.io.ByteArrayOutputStream;
+import java.io.DataOutputStream;
+import java.io.IOException;
+import java.nio.ByteBuffer;
+import java.nio.charset.StandardCharsets;
+import java.nio.file.Files;
+import java.nio.file.Paths;
+import java.util.ArrayList;
+import java.util.List;
+
+import org.eclipse.jetty.http.HttpFields;
+import org.eclipse.jetty.http.HttpVersion;
+import org.eclipse.jetty.http.MetaData;
+import org.eclipse.jetty.http3.qpack.Instruction;
+import org.eclipse.jetty.http3.qpack.QpackDecoder;
+import org.eclipse.jetty.http3.qpack.QpackEncoder;
+import org.eclipse.jetty.io.ByteBufferPool;
+
+public class JettyEncode {
+    public static void main(String[] args) throws Exception {
+        List<HttpFields> sections = load(args[0]);
+        int capacity = Integer.parseInt(args[2]);
+        int blocked = Integer.parseInt(args[3]);
+        boolean ack = args[4].equals("1");
+
+        List<Instruction> encoderInstructions = new ArrayList<>();
+        List<Instruction> decoderInstructions = new ArrayList<>();
+
+        QpackEncoder encoder = new QpackEncoder(encoderInstructions::addAll);
+        encoder.setMaxTableCapacity(capacity);
+        encoder.setMaxBlockedStreams(blocked);
+        if (capacity > 0) {
+            encoder.setTableCapacity(capacity);
+        }
+
+        QpackDecoder decoder = new QpackDecoder(decoderInstructions::addAll);
+        decoder.setMaxTableCapacity(capacity);
+        decoder.setMaxBlockedStreams(blocked);
+        decoder.setBeginNanoTimeSupplier(System::nanoTime);
+
+        ByteArrayOutputStream bytes = new ByteArrayOutputStream();
+        DataOutputStream out = new DataOutputStream(bytes);
+        long streamId = 0;
+
+        for (HttpFields fields : sections) {
+            streamId += 1;
+
+            ByteBuffer section = ByteBuffer.allocate(256 * 1024);
+            encoder.encode(section, streamId, new MetaData(HttpVersion.HTTP_3, fields));
+            section.flip();
+
+            // The instructions the section depends on are written first.
+            byte[] instructions = toBytes(encoderInstructions);
+            encoderInstructions.clear();
+
+            if (instructions.length > 0) {
+                write(out, 0, instructions);
+            }
+            write(out, streamId, copy(section.duplicate()));
+
+            if (ack) {
+                decoder.parseInstructions(ByteBuffer.wrap(instructions));
+                decoder.decode(streamId, section, (id, metadata, wasBlocked) -> {});
+                encoder.parseInstructions(ByteBuffer.wrap(toBytes(decoderInstructions)));
+                decoderInstructions.clear();
+            }
+        }
+
+        Files.write(Paths.get(args[1]), bytes.toByteArray());
+    }
+
+    // One `name\tvalue` field per line, sections separated by empty lines.
+    static List<HttpFields> load(String path) throws IOException {
+        List<HttpFields> sections = new ArrayList<>();
+        HttpFields.Mutable fields = HttpFields.build();
+
+        for (String line : Files.readAllLines(Paths.get(path), StandardCharsets.UTF_8)) {
+            if (line.startsWith("#")) {
+                continue;
+            }
+
+            if (line.isEmpty()) {
+                if (fields.size() > 0) {
+                    sections.add(fields);
+                    fields = HttpFields.build();
+                }
+                continue;
+            }
+
+            String[] parts = line.split("\t", 2);
+            fields.add(parts[0], parts.length > 1 ? parts[1] : "");
+        }
+
+        if (fields.size() > 0) {
+            sections.add(fields);
+        }
+
+        return sections;
+    }
+
+    static byte[] toBytes(List<Instruction> instructions) {
+        ByteBufferPool.Accumulator accumulator = new ByteBufferPool.Accumulator();
+        for (Instruction instruction : instructions) {
+            instruction.encode(ByteBufferPool.NON_POOLING, accumulator);
+        }
+
+        ByteArrayOutputStream bytes = new ByteArrayOutputStream();
+        for (ByteBuffer buffer : accumulator.getByteBuffers()) {
+            byte[] chunk = copy(buffer);
+            bytes.write(chunk, 0, chunk.length);
+        }
+        return bytes.toByteArray();
+    }
+
+    static byte[] copy(ByteBuffer buffer) {
+        byte[] data = new byte[buffer.remaining()];
+        buffer.get(data);
+        return data;
+    }
+
+    // A 64 bit stream ID, a 32 bit length and the data.
+    static void write(DataOutputStream out, long streamId, byte[] data) throws IOException {
+        out.writeLong(streamId);
+        out.writeInt(data.length);
+        out.write(data);
+    }
+}
@@ -62,6 +62,10 @@
 //! manage polling the connection until the connection is complete.
 //! Alternatively, the caller can call `poll` manually.
 //!
+//! To retire a connection, call [`Connection::graceful_shutdown`]. No new
+//! requests may be sent afterwards, and the connection closes once all active
+//! streams have completed.
+//!
 //! # Example
 //!
 //! ```rust, no_run
@@ -128,6 +132,7 @@
 //! [Managing the connection]: #managing-the-connection
 //! [`Connection`]: struct.Connection.html
 //! [`Connection::poll`]: struct.Connection.html#method.poll
+//! [`Connection::graceful_shutdown`]: struct.Connection.html#method.graceful_shutdown
 //! [`SendRequest::send_request`]: struct.SendRequest.html#method.send_request
 //! [`MAX_CONCURRENT_STREAMS`]: http://httpwg.org/specs/rfc7540.html#SettingValues
 //! [`SendRequest`]: struct.SendRequest.html
@@ -140,17 +145,22 @@
 use crate::codec::{Codec, RecvError, SendError, UserError};
 use crate::frame::{Headers, Pseudo, Reason, Settings, StreamId};
 use crate::proto;
-use crate::{PingPong, RecvStream, ReleaseCapacity, SendStream};
+use crate::{
+    FlushStrategy, FrameObserver, HpackStats, Huffman, IndexingPolicy, Padding, PingPong,
+    RecvStream, ReleaseCapacity, SendStream, Sleep, Timer,
+};
 
 use bytes::{Bytes, IntoBuf};
+use futures::future::{self, Either};
 use futures::{ready, FutureExt};
 use http::{uri, HeaderMap, Method, Request, Response, Version};
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
-use std::usize;
+use std::{io, usize};
 use tokio_io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 
 /// Initializes new HTTP/2.0 streams on a connection by sending a request.
@@ -177,6 +187,7 @@ use tokio_io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 pub struct SendRequest<B: IntoBuf> {
     inner: proto::Streams<B::Buf, Peer>,
     pending: Option<proto::OpaqueStreamRef>,
+    request_timeout: Option<Duration>,
 }
 
 /// Returns a `SendRequest` instance once it is ready to send at least one
@@ -240,6 +251,13 @@ pub struct Connection<T, B: IntoBuf = Bytes> {
 pub struct ResponseFuture {
     inner: proto::OpaqueStreamRef,
     push_promise_consumed: bool,
+    deadline: Option<Deadline>,
+}
+
+/// Resets the stream if the response isn't received in time.
+struct Deadline {
+    sleep: Sleep,
+    reason: Reason,
 }
 
 /// A future of a pushed HTTP response.
@@ -327,9 +345,45 @@ pub struct Builder {
     /// Initial `Settings` frame to send as part of the handshake.
     settings: Settings,
 
+    /// Decides how sent headers are indexed by the HPACK encoder.
+    indexing_policy: Option<Arc<dyn IndexingPolicy>>,
+
+    /// When the HPACK encoder Huffman encodes sent string literals.
+    huffman: Huffman,
+
+    /// Padding of sent frames.
+    padding: Option<Padding>,
+
+    /// When buffered frames are flushed.
+    flush: FlushStrategy,
+
+    /// Observes the sent and received frames.
+    frame_observer: Option<Arc<dyn FrameObserver>>,
+
     /// The stream ID of the first (lowest) stream. Subsequent streams will use
     /// monotonically increasing stream IDs.
     stream_id: StreamId,
+
+    /// Provides timers for the time based options below.
+    timer: Option<Arc<dyn Timer>>,
+
+    /// Maximum time to send the preface and receive the first SETTINGS frame.
+    handshake_timeout: Option<Duration>,
+
+    /// Maximum time the connection may go without any streams.
+    idle_timeout: Option<Duration>,
+
+    /// Maximum time to wait for the server to acknowledge our SETTINGS.
+    settings_ack_timeout: Option<Duration>,
+
+    /// How often to send keep-alive PINGs.
+    keep_alive_interval: Option<Duration>,
+
+    /// Maximum time to wait for a keep-alive PING to be acknowledged.
+    keep_alive_timeout: Duration,
+
+    /// Maximum time to wait for the response to each request.
+    request_timeout: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -510,11 +564,18 @@ where
                     self.pending = Some(stream.clone_to_opaque());
                 }
 
-                let response = ResponseFuture {
+                let mut response = ResponseFuture {
                     inner: stream.clone_to_opaque(),
                     push_promise_consumed: false,
+                    deadline: None,
                 };
 
+                if let Some(dur) = self.request_timeout {
+                    response
+                        .set_timeout(dur, Reason::CANCEL)
+                        .expect("`Builder::handshake` requires a timer with a request timeout");
+                }
+
                 let stream = SendStream::new(stream);
 
                 (response, stream)
@@ -539,6 +600,7 @@ where
         SendRequest {
             inner: self.inner.clone(),
             pending: None,
+            request_timeout: self.request_timeout,
         }
     }
 }
@@ -623,7 +685,19 @@ impl Builder {
             initial_target_connection_window_size: None,
             initial_max_send_streams: usize::MAX,
             settings: Default::default(),
+            indexing_policy: None,
+            huffman: Huffman::default(),
+            padding: None,
+            flush: FlushStrategy::Immediate,
+            frame_observer: None,
             stream_id: 1.into(),
+            timer: None,
+            handshake_timeout: None,
+            idle_timeout: None,
+            settings_ack_timeout: None,
+            keep_alive_interval: None,
+            keep_alive_timeout: Duration::from_secs(proto::DEFAULT_KEEP_ALIVE_TIMEOUT_SECS),
+            request_timeout: None,
         }
     }
 
@@ -771,6 +845,252 @@ impl Builder {
         self
     }
 
+    /// Sets the maximum size of the HPACK dynamic table used to decode
+    /// received headers.
+    ///
+    /// This setting informs the peer of the maximum size, in octets, of the
+    /// header compression table it may use when encoding headers sent to us.
+    /// A smaller table uses less memory per connection, while a larger one
+    /// may compress repetitive headers better.
+    ///
+    /// The new size is enforced once the peer has acknowledged the
+    /// `SETTINGS` frame. The default value is 4,096.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use bytes::Bytes;
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .header_table_size(1024)
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    pub fn header_table_size(&mut self, size: u32) -> &mut Self {
+        self.settings.set_header_table_size(Some(size));
+        self
+    }
+
+    /// Sets the policy deciding how sent headers are indexed.
+    ///
+    /// For every header field sent on the connection, the [`IndexingPolicy`]
+    /// chooses whether the HPACK encoder may add it to the dynamic table,
+    /// must send it as a literal, or must send it as a never indexed literal.
+    /// Never indexing credentials protects them against compression based
+    /// attacks, and not indexing high cardinality values keeps them from
+    /// evicting more useful entries.
+    ///
+    /// Values marked as sensitive are always sent as never indexed literals.
+    /// The default is the [`DefaultIndexingPolicy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Indexing, IndexingPolicy};
+    /// # use bytes::Bytes;
+    /// #
+    /// #[derive(Debug)]
+    /// struct NeverIndexAll;
+    ///
+    /// impl IndexingPolicy for NeverIndexAll {
+    ///     fn indexing(&self, _: &str, _: &[u8]) -> Indexing {
+    ///         Indexing::NeverIndexed
+    ///     }
+    /// }
+    ///
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .indexing_policy(NeverIndexAll)
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`IndexingPolicy`]: ../trait.IndexingPolicy.html
+    /// [`DefaultIndexingPolicy`]: ../struct.DefaultIndexingPolicy.html
+    pub fn indexing_policy<P: IndexingPolicy>(&mut self, policy: P) -> &mut Self {
+        self.indexing_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sets when the HPACK encoder Huffman encodes sent string literals.
+    ///
+    /// The default is [`Huffman::WhenShorter`]: a string is only Huffman
+    /// encoded if that makes it shorter. [`Huffman::Never`] saves the cost of
+    /// encoding values that rarely compress, such as base64 tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::Huffman;
+    /// # use bytes::Bytes;
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .huffman(Huffman::Never)
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Huffman::WhenShorter`]: ../enum.Huffman.html#variant.WhenShorter
+    /// [`Huffman::Never`]: ../enum.Huffman.html#variant.Never
+    pub fn huffman(&mut self, huffman: Huffman) -> &mut Self {
+        self.huffman = huffman;
+        self
+    }
+
+    /// Sets the padding of sent `DATA` and `HEADERS` frames.
+    ///
+    /// Padding hides the exact size of requests and responses from an
+    /// observer of the encrypted connection. See [`Padding`] for the
+    /// available policies and how padding interacts with flow control.
+    ///
+    /// By default, frames are not padded.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the bucket size of `Padding::Bucket` is zero,
+    /// or if the range of `Padding::Random` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::Padding;
+    /// # use bytes::Bytes;
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .padding(Padding::Bucket(64))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Padding`]: ../enum.Padding.html
+    pub fn padding(&mut self, padding: Padding) -> &mut Self {
+        padding.assert_valid();
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Sets when the frames buffered by the connection are flushed.
+    ///
+    /// Deferring the flush lets frames sent by many streams share fewer,
+    /// larger writes, at the cost of latency. See [`FlushStrategy`] for the
+    /// available strategies.
+    ///
+    /// By default, frames are flushed as soon as there are no more frames to
+    /// send.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::FlushStrategy;
+    /// # use bytes::Bytes;
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .flush_strategy(FlushStrategy::Coalesce(4 * 1024))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FlushStrategy`]: ../enum.FlushStrategy.html
+    pub fn flush_strategy(&mut self, strategy: FlushStrategy) -> &mut Self {
+        self.flush = strategy;
+        self
+    }
+
+    /// Sets an observer of the frames sent and received on the connection.
+    ///
+    /// The observer is told about every frame, with its header fields once
+    /// decoded, and about the state transitions of the connection. It is
+    /// meant for debugging and tracing tools. See [`FrameObserver`] for
+    /// details.
+    ///
+    /// By default, there is no observer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{FrameEvent, FrameObserver};
+    /// # use bytes::Bytes;
+    /// #
+    /// #[derive(Debug)]
+    /// struct PrintFrames;
+    ///
+    /// impl FrameObserver for PrintFrames {
+    ///     fn frame_received(&self, event: &FrameEvent) {
+    ///         println!("<- {:?}", event);
+    ///     }
+    /// }
+    ///
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .frame_observer(PrintFrames)
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FrameObserver`]: ../trait.FrameObserver.html
+    pub fn frame_observer<O: FrameObserver>(&mut self, observer: O) -> &mut Self {
+        self.frame_observer = Some(Arc::new(observer));
+        self
+    }
+
     /// Sets the maximum number of concurrent streams.
     ///
     /// The maximum concurrent streams setting only controls the maximum number
@@ -1001,6 +1321,327 @@ impl Builder {
         self
     }
 
+    /// Sets the [`Timer`] used by the time based options of the connection.
+    ///
+    /// A timer is required to use [`handshake_timeout`], [`idle_timeout`],
+    /// [`settings_ack_timeout`], [`keep_alive_interval`] or
+    /// [`request_timeout`]. When set, it is also used to expire locally reset
+    /// streams; see [`reset_stream_duration`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .idle_timeout(Duration::from_secs(60))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Timer`]: ../trait.Timer.html
+    /// [`handshake_timeout`]: #method.handshake_timeout
+    /// [`idle_timeout`]: #method.idle_timeout
+    /// [`settings_ack_timeout`]: #method.settings_ack_timeout
+    /// [`keep_alive_interval`]: #method.keep_alive_interval
+    /// [`request_timeout`]: #method.request_timeout
+    /// [`reset_stream_duration`]: #method.reset_stream_duration
+    pub fn timer<T: Timer>(&mut self, timer: T) -> &mut Self {
+        self.timer = Some(Arc::new(timer));
+        self
+    }
+
+    /// Sets the maximum amount of time to complete the HTTP/2.0 handshake.
+    ///
+    /// The handshake is complete once the connection preface has been written
+    /// and the server's first `SETTINGS` frame has been received. If the
+    /// preface cannot be written in time, the handshake fails with an I/O
+    /// error of kind `TimedOut`. If the `SETTINGS` frame is not received in
+    /// time, the connection is closed with `SETTINGS_TIMEOUT`.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .handshake_timeout(Duration::from_secs(10))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`timer`]: #method.timer
+    pub fn handshake_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.handshake_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the maximum amount of time the connection may go without any
+    /// active streams.
+    ///
+    /// Once the connection has been idle for `dur`, a [graceful shutdown] is
+    /// started. The timer starts over whenever the last active stream
+    /// completes.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .idle_timeout(Duration::from_secs(60))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [graceful shutdown]: struct.Connection.html#method.graceful_shutdown
+    /// [`timer`]: #method.timer
+    pub fn idle_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.idle_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the maximum amount of time to wait for the server to acknowledge
+    /// the `SETTINGS` frame sent during the handshake.
+    ///
+    /// If no acknowledgement is received in time, the connection is closed
+    /// with [`SETTINGS_TIMEOUT`], as allowed by section 6.5.3 of the
+    /// specification.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .settings_ack_timeout(Duration::from_secs(5))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`SETTINGS_TIMEOUT`]: ../struct.Reason.html#associatedconstant.SETTINGS_TIMEOUT
+    /// [`timer`]: #method.timer
+    pub fn settings_ack_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.settings_ack_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the interval at which keep-alive PINGs are sent to the server.
+    ///
+    /// If a keep-alive PING isn't acknowledged within the
+    /// [`keep_alive_timeout`], the connection is closed with an I/O error of
+    /// kind `TimedOut`, failing all active streams.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, no keep-alive PINGs are sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .keep_alive_interval(Duration::from_secs(30))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`keep_alive_timeout`]: #method.keep_alive_timeout
+    /// [`timer`]: #method.timer
+    pub fn keep_alive_interval(&mut self, dur: Duration) -> &mut Self {
+        self.keep_alive_interval = Some(dur);
+        self
+    }
+
+    /// Sets the maximum amount of time to wait for a keep-alive PING to be
+    /// acknowledged.
+    ///
+    /// Only has an effect if a [`keep_alive_interval`] is set. The default
+    /// value is 20 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .keep_alive_interval(Duration::from_secs(30))
+    ///     .keep_alive_timeout(Duration::from_secs(10))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`keep_alive_interval`]: #method.keep_alive_interval
+    pub fn keep_alive_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.keep_alive_timeout = dur;
+        self
+    }
+
+    /// Sets the maximum amount of time to wait for the response to each
+    /// request.
+    ///
+    /// If the response head isn't received in time, the request's stream is
+    /// reset with `CANCEL` and its [`ResponseFuture`] fails with an I/O error
+    /// of kind `TimedOut`. Individual requests can override this using
+    /// [`ResponseFuture::set_timeout`].
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// # use bytes::Bytes;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Result<((SendRequest<Bytes>, Connection<T, Bytes>)), h2::Error>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .request_timeout(Duration::from_secs(30))
+    ///     .handshake(my_io);
+    /// # client_fut.await
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`ResponseFuture`]: struct.ResponseFuture.html
+    /// [`ResponseFuture::set_timeout`]: struct.ResponseFuture.html#method.set_timeout
+    /// [`timer`]: #method.timer
+    pub fn request_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.request_timeout = Some(dur);
+        self
+    }
+
     /// Creates a new configured HTTP/2.0 client backed by `io`.
     ///
     /// It is expected that `io` already be in an appropriate state to commence
@@ -1062,6 +1703,13 @@ impl Builder {
     /// #
     /// # pub fn main() {}
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// The handshake fails with a user error if a timeout or a keep-alive
+    /// interval has been configured without a [`timer`].
+    ///
+    /// [`timer`]: #method.timer
     pub fn handshake<T, B>(
         &self,
         io: T,
@@ -1073,6 +1721,16 @@ impl Builder {
     {
         Connection::handshake2(io, self.clone())
     }
+
+    /// Whether a timeout or keep-alive interval is set without a timer.
+    fn is_missing_timer(&self) -> bool {
+        self.timer.is_none()
+            && (self.handshake_timeout.is_some()
+                || self.idle_timeout.is_some()
+                || self.settings_ack_timeout.is_some()
+                || self.keep_alive_interval.is_some()
+                || self.request_timeout.is_some())
+    }
 }
 
 impl Default for Builder {
@@ -1138,8 +1796,35 @@ where
     ) -> Result<(SendRequest<B>, Connection<T, B>), crate::Error> {
         log::debug!("binding client connection");
 
+        if builder.is_missing_timer() {
+            return Err(UserError::MissingTimer.into());
+        }
+
         let msg: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
-        io.write_all(msg).await?;
+
+        let timeout = match (&builder.timer, builder.handshake_timeout) {
+            (Some(timer), Some(dur)) => Some(timer.sleep(dur)),
+            _ => None,
+        };
+
+        let timeout = match timeout {
+            Some(timeout) => match future::select(io.write_all(msg), timeout).await {
+                Either::Left((res, timeout)) => {
+                    res?;
+                    Some(timeout)
+                }
+                Either::Right(_) => {
+                    log::debug!("client handshake timed out");
+                    return Err(
+                        io::Error::new(io::ErrorKind::TimedOut, "handshake timed out").into(),
+                    );
+                }
+            },
+            None => {
+                io.write_all(msg).await?;
+                None
+            }
+        };
 
         log::debug!("client connection bound");
 
@@ -1154,12 +1839,26 @@ where
             codec.set_max_recv_header_list_size(max as usize);
         }
 
+        if let Some(ref policy) = builder.indexing_policy {
+            codec.set_indexing_policy(policy.clone());
+        }
+
+        codec.set_huffman(builder.huffman);
+
+        if let Some(padding) = builder.padding {
+            codec.set_padding(padding);
+        }
+
+        if let Some(ref observer) = builder.frame_observer {
+            codec.set_frame_observer(observer.clone());
+        }
+
         // Send initial settings frame
         codec
             .buffer(builder.settings.clone().into())
             .expect("invalid SETTINGS frame");
 
-        let inner = proto::Connection::new(
+        let mut inner = proto::Connection::new(
             codec,
             proto::Config {
                 next_stream_id: builder.stream_id,
@@ -1167,11 +1866,26 @@ where
                 reset_stream_duration: builder.reset_stream_duration,
                 reset_stream_max: builder.reset_stream_max,
                 settings: builder.settings.clone(),
+                timer: builder.timer.clone(),
+                idle_timeout: builder.idle_timeout,
+                settings_ack_timeout: builder.settings_ack_timeout,
+                keep_alive_interval: builder.keep_alive_interval,
+                keep_alive_timeout: builder.keep_alive_timeout,
+                padding: builder.padding,
+                flush: builder.flush,
+                observer: builder.frame_observer.clone(),
             },
         );
+
+        // The server's first SETTINGS frame is still part of the handshake.
+        if let Some(timeout) = timeout {
+            inner.set_handshake_timeout(timeout);
+        }
+
         let send_request = SendRequest {
             inner: inner.streams().clone(),
             pending: None,
+            request_timeout: builder.request_timeout,
         };
 
         let mut connection = Connection { inner };
@@ -1212,6 +1926,93 @@ where
     pub fn ping_pong(&mut self) -> Option<PingPong> {
         self.inner.take_user_pings().map(PingPong::new)
     }
+
+    /// Returns the HPACK header compression statistics of the connection.
+    ///
+    /// The counters cover the header blocks sent and received since the
+    /// connection was established, and the current state of both dynamic
+    /// tables.
+    pub fn hpack_stats(&self) -> HpackStats {
+        self.inner.hpack_stats()
+    }
+
+    /// Starts a [graceful shutdown][1] process.
+    ///
+    /// This is useful to retire a connection that is still in use, for
+    /// example after the server's address has changed.
+    ///
+    /// A `GOAWAY` frame is sent to the server, refusing any further pushed
+    /// streams. From then on, [`SendRequest::send_request`] and
+    /// [`SendRequest::poll_ready`] return an error. Streams that are already
+    /// open are allowed to complete, after which the connection is closed,
+    /// even if [`SendRequest`] handles are still held.
+    ///
+    /// Must continue being polled to close connection.
+    ///
+    /// [1]: http://httpwg.org/specs/rfc7540.html#GOAWAY
+    /// [`SendRequest`]: struct.SendRequest.html
+    /// [`SendRequest::send_request`]: struct.SendRequest.html#method.send_request
+    /// [`SendRequest::poll_ready`]: struct.SendRequest.html#method.poll_ready
+    pub fn graceful_shutdown(&mut self) {
+        self.inner.go_away_gracefully();
+    }
+
+    /// Starts a [graceful shutdown][1] process that is bounded by `deadline`.
+    ///
+    /// This behaves like [`graceful_shutdown`], but if `deadline` completes
+    /// before all active streams have finished, a `GOAWAY` frame with
+    /// `reason` is sent, all remaining streams are reset and the connection
+    /// is closed.
+    ///
+    /// `deadline` is usually a timer future from the runtime in use. It is
+    /// only polled while the connection itself is being polled.
+    ///
+    /// Must continue being polled to close connection. Use
+    /// [`drain_progress`] to observe the number of remaining streams.
+    ///
+    /// [1]: http://httpwg.org/specs/rfc7540.html#GOAWAY
+    /// [`graceful_shutdown`]: #method.graceful_shutdown
+    /// [`drain_progress`]: #method.drain_progress
+    pub fn drain<D>(&mut self, deadline: D, reason: Reason)
+    where
+        D: Future<Output = ()> + Send + 'static,
+    {
+        self.inner.go_away_gracefully();
+        self.inner.drain(deadline, reason);
+    }
+
+    /// Waits for the number of active streams to change while draining.
+    ///
+    /// The first call after [`drain`] yields the current number of active
+    /// streams, each subsequent call yields once that number changes. Returns
+    /// `None` once the connection has closed.
+    ///
+    /// This function drives the connection in the same way as polling the
+    /// `Connection` future does.
+    ///
+    /// [`drain`]: #method.drain
+    pub async fn drain_progress(&mut self) -> Option<Result<usize, crate::Error>> {
+        futures::future::poll_fn(move |cx| self.poll_drain_progress(cx)).await
+    }
+
+    #[doc(hidden)]
+    pub fn poll_drain_progress(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<usize, crate::Error>>> {
+        let closed = self.poll_unpin(cx)?;
+
+        // Report the final count before signaling the connection is closed.
+        if let Poll::Ready(active) = self.inner.poll_drain_progress() {
+            return Poll::Ready(Some(Ok(active)));
+        }
+
+        if closed.is_ready() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
 }
 
 impl<T, B> Future for Connection<T, B>
@@ -1223,7 +2024,6 @@ where
     type Output = Result<(), crate::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.inner.maybe_close_connection_if_no_streams();
         self.inner.poll(cx).map_err(Into::into)
     }
 }
@@ -1246,10 +2046,27 @@ impl Future for ResponseFuture {
     type Output = Result<Response<RecvStream>, crate::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let (parts, _) = ready!(self.inner.poll_response(cx))?.into_parts();
-        let body = RecvStream::new(ReleaseCapacity::new(self.inner.clone()));
+        if let Poll::Ready(res) = self.inner.poll_response(cx) {
+            let (parts, _) = res?.into_parts();
+            let body = RecvStream::new(ReleaseCapacity::new(self.inner.clone()));
 
-        Poll::Ready(Ok(Response::from_parts(parts, body)))
+            return Poll::Ready(Ok(Response::from_parts(parts, body)));
+        }
+
+        if let Some(ref mut deadline) = self.deadline {
+            ready!(deadline.sleep.as_mut().poll(cx));
+
+            let reason = deadline.reason;
+            self.deadline = None;
+
+            log::debug!("request timed out; resetting stream; reason={:?}", reason);
+            self.inner.cancel(reason);
+
+            let err = io::Error::new(io::ErrorKind::TimedOut, "request timed out");
+            return Poll::Ready(Err(err.into()));
+        }
+
+        Poll::Pending
     }
 }
 
@@ -1262,6 +2079,59 @@ impl ResponseFuture {
     pub fn stream_id(&self) -> crate::StreamId {
         crate::StreamId::from_internal(self.inner.stream_id())
     }
+
+    /// Cancels the request, resetting the stream with `reason`.
+    ///
+    /// Dropping a `ResponseFuture` (along with the request's other handles)
+    /// resets the stream with `CANCEL`; this allows choosing the reason, and
+    /// keeps the `ResponseFuture` around to find out whether the
+    /// `RST_STREAM` frame was sent, using [`poll_canceled`].
+    ///
+    /// The frame is sent once any frames already queued on the stream have
+    /// been sent. Does nothing if the stream is already closed.
+    ///
+    /// [`poll_canceled`]: #method.poll_canceled
+    pub fn cancel(&mut self, reason: Reason) {
+        self.deadline = None;
+        self.inner.cancel(reason);
+    }
+
+    /// Returns `Ready(true)` once the `RST_STREAM` frame sent by [`cancel`],
+    /// or by a [timeout], has been written to the connection.
+    ///
+    /// Returns `Ready(false)` if no `RST_STREAM` frame will be sent, for
+    /// example because the stream completed before it was canceled, the
+    /// server reset it first, or the connection failed.
+    ///
+    /// [`cancel`]: #method.cancel
+    /// [timeout]: #method.set_timeout
+    pub fn poll_canceled(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        self.inner.poll_reset_sent(cx)
+    }
+
+    /// Sets the maximum amount of time to wait for the response.
+    ///
+    /// If the response head isn't received within `dur`, the stream is reset
+    /// with `reason` and this future fails with an I/O error of kind
+    /// `TimedOut`. Replaces any timeout set by
+    /// [`Builder::request_timeout`].
+    ///
+    /// Returns an error if the connection was not configured with a
+    /// [`Timer`].
+    ///
+    /// [`Builder::request_timeout`]: struct.Builder.html#method.request_timeout
+    /// [`Timer`]: ../trait.Timer.html
+    pub fn set_timeout(&mut self, dur: Duration, reason: Reason) -> Result<(), crate::Error> {
+        let timer = self.inner.timer().ok_or(UserError::MissingTimer)?;
+
+        self.deadline = Some(Deadline {
+            sleep: timer.sleep(dur),
+            reason,
+        });
+
+        Ok(())
+    }
+
     /// Returns a stream of PushPromises
     ///
     /// # Panics
@@ -1298,6 +2168,7 @@ impl PushPromises {
                     inner: ResponseFuture {
                         inner: response,
                         push_promise_consumed: false,
+                        deadline: None,
                     },
                 };
                 Poll::Ready(Some(Ok(PushPromise { request, response })))
@@ -1359,6 +2230,16 @@ impl PushedResponseFuture {
     }
 }
 
+// ===== impl Deadline =====
+
+impl fmt::Debug for Deadline {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Deadline")
+            .field("reason", &self.reason)
+            .finish()
+    }
+}
+
 // ===== impl Peer =====
 
 impl Peer {
@@ -1443,10 +2324,31 @@ impl proto::Peer for Peer {
     ) -> Result<Self::Poll, RecvError> {
         let mut b = Response::builder();
 
+        macro_rules! malformed {
+            ($($arg:tt)*) => {{
+                log::debug!($($arg)*);
+                return Err(RecvError::Stream {
+                    id: stream_id,
+                    reason: Reason::PROTOCOL_ERROR,
+                });
+            }}
+        }
+
         b.version(Version::HTTP_2);
 
+        // Request pseudo-header fields cannot appear in a response
+        if pseudo.method.is_some()
+            || pseudo.scheme.is_some()
+            || pseudo.authority.is_some()
+            || pseudo.path.is_some()
+        {
+            malformed!("malformed headers: request pseudo-header field on response");
+        }
+
         if let Some(status) = pseudo.status {
             b.status(status);
+        } else {
+            malformed!("malformed headers: missing status");
         }
 
         let mut response = match b.body(()) {
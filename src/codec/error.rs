@@ -35,9 +35,6 @@ pub enum UserError {
     /// The payload size is too big
     PayloadTooBig,
 
-    /// A header size is too big
-    HeaderTooBig,
-
     /// The application attempted to initiate too many streams to remote.
     Rejected,
 
@@ -60,6 +57,13 @@ pub enum UserError {
 
     /// Calls `PingPong::send_ping` before receiving a pong.
     SendPingWhilePending,
+
+    /// Attempted to open a stream after the connection started shutting down.
+    ConnectionShuttingDown,
+
+    /// Sets a timeout or a keep-alive interval on a connection without a
+    /// timer.
+    MissingTimer,
 }
 
 // ===== impl RecvError =====
@@ -132,7 +136,6 @@ impl error::Error for UserError {
             InactiveStreamId => "inactive stream",
             UnexpectedFrameType => "unexpected frame type",
             PayloadTooBig => "payload too big",
-            HeaderTooBig => "header too big",
             Rejected => "rejected",
             ReleaseCapacityTooBig => "release capacity too big",
             OverflowedStreamId => "stream ID overflowed",
@@ -140,6 +143,8 @@ impl error::Error for UserError {
             MissingUriSchemeAndAuthority => "request URI missing scheme and authority",
             PollResetAfterSendResponse => "poll_reset after send_response is illegal",
             SendPingWhilePending => "send_ping before received previous pong",
+            ConnectionShuttingDown => "connection is shutting down",
+            MissingTimer => "timeout without a timer",
         }
     }
 }
@@ -1,10 +1,13 @@
-use crate::codec::RecvError;
+use crate::codec;
 use crate::frame::{self, Frame, Kind, Reason};
 use crate::frame::{
     DEFAULT_MAX_FRAME_SIZE, DEFAULT_SETTINGS_HEADER_TABLE_SIZE, MAX_MAX_FRAME_SIZE,
 };
 
 use crate::hpack;
+use crate::observer::{FrameEvent, FrameObserver};
+use crate::wire::{FrameError, RecvError};
+use crate::PollExt;
 
 use futures::{ready, Stream};
 
@@ -13,6 +16,7 @@ use bytes::BytesMut;
 use std::io;
 
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio_codec::FramedRead as InnerFramedRead;
 use tokio_codec::{LengthDelimitedCodec, LengthDelimitedCodecError};
@@ -26,11 +30,14 @@ pub struct FramedRead<T> {
     inner: InnerFramedRead<T, LengthDelimitedCodec>,
 
     // hpack decoder state
-    hpack: hpack::Decoder,
+    hpack: hpack::decoder::Decoder,
 
     max_header_list_size: usize,
 
     partial: Option<Partial>,
+
+    /// Observes the decoded frames
+    observer: Option<Arc<dyn FrameObserver>>,
 }
 
 /// Partially loaded headers frame
@@ -53,15 +60,14 @@ impl<T> FramedRead<T> {
     pub fn new(inner: InnerFramedRead<T, LengthDelimitedCodec>) -> FramedRead<T> {
         FramedRead {
             inner,
-            hpack: hpack::Decoder::new(DEFAULT_SETTINGS_HEADER_TABLE_SIZE),
+            hpack: hpack::decoder::Decoder::new(DEFAULT_SETTINGS_HEADER_TABLE_SIZE),
             max_header_list_size: DEFAULT_SETTINGS_MAX_HEADER_LIST_SIZE,
             partial: None,
+            observer: None,
         }
     }
 
     fn decode_frame(&mut self, mut bytes: BytesMut) -> Result<Option<Frame>, RecvError> {
-        use self::RecvError::*;
-
         log::trace!("decoding frame from {}B", bytes.len());
 
         // Parse the head
@@ -69,7 +75,10 @@ impl<T> FramedRead<T> {
 
         if self.partial.is_some() && head.kind() != Kind::Continuation {
             proto_err!(conn: "expected CONTINUATION, got {:?}", head.kind());
-            return Err(Connection(Reason::PROTOCOL_ERROR));
+            return Err(RecvError::connection(
+                Reason::PROTOCOL_ERROR,
+                FrameError::ExpectedContinuation,
+            ));
         }
 
         let kind = head.kind();
@@ -90,14 +99,15 @@ impl<T> FramedRead<T> {
                         // A stream cannot depend on itself. An endpoint MUST
                         // treat this as a stream error (Section 5.4.2) of type
                         // `PROTOCOL_ERROR`.
-                        return Err(Stream {
-                            id: $head.stream_id(),
-                            reason: Reason::PROTOCOL_ERROR,
-                        });
+                        return Err(RecvError::stream(
+                            $head.stream_id(),
+                            Reason::PROTOCOL_ERROR,
+                            FrameError::InvalidDependencyId,
+                        ));
                     },
                     Err(e) => {
                         proto_err!(conn: "failed to load frame; err={:?}", e);
-                        return Err(Connection(Reason::PROTOCOL_ERROR));
+                        return Err(RecvError::connection(e.reason(), e.into()));
                     }
                 };
 
@@ -110,14 +120,15 @@ impl<T> FramedRead<T> {
                     Err(frame::Error::MalformedMessage) => {
                         let id = $head.stream_id();
                         proto_err!(stream: "malformed header block; stream={:?}", id);
-                        return Err(Stream {
+                        return Err(RecvError::stream(
                             id,
-                            reason: Reason::PROTOCOL_ERROR,
-                        });
+                            Reason::PROTOCOL_ERROR,
+                            FrameError::MalformedHeaders,
+                        ));
                     },
                     Err(e) => {
                         proto_err!(conn: "failed HPACK decoding; err={:?}", e);
-                        return Err(Connection(Reason::PROTOCOL_ERROR));
+                        return Err(RecvError::connection(e.reason(), e.into()));
                     }
                 }
 
@@ -142,7 +153,7 @@ impl<T> FramedRead<T> {
 
                 res.map_err(|e| {
                     proto_err!(conn: "failed to load SETTINGS frame; err={:?}", e);
-                    Connection(Reason::PROTOCOL_ERROR)
+                    RecvError::connection(e.reason(), e.into())
                 })?
                 .into()
             }
@@ -151,7 +162,7 @@ impl<T> FramedRead<T> {
 
                 res.map_err(|e| {
                     proto_err!(conn: "failed to load PING frame; err={:?}", e);
-                    Connection(Reason::PROTOCOL_ERROR)
+                    RecvError::connection(e.reason(), e.into())
                 })?
                 .into()
             }
@@ -160,7 +171,7 @@ impl<T> FramedRead<T> {
 
                 res.map_err(|e| {
                     proto_err!(conn: "failed to load WINDOW_UPDATE frame; err={:?}", e);
-                    Connection(Reason::PROTOCOL_ERROR)
+                    RecvError::connection(e.reason(), e.into())
                 })?
                 .into()
             }
@@ -171,7 +182,7 @@ impl<T> FramedRead<T> {
                 // TODO: Should this always be connection level? Probably not...
                 res.map_err(|e| {
                     proto_err!(conn: "failed to load DATA frame; err={:?}", e);
-                    Connection(Reason::PROTOCOL_ERROR)
+                    RecvError::connection(e.reason(), e.into())
                 })?
                 .into()
             }
@@ -180,15 +191,23 @@ impl<T> FramedRead<T> {
                 let res = frame::Reset::load(head, &bytes[frame::HEADER_LEN..]);
                 res.map_err(|e| {
                     proto_err!(conn: "failed to load RESET frame; err={:?}", e);
-                    Connection(Reason::PROTOCOL_ERROR)
+                    RecvError::connection(e.reason(), e.into())
                 })?
                 .into()
             }
             Kind::GoAway => {
+                if !head.stream_id().is_zero() {
+                    proto_err!(conn: "invalid GO_AWAY stream ID; stream={:?}", head.stream_id());
+                    return Err(RecvError::connection(
+                        Reason::PROTOCOL_ERROR,
+                        FrameError::InvalidStreamId,
+                    ));
+                }
+
                 let res = frame::GoAway::load(&bytes[frame::HEADER_LEN..]);
                 res.map_err(|e| {
                     proto_err!(conn: "failed to load GO_AWAY frame; err={:?}", e);
-                    Connection(Reason::PROTOCOL_ERROR)
+                    RecvError::connection(e.reason(), e.into())
                 })?
                 .into()
             }
@@ -197,7 +216,10 @@ impl<T> FramedRead<T> {
                 if head.stream_id() == 0 {
                     // Invalid stream identifier
                     proto_err!(conn: "invalid stream ID 0");
-                    return Err(Connection(Reason::PROTOCOL_ERROR));
+                    return Err(RecvError::connection(
+                        Reason::PROTOCOL_ERROR,
+                        FrameError::InvalidStreamId,
+                    ));
                 }
 
                 match frame::Priority::load(head, &bytes[frame::HEADER_LEN..]) {
@@ -208,14 +230,15 @@ impl<T> FramedRead<T> {
                         // `PROTOCOL_ERROR`.
                         let id = head.stream_id();
                         proto_err!(stream: "PRIORITY invalid dependency ID; stream={:?}", id);
-                        return Err(Stream {
+                        return Err(RecvError::stream(
                             id,
-                            reason: Reason::PROTOCOL_ERROR,
-                        });
+                            Reason::PROTOCOL_ERROR,
+                            FrameError::InvalidDependencyId,
+                        ));
                     }
                     Err(e) => {
                         proto_err!(conn: "failed to load PRIORITY frame; err={:?};", e);
-                        return Err(Connection(Reason::PROTOCOL_ERROR));
+                        return Err(RecvError::connection(e.reason(), e.into()));
                     }
                 }
             }
@@ -226,14 +249,20 @@ impl<T> FramedRead<T> {
                     Some(partial) => partial,
                     None => {
                         proto_err!(conn: "received unexpected CONTINUATION frame");
-                        return Err(Connection(Reason::PROTOCOL_ERROR));
+                        return Err(RecvError::connection(
+                            Reason::PROTOCOL_ERROR,
+                            FrameError::UnexpectedContinuation,
+                        ));
                     }
                 };
 
                 // The stream identifiers must match
                 if partial.frame.stream_id() != head.stream_id() {
                     proto_err!(conn: "CONTINUATION frame stream ID does not match previous frame stream ID");
-                    return Err(Connection(Reason::PROTOCOL_ERROR));
+                    return Err(RecvError::connection(
+                        Reason::PROTOCOL_ERROR,
+                        FrameError::ContinuationStreamMismatch,
+                    ));
                 }
 
                 // Extend the buf
@@ -256,7 +285,10 @@ impl<T> FramedRead<T> {
                         // the attacker to go away.
                         if partial.buf.len() + bytes.len() > self.max_header_list_size {
                             proto_err!(conn: "CONTINUATION frame header block size over ignorable limit");
-                            return Err(Connection(Reason::COMPRESSION_ERROR));
+                            return Err(RecvError::connection(
+                                Reason::COMPRESSION_ERROR,
+                                FrameError::HeaderBlockTooLarge,
+                            ));
                         }
                     }
                     partial.buf.extend_from_slice(&bytes[frame::HEADER_LEN..]);
@@ -273,14 +305,15 @@ impl<T> FramedRead<T> {
                     Err(frame::Error::MalformedMessage) => {
                         let id = head.stream_id();
                         proto_err!(stream: "malformed CONTINUATION frame; stream={:?}", id);
-                        return Err(Stream {
+                        return Err(RecvError::stream(
                             id,
-                            reason: Reason::PROTOCOL_ERROR,
-                        });
+                            Reason::PROTOCOL_ERROR,
+                            FrameError::MalformedHeaders,
+                        ));
                     }
                     Err(e) => {
                         proto_err!(conn: "failed HPACK decoding; err={:?}", e);
-                        return Err(Connection(Reason::PROTOCOL_ERROR));
+                        return Err(RecvError::connection(e.reason(), e.into()));
                     }
                 }
 
@@ -292,8 +325,9 @@ impl<T> FramedRead<T> {
                 }
             }
             Kind::Unknown => {
-                // Unknown frames are ignored
-                return Ok(None);
+                // The connection ignores unknown frames, the codec hands
+                // them over for extensions
+                frame::Unknown::load(&bytes).into()
             }
         };
 
@@ -309,7 +343,6 @@ impl<T> FramedRead<T> {
     }
 
     /// Returns the current max frame size setting
-    #[cfg(feature = "unstable")]
     #[inline]
     pub fn max_frame_size(&self) -> usize {
         self.inner.decoder().max_frame_length()
@@ -329,15 +362,33 @@ impl<T> FramedRead<T> {
     pub fn set_max_header_list_size(&mut self, val: usize) {
         self.max_header_list_size = val;
     }
+
+    /// Update the header table size setting.
+    ///
+    /// The peer must acknowledge the new size with a dynamic table size
+    /// update before the decoder enforces it.
+    #[inline]
+    pub fn set_header_table_size(&mut self, val: usize) {
+        self.hpack.queue_size_update(val);
+    }
+
+    /// Set the observer of the decoded frames.
+    pub fn set_frame_observer(&mut self, observer: Arc<dyn FrameObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Returns the HPACK statistics of the received header blocks.
+    pub fn hpack_stats(&self) -> hpack::HpackCounters {
+        self.hpack.stats()
+    }
 }
 
-impl<T> Stream for FramedRead<T>
+impl<T> FramedRead<T>
 where
     T: AsyncRead + Unpin,
 {
-    type Item = Result<Frame, RecvError>;
-
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    /// Polls for the next frame, with the precise cause of decoding errors.
+    pub fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Frame, RecvError>>> {
         loop {
             log::trace!("poll");
             let bytes = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
@@ -347,7 +398,24 @@ where
             };
 
             log::trace!("poll; bytes={}B", bytes.len());
-            if let Some(frame) = self.decode_frame(bytes)? {
+
+            // Keep the head around for the observer, decoding consumes it
+            let mut head = [0; frame::HEADER_LEN];
+            head.copy_from_slice(&bytes[..frame::HEADER_LEN]);
+
+            let res = self.decode_frame(bytes);
+
+            if let Some(ref observer) = self.observer {
+                let headers = match res {
+                    Ok(Some(Frame::Headers(ref frame))) => Some(frame.parts()),
+                    Ok(Some(Frame::PushPromise(ref frame))) => Some(frame.parts()),
+                    _ => None,
+                };
+
+                observer.frame_received(&FrameEvent::new(&head, headers));
+            }
+
+            if let Some(frame) = res? {
                 log::debug!("received; frame={:?}", frame);
                 return Poll::Ready(Some(Ok(frame)));
             }
@@ -355,11 +423,22 @@ where
     }
 }
 
+impl<T> Stream for FramedRead<T>
+where
+    T: AsyncRead + Unpin,
+{
+    type Item = Result<Frame, codec::RecvError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_frame(cx).map_err_(Into::into)
+    }
+}
+
 fn map_err(err: io::Error) -> RecvError {
     if let io::ErrorKind::InvalidData = err.kind() {
         if let Some(custom) = err.get_ref() {
             if custom.is::<LengthDelimitedCodecError>() {
-                return RecvError::Connection(Reason::FRAME_SIZE_ERROR);
+                return RecvError::connection(Reason::FRAME_SIZE_ERROR, FrameError::TooLarge);
             }
         }
     }
@@ -387,7 +466,7 @@ impl Continuable {
         &mut self,
         src: &mut BytesMut,
         max_header_list_size: usize,
-        decoder: &mut hpack::Decoder,
+        decoder: &mut hpack::decoder::Decoder,
     ) -> Result<(), frame::Error> {
         match *self {
             Continuable::Headers(ref mut h) => h.load_hpack(src, max_header_list_size, decoder),
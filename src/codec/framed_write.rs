@@ -2,14 +2,21 @@ use crate::codec::UserError;
 use crate::codec::UserError::*;
 use crate::frame::{self, Frame, FrameSize};
 use crate::hpack;
+use crate::observer::{FrameEvent, FrameObserver};
+use crate::padding::{Padder, Padding};
 
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use futures::ready;
+use http::HeaderMap;
+use iovec::IoVec;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio_io::{AsyncRead, AsyncWrite};
 
+use std::cmp;
+use std::collections::VecDeque;
 use std::io::{self, Cursor};
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct FramedWrite<T, B> {
@@ -17,27 +24,50 @@ pub struct FramedWrite<T, B> {
     inner: T,
 
     /// HPACK encoder
-    hpack: hpack::Encoder,
+    hpack: hpack::encoder::Encoder,
 
     /// Write buffer
     ///
     /// TODO: Should this be a ring buffer?
     buf: Cursor<BytesMut>,
 
-    /// Next frame to encode
-    next: Option<Next<B>>,
+    /// Encoded frames and chained DATA payloads to write before `buf`
+    queue: VecDeque<Queued<B>>,
 
-    /// Last data frame
-    last_data_frame: Option<frame::Data<B>>,
+    /// Next CONTINUATION frame to encode
+    next: Option<frame::Continuation>,
+
+    /// Data frames that have been written
+    written_data_frames: VecDeque<frame::Data<B>>,
 
     /// Max frame size, this is specified by the peer
     max_frame_size: FrameSize,
+
+    /// Pads HEADERS and PUSH_PROMISE frames
+    padder: Option<Padder>,
+
+    /// Observes the encoded frames
+    observer: Option<Arc<dyn FrameObserver>>,
+
+    /// Fields of the header block being encoded, kept for the observer
+    observed_headers: Option<(frame::Pseudo, HeaderMap)>,
 }
 
 #[derive(Debug)]
-enum Next<B> {
+enum Queued<B> {
+    /// Frames that were encoded to the write buffer
+    Bytes(Bytes),
+
+    /// The payload of a DATA frame whose head is in the previous `Bytes`
     Data(frame::Data<B>),
-    Continuation(frame::Continuation),
+}
+
+/// Gathers the queue and the write buffer into a single `Buf`, so that they
+/// are written with one vectored write when `T` supports it.
+struct Gather<'a, B> {
+    queue: &'a mut VecDeque<Queued<B>>,
+    buf: &'a mut Cursor<BytesMut>,
+    written_data_frames: &'a mut VecDeque<frame::Data<B>>,
 }
 
 /// Initialze the connection with this amount of write buffer.
@@ -54,6 +84,15 @@ const MIN_BUFFER_CAPACITY: usize = frame::HEADER_LEN + CHAIN_THRESHOLD;
 /// than 16kb, so not even close).
 const CHAIN_THRESHOLD: usize = 256;
 
+/// Max number of DATA payloads chained in a single write. Each one takes two
+/// slices of the write, well under the `IOV_MAX` of common platforms.
+const MAX_CHAINED_PAYLOADS: usize = 64;
+
+/// Largest dynamic table the encoder uses, whatever the peer allows. The
+/// peer's SETTINGS_HEADER_TABLE_SIZE is only an upper bound, and can be too
+/// large to be written in a dynamic table size update.
+const MAX_SEND_HEADER_TABLE_SIZE: usize = 1 << 16;
+
 // TODO: Make generic
 impl<T, B> FramedWrite<T, B>
 where
@@ -63,11 +102,15 @@ where
     pub fn new(inner: T) -> FramedWrite<T, B> {
         FramedWrite {
             inner,
-            hpack: hpack::Encoder::default(),
+            hpack: hpack::encoder::Encoder::default(),
             buf: Cursor::new(BytesMut::with_capacity(DEFAULT_BUFFER_CAPACITY)),
+            queue: VecDeque::new(),
             next: None,
-            last_data_frame: None,
+            written_data_frames: VecDeque::new(),
             max_frame_size: frame::DEFAULT_MAX_FRAME_SIZE,
+            padder: None,
+            observer: None,
+            observed_headers: None,
         }
     }
 
@@ -98,23 +141,27 @@ where
 
         log::debug!("send; frame={:?}", item);
 
+        // Offset of the frame head in the buffer
+        let start = self.buf.get_ref().len();
+
         match item {
             Frame::Data(mut v) => {
                 // Ensure that the payload is not greater than the max frame.
                 let len = v.payload().remaining();
 
-                if len > self.max_frame_size() {
+                if len + v.padding_len() > self.max_frame_size() {
                     return Err(PayloadTooBig);
                 }
 
                 if len >= CHAIN_THRESHOLD {
-                    let head = v.head();
-
-                    // Encode the frame head to the buffer
-                    head.encode(len, self.buf.get_mut());
-
-                    // Save the data frame
-                    self.next = Some(Next::Data(v));
+                    // Encode the frame head to the buffer, and queue the
+                    // payload after it. The padding follows in the buffer.
+                    v.encode_head(self.buf.get_mut());
+                    self.observe_sent(start);
+                    self.chain_buffer();
+                    v.encode_padding(self.buf.get_mut());
+
+                    self.queue.push_back(Queued::Data(v));
                 } else {
                     v.encode_chunk(self.buf.get_mut());
 
@@ -122,47 +169,86 @@ where
                     // keep it around
                     assert_eq!(v.payload().remaining(), 0, "chunk not fully encoded");
 
-                    // Save off the last frame...
-                    self.last_data_frame = Some(v);
+                    // Save off the frame...
+                    self.written_data_frames.push_back(v);
+                    self.observe_sent(start);
                 }
             }
             Frame::Headers(v) => {
-                if let Some(continuation) = v.encode(&mut self.hpack, self.buf.get_mut()) {
-                    self.next = Some(Next::Continuation(continuation));
+                let max_frame_size = self.max_frame_size();
+
+                if self.observer.is_some() {
+                    let (pseudo, fields) = v.parts();
+                    self.observed_headers = Some((pseudo.clone(), fields.clone()));
                 }
+
+                self.next = v.encode(
+                    &mut self.hpack,
+                    self.buf.get_mut(),
+                    max_frame_size,
+                    self.padder.as_mut(),
+                );
+                self.observe_sent(start);
             }
             Frame::PushPromise(v) => {
-                if let Some(continuation) = v.encode(&mut self.hpack, self.buf.get_mut()) {
-                    self.next = Some(Next::Continuation(continuation));
+                let max_frame_size = self.max_frame_size();
+
+                if self.observer.is_some() {
+                    let (pseudo, fields) = v.parts();
+                    self.observed_headers = Some((pseudo.clone(), fields.clone()));
                 }
+
+                self.next = v.encode(
+                    &mut self.hpack,
+                    self.buf.get_mut(),
+                    max_frame_size,
+                    self.padder.as_mut(),
+                );
+                self.observe_sent(start);
             }
             Frame::Settings(v) => {
                 v.encode(self.buf.get_mut());
                 log::trace!("encoded settings; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
             }
             Frame::GoAway(v) => {
+                self.reserve_frame(v.encoded_len())?;
                 v.encode(self.buf.get_mut());
                 log::trace!("encoded go_away; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
             }
             Frame::Ping(v) => {
                 v.encode(self.buf.get_mut());
                 log::trace!("encoded ping; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
             }
             Frame::WindowUpdate(v) => {
                 v.encode(self.buf.get_mut());
                 log::trace!("encoded window_update; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
             }
 
-            Frame::Priority(_) => {
-                /*
+            Frame::Priority(v) => {
                 v.encode(self.buf.get_mut());
                 log::trace!("encoded priority; rem={:?}", self.buf.remaining());
-                */
-                unimplemented!();
+                self.observe_sent(start);
             }
             Frame::Reset(v) => {
                 v.encode(self.buf.get_mut());
                 log::trace!("encoded reset; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
+            }
+            Frame::Continuation(v) => {
+                self.reserve_frame(v.encoded_len())?;
+                v.encode(self.buf.get_mut());
+                log::trace!("encoded continuation; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
+            }
+            Frame::Unknown(v) => {
+                self.reserve_frame(v.encoded_len())?;
+                v.encode(self.buf.get_mut());
+                log::trace!("encoded unknown frame; rem={:?}", self.buf.remaining());
+                self.observe_sent(start);
             }
         }
 
@@ -175,45 +261,28 @@ where
 
         loop {
             while !self.is_empty() {
-                match self.next {
-                    Some(Next::Data(ref mut frame)) => {
-                        log::trace!("  -> queued data frame");
-                        let mut buf = Buf::by_ref(&mut self.buf).chain(frame.payload_mut());
-                        ready!(Pin::new(&mut self.inner).poll_write_buf(cx, &mut buf))?;
-                    }
-                    _ => {
-                        log::trace!("  -> not a queued data frame");
-                        ready!(Pin::new(&mut self.inner).poll_write_buf(cx, &mut self.buf))?;
-                    }
-                }
+                log::trace!("  -> writing; queued={}", self.queue.len());
+
+                let mut buf = Gather {
+                    queue: &mut self.queue,
+                    buf: &mut self.buf,
+                    written_data_frames: &mut self.written_data_frames,
+                };
+
+                ready!(Pin::new(&mut self.inner).poll_write_buf(cx, &mut buf))?;
             }
 
             // Clear internal buffer
             self.buf.set_position(0);
             self.buf.get_mut().clear();
 
-            // The data frame has been written, so unset it
             match self.next.take() {
-                Some(Next::Data(frame)) => {
-                    self.last_data_frame = Some(frame);
-                    debug_assert!(self.is_empty());
-                    break;
-                }
-                Some(Next::Continuation(frame)) => {
+                Some(frame) => {
+                    let max_frame_size = self.max_frame_size();
+
                     // Buffer the continuation frame, then try to write again
-                    if let Some(continuation) = frame.encode(&mut self.hpack, self.buf.get_mut()) {
-                        // We previously had a CONTINUATION, and after encoding
-                        // it, we got *another* one? Let's just double check
-                        // that at least some progress is being made...
-                        if self.buf.get_ref().len() == frame::HEADER_LEN {
-                            // If *only* the CONTINUATION frame header was
-                            // written, and *no* header fields, we're stuck
-                            // in a loop...
-                            panic!("CONTINUATION frame write loop; header value too big to encode");
-                        }
-
-                        self.next = Some(Next::Continuation(continuation));
-                    }
+                    self.next = frame.encode(&mut self.hpack, self.buf.get_mut(), max_frame_size);
+                    self.observe_sent(0);
                 }
                 None => {
                     break;
@@ -221,6 +290,10 @@ where
             }
         }
 
+        // Chaining payloads gave part of the buffer away, get it back now
+        // that all of it has been written.
+        self.buf.get_mut().reserve(DEFAULT_BUFFER_CAPACITY);
+
         log::trace!("flushing buffer");
         // Flush the upstream
         ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
@@ -234,16 +307,60 @@ where
         Pin::new(&mut self.inner).poll_shutdown(cx)
     }
 
+    /// Returns the number of bytes buffered and not yet written.
+    pub fn buffered_len(&self) -> usize {
+        let queued: usize = self.queue.iter().map(Queued::remaining).sum();
+        queued + self.buf.remaining()
+    }
+
     fn has_capacity(&self) -> bool {
-        self.next.is_none() && self.buf.get_ref().remaining_mut() >= MIN_BUFFER_CAPACITY
+        self.next.is_none()
+            && self.queue.len() < 2 * MAX_CHAINED_PAYLOADS
+            && self.buf.get_ref().remaining_mut() >= MIN_BUFFER_CAPACITY
     }
 
     fn is_empty(&self) -> bool {
-        match self.next {
-            Some(Next::Data(ref frame)) => !frame.payload().has_remaining(),
-            _ => !self.buf.has_remaining(),
+        self.queue.is_empty() && !self.buf.has_remaining()
+    }
+
+    /// Report the frame whose head was encoded at `start` in the buffer to the
+    /// observer, if any.
+    fn observe_sent(&mut self, start: usize) {
+        // Header fields are reported with the frame ending the header block
+        let headers = match self.next {
+            Some(_) => None,
+            None => self.observed_headers.take(),
+        };
+
+        if let Some(ref observer) = self.observer {
+            let head = &self.buf.get_ref()[start..start + frame::HEADER_LEN];
+            let headers = headers.as_ref().map(|(pseudo, fields)| (pseudo, fields));
+
+            observer.frame_sent(&FrameEvent::new(head, headers));
         }
     }
+
+    /// Makes room in the buffer for a frame of `len` octets, head included,
+    /// with a payload of any size.
+    fn reserve_frame(&mut self, len: usize) -> Result<(), UserError> {
+        if len - frame::HEADER_LEN > self.max_frame_size() {
+            return Err(PayloadTooBig);
+        }
+
+        self.buf.get_mut().reserve(len);
+        Ok(())
+    }
+
+    /// Queue the unwritten contents of the buffer, so that a payload can be
+    /// queued after them.
+    fn chain_buffer(&mut self) {
+        let pos = self.buf.position() as usize;
+        let mut bytes = self.buf.get_mut().take().freeze();
+        bytes.advance(pos);
+
+        self.buf.set_position(0);
+        self.queue.push_back(Queued::Bytes(bytes));
+    }
 }
 
 impl<T, B> FramedWrite<T, B> {
@@ -258,9 +375,48 @@ impl<T, B> FramedWrite<T, B> {
         self.max_frame_size = val as FrameSize;
     }
 
-    /// Retrieve the last data frame that has been sent
-    pub fn take_last_data_frame(&mut self) -> Option<frame::Data<B>> {
-        self.last_data_frame.take()
+    /// Set the peer's header table size.
+    ///
+    /// The next HEADERS or PUSH_PROMISE frame starts with a dynamic table size
+    /// update.
+    pub fn set_header_table_size(&mut self, val: usize) {
+        self.hpack
+            .update_max_size(cmp::min(val, MAX_SEND_HEADER_TABLE_SIZE));
+    }
+
+    /// Set the policy deciding how sent headers are indexed.
+    pub fn set_indexing_policy(&mut self, policy: Arc<dyn hpack::IndexingPolicy>) {
+        self.hpack.set_indexing_policy(policy);
+    }
+
+    /// Set when string literals of sent headers are Huffman encoded.
+    pub fn set_huffman(&mut self, huffman: hpack::Huffman) {
+        self.hpack.set_huffman(huffman);
+    }
+
+    /// Set the padding of sent HEADERS and PUSH_PROMISE frames.
+    pub fn set_padding(&mut self, padding: Padding) {
+        self.padder = Some(Padder::new(padding));
+    }
+
+    /// Set the observer of the encoded frames.
+    pub fn set_frame_observer(&mut self, observer: Arc<dyn FrameObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Returns the HPACK statistics of the sent header blocks.
+    pub fn hpack_stats(&self) -> hpack::HpackCounters {
+        self.hpack.stats()
+    }
+
+    /// Retrieve the next data frame that has been sent, in the order they
+    /// were written
+    pub fn take_written_data_frame(&mut self) -> Option<frame::Data<B>> {
+        self.written_data_frames.pop_front()
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
     }
 
     pub fn get_mut(&mut self) -> &mut T {
@@ -268,6 +424,106 @@ impl<T, B> FramedWrite<T, B> {
     }
 }
 
+// ===== impl Queued =====
+
+impl<B: Buf> Queued<B> {
+    fn remaining(&self) -> usize {
+        match *self {
+            Queued::Bytes(ref bytes) => bytes.len(),
+            Queued::Data(ref frame) => frame.payload().remaining(),
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match *self {
+            Queued::Bytes(ref bytes) => &bytes[..],
+            Queued::Data(ref frame) => frame.payload().bytes(),
+        }
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        match *self {
+            Queued::Bytes(ref mut bytes) => bytes.advance(cnt),
+            Queued::Data(ref mut frame) => frame.payload_mut().advance(cnt),
+        }
+    }
+
+    fn bytes_vec<'a>(&'a self, dst: &mut [&'a IoVec]) -> usize {
+        match *self {
+            Queued::Bytes(ref bytes) => {
+                if dst.is_empty() || bytes.is_empty() {
+                    return 0;
+                }
+
+                dst[0] = bytes[..].into();
+                1
+            }
+            Queued::Data(ref frame) => frame.payload().bytes_vec(dst),
+        }
+    }
+}
+
+// ===== impl Gather =====
+
+impl<'a, B: Buf> Buf for Gather<'a, B> {
+    fn remaining(&self) -> usize {
+        let queued: usize = self.queue.iter().map(Queued::remaining).sum();
+        queued + self.buf.remaining()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self.queue.front() {
+            Some(queued) => queued.bytes(),
+            None => self.buf.bytes(),
+        }
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        while cnt > 0 {
+            let queued = match self.queue.front_mut() {
+                Some(queued) => queued,
+                None => {
+                    self.buf.advance(cnt);
+                    return;
+                }
+            };
+
+            let n = cmp::min(cnt, queued.remaining());
+            queued.advance(n);
+            cnt -= n;
+
+            if queued.remaining() == 0 {
+                // The data frame has been written, so save it off
+                if let Some(Queued::Data(frame)) = self.queue.pop_front() {
+                    self.written_data_frames.push_back(frame);
+                }
+            }
+        }
+    }
+
+    fn bytes_vec<'b>(&'b self, dst: &mut [&'b IoVec]) -> usize {
+        let mut n = 0;
+
+        for queued in self.queue.iter() {
+            if n == dst.len() {
+                return n;
+            }
+
+            let cnt = queued.bytes_vec(&mut dst[n..]);
+            let len: usize = dst[n..n + cnt].iter().map(|iov| iov.len()).sum();
+            n += cnt;
+
+            // Some payloads, such as `Take`, only expose their first slice.
+            // The rest of the payload must be written before what follows.
+            if len < queued.remaining() {
+                return n;
+            }
+        }
+
+        n + self.buf.bytes_vec(&mut dst[n..])
+    }
+}
+
 impl<T: AsyncRead + Unpin, B: Unpin> AsyncRead for FramedWrite<T, B> {
     unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
         self.inner.prepare_uninitialized_buffer(buf)
@@ -289,14 +545,3 @@ impl<T: AsyncRead + Unpin, B: Unpin> AsyncRead for FramedWrite<T, B> {
         Pin::new(&mut self.inner).poll_read_buf(cx, buf)
     }
 }
-
-#[cfg(feature = "unstable")]
-mod unstable {
-    use super::*;
-
-    impl<T, B> FramedWrite<T, B> {
-        pub fn get_ref(&self) -> &T {
-            &self.inner
-        }
-    }
-}
@@ -8,6 +8,10 @@ use self::framed_read::FramedRead;
 use self::framed_write::FramedWrite;
 
 use crate::frame::{self, Data, Frame};
+use crate::hpack::{HpackStats, Huffman, IndexingPolicy};
+use crate::observer::FrameObserver;
+use crate::padding::Padding;
+use crate::wire;
 
 use futures::*;
 
@@ -18,6 +22,7 @@ use tokio_codec::length_delimited;
 use tokio_io::{AsyncRead, AsyncWrite};
 
 use std::io;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct Codec<T, B> {
@@ -73,7 +78,6 @@ impl<T, B> Codec<T, B> {
     ///
     /// This is the largest size this codec will accept from the wire. Larger
     /// frames will be rejected.
-    #[cfg(feature = "unstable")]
     #[inline]
     pub fn max_recv_frame_size(&self) -> usize {
         self.inner.max_frame_size()
@@ -94,8 +98,43 @@ impl<T, B> Codec<T, B> {
         self.inner.set_max_header_list_size(val);
     }
 
+    /// Set the peer's header table size, used to encode sent headers.
+    pub fn set_send_header_table_size(&mut self, val: usize) {
+        self.framed_write().set_header_table_size(val)
+    }
+
+    /// Set the header table size used to decode received headers.
+    pub fn set_recv_header_table_size(&mut self, val: usize) {
+        self.inner.set_header_table_size(val)
+    }
+
+    /// Set the policy deciding how sent headers are indexed.
+    pub fn set_indexing_policy(&mut self, policy: Arc<dyn IndexingPolicy>) {
+        self.framed_write().set_indexing_policy(policy)
+    }
+
+    /// Set when string literals of sent headers are Huffman encoded.
+    pub fn set_huffman(&mut self, huffman: Huffman) {
+        self.framed_write().set_huffman(huffman)
+    }
+
+    /// Set the padding of sent HEADERS and PUSH_PROMISE frames.
+    pub fn set_padding(&mut self, padding: Padding) {
+        self.framed_write().set_padding(padding)
+    }
+
+    /// Set the observer of the sent and received frames.
+    pub fn set_frame_observer(&mut self, observer: Arc<dyn FrameObserver>) {
+        self.framed_write().set_frame_observer(observer.clone());
+        self.inner.set_frame_observer(observer)
+    }
+
+    /// Returns the HPACK statistics of the connection.
+    pub fn hpack_stats(&self) -> HpackStats {
+        HpackStats::new(self.inner.get_ref().hpack_stats(), self.inner.hpack_stats())
+    }
+
     /// Get a reference to the inner stream.
-    #[cfg(feature = "unstable")]
     pub fn get_ref(&self) -> &T {
         self.inner.get_ref().get_ref()
     }
@@ -105,9 +144,9 @@ impl<T, B> Codec<T, B> {
         self.inner.get_mut().get_mut()
     }
 
-    /// Takes the data payload value that was fully written to the socket
-    pub(crate) fn take_last_data_frame(&mut self) -> Option<Data<B>> {
-        self.framed_write().take_last_data_frame()
+    /// Takes the next data payload value that was fully written to the socket
+    pub(crate) fn take_written_data_frame(&mut self) -> Option<Data<B>> {
+        self.framed_write().take_written_data_frame()
     }
 
     fn framed_write(&mut self) -> &mut FramedWrite<T, B> {
@@ -140,12 +179,31 @@ where
         self.framed_write().flush(cx)
     }
 
+    /// Returns the number of bytes buffered and not yet written
+    pub(crate) fn buffered_len(&self) -> usize {
+        self.inner.get_ref().buffered_len()
+    }
+
     /// Shutdown the send half
     pub fn shutdown(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
         self.framed_write().shutdown(cx)
     }
 }
 
+impl<T, B> Codec<T, B>
+where
+    T: AsyncRead + Unpin,
+    B: Unpin,
+{
+    /// Polls for the next frame, with the precise cause of decoding errors.
+    pub(crate) fn poll_frame(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame, wire::RecvError>>> {
+        self.inner.poll_frame(cx)
+    }
+}
+
 impl<T, B> Stream for Codec<T, B>
 where
     T: AsyncRead + Unpin,
@@ -0,0 +1,81 @@
+/// When the frames buffered by a connection are flushed to the I/O.
+///
+/// Frames are encoded to a write buffer, which is flushed once the
+/// connection has no more frames to send. That gives the lowest latency, but
+/// a connection serving many small streams then issues many small writes,
+/// each of which may become its own TLS record. Deferring the flush lets the
+/// frames queued by other tasks in the meantime share a single write.
+///
+/// A deferred flush wakes the connection task, so the buffered frames are
+/// written on a later poll of the connection even if nothing else happens.
+/// The write buffer is always flushed once it is full.
+///
+/// It is set with [`client::Builder::flush_strategy`] and
+/// [`server::Builder::flush_strategy`].
+///
+/// # Examples
+///
+/// ```
+/// use h2::FlushStrategy;
+///
+/// // Write as soon as 4kb are buffered, or once a poll of the connection
+/// // finds nothing new to send.
+/// let strategy = FlushStrategy::Coalesce(4 * 1024);
+/// # drop(strategy);
+/// ```
+///
+/// [`client::Builder::flush_strategy`]: client/struct.Builder.html#method.flush_strategy
+/// [`server::Builder::flush_strategy`]: server/struct.Builder.html#method.flush_strategy
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FlushStrategy {
+    /// Flushes as soon as there are no more frames to send.
+    ///
+    /// This is the default.
+    Immediate,
+
+    /// Defers the flush while fewer than the given number of bytes are
+    /// buffered, and flushes once a poll finds nothing new to send.
+    Coalesce(usize),
+
+    /// Defers the flush for as long as each poll of the connection buffers
+    /// more frames, and flushes once a poll finds nothing new to send.
+    OnIdle,
+}
+
+/// Decides when to flush the frames buffered by the codec, according to a
+/// `FlushStrategy`.
+#[derive(Debug)]
+pub struct Flusher {
+    strategy: FlushStrategy,
+
+    /// Number of bytes that were buffered when the flush was last deferred
+    deferred: Option<usize>,
+}
+
+// ===== impl Flusher =====
+
+impl Flusher {
+    pub fn new(strategy: FlushStrategy) -> Flusher {
+        Flusher {
+            strategy,
+            deferred: None,
+        }
+    }
+
+    /// Returns true if the `buffered` bytes must be flushed now, once there
+    /// are no more frames to send.
+    ///
+    /// If this returns false, the caller must arrange to be polled again.
+    pub fn should_flush(&mut self, buffered: usize) -> bool {
+        let defer = buffered > 0
+            && match self.strategy {
+                FlushStrategy::Immediate => false,
+                FlushStrategy::Coalesce(max) => buffered < max && self.deferred != Some(buffered),
+                FlushStrategy::OnIdle => self.deferred != Some(buffered),
+            };
+
+        self.deferred = if defer { Some(buffered) } else { None };
+
+        !defer
+    }
+}
@@ -75,6 +75,31 @@ impl<T> Data<T> {
         self.flags.set_padded();
     }
 
+    /// Returns the number of padding octets of this frame, if it is padded.
+    #[cfg(feature = "unstable")]
+    pub fn pad_len(&self) -> Option<u8> {
+        self.pad_len
+    }
+
+    /// Sets the number of padding octets to encode with this frame.
+    ///
+    /// `None` sends the frame without padding.
+    pub fn set_pad_len(&mut self, pad_len: Option<u8>) {
+        self.pad_len = pad_len;
+
+        if pad_len.is_some() {
+            self.flags.set_padded();
+        } else {
+            self.flags.unset_padded();
+        }
+    }
+
+    /// Returns the number of octets the Pad Length field and the padding add
+    /// to the frame payload.
+    pub(crate) fn padding_len(&self) -> usize {
+        self.pad_len.map_or(0, |pad_len| pad_len as usize + 1)
+    }
+
     /// Returns a reference to this frame's payload.
     ///
     /// This does **not** include any padding that might have been originally
@@ -150,10 +175,30 @@ impl<T: Buf> Data<T> {
     pub(crate) fn encode_chunk<U: BufMut>(&mut self, dst: &mut U) {
         let len = self.data.remaining() as usize;
 
-        assert!(dst.remaining_mut() >= len);
+        assert!(dst.remaining_mut() >= len + self.padding_len());
 
-        self.head().encode(len, dst);
+        self.encode_head(dst);
         dst.put(&mut self.data);
+        self.encode_padding(dst);
+    }
+
+    /// Encode the frame head, followed by the Pad Length field if the frame
+    /// is padded.
+    pub(crate) fn encode_head<U: BufMut>(&self, dst: &mut U) {
+        let len = self.data.remaining();
+
+        self.head().encode(len + self.padding_len(), dst);
+
+        if let Some(pad_len) = self.pad_len {
+            dst.put_u8(pad_len);
+        }
+    }
+
+    /// Encode the padding that follows the payload.
+    pub(crate) fn encode_padding<U: BufMut>(&self, dst: &mut U) {
+        if let Some(pad_len) = self.pad_len {
+            dst.put_slice(&[0; 255][..pad_len as usize]);
+        }
     }
 }
 
@@ -205,10 +250,13 @@ impl DataFlags {
         self.0 & PADDED == PADDED
     }
 
-    #[cfg(feature = "unstable")]
     fn set_padded(&mut self) {
         self.0 |= PADDED
     }
+
+    fn unset_padded(&mut self) {
+        self.0 &= !PADDED
+    }
 }
 
 impl Default for DataFlags {
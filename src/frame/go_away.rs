@@ -1,11 +1,12 @@
 use crate::frame::{self, Error, Head, Kind, Reason, StreamId};
 
-use bytes::BufMut;
+use bytes::{BufMut, Bytes};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct GoAway {
     last_stream_id: StreamId,
     error_code: Reason,
+    debug_data: Bytes,
 }
 
 impl GoAway {
@@ -13,6 +14,15 @@ impl GoAway {
         GoAway {
             last_stream_id,
             error_code: reason,
+            debug_data: Bytes::new(),
+        }
+    }
+
+    pub fn with_debug_data(last_stream_id: StreamId, reason: Reason, debug_data: Bytes) -> Self {
+        GoAway {
+            last_stream_id,
+            error_code: reason,
+            debug_data,
         }
     }
 
@@ -24,6 +34,10 @@ impl GoAway {
         self.error_code
     }
 
+    pub fn debug_data(&self) -> &Bytes {
+        &self.debug_data
+    }
+
     pub fn load(payload: &[u8]) -> Result<GoAway, Error> {
         if payload.len() < 8 {
             return Err(Error::BadFrameSize);
@@ -31,19 +45,27 @@ impl GoAway {
 
         let (last_stream_id, _) = StreamId::parse(&payload[..4]);
         let error_code = unpack_octets_4!(payload, 4, u32);
+        let debug_data = Bytes::from(&payload[8..]);
 
         Ok(GoAway {
             last_stream_id,
             error_code: error_code.into(),
+            debug_data,
         })
     }
 
+    /// Returns the length of the encoded frame, head included.
+    pub fn encoded_len(&self) -> usize {
+        frame::HEADER_LEN + 8 + self.debug_data.len()
+    }
+
     pub fn encode<B: BufMut>(&self, dst: &mut B) {
         log::trace!("encoding GO_AWAY; code={:?}", self.error_code);
         let head = Head::new(Kind::GoAway, 0, StreamId::zero());
-        head.encode(8, dst);
+        head.encode(8 + self.debug_data.len(), dst);
         dst.put_u32_be(self.last_stream_id.into());
         dst.put_u32_be(self.error_code.into());
+        dst.put_slice(&self.debug_data);
     }
 }
 
@@ -1,20 +1,18 @@
 use super::{util, StreamDependency, StreamId};
 use crate::frame::{Error, Frame, Head, Kind};
 use crate::hpack;
+use crate::padding::{Padder, Padding};
 
 use http::header::{self, HeaderName, HeaderValue};
 use http::{uri, HeaderMap, Method, StatusCode, Uri};
 
-use bytes::{Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use string::String;
 
+use std::cmp;
 use std::fmt;
 use std::io::Cursor;
 
-// Minimum MAX_FRAME_SIZE is 16kb, so save some arbitrary space for frame
-// head and other header bits.
-const MAX_HEADER_LENGTH: usize = 1024 * 16 - 100;
-
 /// Header frame
 ///
 /// This could be either a request or a response.
@@ -31,6 +29,9 @@ pub struct Headers {
 
     /// The associated flags
     flags: HeadersFlag,
+
+    /// The number of padding octets, if the frame is padded.
+    pad_len: Option<u8>,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -60,10 +61,22 @@ pub struct Continuation {
     stream_id: StreamId,
 
     header_block: EncodingHeaderBlock,
+
+    /// Whether the last frame of the header block sets `END_HEADERS`
+    is_end_headers: bool,
+}
+
+/// A CONTINUATION frame carrying a header block fragment that is already
+/// encoded, sent as is.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Fragment {
+    stream_id: StreamId,
+    fragment: Bytes,
+    is_end_headers: bool,
 }
 
 // TODO: These fields shouldn't be `pub`
-#[derive(Debug, Default, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct Pseudo {
     // Request
     pub method: Option<Method>,
@@ -104,6 +117,12 @@ struct EncodingHeaderBlock {
 
     /// remaining headers to encode
     headers: Iter,
+
+    /// Encoded part of the header block that was not written to a frame yet
+    buf: BytesMut,
+
+    /// Whether all the headers have been encoded
+    is_encoded: bool,
 }
 
 const END_STREAM: u8 = 0x1;
@@ -126,6 +145,7 @@ impl Headers {
                 pseudo,
             },
             flags: HeadersFlag::default(),
+            pad_len: None,
         }
     }
 
@@ -142,6 +162,7 @@ impl Headers {
                 pseudo: Pseudo::default(),
             },
             flags,
+            pad_len: None,
         }
     }
 
@@ -151,15 +172,22 @@ impl Headers {
     pub fn load(head: Head, mut src: BytesMut) -> Result<(Self, BytesMut), Error> {
         let flags = HeadersFlag(head.flag());
         let mut pad = 0;
+        let mut pad_len = None;
 
         log::trace!("loading headers; flags={:?}", flags);
 
+        // The stream identifier must not be zero
+        if head.stream_id().is_zero() {
+            return Err(Error::InvalidStreamId);
+        }
+
         // Read the padding length
         if flags.is_padded() {
             if src.is_empty() {
                 return Err(Error::MalformedMessage);
             }
             pad = src[0] as usize;
+            pad_len = Some(src[0]);
 
             // Drop the padding
             let _ = src.split_to(1);
@@ -202,6 +230,7 @@ impl Headers {
                 pseudo: Pseudo::default(),
             },
             flags,
+            pad_len,
         };
 
         Ok((headers, src))
@@ -211,7 +240,7 @@ impl Headers {
         &mut self,
         src: &mut BytesMut,
         max_header_list_size: usize,
-        decoder: &mut hpack::Decoder,
+        decoder: &mut hpack::decoder::Decoder,
     ) -> Result<(), Error> {
         self.header_block.load(src, max_header_list_size, decoder)
     }
@@ -228,6 +257,12 @@ impl Headers {
         self.flags.set_end_headers();
     }
 
+    /// Unsets `END_HEADERS`, the header block then goes on in the
+    /// CONTINUATION frames sent after this one.
+    pub fn unset_end_headers(&mut self) {
+        self.flags.unset_end_headers();
+    }
+
     pub fn is_end_stream(&self) -> bool {
         self.flags.is_end_stream()
     }
@@ -236,18 +271,39 @@ impl Headers {
         self.flags.set_end_stream()
     }
 
-    pub fn is_over_size(&self) -> bool {
-        self.header_block.is_over_size
+    /// Returns the number of padding octets of this frame, if it is padded.
+    #[cfg(feature = "unstable")]
+    pub fn pad_len(&self) -> Option<u8> {
+        self.pad_len
     }
 
-    pub(crate) fn has_too_big_field(&self) -> bool {
-        self.header_block.has_too_big_field()
+    /// Sets the number of padding octets to encode with this frame.
+    ///
+    /// `None` sends the frame without padding. The padding policy of the
+    /// connection, if any, takes precedence.
+    #[cfg(feature = "unstable")]
+    pub fn set_pad_len(&mut self, pad_len: Option<u8>) {
+        self.pad_len = pad_len;
+
+        if pad_len.is_some() {
+            self.flags.0 |= PADDED;
+        } else {
+            self.flags.0 &= !PADDED;
+        }
+    }
+
+    pub fn is_over_size(&self) -> bool {
+        self.header_block.is_over_size
     }
 
     pub fn into_parts(self) -> (Pseudo, HeaderMap) {
         (self.header_block.pseudo, self.header_block.fields)
     }
 
+    pub(crate) fn parts(&self) -> (&Pseudo, &HeaderMap) {
+        (&self.header_block.pseudo, &self.header_block.fields)
+    }
+
     #[cfg(feature = "unstable")]
     pub fn pseudo_mut(&mut self) -> &mut Pseudo {
         &mut self.header_block.pseudo
@@ -261,16 +317,29 @@ impl Headers {
         self.header_block.fields
     }
 
-    pub fn encode(self, encoder: &mut hpack::Encoder, dst: &mut BytesMut) -> Option<Continuation> {
-        // At this point, the `is_end_headers` flag should always be set
-        debug_assert!(self.flags.is_end_headers());
-
+    pub fn encode(
+        self,
+        encoder: &mut hpack::encoder::Encoder,
+        dst: &mut BytesMut,
+        max_frame_size: usize,
+        padder: Option<&mut Padder>,
+    ) -> Option<Continuation> {
         // Get the HEADERS frame head
         let head = self.head();
 
-        self.header_block
-            .into_encoding()
-            .encode(&head, encoder, dst, |_| {})
+        // Without a padding policy, the frame is padded as it asks
+        let mut fixed = self
+            .pad_len
+            .map(|pad_len| Padder::new(Padding::Fixed(pad_len)));
+
+        self.header_block.into_encoding().encode(
+            &head,
+            encoder,
+            dst,
+            max_frame_size,
+            padder.or(fixed.as_mut()),
+            |_| {},
+        )
     }
 
     fn head(&self) -> Head {
@@ -310,6 +379,11 @@ impl PushPromise {
         let flags = PushPromiseFlag(head.flag());
         let mut pad = 0;
 
+        // The stream identifier must not be zero
+        if head.stream_id().is_zero() {
+            return Err(Error::InvalidStreamId);
+        }
+
         // Read the padding length
         if flags.is_padded() {
             if src.is_empty() {
@@ -357,7 +431,7 @@ impl PushPromise {
         &mut self,
         src: &mut BytesMut,
         max_header_list_size: usize,
-        decoder: &mut hpack::Decoder,
+        decoder: &mut hpack::decoder::Decoder,
     ) -> Result<(), Error> {
         self.header_block.load(src, max_header_list_size, decoder)
     }
@@ -378,24 +452,36 @@ impl PushPromise {
         self.flags.set_end_headers();
     }
 
+    /// Unsets `END_HEADERS`, the header block then goes on in the
+    /// CONTINUATION frames sent after this one.
+    pub fn unset_end_headers(&mut self) {
+        self.flags.unset_end_headers();
+    }
+
     pub fn is_over_size(&self) -> bool {
         self.header_block.is_over_size
     }
 
-    pub fn encode(self, encoder: &mut hpack::Encoder, dst: &mut BytesMut) -> Option<Continuation> {
-        use bytes::BufMut;
-
-        // At this point, the `is_end_headers` flag should always be set
-        debug_assert!(self.flags.is_end_headers());
-
+    pub fn encode(
+        self,
+        encoder: &mut hpack::encoder::Encoder,
+        dst: &mut BytesMut,
+        max_frame_size: usize,
+        padder: Option<&mut Padder>,
+    ) -> Option<Continuation> {
         let head = self.head();
         let promised_id = self.promised_id;
 
-        self.header_block
-            .into_encoding()
-            .encode(&head, encoder, dst, |dst| {
+        self.header_block.into_encoding().encode(
+            &head,
+            encoder,
+            dst,
+            max_frame_size,
+            padder,
+            |dst| {
                 dst.put_u32_be(promised_id.into());
-            })
+            },
+        )
     }
 
     fn head(&self) -> Head {
@@ -408,10 +494,11 @@ impl PushPromise {
     pub fn into_parts(self) -> (Pseudo, HeaderMap) {
         (self.header_block.pseudo, self.header_block.fields)
     }
-}
 
-#[cfg(feature = "unstable")]
-impl PushPromise {
+    pub(crate) fn parts(&self) -> (&Pseudo, &HeaderMap) {
+        (&self.header_block.pseudo, &self.header_block.fields)
+    }
+
     pub fn new(
         stream_id: StreamId,
         promised_id: StreamId,
@@ -429,7 +516,10 @@ impl PushPromise {
             stream_id,
         }
     }
+}
 
+#[cfg(feature = "unstable")]
+impl PushPromise {
     pub fn fields(&self) -> &HeaderMap {
         &self.header_block.fields
     }
@@ -460,14 +550,74 @@ impl fmt::Debug for PushPromise {
 
 impl Continuation {
     fn head(&self) -> Head {
-        Head::new(Kind::Continuation, END_HEADERS, self.stream_id)
+        let flags = if self.is_end_headers { END_HEADERS } else { 0 };
+        Head::new(Kind::Continuation, flags, self.stream_id)
     }
 
-    pub fn encode(self, encoder: &mut hpack::Encoder, dst: &mut BytesMut) -> Option<Continuation> {
+    pub fn encode(
+        self,
+        encoder: &mut hpack::encoder::Encoder,
+        dst: &mut BytesMut,
+        max_frame_size: usize,
+    ) -> Option<Continuation> {
         // Get the CONTINUATION frame head
         let head = self.head();
 
-        self.header_block.encode(&head, encoder, dst, |_| {})
+        // CONTINUATION frames cannot be padded
+        self.header_block
+            .encode(&head, encoder, dst, max_frame_size, None, |_| {})
+    }
+}
+
+// ===== impl Fragment =====
+
+impl Fragment {
+    /// Creates a CONTINUATION frame with `END_HEADERS` set.
+    pub fn new(stream_id: StreamId, fragment: Bytes) -> Fragment {
+        Fragment {
+            stream_id,
+            fragment,
+            is_end_headers: true,
+        }
+    }
+
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    pub fn fragment(&self) -> &Bytes {
+        &self.fragment
+    }
+
+    pub fn is_end_headers(&self) -> bool {
+        self.is_end_headers
+    }
+
+    pub fn set_end_headers(&mut self) {
+        self.is_end_headers = true;
+    }
+
+    pub fn unset_end_headers(&mut self) {
+        self.is_end_headers = false;
+    }
+
+    /// Returns the length of the encoded frame, head included.
+    pub fn encoded_len(&self) -> usize {
+        super::HEADER_LEN + self.fragment.len()
+    }
+
+    pub fn encode<B: BufMut>(&self, dst: &mut B) {
+        log::trace!("encoding CONTINUATION; id={:?}", self.stream_id);
+        let flags = if self.is_end_headers { END_HEADERS } else { 0 };
+        let head = Head::new(Kind::Continuation, flags, self.stream_id);
+        head.encode(self.fragment.len(), dst);
+        dst.put_slice(&self.fragment);
+    }
+}
+
+impl<T> From<Fragment> for Frame<T> {
+    fn from(src: Fragment) -> Self {
+        Frame::Continuation(src)
     }
 }
 
@@ -527,6 +677,15 @@ impl Pseudo {
     pub fn set_authority(&mut self, authority: String<Bytes>) {
         self.authority = Some(authority);
     }
+
+    /// Whether no pseudo header field is set.
+    pub fn is_empty(&self) -> bool {
+        self.method.is_none()
+            && self.scheme.is_none()
+            && self.authority.is_none()
+            && self.path.is_none()
+            && self.status.is_none()
+    }
 }
 
 fn to_string(src: Bytes) -> String<Bytes> {
@@ -536,11 +695,18 @@ fn to_string(src: Bytes) -> String<Bytes> {
 // ===== impl EncodingHeaderBlock =====
 
 impl EncodingHeaderBlock {
+    /// Encodes the next frame of the header block.
+    ///
+    /// The frame is as large as `max_frame_size` and the room left in `dst`
+    /// allow. If the header block does not fit, the returned `Continuation`
+    /// encodes the rest of it. The frame is padded when a `padder` is given.
     fn encode<F>(
         mut self,
         head: &Head,
-        encoder: &mut hpack::Encoder,
+        encoder: &mut hpack::encoder::Encoder,
         dst: &mut BytesMut,
+        max_frame_size: usize,
+        mut padder: Option<&mut Padder>,
         f: F,
     ) -> Option<Continuation>
     where
@@ -555,19 +721,48 @@ impl EncodingHeaderBlock {
 
         let payload_pos = dst.len();
 
+        if padder.is_some() {
+            // The Pad Length field, set once the padding is known
+            dst.put_u8(0);
+        }
+
         f(dst);
 
-        // Now, encode the header payload
-        let continuation = match encoder.encode(self.hpack, &mut self.headers, dst) {
-            hpack::Encode::Full => None,
-            hpack::Encode::Partial(state) => Some(Continuation {
-                stream_id: head.stream_id(),
-                header_block: EncodingHeaderBlock {
-                    hpack: Some(state),
-                    headers: self.headers,
-                },
-            }),
-        };
+        let mut room = cmp::min(
+            max_frame_size - (dst.len() - payload_pos),
+            dst.remaining_mut(),
+        );
+
+        if let Some(ref padder) = padder {
+            // Keep room for the padding, without leaving out the header block
+            room -= cmp::min(padder.max_pad_len(), room / 2);
+        }
+
+        // When the frame may fill `dst`, headers are encoded in place, up to
+        // the first one that does not fit.
+        if self.buf.is_empty() && !self.is_encoded && dst.remaining_mut() <= room {
+            let len = dst.len();
+
+            self.encode_headers(encoder, dst);
+            room -= dst.len() - len;
+        }
+
+        // The rest of the frame is filled from the header block encoded
+        // ahead, which may split a header across frames.
+        self.fill(encoder, room);
+
+        let fragment = self.buf.split_to(cmp::min(room, self.buf.len()));
+        dst.put_slice(&fragment);
+
+        if let Some(ref mut padder) = padder {
+            let len = dst.len() - payload_pos;
+            let max = cmp::min(max_frame_size - len, dst.remaining_mut());
+            let pad_len = padder.pad_len(len - 1, max);
+
+            dst.put_slice(&[0; 255][..pad_len as usize]);
+            dst[payload_pos] = pad_len;
+            dst[head_pos + 4] |= PADDED;
+        }
 
         // Compute the header block length
         let payload_len = (dst.len() - payload_pos) as u64;
@@ -577,15 +772,49 @@ impl EncodingHeaderBlock {
         assert!(payload_len_be[0..5].iter().all(|b| *b == 0));
         (&mut dst[head_pos..head_pos + 3]).copy_from_slice(&payload_len_be[5..]);
 
-        if continuation.is_some() {
-            // There will be continuation frames, so the `is_end_headers` flag
-            // must be unset
-            debug_assert!(dst[head_pos + 4] & END_HEADERS == END_HEADERS);
+        if self.is_encoded && self.buf.is_empty() {
+            return None;
+        }
+
+        // There will be continuation frames, so the `END_HEADERS` flag, if
+        // any, moves to the last of them
+        dst[head_pos + 4] &= !END_HEADERS;
+
+        Some(Continuation {
+            stream_id: head.stream_id(),
+            header_block: self,
+            is_end_headers: head.flag() & END_HEADERS == END_HEADERS,
+        })
+    }
+
+    /// Encodes headers to `buf` until it holds at least `len` octets, or all
+    /// headers are encoded.
+    fn fill(&mut self, encoder: &mut hpack::encoder::Encoder, len: usize) {
+        while !self.is_encoded && self.buf.len() < len {
+            self.buf.reserve(len - self.buf.len());
+
+            match encoder.encode(self.hpack.take(), &mut self.headers, &mut self.buf) {
+                hpack::Encode::Full => self.is_encoded = true,
+                hpack::Encode::Partial(state) => {
+                    if self.buf.len() < len {
+                        // Make room for the whole header, so that it is
+                        // encoded once instead of growing the buffer one
+                        // try at a time.
+                        self.buf.reserve(encoder.max_encoded_len(&state));
+                    }
 
-            dst[head_pos + 4] -= END_HEADERS;
+                    self.hpack = Some(state);
+                }
+            }
         }
+    }
 
-        continuation
+    /// Encodes as many headers as fit in `dst`.
+    fn encode_headers(&mut self, encoder: &mut hpack::encoder::Encoder, dst: &mut BytesMut) {
+        match encoder.encode(self.hpack.take(), &mut self.headers, dst) {
+            hpack::Encode::Full => self.is_encoded = true,
+            hpack::Encode::Partial(state) => self.hpack = Some(state),
+        }
     }
 }
 
@@ -654,6 +883,10 @@ impl HeadersFlag {
         self.0 |= END_HEADERS;
     }
 
+    pub fn unset_end_headers(&mut self) {
+        self.0 &= !END_HEADERS;
+    }
+
     pub fn is_padded(&self) -> bool {
         self.0 & PADDED == PADDED
     }
@@ -706,6 +939,10 @@ impl PushPromiseFlag {
         self.0 |= END_HEADERS;
     }
 
+    pub fn unset_end_headers(&mut self) {
+        self.0 &= !END_HEADERS;
+    }
+
     pub fn is_padded(&self) -> bool {
         self.0 & PADDED == PADDED
     }
@@ -740,7 +977,7 @@ impl HeaderBlock {
         &mut self,
         src: &mut BytesMut,
         max_header_list_size: usize,
-        decoder: &mut hpack::Decoder,
+        decoder: &mut hpack::decoder::Decoder,
     ) -> Result<(), Error> {
         let mut reg = !self.fields.is_empty();
         let mut malformed = false;
@@ -833,6 +1070,8 @@ impl HeaderBlock {
                 pseudo: Some(self.pseudo),
                 fields: self.fields.into_iter(),
             },
+            buf: BytesMut::new(),
+            is_encoded: false,
         }
     }
 
@@ -865,46 +1104,6 @@ impl HeaderBlock {
                 .map(|(name, value)| decoded_header_size(name.as_str().len(), value.len()))
                 .sum::<usize>()
     }
-
-    /// Iterate over all pseudos and headers to see if any individual pair
-    /// would be too large to encode.
-    pub(crate) fn has_too_big_field(&self) -> bool {
-        macro_rules! pseudo_size {
-            ($name:ident) => {{
-                self.pseudo
-                    .$name
-                    .as_ref()
-                    .map(|m| decoded_header_size(stringify!($name).len() + 1, m.as_str().len()))
-                    .unwrap_or(0)
-            }};
-        }
-
-        if pseudo_size!(method) > MAX_HEADER_LENGTH {
-            return true;
-        }
-
-        if pseudo_size!(scheme) > MAX_HEADER_LENGTH {
-            return true;
-        }
-
-        if pseudo_size!(authority) > MAX_HEADER_LENGTH {
-            return true;
-        }
-
-        if pseudo_size!(path) > MAX_HEADER_LENGTH {
-            return true;
-        }
-
-        // skip :status, its never going to be too big
-
-        for (name, value) in &self.fields {
-            if decoded_header_size(name.as_str().len(), value.len()) > MAX_HEADER_LENGTH {
-                return true;
-            }
-        }
-
-        false
-    }
 }
 
 fn decoded_header_size(name: usize, value: usize) -> usize {
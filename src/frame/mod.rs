@@ -36,21 +36,26 @@ mod reason;
 mod reset;
 mod settings;
 mod stream_id;
+mod unknown;
 mod util;
 mod window_update;
 
 pub use self::data::Data;
 pub use self::go_away::GoAway;
 pub use self::head::{Head, Kind};
-pub use self::headers::{Continuation, Headers, Pseudo, PushPromise};
+pub use self::headers::{Continuation, Fragment, Headers, Pseudo, PushPromise};
 pub use self::ping::Ping;
 pub use self::priority::{Priority, StreamDependency};
 pub use self::reason::Reason;
 pub use self::reset::Reset;
 pub use self::settings::Settings;
 pub use self::stream_id::{StreamId, StreamIdOverflow};
+pub use self::unknown::Unknown;
 pub use self::window_update::WindowUpdate;
 
+#[cfg(feature = "unstable")]
+pub use self::settings::Setting;
+
 // Re-export some constants
 
 pub use self::settings::{
@@ -73,6 +78,10 @@ pub enum Frame<T = Bytes> {
     GoAway(GoAway),
     WindowUpdate(WindowUpdate),
     Reset(Reset),
+    /// Only sent: received CONTINUATION frames are merged into the frame
+    /// starting their header block.
+    Continuation(Fragment),
+    Unknown(Unknown),
 }
 
 impl<T> Frame<T> {
@@ -92,6 +101,8 @@ impl<T> Frame<T> {
             GoAway(frame) => frame.into(),
             WindowUpdate(frame) => frame.into(),
             Reset(frame) => frame.into(),
+            Continuation(frame) => frame.into(),
+            Unknown(frame) => frame.into(),
         }
     }
 }
@@ -110,6 +121,8 @@ impl<T> fmt::Debug for Frame<T> {
             GoAway(ref frame) => fmt::Debug::fmt(frame, fmt),
             WindowUpdate(ref frame) => fmt::Debug::fmt(frame, fmt),
             Reset(ref frame) => fmt::Debug::fmt(frame, fmt),
+            Continuation(ref frame) => fmt::Debug::fmt(frame, fmt),
+            Unknown(ref frame) => fmt::Debug::fmt(frame, fmt),
         }
     }
 }
@@ -127,6 +140,9 @@ pub enum Error {
     /// An invalid setting value was provided
     InvalidSettingValue,
 
+    /// The initial window size setting is above the max window size
+    InvalidInitialWindowSize,
+
     /// An invalid window update value
     InvalidWindowUpdateValue,
 
@@ -155,3 +171,24 @@ pub enum Error {
     /// Failed to perform HPACK decoding
     Hpack(hpack::DecoderError),
 }
+
+impl Error {
+    /// Returns the error code of the connection error caused by a frame that
+    /// failed to load.
+    pub fn reason(&self) -> Reason {
+        use crate::hpack::DecoderError::*;
+
+        match *self {
+            Error::BadFrameSize
+            | Error::InvalidPayloadLength
+            | Error::InvalidPayloadAckSettings => Reason::FRAME_SIZE_ERROR,
+            Error::InvalidInitialWindowSize => Reason::FLOW_CONTROL_ERROR,
+            // The block was decoded, but its fields are not valid
+            Error::Hpack(InvalidUtf8) | Error::Hpack(InvalidStatusCode) => Reason::PROTOCOL_ERROR,
+            Error::Hpack(InvalidHeaderValue) => Reason::PROTOCOL_ERROR,
+            Error::Hpack(InvalidPseudoheader) => Reason::PROTOCOL_ERROR,
+            Error::Hpack(_) => Reason::COMPRESSION_ERROR,
+            _ => Reason::PROTOCOL_ERROR,
+        }
+    }
+}
@@ -15,6 +15,7 @@ pub struct Ping {
 // zeroes to distinguish this specific PING from any other.
 const SHUTDOWN_PAYLOAD: Payload = [0x0b, 0x7b, 0xa2, 0xf0, 0x8b, 0x9b, 0xfe, 0x54];
 const USER_PAYLOAD: Payload = [0x3b, 0x7c, 0xdb, 0x7a, 0x0b, 0x87, 0x16, 0xb4];
+const KEEPALIVE_PAYLOAD: Payload = [0x6f, 0x1d, 0x5e, 0xa3, 0x29, 0xc4, 0x80, 0x17];
 
 impl Ping {
     #[cfg(feature = "unstable")]
@@ -29,6 +30,12 @@ impl Ping {
     #[cfg(not(feature = "unstable"))]
     pub(crate) const USER: Payload = USER_PAYLOAD;
 
+    #[cfg(feature = "unstable")]
+    pub const KEEPALIVE: Payload = KEEPALIVE_PAYLOAD;
+
+    #[cfg(not(feature = "unstable"))]
+    pub(crate) const KEEPALIVE: Payload = KEEPALIVE_PAYLOAD;
+
     pub fn new(payload: Payload) -> Ping {
         Ping {
             ack: false,
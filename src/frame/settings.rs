@@ -64,6 +64,14 @@ impl Settings {
         self.flags.is_ack()
     }
 
+    pub fn header_table_size(&self) -> Option<u32> {
+        self.header_table_size
+    }
+
+    pub fn set_header_table_size(&mut self, size: Option<u32>) {
+        self.header_table_size = size;
+    }
+
     pub fn initial_window_size(&self) -> Option<u32> {
         self.initial_window_size
     }
@@ -122,7 +130,7 @@ impl Settings {
         if flag.is_ack() {
             // Ensure that the payload is empty
             if !payload.is_empty() {
-                return Err(Error::InvalidPayloadLength);
+                return Err(Error::InvalidPayloadAckSettings);
             }
 
             // Return the ACK frame
@@ -132,7 +140,7 @@ impl Settings {
         // Ensure the payload length is correct, each setting is 6 bytes long.
         if payload.len() % 6 != 0 {
             log::debug!("invalid settings payload length; len={:?}", payload.len());
-            return Err(Error::InvalidPayloadAckSettings);
+            return Err(Error::InvalidPayloadLength);
         }
 
         let mut settings = Settings::default();
@@ -156,7 +164,7 @@ impl Settings {
                 }
                 Some(InitialWindowSize(val)) => {
                     if val as usize > MAX_INITIAL_WINDOW_SIZE {
-                        return Err(Error::InvalidSettingValue);
+                        return Err(Error::InvalidInitialWindowSize);
                     } else {
                         settings.initial_window_size = Some(val);
                     }
@@ -200,7 +208,8 @@ impl Settings {
         });
     }
 
-    fn for_each<F: FnMut(Setting)>(&self, mut f: F) {
+    /// Calls `f` with each setting carried by the frame.
+    pub fn for_each<F: FnMut(Setting)>(&self, mut f: F) {
         use self::Setting::*;
 
         if let Some(v) = self.header_table_size {
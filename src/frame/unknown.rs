@@ -0,0 +1,80 @@
+use crate::frame::{self, Head, StreamId};
+
+use bytes::{BufMut, Bytes};
+
+/// A frame of a type that is not defined by RFC 7540.
+///
+/// The connection ignores these frames, as the RFC requires. They are kept
+/// as is so that extensions can be sent and received with the codec.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unknown {
+    kind: u8,
+    flags: u8,
+    stream_id: StreamId,
+    payload: Bytes,
+}
+
+impl Unknown {
+    pub fn new(kind: u8, flags: u8, stream_id: StreamId, payload: Bytes) -> Unknown {
+        Unknown {
+            kind,
+            flags,
+            stream_id,
+            payload,
+        }
+    }
+
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// Loads the frame from `src`, frame head included.
+    pub fn load(src: &[u8]) -> Unknown {
+        let head = Head::parse(src);
+
+        Unknown {
+            kind: src[3],
+            flags: head.flag(),
+            stream_id: head.stream_id(),
+            payload: Bytes::from(&src[frame::HEADER_LEN..]),
+        }
+    }
+
+    /// Returns the length of the encoded frame, head included.
+    pub fn encoded_len(&self) -> usize {
+        frame::HEADER_LEN + self.payload.len()
+    }
+
+    pub fn encode<B: BufMut>(&self, dst: &mut B) {
+        log::trace!(
+            "encoding unknown frame; type=0x{:02x} id={:?}",
+            self.kind,
+            self.stream_id
+        );
+
+        // `Head` only knows the frame types of RFC 7540
+        dst.put_uint_be(self.payload.len() as u64, 3);
+        dst.put_u8(self.kind);
+        dst.put_u8(self.flags);
+        dst.put_u32_be(self.stream_id.into());
+        dst.put_slice(&self.payload);
+    }
+}
+
+impl<B> From<Unknown> for frame::Frame<B> {
+    fn from(src: Unknown) -> Self {
+        frame::Frame::Unknown(src)
+    }
+}
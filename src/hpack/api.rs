@@ -0,0 +1,457 @@
+use super::table::{self, DYN_OFFSET};
+use super::{decoder, encoder, DecoderError, Header, Huffman, IndexingPolicy};
+
+use bytes::{Bytes, BytesMut};
+use http::header::{HeaderName, HeaderValue};
+
+use std::io::Cursor;
+use std::sync::Arc;
+use std::{cmp, fmt};
+
+/// The size of the dynamic table before any size update, as defined by
+/// `SETTINGS_HEADER_TABLE_SIZE`.
+const DEFAULT_TABLE_SIZE: usize = 4_096;
+
+/// Space reserved in the destination buffer each time it fills up.
+const RESERVE: usize = 1_024;
+
+/// A header field, as encoded into or decoded from a header block.
+///
+/// A field is either a regular header, or one of the HTTP/2.0 pseudo header
+/// fields such as `:method` or `:path`.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Field {
+    header: Header,
+}
+
+/// Encodes header fields into HPACK header blocks.
+pub struct Encoder {
+    inner: encoder::Encoder,
+}
+
+/// Decodes HPACK header blocks into header fields.
+pub struct Decoder {
+    inner: decoder::Decoder,
+}
+
+/// A read-only view of the dynamic table of an `Encoder` or a `Decoder`.
+#[derive(Clone, Copy)]
+pub struct Table<'a> {
+    inner: TableRef<'a>,
+}
+
+/// An entry of a dynamic [`Table`].
+///
+/// [`Table`]: struct.Table.html
+#[derive(Clone, Copy)]
+pub struct Entry<'a> {
+    header: &'a Header,
+    index: usize,
+}
+
+/// An iterator over the entries of a dynamic [`Table`], from the most
+/// recently inserted.
+///
+/// [`Table`]: struct.Table.html
+#[derive(Clone)]
+pub struct Entries<'a> {
+    table: Table<'a>,
+    pos: usize,
+}
+
+#[derive(Clone, Copy)]
+enum TableRef<'a> {
+    Encoder(&'a table::Table),
+    Decoder(&'a decoder::Table),
+}
+
+// ===== impl Field =====
+
+impl Field {
+    /// Creates a regular header field.
+    ///
+    /// If `value` is [sensitive], the field is encoded as never indexed.
+    ///
+    /// [sensitive]: https://docs.rs/http/0.1/http/header/struct.HeaderValue.html#method.set_sensitive
+    pub fn new(name: HeaderName, value: HeaderValue) -> Field {
+        Field {
+            header: Header::Field { name, value },
+        }
+    }
+
+    /// Creates a field from its raw name and value.
+    ///
+    /// Names starting with `:` are parsed as pseudo header fields. An error is
+    /// returned if the name is not a valid lower case header name or a known
+    /// pseudo header, or if the value is not valid for that name.
+    pub fn from_bytes(name: &[u8], value: &[u8]) -> Result<Field, DecoderError> {
+        let header = Header::new(Bytes::from(name), Bytes::from(value))?;
+        Ok(Field { header })
+    }
+
+    /// Returns the field name, including the leading `:` of pseudo header
+    /// fields.
+    pub fn name(&self) -> &str {
+        self.header.name().as_str()
+    }
+
+    /// Returns the field value.
+    pub fn value(&self) -> &[u8] {
+        self.header.value_slice()
+    }
+
+    /// Returns true if this is a pseudo header field.
+    pub fn is_pseudo(&self) -> bool {
+        match self.header {
+            Header::Field { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Returns true if the value is sensitive.
+    ///
+    /// Decoded values are sensitive if they were encoded as never indexed.
+    /// Pseudo header values are never sensitive.
+    pub fn is_sensitive(&self) -> bool {
+        self.header.is_sensitive()
+    }
+
+    /// Returns the size of the field as accounted for by the dynamic table.
+    pub fn size(&self) -> usize {
+        self.header.len()
+    }
+
+    /// Converts a regular header field into its name and value.
+    ///
+    /// Returns `Err` with the field itself if it is a pseudo header field.
+    pub fn into_header(self) -> Result<(HeaderName, HeaderValue), Field> {
+        match self.header {
+            Header::Field { name, value } => Ok((name, value)),
+            header => Err(Field { header }),
+        }
+    }
+
+    #[cfg(feature = "qpack")]
+    pub(crate) fn from_inner(header: Header) -> Field {
+        Field { header }
+    }
+
+    #[cfg(feature = "qpack")]
+    pub(crate) fn into_inner(self) -> Header {
+        self.header
+    }
+}
+
+impl From<(HeaderName, HeaderValue)> for Field {
+    fn from((name, value): (HeaderName, HeaderValue)) -> Field {
+        Field::new(name, value)
+    }
+}
+
+impl fmt::Debug for Field {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Field")
+            .field("name", &self.name())
+            .field("value", &String::from_utf8_lossy(self.value()))
+            .field("sensitive", &self.is_sensitive())
+            .finish()
+    }
+}
+
+// ===== impl Encoder =====
+
+impl Encoder {
+    /// Creates an encoder with the default dynamic table size of 4,096
+    /// octets.
+    pub fn new() -> Encoder {
+        Encoder::with_max_table_size(DEFAULT_TABLE_SIZE)
+    }
+
+    /// Creates an encoder whose dynamic table starts with the given maximum
+    /// size.
+    ///
+    /// No dynamic table size update is emitted for this initial size, so it
+    /// must match the size the decoder starts with.
+    pub fn with_max_table_size(max_size: usize) -> Encoder {
+        Encoder {
+            inner: encoder::Encoder::new(max_size, 0),
+        }
+    }
+
+    /// Changes the maximum size of the dynamic table.
+    ///
+    /// The next header block starts with a dynamic table size update. The
+    /// size must not exceed the limit advertised by the decoder, which is
+    /// `SETTINGS_HEADER_TABLE_SIZE` in HTTP/2.0.
+    pub fn update_max_table_size(&mut self, max_size: usize) {
+        self.inner.update_max_size(max_size);
+    }
+
+    /// Sets the policy deciding how fields are indexed.
+    ///
+    /// The default is the [`DefaultIndexingPolicy`].
+    ///
+    /// [`DefaultIndexingPolicy`]: struct.DefaultIndexingPolicy.html
+    pub fn set_indexing_policy<P: IndexingPolicy>(&mut self, policy: P) {
+        self.inner.set_indexing_policy(Arc::new(policy));
+    }
+
+    /// Sets when string literals are Huffman encoded.
+    ///
+    /// The default is [`Huffman::WhenShorter`]: a string is only Huffman
+    /// encoded when that does not make it longer. Otherwise, such as for
+    /// base64 tokens, it is sent as is.
+    ///
+    /// [`Huffman::WhenShorter`]: enum.Huffman.html#variant.WhenShorter
+    pub fn set_huffman(&mut self, huffman: Huffman) {
+        self.inner.set_huffman(huffman);
+    }
+
+    /// Encodes `fields` as a single header block, appended to `dst`.
+    ///
+    /// `dst` is grown as needed.
+    pub fn encode<I>(&mut self, fields: I, dst: &mut BytesMut)
+    where
+        I: IntoIterator<Item = Field>,
+    {
+        let mut fields = fields
+            .into_iter()
+            .map(|field| Header::<Option<HeaderName>>::from(field.header));
+        let mut resume = None;
+
+        // Size updates are written before any field and must fit at once.
+        dst.reserve(RESERVE);
+
+        loop {
+            match self.inner.encode(resume.take(), &mut fields, dst) {
+                encoder::Encode::Full => return,
+                encoder::Encode::Partial(state) => {
+                    let additional = cmp::max(dst.capacity() - dst.len(), RESERVE);
+                    dst.reserve(additional * 2);
+                    resume = Some(state);
+                }
+            }
+        }
+    }
+
+    /// Returns a view of the dynamic table.
+    pub fn table(&self) -> Table<'_> {
+        Table {
+            inner: TableRef::Encoder(self.inner.table()),
+        }
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
+}
+
+impl fmt::Debug for Encoder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Encoder")
+            .field("table", &self.table())
+            .finish()
+    }
+}
+
+// ===== impl Decoder =====
+
+impl Decoder {
+    /// Creates a decoder with the default dynamic table size of 4,096
+    /// octets.
+    pub fn new() -> Decoder {
+        Decoder::with_max_table_size(DEFAULT_TABLE_SIZE)
+    }
+
+    /// Creates a decoder whose dynamic table starts with the given maximum
+    /// size.
+    pub fn with_max_table_size(max_size: usize) -> Decoder {
+        Decoder {
+            inner: decoder::Decoder::new(max_size),
+        }
+    }
+
+    /// Changes the maximum size the encoder may use for the dynamic table.
+    ///
+    /// This is the value advertised with `SETTINGS_HEADER_TABLE_SIZE` in
+    /// HTTP/2.0, and should be set once the encoder knows about it. If the
+    /// size is reduced, the next header block must start with a dynamic table
+    /// size update.
+    pub fn set_max_table_size(&mut self, max_size: usize) {
+        self.inner.queue_size_update(max_size);
+    }
+
+    /// Decodes a complete header block.
+    ///
+    /// Fields encoded as never indexed are returned with a sensitive value,
+    /// so that they keep that representation when encoded again.
+    pub fn decode(&mut self, src: &[u8]) -> Result<Vec<Field>, DecoderError> {
+        let mut fields = vec![];
+        let mut src = BytesMut::from(src);
+
+        self.inner.decode(&mut Cursor::new(&mut src), |header| {
+            fields.push(Field { header })
+        })?;
+
+        Ok(fields)
+    }
+
+    /// Returns a view of the dynamic table.
+    pub fn table(&self) -> Table<'_> {
+        Table {
+            inner: TableRef::Decoder(self.inner.table()),
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Decoder")
+            .field("table", &self.table())
+            .finish()
+    }
+}
+
+// ===== impl Table =====
+
+impl<'a> Table<'a> {
+    /// Returns the number of entries in the table.
+    pub fn len(&self) -> usize {
+        match self.inner {
+            TableRef::Encoder(table) => table.len(),
+            TableRef::Decoder(table) => table.len(),
+        }
+    }
+
+    /// Returns true if the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size of the table, as defined by [RFC 7541 section
+    /// 4.1](https://tools.ietf.org/html/rfc7541#section-4.1).
+    pub fn size(&self) -> usize {
+        match self.inner {
+            TableRef::Encoder(table) => table.size(),
+            TableRef::Decoder(table) => table.size(),
+        }
+    }
+
+    /// Returns the maximum size of the table.
+    pub fn max_size(&self) -> usize {
+        match self.inner {
+            TableRef::Encoder(table) => table.max_size(),
+            TableRef::Decoder(table) => table.max_size(),
+        }
+    }
+
+    /// Returns the number of entries evicted from the table so far, either to
+    /// make room for new entries or because the table was resized.
+    pub fn evictions(&self) -> usize {
+        match self.inner {
+            TableRef::Encoder(table) => table.evictions(),
+            TableRef::Decoder(table) => table.evictions(),
+        }
+    }
+
+    /// Returns the entry at the given HPACK index.
+    ///
+    /// Dynamic table entries start at index 62, after the static table.
+    pub fn get(&self, index: usize) -> Option<Entry<'a>> {
+        let idx = index.checked_sub(DYN_OFFSET)?;
+
+        let header = match self.inner {
+            TableRef::Encoder(table) => table.entry(idx)?,
+            TableRef::Decoder(table) => table.entry(idx)?,
+        };
+
+        Some(Entry { header, index })
+    }
+
+    /// Returns an iterator over the entries, from the most recently inserted.
+    pub fn entries(&self) -> Entries<'a> {
+        Entries {
+            table: *self,
+            pos: DYN_OFFSET,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Table<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Table")
+            .field("size", &self.size())
+            .field("max_size", &self.max_size())
+            .field("evictions", &self.evictions())
+            .field("entries", &self.entries())
+            .finish()
+    }
+}
+
+// ===== impl Entry =====
+
+impl<'a> Entry<'a> {
+    /// Returns the HPACK index of the entry.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the entry name, including the leading `:` of pseudo header
+    /// fields.
+    pub fn name(&self) -> &'a str {
+        self.header.name().as_str()
+    }
+
+    /// Returns the entry value.
+    pub fn value(&self) -> &'a [u8] {
+        self.header.value_slice()
+    }
+
+    /// Returns the size of the entry, as accounted for by the table.
+    pub fn size(&self) -> usize {
+        self.header.len()
+    }
+
+    /// Returns the entry as a `Field`.
+    pub fn to_field(&self) -> Field {
+        Field {
+            header: self.header.clone(),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Entry<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Entry")
+            .field("index", &self.index)
+            .field("name", &self.name())
+            .field("value", &String::from_utf8_lossy(self.value()))
+            .finish()
+    }
+}
+
+// ===== impl Entries =====
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Entry<'a>> {
+        let entry = self.table.get(self.pos)?;
+        self.pos += 1;
+        Some(entry)
+    }
+}
+
+impl<'a> fmt::Debug for Entries<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.clone()).finish()
+    }
+}
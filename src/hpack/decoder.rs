@@ -1,4 +1,5 @@
-use super::{huffman, Header};
+use super::table::DYN_OFFSET;
+use super::{huffman, DynamicEntries, Header, HpackCounters};
 use crate::frame;
 
 use bytes::{Buf, Bytes, BytesMut};
@@ -8,7 +9,8 @@ use http::status::{self, StatusCode};
 use string::String;
 
 use std::cmp;
-use std::collections::VecDeque;
+use std::error;
+use std::fmt;
 use std::io::Cursor;
 use std::str::Utf8Error;
 
@@ -20,28 +22,47 @@ pub struct Decoder {
     last_max_update: usize,
     table: Table,
     buffer: BytesMut,
+    stats: HpackCounters,
 }
 
 /// Represents all errors that can be encountered while performing the decoding
 /// of an HPACK header set.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DecoderError {
+    /// The first byte of a field does not start a known representation.
     InvalidRepresentation,
+    /// An integer prefix has an invalid number of bits.
     InvalidIntegerPrefix,
+    /// A field references an index that is not in the static or dynamic
+    /// table.
     InvalidTableIndex,
+    /// A string contains an invalid Huffman code.
     InvalidHuffmanCode,
+    /// A field name or value contains invalid characters.
     InvalidUtf8,
+    /// A header field value contains invalid characters.
+    InvalidHeaderValue,
+    /// The `:status` pseudo header field is not a valid status code.
     InvalidStatusCode,
+    /// A field uses an unknown pseudo header name.
     InvalidPseudoheader,
+    /// A dynamic table size update exceeds the allowed size, or is missing
+    /// or misplaced.
     InvalidMaxDynamicSize,
+    /// An integer does not fit in a `usize`.
     IntegerOverflow,
+    /// The header block ended in the middle of a field.
     NeedMore(NeedMore),
 }
 
+/// Describes where a header block was truncated.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum NeedMore {
+    /// The block ended before a field could be read.
     UnexpectedEndOfStream,
+    /// The block ended in the middle of an integer.
     IntegerUnderflow,
+    /// The block ended in the middle of a string.
     StringUnderflow,
 }
 
@@ -137,10 +158,8 @@ enum Representation {
 }
 
 #[derive(Debug)]
-struct Table {
-    entries: VecDeque<Header>,
-    size: usize,
-    max_size: usize,
+pub struct Table {
+    entries: DynamicEntries,
 }
 
 // ===== impl Decoder =====
@@ -153,11 +172,26 @@ impl Decoder {
             last_max_update: size,
             table: Table::new(size),
             buffer: BytesMut::with_capacity(4096),
+            stats: HpackCounters::default(),
+        }
+    }
+
+    /// Returns the dynamic table
+    #[cfg(feature = "hpack")]
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Returns the compression statistics of the decoded header blocks.
+    pub fn stats(&self) -> HpackCounters {
+        HpackCounters {
+            evictions: self.table.evictions() as u64,
+            table_size: self.table.size(),
+            ..self.stats.clone()
         }
     }
 
     /// Queues a potential size update
-    #[allow(dead_code)]
     pub fn queue_size_update(&mut self, size: usize) {
         let size = match self.max_size_update {
             Some(v) => cmp::max(v, size),
@@ -180,22 +214,47 @@ impl Decoder {
 
         let mut can_resize = true;
 
-        if let Some(size) = self.max_size_update.take() {
-            self.last_max_update = size;
-        }
+        // Set if the table was shrunk, in which case the encoder must
+        // acknowledge the new size before using the table.
+        let mut must_resize = match self.max_size_update {
+            Some(size) => size < self.table.max_size(),
+            None => false,
+        };
 
         log::trace!("decode");
 
         while let Some(ty) = peek_u8(src) {
+            let len = src.get_ref().len();
+
+            // The pending update is only applied by a header block that has
+            // a representation, an empty block leaves it pending.
+            if let Some(size) = self.max_size_update.take() {
+                self.last_max_update = size;
+            }
+
             // At this point we are always at the beginning of the next block
             // within the HPACK data. The type of the block can always be
             // determined from the first byte.
-            match Representation::load(ty)? {
+            let representation = Representation::load(ty)?;
+
+            // > This dynamic table size update MUST occur at the beginning of
+            // > the first header block following the change to the dynamic
+            // > table size.
+            if must_resize {
+                if let SizeUpdate = representation {
+                    must_resize = false;
+                } else {
+                    return Err(DecoderError::InvalidMaxDynamicSize);
+                }
+            }
+
+            match representation {
                 Indexed => {
                     log::trace!("    Indexed; rem={:?}", src.remaining());
                     can_resize = false;
                     let entry = self.decode_indexed(src)?;
                     consume(src);
+                    self.stats.record_field(&entry);
                     f(entry);
                 }
                 LiteralWithIndexing => {
@@ -207,6 +266,7 @@ impl Decoder {
                     self.table.insert(entry.clone());
                     consume(src);
 
+                    self.stats.record_field(&entry);
                     f(entry);
                 }
                 LiteralWithoutIndexing => {
@@ -214,16 +274,21 @@ impl Decoder {
                     can_resize = false;
                     let entry = self.decode_literal(src, false)?;
                     consume(src);
+                    self.stats.record_field(&entry);
                     f(entry);
                 }
                 LiteralNeverIndexed => {
                     log::trace!("    LiteralNeverIndexed; rem={:?}", src.remaining());
                     can_resize = false;
-                    let entry = self.decode_literal(src, false)?;
+                    let mut entry = self.decode_literal(src, false)?;
                     consume(src);
 
-                    // TODO: Track that this should never be indexed
+                    // Intermediaries must forward the field with the same
+                    // representation, which the encoder does for sensitive
+                    // values.
+                    entry.set_sensitive(true);
 
+                    self.stats.record_field(&entry);
                     f(entry);
                 }
                 SizeUpdate => {
@@ -237,6 +302,9 @@ impl Decoder {
                     consume(src);
                 }
             }
+
+            // Every representation has been consumed from `src` once decoded.
+            self.stats.encoded_bytes += (len - src.get_ref().len()) as u64;
         }
 
         Ok(())
@@ -256,13 +324,20 @@ impl Decoder {
         );
 
         self.table.set_max_size(new_size);
+        self.stats.size_updates += 1;
 
         Ok(())
     }
 
-    fn decode_indexed(&self, buf: &mut Cursor<&mut BytesMut>) -> Result<Header, DecoderError> {
+    fn decode_indexed(&mut self, buf: &mut Cursor<&mut BytesMut>) -> Result<Header, DecoderError> {
         let index = decode_int(buf, 7)?;
-        self.table.get(index)
+        let entry = self.table.get(index)?;
+
+        if index >= DYN_OFFSET {
+            self.stats.field_hits += 1;
+        }
+
+        Ok(entry)
     }
 
     fn decode_literal(
@@ -277,22 +352,56 @@ impl Decoder {
 
         // First, read the header name
         if table_idx == 0 {
-            // Read the name as a literal
-            let name = self.decode_string(buf)?;
-            let value = self.decode_string(buf)?;
+            // Read the name as a literal. Nothing is taken from the buffer
+            // until the value is read too, so that a field split across
+            // frames is decoded again from its start.
+            let old_pos = buf.position();
+            let name_marker = self.try_decode_string(buf)?;
+            let value_marker = self.try_decode_string(buf)?;
+            buf.set_position(old_pos);
+
+            let name = name_marker.consume(buf);
+            let value = value_marker.consume(buf);
 
             Header::new(name, value)
-        } else {
-            let e = self.table.get(table_idx)?;
+        } else if table_idx < DYN_OFFSET {
+            let e = get_static(table_idx);
             let value = self.decode_string(buf)?;
 
             e.name().into_entry(value)
+        } else {
+            let value = self.decode_string(buf)?;
+
+            // Borrow the entry, its name is shared with the new header
+            // instead of cloning the whole entry.
+            let e = self
+                .table
+                .entry(table_idx - DYN_OFFSET)
+                .ok_or(DecoderError::InvalidTableIndex)?;
+            let entry = e.name().into_entry(value)?;
+
+            self.stats.name_hits += 1;
+
+            Ok(entry)
         }
     }
 
     fn decode_string(&mut self, buf: &mut Cursor<&mut BytesMut>) -> Result<Bytes, DecoderError> {
+        let old_pos = buf.position();
+        let marker = self.try_decode_string(buf)?;
+        buf.set_position(old_pos);
+        Ok(marker.consume(buf))
+    }
+
+    /// Reads a string without taking it from the buffer.
+    fn try_decode_string(
+        &mut self,
+        buf: &mut Cursor<&mut BytesMut>,
+    ) -> Result<StringMarker, DecoderError> {
         const HUFF_FLAG: u8 = 0b1000_0000;
 
+        let old_pos = buf.position();
+
         // The first bit in the first byte contains the huffman encoded flag.
         let huff = match peek_u8(buf) {
             Some(hdr) => (hdr & HUFF_FLAG) == HUFF_FLAG,
@@ -311,17 +420,46 @@ impl Decoder {
             return Err(DecoderError::NeedMore(NeedMore::StringUnderflow));
         }
 
-        if huff {
-            let ret = {
-                let raw = &buf.bytes()[..len];
-                huffman::decode(raw, &mut self.buffer).map(Into::into)
-            };
+        let offset = (buf.position() - old_pos) as usize;
 
-            buf.advance(len);
-            return ret;
-        }
+        let string = if huff {
+            let raw = &buf.bytes()[..len];
+            Some(huffman::decode(raw, &mut self.buffer)?.freeze())
+        } else {
+            None
+        };
+
+        buf.advance(len);
 
-        Ok(take(buf, len))
+        Ok(StringMarker {
+            offset,
+            len,
+            string,
+        })
+    }
+}
+
+/// A string read from a header block, and where it is in the buffer.
+struct StringMarker {
+    /// Length of the string length prefix
+    offset: usize,
+    len: usize,
+    /// The decoded string, if Huffman encoded
+    string: Option<Bytes>,
+}
+
+impl StringMarker {
+    /// Takes the string from `buf`, positioned at the length prefix.
+    fn consume(self, buf: &mut Cursor<&mut BytesMut>) -> Bytes {
+        buf.advance(self.offset);
+
+        match self.string {
+            Some(string) => {
+                buf.advance(self.len);
+                string
+            }
+            None => take(buf, self.len),
+        }
     }
 }
 
@@ -447,14 +585,38 @@ fn consume(buf: &mut Cursor<&mut BytesMut>) {
 impl Table {
     fn new(max_size: usize) -> Table {
         Table {
-            entries: VecDeque::new(),
-            size: 0,
-            max_size,
+            entries: DynamicEntries::new(max_size),
         }
     }
 
-    fn size(&self) -> usize {
-        self.size
+    #[cfg(feature = "hpack")]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.size()
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.entries.max_size()
+    }
+
+    /// Returns the number of entries evicted since the table was created.
+    pub fn evictions(&self) -> usize {
+        self.entries.evictions()
+    }
+
+    /// Returns the dynamic table entry at `idx`, the most recently inserted
+    /// entry being at 0.
+    pub fn entry(&self, idx: usize) -> Option<&Header> {
+        let len = self.entries.len();
+
+        if idx >= len {
+            return None;
+        }
+
+        self.entries.get(len - idx - 1)
     }
 
     /// Returns the entry located at the given index.
@@ -470,68 +632,50 @@ impl Table {
             return Err(DecoderError::InvalidTableIndex);
         }
 
-        if index <= 61 {
+        if index < DYN_OFFSET {
             return Ok(get_static(index));
         }
 
         // Convert the index for lookup in the entries structure.
-        match self.entries.get(index - 62) {
+        match self.entry(index - DYN_OFFSET) {
             Some(e) => Ok(e.clone()),
             None => Err(DecoderError::InvalidTableIndex),
         }
     }
 
     fn insert(&mut self, entry: Header) {
-        let len = entry.len();
-
-        self.reserve(len);
-
-        if self.size + len <= self.max_size {
-            self.size += len;
-
-            // Track the entry
-            self.entries.push_front(entry);
-        }
+        self.entries.insert(entry);
     }
 
     fn set_max_size(&mut self, size: usize) {
-        self.max_size = size;
         // Make the table size fit within the new constraints.
-        self.consolidate();
-    }
-
-    fn reserve(&mut self, size: usize) {
-        while self.size + size > self.max_size {
-            match self.entries.pop_back() {
-                Some(last) => {
-                    self.size -= last.len();
-                }
-                None => return,
-            }
-        }
+        self.entries.set_max_size(size);
     }
+}
 
-    fn consolidate(&mut self) {
-        while self.size > self.max_size {
-            {
-                let last = match self.entries.back() {
-                    Some(x) => x,
-                    None => {
-                        // Can never happen as the size of the table must reach
-                        // 0 by the time we've exhausted all elements.
-                        panic!("Size of table != 0, but no headers left!");
-                    }
-                };
+// ===== impl DecoderError =====
 
-                self.size -= last.len();
-            }
+impl fmt::Display for DecoderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            DecoderError::InvalidRepresentation => "invalid representation",
+            DecoderError::InvalidIntegerPrefix => "invalid integer prefix",
+            DecoderError::InvalidTableIndex => "invalid table index",
+            DecoderError::InvalidHuffmanCode => "invalid huffman code",
+            DecoderError::InvalidUtf8 => "invalid field name or value",
+            DecoderError::InvalidHeaderValue => "invalid header value",
+            DecoderError::InvalidStatusCode => "invalid status code",
+            DecoderError::InvalidPseudoheader => "invalid pseudo header",
+            DecoderError::InvalidMaxDynamicSize => "invalid dynamic table size update",
+            DecoderError::IntegerOverflow => "integer overflow",
+            DecoderError::NeedMore(_) => "unexpected end of header block",
+        };
 
-            self.entries.pop_back();
-        }
+        fmt.write_str(description)
     }
 }
 
-// ===== impl DecoderError =====
+impl error::Error for DecoderError {}
 
 impl From<Utf8Error> for DecoderError {
     fn from(_: Utf8Error) -> DecoderError {
@@ -542,8 +686,13 @@ impl From<Utf8Error> for DecoderError {
 
 impl From<header::InvalidHeaderValue> for DecoderError {
     fn from(_: header::InvalidHeaderValue) -> DecoderError {
-        // TODO: Better error?
-        DecoderError::InvalidUtf8
+        DecoderError::InvalidHeaderValue
+    }
+}
+
+impl From<header::InvalidHeaderValueBytes> for DecoderError {
+    fn from(_: header::InvalidHeaderValueBytes) -> DecoderError {
+        DecoderError::InvalidHeaderValue
     }
 }
 
@@ -852,6 +1001,121 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_decode_requires_size_update_after_shrink() {
+        let mut de = Decoder::new(4096);
+        de.queue_size_update(0);
+
+        // `:method: GET`, without the required size update.
+        let mut buf: BytesMut = vec![0x80 | 2].into();
+        let err = de.decode(&mut Cursor::new(&mut buf), |_| {}).unwrap_err();
+        assert_eq!(err, DecoderError::InvalidMaxDynamicSize);
+
+        de.queue_size_update(0);
+
+        // A size update to 0 followed by `:method: GET`.
+        let mut buf: BytesMut = vec![0b0010_0000, 0x80 | 2].into();
+        let mut res = vec![];
+        de.decode(&mut Cursor::new(&mut buf), |h| res.push(h))
+            .unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(de.table.max_size(), 0);
+    }
+
+    #[test]
+    fn test_decode_empty_block_keeps_size_update_pending() {
+        let mut de = Decoder::new(4096);
+        de.queue_size_update(0);
+
+        let mut buf = BytesMut::new();
+        de.decode(&mut Cursor::new(&mut buf), |_| {}).unwrap();
+
+        // `:method: GET`, the size update is still required.
+        let mut buf: BytesMut = vec![0x80 | 2].into();
+        let err = de.decode(&mut Cursor::new(&mut buf), |_| {}).unwrap_err();
+        assert_eq!(err, DecoderError::InvalidMaxDynamicSize);
+    }
+
+    #[test]
+    fn test_decode_literal_shares_buffer() {
+        let mut de = Decoder::new(4096);
+        let value = "x".repeat(40);
+
+        // `x-large: xxx...`, with incremental indexing and a raw value,
+        // followed by a literal naming the new dynamic table entry.
+        let mut buf = vec![0b0100_0000, 7];
+        buf.extend(b"x-large");
+        buf.push(40);
+        buf.extend(value.as_bytes());
+        buf.extend(&[0b0000_1111, (DYN_OFFSET - 15) as u8, 40]);
+        buf.extend(value.as_bytes());
+
+        let mut buf: BytesMut = buf.into();
+        let range = buf.as_ptr() as usize..buf.as_ptr() as usize + buf.len();
+
+        let mut res = vec![];
+        de.decode(&mut Cursor::new(&mut buf), |h| res.push(h))
+            .unwrap();
+
+        assert_eq!(res.len(), 2);
+
+        for header in &res {
+            match *header {
+                Header::Field {
+                    ref name,
+                    ref value,
+                } => {
+                    assert_eq!(name, "x-large");
+                    assert!(range.contains(&(value.as_bytes().as_ptr() as usize)));
+                }
+                _ => panic!(),
+            }
+        }
+
+        assert_eq!(de.stats().name_hits, 1);
+    }
+
+    #[test]
+    fn test_decode_invalid_header_value() {
+        let mut de = Decoder::new(0);
+
+        // `foo: a\nb`, without indexing and with a raw name.
+        let mut buf: BytesMut = vec![0, 3, b'f', b'o', b'o', 3, b'a', b'\n', b'b'].into();
+        let err = de.decode(&mut Cursor::new(&mut buf), |_| {}).unwrap_err();
+        assert_eq!(err, DecoderError::InvalidHeaderValue);
+    }
+
+    #[test]
+    fn test_decode_literal_split_in_value() {
+        let mut de = Decoder::new(0);
+
+        // `foo: bar`, without indexing and with a raw name, cut in the value.
+        let mut buf: BytesMut = vec![0, 3, b'f', b'o', b'o', 3, b'b'].into();
+        let err = de.decode(&mut Cursor::new(&mut buf), |_| {}).unwrap_err();
+        assert_eq!(err, DecoderError::NeedMore(NeedMore::StringUnderflow));
+
+        // The whole field is decoded again once the rest is received.
+        buf.extend_from_slice(b"ar");
+
+        let mut res = vec![];
+        de.decode(&mut Cursor::new(&mut buf), |h| res.push(h))
+            .unwrap();
+
+        assert_eq!(res.len(), 1);
+
+        match res[0] {
+            Header::Field {
+                ref name,
+                ref value,
+            } => {
+                assert_eq!(name, "foo");
+                assert_eq!(value, "bar");
+            }
+            _ => panic!(),
+        }
+    }
+
     fn huff_encode(src: &[u8]) -> BytesMut {
         let mut buf = BytesMut::new();
         huffman::encode(src, &mut buf).unwrap();
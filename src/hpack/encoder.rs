@@ -1,13 +1,20 @@
 use super::table::{Index, Table};
-use super::{huffman, Header};
+use super::{
+    huffman, DefaultIndexingPolicy, Header, HpackCounters, Huffman, Indexing, IndexingPolicy,
+};
 
 use bytes::{BufMut, BytesMut};
 use http::header::{HeaderName, HeaderValue};
 
+use std::sync::Arc;
+
 #[derive(Debug)]
 pub struct Encoder {
     table: Table,
     size_update: Option<SizeUpdate>,
+    policy: Arc<dyn IndexingPolicy>,
+    huffman: Huffman,
+    stats: HpackCounters,
 }
 
 #[derive(Debug)]
@@ -20,6 +27,7 @@ pub enum Encode {
 pub struct EncodeState {
     index: Index,
     value: Option<HeaderValue>,
+    never_index: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -38,13 +46,41 @@ impl Encoder {
         Encoder {
             table: Table::new(max_size, capacity),
             size_update: None,
+            policy: Arc::new(DefaultIndexingPolicy),
+            huffman: Huffman::default(),
+            stats: HpackCounters::default(),
         }
     }
 
+    /// Returns the dynamic table
+    #[cfg(any(test, feature = "hpack"))]
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Returns the compression statistics of the encoded header blocks.
+    pub fn stats(&self) -> HpackCounters {
+        HpackCounters {
+            evictions: self.table.evictions() as u64,
+            table_size: self.table.size(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Sets the policy deciding which headers may be added to the dynamic
+    /// table.
+    pub fn set_indexing_policy(&mut self, policy: Arc<dyn IndexingPolicy>) {
+        self.policy = policy;
+    }
+
+    /// Sets when string literals are Huffman encoded.
+    pub fn set_huffman(&mut self, huffman: Huffman) {
+        self.huffman = huffman;
+    }
+
     /// Queues a max size update.
     ///
     /// The next call to `encode` will include a dynamic size update frame.
-    #[allow(dead_code)]
     pub fn update_max_size(&mut self, val: usize) {
         match self.size_update {
             Some(SizeUpdate::One(old)) => {
@@ -85,11 +121,11 @@ impl Encoder {
     where
         I: Iterator<Item = Header<Option<HeaderName>>>,
     {
-        let len = dst.len();
+        let start = dst.len();
 
         if let Err(e) = self.encode_size_updates(dst) {
             if e == EncoderError::BufferOverflow {
-                dst.truncate(len);
+                dst.truncate(start);
             }
 
             unreachable!("encode_size_updates errored");
@@ -101,12 +137,15 @@ impl Encoder {
             let len = dst.len();
 
             let res = match resume.value {
-                Some(ref value) => self.encode_header_without_name(&resume.index, value, dst),
-                None => self.encode_header(&resume.index, dst),
+                Some(ref value) => {
+                    self.encode_header_without_name(&resume.index, value, resume.never_index, dst)
+                }
+                None => self.encode_header(&resume.index, resume.never_index, dst),
             };
 
             if res.is_err() {
                 dst.truncate(len);
+                self.stats.encoded_bytes += (dst.len() - start) as u64;
                 return Encode::Partial(resume);
             }
             last_index = Some(resume.index);
@@ -119,12 +158,19 @@ impl Encoder {
                 // The header has an associated name. In which case, try to
                 // index it in the table.
                 Ok(header) => {
-                    let index = self.table.index(header);
-                    let res = self.encode_header(&index, dst);
+                    let indexing = self.indexing(&header);
+                    let never_index = indexing == Indexing::NeverIndexed;
+                    let index = self.table.index(header, indexing);
+                    let res = self.encode_header(&index, never_index, dst);
 
                     if res.is_err() {
                         dst.truncate(len);
-                        return Encode::Partial(EncodeState { index, value: None });
+                        self.stats.encoded_bytes += (dst.len() - start) as u64;
+                        return Encode::Partial(EncodeState {
+                            index,
+                            value: None,
+                            never_index,
+                        });
                     }
 
                     last_index = Some(index);
@@ -134,39 +180,77 @@ impl Encoder {
                 // which case, we skip table lookup and just use the same index
                 // as the previous entry.
                 Err(value) => {
-                    let res = self.encode_header_without_name(
-                        last_index.as_ref().unwrap_or_else(|| {
-                            panic!("encoding header without name, but no previous index to use for name");
-                        }),
-                        &value,
-                        dst,
-                    );
+                    let last = last_index.as_ref().unwrap_or_else(|| {
+                        panic!(
+                            "encoding header without name, but no previous index to use for name"
+                        );
+                    });
+                    let never_index = self.never_index_value(last, &value);
+                    let res = self.encode_header_without_name(last, &value, never_index, dst);
 
                     if res.is_err() {
                         dst.truncate(len);
+                        self.stats.encoded_bytes += (dst.len() - start) as u64;
                         return Encode::Partial(EncodeState {
                             index: last_index.unwrap(), // checked just above
                             value: Some(value),
+                            never_index,
                         });
                     }
                 }
             };
         }
 
+        self.stats.encoded_bytes += (dst.len() - start) as u64;
+
         Encode::Full
     }
 
+    /// Returns an upper bound of the number of octets needed to encode the
+    /// header that `state` resumes from.
+    pub fn max_encoded_len(&self, state: &EncodeState) -> usize {
+        let header = self.table.resolve(&state.index);
+        let value_len = match state.value {
+            Some(ref value) => value.len(),
+            None => header.value_slice().len(),
+        };
+
+        // Each of the index and the two string lengths takes at most 10
+        // octets, and no Huffman code is longer than 30 bits.
+        3 * 10 + 4 * (header.name().as_slice().len() + value_len)
+    }
+
+    fn indexing(&self, header: &Header) -> Indexing {
+        if header.is_sensitive() {
+            return Indexing::NeverIndexed;
+        }
+
+        self.policy
+            .indexing(header.name().as_str(), header.value_slice())
+    }
+
+    fn never_index_value(&self, last: &Index, value: &HeaderValue) -> bool {
+        if value.is_sensitive() {
+            return true;
+        }
+
+        let name = self.table.resolve(last).name();
+        self.policy.indexing(name.as_str(), value.as_ref()) == Indexing::NeverIndexed
+    }
+
     fn encode_size_updates(&mut self, dst: &mut BytesMut) -> Result<(), EncoderError> {
         match self.size_update.take() {
             Some(SizeUpdate::One(val)) => {
                 self.table.resize(val);
                 encode_size_update(val, dst)?;
+                self.stats.size_updates += 1;
             }
             Some(SizeUpdate::Two(min, max)) => {
                 self.table.resize(min);
                 self.table.resize(max);
                 encode_size_update(min, dst)?;
                 encode_size_update(max, dst)?;
+                self.stats.size_updates += 2;
             }
             None => {}
         }
@@ -174,7 +258,12 @@ impl Encoder {
         Ok(())
     }
 
-    fn encode_header(&mut self, index: &Index, dst: &mut BytesMut) -> Result<(), EncoderError> {
+    fn encode_header(
+        &mut self,
+        index: &Index,
+        never_index: bool,
+        dst: &mut BytesMut,
+    ) -> Result<(), EncoderError> {
         match *index {
             Index::Indexed(idx, _) => {
                 encode_int(idx, 7, 0x80, dst)?;
@@ -182,7 +271,7 @@ impl Encoder {
             Index::Name(idx, _) => {
                 let header = self.table.resolve(&index);
 
-                encode_not_indexed(idx, header.value_slice(), header.is_sensitive(), dst)?;
+                encode_not_indexed(idx, header.value_slice(), never_index, self.huffman, dst)?;
             }
             Index::Inserted(_) => {
                 let header = self.table.resolve(&index);
@@ -195,8 +284,8 @@ impl Encoder {
 
                 dst.put_u8(0b0100_0000);
 
-                encode_str(header.name().as_slice(), dst)?;
-                encode_str(header.value_slice(), dst)?;
+                encode_str(header.name().as_slice(), self.huffman, dst)?;
+                encode_str(header.value_slice(), self.huffman, dst)?;
             }
             Index::InsertedValue(idx, _) => {
                 let header = self.table.resolve(&index);
@@ -204,7 +293,7 @@ impl Encoder {
                 assert!(!header.is_sensitive());
 
                 encode_int(idx, 6, 0b0100_0000, dst)?;
-                encode_str(header.value_slice(), dst)?;
+                encode_str(header.value_slice(), self.huffman, dst)?;
             }
             Index::NotIndexed(_) => {
                 let header = self.table.resolve(&index);
@@ -212,12 +301,16 @@ impl Encoder {
                 encode_not_indexed2(
                     header.name().as_slice(),
                     header.value_slice(),
-                    header.is_sensitive(),
+                    never_index,
+                    self.huffman,
                     dst,
                 )?;
             }
         }
 
+        self.stats.record_field(self.table.resolve(index));
+        self.table.record_hit(index, &mut self.stats);
+
         Ok(())
     }
 
@@ -225,6 +318,7 @@ impl Encoder {
         &mut self,
         last: &Index,
         value: &HeaderValue,
+        never_index: bool,
         dst: &mut BytesMut,
     ) -> Result<(), EncoderError> {
         match *last {
@@ -234,7 +328,7 @@ impl Encoder {
             | Index::InsertedValue(..) => {
                 let idx = self.table.resolve_idx(last);
 
-                encode_not_indexed(idx, value.as_ref(), value.is_sensitive(), dst)?;
+                encode_not_indexed(idx, value.as_ref(), never_index, self.huffman, dst)?;
             }
             Index::NotIndexed(_) => {
                 let last = self.table.resolve(last);
@@ -242,12 +336,17 @@ impl Encoder {
                 encode_not_indexed2(
                     last.name().as_slice(),
                     value.as_ref(),
-                    value.is_sensitive(),
+                    never_index,
+                    self.huffman,
                     dst,
                 )?;
             }
         }
 
+        let name = self.table.resolve(last).name();
+        self.stats.header_bytes += (name.as_slice().len() + value.len()) as u64;
+        self.table.record_name_hit(last, &mut self.stats);
+
         Ok(())
     }
 }
@@ -266,6 +365,7 @@ fn encode_not_indexed(
     name: usize,
     value: &[u8],
     sensitive: bool,
+    huffman: Huffman,
     dst: &mut BytesMut,
 ) -> Result<(), EncoderError> {
     if sensitive {
@@ -274,7 +374,7 @@ fn encode_not_indexed(
         encode_int(name, 4, 0, dst)?;
     }
 
-    encode_str(value, dst)?;
+    encode_str(value, huffman, dst)?;
     Ok(())
 }
 
@@ -282,6 +382,7 @@ fn encode_not_indexed2(
     name: &[u8],
     value: &[u8],
     sensitive: bool,
+    huffman: Huffman,
     dst: &mut BytesMut,
 ) -> Result<(), EncoderError> {
     if !dst.has_remaining_mut() {
@@ -294,60 +395,41 @@ fn encode_not_indexed2(
         dst.put_u8(0);
     }
 
-    encode_str(name, dst)?;
-    encode_str(value, dst)?;
+    encode_str(name, huffman, dst)?;
+    encode_str(value, huffman, dst)?;
     Ok(())
 }
 
-fn encode_str(val: &[u8], dst: &mut BytesMut) -> Result<(), EncoderError> {
-    use std::io::Cursor;
-
+fn encode_str(val: &[u8], huffman: Huffman, dst: &mut BytesMut) -> Result<(), EncoderError> {
     if !dst.has_remaining_mut() {
         return Err(EncoderError::BufferOverflow);
     }
 
     if !val.is_empty() {
-        let idx = dst.len();
+        let huff_len = huffman::encoded_len(val);
 
-        // Push a placeholder byte for the length header
-        dst.put_u8(0);
-
-        // Encode with huffman
-        huffman::encode(val, dst)?;
-
-        let huff_len = dst.len() - (idx + 1);
-
-        if encode_int_one_byte(huff_len, 7) {
-            // Write the string head
-            dst[idx] = 0x80 | huff_len as u8;
-        } else {
-            // Write the head to a placeholer
-            let mut buf = [0; 8];
+        let use_huffman = match huffman {
+            Huffman::WhenShorter => huff_len <= val.len(),
+            Huffman::Always => true,
+            Huffman::Never => false,
+        };
 
-            let head_len = {
-                let mut head_dst = Cursor::new(&mut buf);
-                encode_int(huff_len, 7, 0x80, &mut head_dst)?;
-                head_dst.position() as usize
-            };
+        if use_huffman {
+            encode_int(huff_len, 7, 0x80, dst)?;
 
-            if dst.remaining_mut() < head_len {
+            if dst.remaining_mut() < huff_len {
                 return Err(EncoderError::BufferOverflow);
             }
 
-            // This is just done to reserve space in the destination
-            dst.put_slice(&buf[1..head_len]);
+            huffman::encode(val, dst)?;
+        } else {
+            encode_int(val.len(), 7, 0, dst)?;
 
-            // Shift the header forward
-            for i in 0..huff_len {
-                let src_i = idx + 1 + (huff_len - (i + 1));
-                let dst_i = idx + head_len + (huff_len - (i + 1));
-                dst[dst_i] = dst[src_i];
+            if dst.remaining_mut() < val.len() {
+                return Err(EncoderError::BufferOverflow);
             }
 
-            // Copy in the head
-            for i in 0..head_len {
-                dst[idx + i] = buf[i];
-            }
+            dst.put_slice(val);
         }
     } else {
         // Write an empty string
@@ -836,6 +918,190 @@ mod test {
         assert_eq!("sup", huff_decode(&dst[9..]));
     }
 
+    #[test]
+    fn test_max_encoded_len_fits_resumed_header() {
+        let mut encoder = Encoder::default();
+        encoder.set_huffman(Huffman::Always);
+
+        // A backslash takes 19 bits once Huffman encoded.
+        let value = "\\".repeat(1_000);
+        let mut input = vec![header("x-large", &value)].into_iter();
+        let mut dst = BytesMut::with_capacity(64);
+
+        let resume = match encoder.encode(None, &mut input, &mut dst) {
+            Encode::Partial(r) => r,
+            _ => panic!(),
+        };
+
+        assert!(dst.is_empty());
+
+        let max = encoder.max_encoded_len(&resume);
+        dst.reserve(max);
+
+        match encoder.encode(Some(resume), &mut input, &mut dst) {
+            Encode::Full => {}
+            unexpected => panic!("resume returned unexpected: {:?}", unexpected),
+        }
+
+        assert!(dst.len() > value.len());
+        assert!(dst.len() <= max);
+    }
+
+    #[test]
+    fn test_indexing_policy_not_indexed() {
+        let mut encoder = Encoder::default();
+        encoder.set_indexing_policy(Arc::new(TestPolicy));
+
+        for _ in 0..2 {
+            let res = encode(&mut encoder, vec![header("x-trace-id", "abc")]);
+
+            assert_eq!(&[0, 0x80 | 7], &res[..2]);
+            assert_eq!("x-trace-id", huff_decode(&res[2..9]));
+            assert_eq!(0x80 | 2, res[9]);
+            assert_eq!("abc", huff_decode(&res[10..]));
+        }
+
+        assert_eq!(0, encoder.table.len());
+    }
+
+    #[test]
+    fn test_indexing_policy_never_indexed() {
+        let mut encoder = Encoder::default();
+        encoder.set_indexing_policy(Arc::new(TestPolicy));
+
+        let value = bytes::Bytes::from_static(b"abc");
+        let authority = Header::Authority(string::TryFrom::try_from(value).unwrap());
+        let res = encode(&mut encoder, vec![authority]);
+
+        assert_eq!(&[0b10001, 0x80 | 2], &res[..2]);
+        assert_eq!("abc", huff_decode(&res[2..]));
+
+        // Repeated values of a never indexed field are never indexed too.
+        let res = encode(
+            &mut encoder,
+            vec![
+                header("x-api-key", "abc"),
+                Header::Field {
+                    name: None,
+                    value: HeaderValue::from_static("def"),
+                },
+            ],
+        );
+
+        assert_eq!(&[0b10000, 0x80 | 7], &res[..2]);
+        assert_eq!("x-api-key", huff_decode(&res[2..9]));
+        assert_eq!(0x80 | 2, res[9]);
+        assert_eq!("abc", huff_decode(&res[10..12]));
+        assert_eq!(&[0b10000, 0x80 | 7], &res[12..14]);
+        assert_eq!(0x80 | 3, res[21]);
+        assert_eq!("def", huff_decode(&res[22..]));
+
+        assert_eq!(0, encoder.table.len());
+    }
+
+    #[derive(Debug)]
+    struct TestPolicy;
+
+    impl IndexingPolicy for TestPolicy {
+        fn indexing(&self, name: &str, _: &[u8]) -> Indexing {
+            match name {
+                ":authority" | "x-api-key" => Indexing::NeverIndexed,
+                "x-trace-id" => Indexing::NotIndexed,
+                _ => Indexing::Indexed,
+            }
+        }
+    }
+
+    #[test]
+    fn test_huffman_only_when_shorter() {
+        let mut encoder = Encoder::new(0, 0);
+
+        // Huffman encoding "custom-value" takes 9 octets instead of 12.
+        let res = encode(&mut encoder, vec![header("custom-key", "custom-value")]);
+
+        assert_eq!(&[0, 0x80 | 8], &res[..2]);
+        assert_eq!("custom-key", huff_decode(&res[2..10]));
+        assert_eq!(0x80 | 9, res[10]);
+        assert_eq!("custom-value", huff_decode(&res[11..]));
+
+        // Symbols and upper case letters have long codes, "J#Q~|X^Z" would
+        // take 10 octets.
+        let res = encode(&mut encoder, vec![header("x-token", "J#Q~|X^Z")]);
+
+        assert_eq!(0x80 | 6, res[1]);
+        assert_eq!(8, res[8]);
+        assert_eq!(b"J#Q~|X^Z", &res[9..]);
+
+        // Huffman encoding is kept when the lengths are equal.
+        let res = encode(&mut encoder, vec![header("x-token", "QV%/+Z=")]);
+
+        assert_eq!(0x80 | 7, res[8]);
+        assert_eq!("QV%/+Z=", huff_decode(&res[9..]));
+    }
+
+    #[test]
+    fn test_always_huffman() {
+        let mut encoder = Encoder::new(0, 0);
+        encoder.set_huffman(Huffman::Always);
+
+        let res = encode(&mut encoder, vec![header("x-token", "J#Q~|X^Z")]);
+
+        assert_eq!(0x80 | 6, res[1]);
+        assert_eq!(0x80 | 10, res[8]);
+        assert_eq!("J#Q~|X^Z", huff_decode(&res[9..]));
+    }
+
+    #[test]
+    fn test_never_huffman() {
+        let mut encoder = Encoder::new(0, 0);
+        encoder.set_huffman(Huffman::Never);
+
+        let res = encode(&mut encoder, vec![header("custom-key", "custom-value")]);
+
+        assert_eq!(&[0, 10], &res[..2]);
+        assert_eq!(b"custom-key", &res[2..12]);
+        assert_eq!(12, res[12]);
+        assert_eq!(b"custom-value", &res[13..]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut encoder = Encoder::default();
+        encoder.update_max_size(1024);
+
+        let mut len = 0;
+
+        for value in &["custom-value", "custom-value", "other"] {
+            len += encode(&mut encoder, vec![header("custom-key", value)]).len();
+        }
+
+        let stats = encoder.stats();
+        assert_eq!(stats.header_bytes, 22 + 22 + 15);
+        assert_eq!(stats.encoded_bytes, len as u64);
+        assert_eq!(stats.field_hits, 1);
+        assert_eq!(stats.name_hits, 1);
+        assert_eq!(stats.size_updates, 1);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.table_size, 54 + 47);
+
+        // Repeating the name of the previous field
+        encode(
+            &mut encoder,
+            vec![
+                header("custom-key", "other"),
+                Header::Field {
+                    name: None,
+                    value: HeaderValue::from_static("third"),
+                },
+            ],
+        );
+
+        let stats = encoder.stats();
+        assert_eq!(stats.header_bytes, 22 + 22 + 15 + 15 + 15);
+        assert_eq!(stats.field_hits, 2);
+        assert_eq!(stats.name_hits, 2);
+    }
+
     #[test]
     #[ignore]
     fn test_evicted_overflow() {
@@ -84,7 +84,7 @@ impl Header {
         } else {
             // HTTP/2 requires lower case header names
             let name = HeaderName::from_lowercase(&name)?;
-            let value = HeaderValue::from_bytes(&value)?;
+            let value = HeaderValue::from_shared(value)?;
 
             Ok(Header::Field { name, value })
         }
@@ -162,29 +162,15 @@ impl Header {
     pub fn is_sensitive(&self) -> bool {
         match *self {
             Header::Field { ref value, .. } => value.is_sensitive(),
-            // TODO: Technically these other header values can be sensitive too.
+            // Pseudo header values carry no sensitive flag. They can still be
+            // sent as never indexed through the `IndexingPolicy`.
             _ => false,
         }
     }
 
-    pub fn skip_value_index(&self) -> bool {
-        use http::header;
-
-        match *self {
-            Header::Field { ref name, .. } => match *name {
-                header::AGE
-                | header::AUTHORIZATION
-                | header::CONTENT_LENGTH
-                | header::ETAG
-                | header::IF_MODIFIED_SINCE
-                | header::IF_NONE_MATCH
-                | header::LOCATION
-                | header::COOKIE
-                | header::SET_COOKIE => true,
-                _ => false,
-            },
-            Header::Path(..) => true,
-            _ => false,
+    pub fn set_sensitive(&mut self, val: bool) {
+        if let Header::Field { ref mut value, .. } = *self {
+            value.set_sensitive(val);
         }
     }
 }
@@ -211,7 +197,7 @@ impl<'a> Name<'a> {
         match self {
             Name::Field(name) => Ok(Header::Field {
                 name: name.clone(),
-                value: HeaderValue::from_bytes(&*value)?,
+                value: HeaderValue::from_shared(value)?,
             }),
             Name::Authority => Ok(Header::Authority(String::try_from(value)?)),
             Name::Method => Ok(Header::Method(Method::from_bytes(&*value)?)),
@@ -228,13 +214,17 @@ impl<'a> Name<'a> {
     }
 
     pub fn as_slice(&self) -> &[u8] {
+        self.as_str().as_bytes()
+    }
+
+    pub fn as_str(&self) -> &'a str {
         match *self {
-            Name::Field(ref name) => name.as_ref(),
-            Name::Authority => b":authority",
-            Name::Method => b":method",
-            Name::Scheme => b":scheme",
-            Name::Path => b":path",
-            Name::Status => b":status",
+            Name::Field(ref name) => name.as_str(),
+            Name::Authority => ":authority",
+            Name::Method => ":method",
+            Name::Scheme => ":scheme",
+            Name::Path => ":path",
+            Name::Status => ":status",
         }
     }
 }
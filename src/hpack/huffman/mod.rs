@@ -1,7 +1,8 @@
 mod table;
 
 use self::table::{DECODE_TABLE, ENCODE_TABLE};
-use crate::hpack::{DecoderError, EncoderError};
+use crate::hpack::encoder::EncoderError;
+use crate::hpack::DecoderError;
 
 use bytes::{BufMut, BytesMut};
 
@@ -16,6 +17,7 @@ struct Decoder {
 const MAYBE_EOS: u8 = 1;
 const DECODED: u8 = 2;
 const ERROR: u8 = 4;
+const DECODED_TWO: u8 = 8;
 
 pub fn decode(src: &[u8], buf: &mut BytesMut) -> Result<BytesMut, DecoderError> {
     let mut decoder = Decoder::new();
@@ -23,14 +25,8 @@ pub fn decode(src: &[u8], buf: &mut BytesMut) -> Result<BytesMut, DecoderError>
     // Max compression ratio is >= 0.5
     buf.reserve(src.len() << 1);
 
-    for b in src {
-        if let Some(b) = decoder.decode4(b >> 4)? {
-            buf.put_u8(b);
-        }
-
-        if let Some(b) = decoder.decode4(b & 0xf)? {
-            buf.put_u8(b);
-        }
+    for &b in src {
+        decoder.decode8(b, buf)?;
     }
 
     if !decoder.is_final() {
@@ -40,6 +36,13 @@ pub fn decode(src: &[u8], buf: &mut BytesMut) -> Result<BytesMut, DecoderError>
     Ok(buf.take())
 }
 
+/// Returns the number of octets `src` takes once Huffman encoded.
+pub fn encoded_len(src: &[u8]) -> usize {
+    let bits: usize = src.iter().map(|&b| ENCODE_TABLE[b as usize].0).sum();
+
+    (bits + 7) / 8
+}
+
 // TODO: return error when there is not enough room to encode the value
 pub fn encode<B: BufMut>(src: &[u8], dst: &mut B) -> Result<(), EncoderError> {
     let mut bits: u64 = 0;
@@ -86,26 +89,28 @@ impl Decoder {
         }
     }
 
-    // Decodes 4 bits
-    fn decode4(&mut self, input: u8) -> Result<Option<u8>, DecoderError> {
-        // (next-state, byte, flags)
-        let (next, byte, flags) = DECODE_TABLE[self.state][input as usize];
+    // Decodes 8 bits, pushing up to two decoded bytes to `buf`
+    fn decode8(&mut self, input: u8, buf: &mut BytesMut) -> Result<(), DecoderError> {
+        // (next-state, byte, second byte, flags)
+        let (next, byte, second, flags) = DECODE_TABLE[self.state][input as usize];
 
         if flags & ERROR == ERROR {
             // Data followed the EOS marker
             return Err(DecoderError::InvalidHuffmanCode);
         }
 
-        let mut ret = None;
-
         if flags & DECODED == DECODED {
-            ret = Some(byte);
+            buf.put_u8(byte);
+        }
+
+        if flags & DECODED_TWO == DECODED_TWO {
+            buf.put_u8(second);
         }
 
-        self.state = next;
+        self.state = next as usize;
         self.maybe_eos = flags & MAYBE_EOS == MAYBE_EOS;
 
-        Ok(ret)
+        Ok(())
     }
 
     fn is_final(&self) -> bool {
@@ -158,6 +163,17 @@ mod test {
         assert_eq!(&dst[..], &[(0x21 << 2) + 3]);
     }
 
+    #[test]
+    fn encoded_len() {
+        for s in &[&b""[..], b"o", b"custom-value", b"\0\x01\xFF", b"J#Q~|X^Z"] {
+            let mut dst = Vec::new();
+
+            encode(s, &mut dst).unwrap();
+
+            assert_eq!(super::encoded_len(s), dst.len());
+        }
+    }
+
     #[test]
     fn encode_decode_str() {
         const DATA: &'static [&'static str] = &[
@@ -210,4 +226,29 @@ mod test {
             assert_eq!(&decoded[..], &s[..]);
         }
     }
+
+    #[test]
+    fn encode_decode_all_pairs() {
+        // Covers octets decoding two bytes at once, and codes straddling
+        // octet boundaries at every offset.
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                let mut dst = Vec::new();
+
+                encode(&[a, b, a], &mut dst).unwrap();
+
+                let decoded = decode(&dst).unwrap();
+
+                assert_eq!(&decoded[..], &[a, b, a]);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_rejects_eos() {
+        // A complete EOS code is an error, even when padded.
+        assert!(decode(&[0xff, 0xff, 0xff, 0xff]).is_err());
+        // Padding longer than 7 bits is an error.
+        assert!(decode(&[0x07, 0xff]).is_err());
+    }
 }
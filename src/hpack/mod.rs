@@ -1,12 +1,65 @@
-mod decoder;
-mod encoder;
+//! HPACK header compression.
+//!
+//! This module exposes the [HPACK] implementation used by the HTTP/2.0
+//! connections of this crate, for tools that need to encode or decode header
+//! blocks on their own, such as traffic analyzers or proxies.
+//!
+//! An [`Encoder`] turns a list of [`Field`]s into a header block, and a
+//! [`Decoder`] turns a header block back into fields. Each keeps a dynamic
+//! table which can be inspected with their `table` method, and which is
+//! shared by all header blocks they process, so a given header block must be
+//! decoded by the decoder paired with the encoder that produced it.
+//!
+//! # Examples
+//!
+//! ```
+//! use bytes::BytesMut;
+//! use h2::hpack::{Decoder, Encoder, Field};
+//! use http::header::{HeaderName, HeaderValue};
+//!
+//! let mut encoder = Encoder::new();
+//! let mut decoder = Decoder::new();
+//!
+//! let fields = vec![
+//!     Field::from_bytes(b":method", b"GET").unwrap(),
+//!     Field::new(
+//!         HeaderName::from_static("accept"),
+//!         HeaderValue::from_static("text/html"),
+//!     ),
+//! ];
+//!
+//! let mut block = BytesMut::new();
+//! encoder.encode(fields.clone(), &mut block);
+//!
+//! assert_eq!(decoder.decode(&block).unwrap(), fields);
+//! assert_eq!(decoder.table().len(), 1);
+//! ```
+//!
+//! This module is only available with the `hpack` cargo feature.
+//!
+//! [HPACK]: https://tools.ietf.org/html/rfc7541
+//! [`Encoder`]: struct.Encoder.html
+//! [`Decoder`]: struct.Decoder.html
+//! [`Field`]: struct.Field.html
+
+#[cfg(feature = "hpack")]
+mod api;
+pub(crate) mod decoder;
+pub(crate) mod encoder;
 pub(crate) mod header;
-mod huffman;
+pub(crate) mod huffman;
+mod policy;
+mod stats;
 mod table;
 
 #[cfg(test)]
 mod test;
 
-pub use self::decoder::{Decoder, DecoderError, NeedMore};
-pub use self::encoder::{Encode, EncodeState, Encoder, EncoderError};
-pub use self::header::Header;
+#[cfg(feature = "hpack")]
+pub use self::api::{Decoder, Encoder, Entries, Entry, Field, Table};
+pub use self::decoder::{DecoderError, NeedMore};
+pub(crate) use self::encoder::{Encode, EncodeState};
+pub(crate) use self::header::Header;
+pub use self::policy::{DefaultIndexingPolicy, Huffman, Indexing, IndexingPolicy};
+pub use self::stats::{HpackCounters, HpackStats};
+pub(crate) use self::table::DynamicEntries;
@@ -0,0 +1,118 @@
+use std::fmt;
+
+/// How a header field is represented by the HPACK encoder.
+///
+/// See [RFC 7541 section 6.2] for the details of each representation.
+///
+/// [RFC 7541 section 6.2]: https://tools.ietf.org/html/rfc7541#section-6.2
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Indexing {
+    /// The field may be added to the dynamic table, so that later
+    /// occurrences can be sent as a single index.
+    Indexed,
+
+    /// The field is sent as a literal and is not added to the dynamic table.
+    ///
+    /// This is useful for values that rarely repeat, such as trace IDs or
+    /// timestamps, which would otherwise evict more useful entries.
+    NotIndexed,
+
+    /// The field is sent as a literal and intermediaries are told to never
+    /// index it when re-encoding the headers.
+    ///
+    /// This protects values such as credentials against compression based
+    /// attacks like CRIME.
+    NeverIndexed,
+}
+
+/// When the HPACK encoder Huffman encodes string literals.
+///
+/// The mode is set with [`client::Builder::huffman`] or
+/// [`server::Builder::huffman`]. See [RFC 7541 section 5.2] for the string
+/// literal representation.
+///
+/// [`client::Builder::huffman`]: client/struct.Builder.html#method.huffman
+/// [`server::Builder::huffman`]: server/struct.Builder.html#method.huffman
+/// [RFC 7541 section 5.2]: https://tools.ietf.org/html/rfc7541#section-5.2
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Huffman {
+    /// Strings are Huffman encoded unless that makes them longer, in which
+    /// case they are sent as raw octets. This is the default.
+    ///
+    /// Huffman coding makes strings of unusual octets, such as base64 tokens
+    /// or binary data, longer than the raw octets.
+    WhenShorter,
+
+    /// Strings are always Huffman encoded.
+    Always,
+
+    /// Strings are never Huffman encoded and always sent as raw octets.
+    Never,
+}
+
+impl Default for Huffman {
+    fn default() -> Huffman {
+        Huffman::WhenShorter
+    }
+}
+
+/// Decides how the HPACK encoder represents each header field it sends.
+///
+/// A policy is set with [`client::Builder::indexing_policy`] or
+/// [`server::Builder::indexing_policy`] and is consulted once for every
+/// field of every header block sent on the connection, including pseudo
+/// header fields such as `:path`. Field names are passed lower case, pseudo
+/// header fields with their leading colon.
+///
+/// Values marked as [sensitive] are always sent as
+/// [`Indexing::NeverIndexed`], whatever the policy returns. Fields the policy
+/// allows to be indexed may still be sent as literals, for example when they
+/// are too large for the dynamic table.
+///
+/// # Examples
+///
+/// ```
+/// use h2::{DefaultIndexingPolicy, Indexing, IndexingPolicy};
+///
+/// #[derive(Debug)]
+/// struct MyPolicy;
+///
+/// impl IndexingPolicy for MyPolicy {
+///     fn indexing(&self, name: &str, value: &[u8]) -> Indexing {
+///         match name {
+///             "x-api-key" => Indexing::NeverIndexed,
+///             "x-request-id" => Indexing::NotIndexed,
+///             _ => DefaultIndexingPolicy.indexing(name, value),
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`client::Builder::indexing_policy`]: client/struct.Builder.html#method.indexing_policy
+/// [`server::Builder::indexing_policy`]: server/struct.Builder.html#method.indexing_policy
+/// [sensitive]: https://docs.rs/http/0.1/http/header/struct.HeaderValue.html#method.set_sensitive
+/// [`Indexing::NeverIndexed`]: enum.Indexing.html#variant.NeverIndexed
+pub trait IndexingPolicy: fmt::Debug + Send + Sync + 'static {
+    /// Returns how the field `name: value` should be represented.
+    fn indexing(&self, name: &str, value: &[u8]) -> Indexing;
+}
+
+/// The indexing policy used when none is set on the builder.
+///
+/// Fields that are unlikely to repeat, or that carry credentials, are not
+/// added to the dynamic table. This list is borrowed from nghttp2. All other
+/// fields may be indexed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultIndexingPolicy;
+
+impl IndexingPolicy for DefaultIndexingPolicy {
+    fn indexing(&self, name: &str, _: &[u8]) -> Indexing {
+        match name {
+            "age" | "authorization" | "content-length" | "etag" | "if-modified-since"
+            | "if-none-match" | "location" | "cookie" | "set-cookie" | ":path" => {
+                Indexing::NotIndexed
+            }
+            _ => Indexing::Indexed,
+        }
+    }
+}
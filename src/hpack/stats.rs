@@ -0,0 +1,118 @@
+use super::Header;
+
+/// HPACK compression statistics of a connection.
+///
+/// Returned by [`client::Connection::hpack_stats`] and
+/// [`server::Connection::hpack_stats`], with the counters of the header
+/// blocks sent and received on the connection so far.
+///
+/// # Examples
+///
+/// ```
+/// # use h2::HpackStats;
+/// fn log_stats(stats: &HpackStats) {
+///     let sent = stats.sent();
+///
+///     println!(
+///         "sent {} header bytes in {} bytes, {} full and {} name hits",
+///         sent.header_bytes(),
+///         sent.encoded_bytes(),
+///         sent.field_hits(),
+///         sent.name_hits(),
+///     );
+/// }
+/// ```
+///
+/// [`client::Connection::hpack_stats`]: client/struct.Connection.html#method.hpack_stats
+/// [`server::Connection::hpack_stats`]: server/struct.Connection.html#method.hpack_stats
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct HpackStats {
+    sent: HpackCounters,
+    received: HpackCounters,
+}
+
+/// HPACK counters for one direction of a connection.
+///
+/// See [`HpackStats`] for details.
+///
+/// [`HpackStats`]: struct.HpackStats.html
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct HpackCounters {
+    pub(crate) header_bytes: u64,
+    pub(crate) encoded_bytes: u64,
+    pub(crate) field_hits: u64,
+    pub(crate) name_hits: u64,
+    pub(crate) evictions: u64,
+    pub(crate) table_size: usize,
+    pub(crate) size_updates: u64,
+}
+
+// ===== impl HpackStats =====
+
+impl HpackStats {
+    pub(crate) fn new(sent: HpackCounters, received: HpackCounters) -> HpackStats {
+        HpackStats { sent, received }
+    }
+
+    /// Returns the counters of the header blocks encoded and sent to the
+    /// peer.
+    pub fn sent(&self) -> &HpackCounters {
+        &self.sent
+    }
+
+    /// Returns the counters of the header blocks received from the peer and
+    /// decoded.
+    pub fn received(&self) -> &HpackCounters {
+        &self.received
+    }
+}
+
+// ===== impl HpackCounters =====
+
+impl HpackCounters {
+    /// Returns the size of the header fields before compression, which is
+    /// the sum of the lengths of their names and values.
+    pub fn header_bytes(&self) -> u64 {
+        self.header_bytes
+    }
+
+    /// Returns the size of the HPACK encoded header blocks.
+    pub fn encoded_bytes(&self) -> u64 {
+        self.encoded_bytes
+    }
+
+    /// Returns the number of fields represented by a dynamic table index,
+    /// both name and value matching an entry.
+    pub fn field_hits(&self) -> u64 {
+        self.field_hits
+    }
+
+    /// Returns the number of fields whose name is a dynamic table index, the
+    /// value being sent as a literal.
+    pub fn name_hits(&self) -> u64 {
+        self.name_hits
+    }
+
+    /// Returns the number of entries evicted from the dynamic table.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Returns the current size of the dynamic table, as defined by
+    /// [RFC 7541 section 4.1].
+    ///
+    /// [RFC 7541 section 4.1]: https://tools.ietf.org/html/rfc7541#section-4.1
+    pub fn table_size(&self) -> usize {
+        self.table_size
+    }
+
+    /// Returns the number of dynamic table size updates.
+    pub fn size_updates(&self) -> u64 {
+        self.size_updates
+    }
+
+    pub(crate) fn record_field(&mut self, header: &Header) {
+        let len = header.name().as_slice().len() + header.value_slice().len();
+        self.header_bytes += len as u64;
+    }
+}
@@ -1,4 +1,4 @@
-use super::Header;
+use super::{Header, HpackCounters, Indexing};
 
 use fnv::FnvHasher;
 use http::header;
@@ -18,6 +18,7 @@ pub struct Table {
     // Size is in bytes
     size: usize,
     max_size: usize,
+    evictions: usize,
 }
 
 #[derive(Debug)]
@@ -38,6 +39,19 @@ pub enum Index {
     NotIndexed(Header),
 }
 
+/// The entries of a dynamic table, from the oldest to the most recent, and
+/// the accounting of their size.
+///
+/// The oldest entries are evicted to keep the size of the entries within the
+/// maximum size. This is shared by the HPACK decoder and by QPACK.
+#[derive(Debug)]
+pub(crate) struct DynamicEntries {
+    entries: VecDeque<Header>,
+    size: usize,
+    max_size: usize,
+    evictions: usize,
+}
+
 #[derive(Debug)]
 struct Slot {
     hash: HashValue,
@@ -55,7 +69,8 @@ struct Pos {
 struct HashValue(usize);
 
 const MAX_SIZE: usize = (1 << 16);
-const DYN_OFFSET: usize = 62;
+/// Index of the first dynamic table entry, following the static table
+pub const DYN_OFFSET: usize = 62;
 
 macro_rules! probe_loop {
     ($probe_var: ident < $len: expr, $body: expr) => {
@@ -81,6 +96,7 @@ impl Table {
                 inserted: 0,
                 size: 0,
                 max_size,
+                evictions: 0,
             }
         } else {
             let capacity = cmp::max(to_raw_capacity(capacity).next_power_of_two(), 8);
@@ -92,6 +108,7 @@ impl Table {
                 inserted: 0,
                 size: 0,
                 max_size,
+                evictions: 0,
             }
         }
     }
@@ -131,19 +148,13 @@ impl Table {
     }
 
     /// Index the header in the HPACK table.
-    pub fn index(&mut self, header: Header) -> Index {
+    ///
+    /// The header is only added to the dynamic table if `indexing` allows it.
+    /// Otherwise, existing entries may still be referenced.
+    pub fn index(&mut self, header: Header, indexing: Indexing) -> Index {
         // Check the static table
         let statik = index_static(&header);
 
-        // Don't index certain headers. This logic is borrowed from nghttp2.
-        if header.skip_value_index() {
-            // Right now, if this is true, the header name is always in the
-            // static table. At some point in the future, this might not be true
-            // and this logic will need to be updated.
-            debug_assert!(statik.is_some(), "skip_value_index requires a static name",);
-            return Index::new(statik, header);
-        }
-
         // If the header is already indexed by the static table, return that
         if let Some((n, true)) = statik {
             return Index::Indexed(n, header);
@@ -154,13 +165,18 @@ impl Table {
             return Index::new(statik, header);
         }
 
-        self.index_dynamic(header, statik)
+        self.index_dynamic(header, statik, indexing == Indexing::Indexed)
     }
 
-    fn index_dynamic(&mut self, header: Header, statik: Option<(usize, bool)>) -> Index {
+    fn index_dynamic(
+        &mut self,
+        header: Header,
+        statik: Option<(usize, bool)>,
+        insert: bool,
+    ) -> Index {
         debug_assert!(self.assert_valid_state("one"));
 
-        if header.len() + self.size < self.max_size || !header.is_sensitive() {
+        if header.len() + self.size < self.max_size || insert {
             // Only grow internal storage if needed
             self.reserve_one();
         }
@@ -189,13 +205,19 @@ impl Table {
 
                 if their_dist < dist {
                     // Index robinhood
-                    return self.index_vacant(header, hash, dist, probe, statik);
+                    return self.index_vacant(header, hash, dist, probe, statik, insert);
                 } else if pos.hash == hash && self.slots[slot_idx].header.name() == header.name() {
                     // Matching name, check values
-                    return self.index_occupied(header, hash, pos.index, statik.map(|(n, _)| n));
+                    return self.index_occupied(
+                        header,
+                        hash,
+                        pos.index,
+                        statik.map(|(n, _)| n),
+                        insert,
+                    );
                 }
             } else {
-                return self.index_vacant(header, hash, dist, probe, statik);
+                return self.index_vacant(header, hash, dist, probe, statik, insert);
             }
 
             dist += 1;
@@ -208,6 +230,7 @@ impl Table {
         hash: HashValue,
         mut index: usize,
         statik: Option<usize>,
+        insert: bool,
     ) -> Index {
         debug_assert!(self.assert_valid_state("top"));
 
@@ -228,7 +251,7 @@ impl Table {
                 continue;
             }
 
-            if header.is_sensitive() {
+            if !insert {
                 // Should we assert this?
                 // debug_assert!(statik.is_none());
                 return Index::Name(real_idx + DYN_OFFSET, header);
@@ -270,8 +293,9 @@ impl Table {
         mut dist: usize,
         mut probe: usize,
         statik: Option<(usize, bool)>,
+        insert: bool,
     ) -> Index {
-        if header.is_sensitive() {
+        if !insert {
             return Index::new(statik, header);
         }
 
@@ -357,6 +381,7 @@ impl Table {
                 *i = None;
             }
 
+            self.evictions += self.slots.len();
             self.slots.clear();
             self.inserted = 0;
         } else {
@@ -388,6 +413,7 @@ impl Table {
 
         // Remove the header
         let slot = self.slots.pop_back().unwrap();
+        self.evictions += 1;
         let mut probe = desired_pos(self.mask, slot.hash);
 
         // Update the size
@@ -621,9 +647,9 @@ impl Table {
     }
 }
 
-#[cfg(test)]
 impl Table {
     /// Returns the number of headers in the table
+    #[cfg(any(test, feature = "hpack"))]
     pub fn len(&self) -> usize {
         self.slots.len()
     }
@@ -632,6 +658,41 @@ impl Table {
     pub fn size(&self) -> usize {
         self.size
     }
+
+    /// Returns the number of entries evicted since the table was created.
+    pub fn evictions(&self) -> usize {
+        self.evictions
+    }
+
+    /// Returns the dynamic table entry at `idx`, the most recently inserted
+    /// entry being at 0.
+    #[cfg(feature = "hpack")]
+    pub fn entry(&self, idx: usize) -> Option<&Header> {
+        self.slots.get(idx).map(|slot| &slot.header)
+    }
+
+    /// Counts the dynamic table references of a field encoded as `index`.
+    pub fn record_hit(&self, index: &Index, stats: &mut HpackCounters) {
+        match *index {
+            Index::Indexed(idx, _) if idx >= DYN_OFFSET => stats.field_hits += 1,
+            Index::Name(idx, _) | Index::InsertedValue(idx, _) if idx >= DYN_OFFSET => {
+                stats.name_hits += 1
+            }
+            _ => {}
+        }
+    }
+
+    /// Counts the dynamic table reference of a field reusing the name of the
+    /// field encoded as `last`.
+    pub fn record_name_hit(&self, last: &Index, stats: &mut HpackCounters) {
+        if let Index::NotIndexed(_) = *last {
+            return;
+        }
+
+        if self.resolve_idx(last) >= DYN_OFFSET {
+            stats.name_hits += 1;
+        }
+    }
 }
 
 impl Index {
@@ -644,6 +705,80 @@ impl Index {
     }
 }
 
+impl DynamicEntries {
+    pub fn new(max_size: usize) -> DynamicEntries {
+        DynamicEntries {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size,
+            evictions: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns the number of entries evicted since the table was created.
+    pub fn evictions(&self) -> usize {
+        self.evictions
+    }
+
+    /// Returns the entry at `idx`, the oldest entry being at 0.
+    pub fn get(&self, idx: usize) -> Option<&Header> {
+        self.entries.get(idx)
+    }
+
+    /// Returns the entries, from the oldest to the most recent.
+    #[cfg(feature = "qpack")]
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, Header> {
+        self.entries.iter()
+    }
+
+    /// Inserts an entry, evicting the oldest entries to make room for it.
+    ///
+    /// An entry larger than the maximum size empties the table, and is not
+    /// inserted.
+    pub fn insert(&mut self, header: Header) {
+        let len = header.len();
+
+        if len > self.max_size {
+            self.evict_to(0);
+            return;
+        }
+
+        self.evict_to(self.max_size - len);
+        self.size += len;
+        self.entries.push_back(header);
+    }
+
+    /// Sets the maximum size, evicting the entries that no longer fit.
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict_to(max_size);
+    }
+
+    fn evict_to(&mut self, size: usize) {
+        while self.size > size {
+            let entry = self
+                .entries
+                .pop_front()
+                .expect("table size is out of sync with its entries");
+
+            self.size -= entry.len();
+            self.evictions += 1;
+        }
+    }
+}
+
 #[inline]
 fn usable_capacity(cap: usize) -> usize {
     cap - cap / 4
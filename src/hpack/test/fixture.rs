@@ -1,4 +1,7 @@
-use crate::hpack::{Decoder, Encoder, Header};
+use super::reference;
+use crate::hpack::decoder::Decoder;
+use crate::hpack::encoder::Encoder;
+use crate::hpack::{Header, Huffman};
 
 use bytes::BytesMut;
 use hex::FromHex;
@@ -10,16 +13,19 @@ use std::io::Cursor;
 use std::path::Path;
 use std::str;
 
-fn test_fixture(path: &Path) {
+fn test_fixture(path: &Path, ours: Option<Huffman>) {
     let mut file = File::open(path).unwrap();
     let mut data = String::new();
     file.read_to_string(&mut data).unwrap();
 
     let story: Value = serde_json::from_str(&data).unwrap();
-    test_story(story);
+
+    test_story(story, ours);
 }
 
-fn test_story(story: Value) {
+/// `ours` is set for the stories of our encoder, to the Huffman coding mode
+/// they were encoded with.
+fn test_story(story: Value, ours: Option<Huffman>) {
     let story = story.as_object().unwrap();
 
     if let Some(cases) = story.get("cases") {
@@ -50,11 +56,20 @@ fn test_story(story: Value) {
                     })
                     .collect();
 
+                let never_indexed = case.get("never_indexed").map(|v| {
+                    v.as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|name| name.as_str().unwrap().to_string())
+                        .collect()
+                });
+
                 Case {
                     seqno: case.get("seqno").unwrap().as_u64().unwrap(),
                     wire: wire,
                     expect: expect,
                     header_table_size: size,
+                    never_indexed: never_indexed,
                 }
             })
             .collect();
@@ -76,14 +91,47 @@ fn test_story(story: Value) {
                     let (name, value) = expect.remove(0);
                     assert_eq!(name, key_str(&e));
                     assert_eq!(value, value_str(&e));
+
+                    // Only some stories record which fields were never indexed
+                    if let Some(ref never_indexed) = case.never_indexed {
+                        assert_eq!(never_indexed.contains(&name), e.is_sensitive());
+                    }
                 })
                 .unwrap();
 
             assert_eq!(0, expect.len());
         }
 
+        let mut model = reference::Decoder::new(4096);
+
+        // The reference model must agree
+        for case in &cases {
+            if let Some(size) = case.header_table_size {
+                model.set_limit(size);
+            }
+
+            let decoded = model
+                .decode(&case.wire)
+                .unwrap_or_else(|e| panic!("seqno={}; {}", case.seqno, e));
+
+            let decoded: Vec<_> = decoded
+                .into_iter()
+                .map(|field| {
+                    if let Some(ref never_indexed) = case.never_indexed {
+                        assert_eq!(never_indexed.contains(&field.name), field.never_indexed);
+                    }
+                    (field.name, field.value)
+                })
+                .collect();
+
+            assert_eq!(case.expect, decoded, "seqno={}", case.seqno);
+        }
+
         let mut encoder = Encoder::default();
         let mut decoder = Decoder::default();
+        let mut model = reference::Decoder::new(4096);
+
+        encoder.set_huffman(ours.unwrap_or_default());
 
         // Now, encode the headers
         for case in &cases {
@@ -92,28 +140,62 @@ fn test_story(story: Value) {
             if let Some(size) = case.header_table_size {
                 encoder.update_max_size(size);
                 decoder.queue_size_update(size);
+                model.set_limit(size);
             }
 
             let mut input: Vec<_> = case
                 .expect
                 .iter()
                 .map(|&(ref name, ref value)| {
-                    Header::new(name.clone().into(), value.clone().into())
-                        .unwrap()
-                        .into()
+                    let mut header =
+                        Header::new(name.clone().into(), value.clone().into()).unwrap();
+                    if let Some(ref never_indexed) = case.never_indexed {
+                        header.set_sensitive(never_indexed.contains(name));
+                    }
+                    header.into()
                 })
                 .collect();
 
             encoder.encode(None, &mut input.clone().into_iter(), &mut buf);
 
+            if ours.is_some() {
+                assert!(
+                    buf[..] == case.wire[..],
+                    "seqno={}; the encoder output changed, run `genfixture encode`",
+                    case.seqno
+                );
+            }
+
+            // Our output decodes the same in the reference model
+            let decoded = model
+                .decode(&buf)
+                .unwrap_or_else(|e| panic!("seqno={}; {}", case.seqno, e));
+
+            for (field, &(ref name, ref value)) in decoded.iter().zip(&case.expect) {
+                assert_eq!((&field.name, &field.value), (name, value));
+
+                if let Some(ref never_indexed) = case.never_indexed {
+                    assert_eq!(never_indexed.contains(name), field.never_indexed);
+                }
+            }
+
+            assert_eq!(case.expect.len(), decoded.len());
+            assert_eq!(encoder.table().len(), model.len());
+            assert_eq!(encoder.table().size(), model.size());
+
             decoder
                 .decode(&mut Cursor::new(&mut buf), |e| {
-                    assert_eq!(e, input.remove(0).reify().unwrap());
+                    let expect = input.remove(0).reify().unwrap();
+                    assert_eq!(expect.is_sensitive(), e.is_sensitive());
+                    assert_eq!(e, expect);
                 })
                 .unwrap();
 
             assert_eq!(0, input.len());
         }
+
+        // Both ends see the same table references.
+        assert_eq!(encoder.stats(), decoder.stats());
     }
 }
 
@@ -122,6 +204,7 @@ struct Case {
     wire: Vec<u8>,
     expect: Vec<(String, String)>,
     header_table_size: Option<usize>,
+    never_indexed: Option<Vec<String>>,
 }
 
 fn key_str(e: &Header) -> &str {
@@ -146,12 +229,25 @@ fn value_str(e: &Header) -> &str {
     }
 }
 
+/// Stories of our own encoder, generated by util/genfixture, are marked with
+/// `ours(huffman)`, where `huffman` is the `Huffman` coding mode of the
+/// encoder.
 macro_rules! fixture_mod {
     ($module:ident => {
         $(
             ($fn:ident, $path:expr);
         )+
     }) => {
+        fixture_mod!(@stories $module, None, $(($fn, $path);)+);
+    };
+    ($module:ident ours($huffman:ident) => {
+        $(
+            ($fn:ident, $path:expr);
+        )+
+    }) => {
+        fixture_mod!(@stories $module, Some(crate::hpack::Huffman::$huffman), $(($fn, $path);)+);
+    };
+    (@stories $module:ident, $ours:expr, $(($fn:ident, $path:expr);)+) => {
         mod $module {
             $(
                 #[test]
@@ -160,13 +256,19 @@ macro_rules! fixture_mod {
                         .join("fixtures/hpack")
                         .join($path);
 
-                    super::test_fixture(path.as_ref());
+                    super::test_fixture(path.as_ref(), $ours);
                 }
             )+
         }
-    }
+    };
 }
 
+fixture_mod!(
+    never_indexed => {
+        (story_00, "never-indexed/story_00.json");
+    }
+);
+
 fixture_mod!(
     haskell_http2_linear_huffman => {
         (story_00, "haskell-http2-linear-huffman/story_00.json");
@@ -608,3 +710,114 @@ fixture_mod!(
         (story_31, "go-hpack/story_31.json");
     }
 );
+
+fixture_mod!(
+    rust_h2 ours(Never) => {
+        (story_00, "rust-h2/story_00.json");
+        (story_01, "rust-h2/story_01.json");
+        (story_02, "rust-h2/story_02.json");
+        (story_03, "rust-h2/story_03.json");
+        (story_04, "rust-h2/story_04.json");
+        (story_05, "rust-h2/story_05.json");
+        (story_06, "rust-h2/story_06.json");
+        (story_07, "rust-h2/story_07.json");
+        (story_08, "rust-h2/story_08.json");
+        (story_09, "rust-h2/story_09.json");
+        (story_10, "rust-h2/story_10.json");
+        (story_11, "rust-h2/story_11.json");
+        (story_12, "rust-h2/story_12.json");
+        (story_13, "rust-h2/story_13.json");
+        (story_14, "rust-h2/story_14.json");
+        (story_15, "rust-h2/story_15.json");
+        (story_16, "rust-h2/story_16.json");
+        (story_17, "rust-h2/story_17.json");
+        (story_18, "rust-h2/story_18.json");
+        (story_19, "rust-h2/story_19.json");
+        (story_20, "rust-h2/story_20.json");
+        (story_21, "rust-h2/story_21.json");
+        (story_22, "rust-h2/story_22.json");
+        (story_23, "rust-h2/story_23.json");
+        (story_24, "rust-h2/story_24.json");
+        (story_25, "rust-h2/story_25.json");
+        (story_26, "rust-h2/story_26.json");
+        (story_27, "rust-h2/story_27.json");
+        (story_28, "rust-h2/story_28.json");
+        (story_29, "rust-h2/story_29.json");
+        (story_30, "rust-h2/story_30.json");
+        (story_31, "rust-h2/story_31.json");
+    }
+);
+
+fixture_mod!(
+    rust_h2_huffman ours(Always) => {
+        (story_00, "rust-h2-huffman/story_00.json");
+        (story_01, "rust-h2-huffman/story_01.json");
+        (story_02, "rust-h2-huffman/story_02.json");
+        (story_03, "rust-h2-huffman/story_03.json");
+        (story_04, "rust-h2-huffman/story_04.json");
+        (story_05, "rust-h2-huffman/story_05.json");
+        (story_06, "rust-h2-huffman/story_06.json");
+        (story_07, "rust-h2-huffman/story_07.json");
+        (story_08, "rust-h2-huffman/story_08.json");
+        (story_09, "rust-h2-huffman/story_09.json");
+        (story_10, "rust-h2-huffman/story_10.json");
+        (story_11, "rust-h2-huffman/story_11.json");
+        (story_12, "rust-h2-huffman/story_12.json");
+        (story_13, "rust-h2-huffman/story_13.json");
+        (story_14, "rust-h2-huffman/story_14.json");
+        (story_15, "rust-h2-huffman/story_15.json");
+        (story_16, "rust-h2-huffman/story_16.json");
+        (story_17, "rust-h2-huffman/story_17.json");
+        (story_18, "rust-h2-huffman/story_18.json");
+        (story_19, "rust-h2-huffman/story_19.json");
+        (story_20, "rust-h2-huffman/story_20.json");
+        (story_21, "rust-h2-huffman/story_21.json");
+        (story_22, "rust-h2-huffman/story_22.json");
+        (story_23, "rust-h2-huffman/story_23.json");
+        (story_24, "rust-h2-huffman/story_24.json");
+        (story_25, "rust-h2-huffman/story_25.json");
+        (story_26, "rust-h2-huffman/story_26.json");
+        (story_27, "rust-h2-huffman/story_27.json");
+        (story_28, "rust-h2-huffman/story_28.json");
+        (story_29, "rust-h2-huffman/story_29.json");
+        (story_30, "rust-h2-huffman/story_30.json");
+        (story_31, "rust-h2-huffman/story_31.json");
+    }
+);
+
+fixture_mod!(
+    rust_h2_change_table_size ours(WhenShorter) => {
+        (story_00, "rust-h2-change-table-size/story_00.json");
+        (story_01, "rust-h2-change-table-size/story_01.json");
+        (story_02, "rust-h2-change-table-size/story_02.json");
+        (story_03, "rust-h2-change-table-size/story_03.json");
+        (story_04, "rust-h2-change-table-size/story_04.json");
+        (story_05, "rust-h2-change-table-size/story_05.json");
+        (story_06, "rust-h2-change-table-size/story_06.json");
+        (story_07, "rust-h2-change-table-size/story_07.json");
+        (story_08, "rust-h2-change-table-size/story_08.json");
+        (story_09, "rust-h2-change-table-size/story_09.json");
+        (story_10, "rust-h2-change-table-size/story_10.json");
+        (story_11, "rust-h2-change-table-size/story_11.json");
+        (story_12, "rust-h2-change-table-size/story_12.json");
+        (story_13, "rust-h2-change-table-size/story_13.json");
+        (story_14, "rust-h2-change-table-size/story_14.json");
+        (story_15, "rust-h2-change-table-size/story_15.json");
+        (story_16, "rust-h2-change-table-size/story_16.json");
+        (story_17, "rust-h2-change-table-size/story_17.json");
+        (story_18, "rust-h2-change-table-size/story_18.json");
+        (story_19, "rust-h2-change-table-size/story_19.json");
+        (story_20, "rust-h2-change-table-size/story_20.json");
+        (story_21, "rust-h2-change-table-size/story_21.json");
+        (story_22, "rust-h2-change-table-size/story_22.json");
+        (story_23, "rust-h2-change-table-size/story_23.json");
+        (story_24, "rust-h2-change-table-size/story_24.json");
+        (story_25, "rust-h2-change-table-size/story_25.json");
+        (story_26, "rust-h2-change-table-size/story_26.json");
+        (story_27, "rust-h2-change-table-size/story_27.json");
+        (story_28, "rust-h2-change-table-size/story_28.json");
+        (story_29, "rust-h2-change-table-size/story_29.json");
+        (story_30, "rust-h2-change-table-size/story_30.json");
+        (story_31, "rust-h2-change-table-size/story_31.json");
+    }
+);
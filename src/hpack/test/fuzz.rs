@@ -1,4 +1,6 @@
-use crate::hpack::{Decoder, Encode, Encoder, Header};
+use crate::hpack::decoder::Decoder;
+use crate::hpack::encoder::Encoder;
+use crate::hpack::{Encode, Header};
 
 use http::header::{HeaderName, HeaderValue};
 
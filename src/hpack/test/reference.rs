@@ -0,0 +1,641 @@
+//! A reference model of an HPACK decoder, written straight from RFC 7541.
+//!
+//! It shares nothing with `hpack::decoder`, not even the Huffman code, which
+//! is copied from the RFC and decoded bit by bit as a canonical code instead
+//! of with the generated state machine. It is slow and simple, and only meant
+//! to check the blocks our encoder produces.
+
+use std::collections::VecDeque;
+
+/// RFC 7541, Appendix A
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// RFC 7541, Appendix B, the code of each symbol and its length in bits.
+const HUFFMAN_CODE: [(u32, usize); 257] = [
+    (0x1ff8, 13),     // (  0)
+    (0x7fffd8, 23),   // (  1)
+    (0xfffffe2, 28),  // (  2)
+    (0xfffffe3, 28),  // (  3)
+    (0xfffffe4, 28),  // (  4)
+    (0xfffffe5, 28),  // (  5)
+    (0xfffffe6, 28),  // (  6)
+    (0xfffffe7, 28),  // (  7)
+    (0xfffffe8, 28),  // (  8)
+    (0xffffea, 24),   // (  9)
+    (0x3ffffffc, 30), // ( 10)
+    (0xfffffe9, 28),  // ( 11)
+    (0xfffffea, 28),  // ( 12)
+    (0x3ffffffd, 30), // ( 13)
+    (0xfffffeb, 28),  // ( 14)
+    (0xfffffec, 28),  // ( 15)
+    (0xfffffed, 28),  // ( 16)
+    (0xfffffee, 28),  // ( 17)
+    (0xfffffef, 28),  // ( 18)
+    (0xffffff0, 28),  // ( 19)
+    (0xffffff1, 28),  // ( 20)
+    (0xffffff2, 28),  // ( 21)
+    (0x3ffffffe, 30), // ( 22)
+    (0xffffff3, 28),  // ( 23)
+    (0xffffff4, 28),  // ( 24)
+    (0xffffff5, 28),  // ( 25)
+    (0xffffff6, 28),  // ( 26)
+    (0xffffff7, 28),  // ( 27)
+    (0xffffff8, 28),  // ( 28)
+    (0xffffff9, 28),  // ( 29)
+    (0xffffffa, 28),  // ( 30)
+    (0xffffffb, 28),  // ( 31)
+    (0x14, 6),        // ' ' ( 32)
+    (0x3f8, 10),      // '!' ( 33)
+    (0x3f9, 10),      // '"' ( 34)
+    (0xffa, 12),      // '#' ( 35)
+    (0x1ff9, 13),     // '$' ( 36)
+    (0x15, 6),        // '%' ( 37)
+    (0xf8, 8),        // '&' ( 38)
+    (0x7fa, 11),      // "'" ( 39)
+    (0x3fa, 10),      // '(' ( 40)
+    (0x3fb, 10),      // ')' ( 41)
+    (0xf9, 8),        // '*' ( 42)
+    (0x7fb, 11),      // '+' ( 43)
+    (0xfa, 8),        // ',' ( 44)
+    (0x16, 6),        // '-' ( 45)
+    (0x17, 6),        // '.' ( 46)
+    (0x18, 6),        // '/' ( 47)
+    (0x0, 5),         // '0' ( 48)
+    (0x1, 5),         // '1' ( 49)
+    (0x2, 5),         // '2' ( 50)
+    (0x19, 6),        // '3' ( 51)
+    (0x1a, 6),        // '4' ( 52)
+    (0x1b, 6),        // '5' ( 53)
+    (0x1c, 6),        // '6' ( 54)
+    (0x1d, 6),        // '7' ( 55)
+    (0x1e, 6),        // '8' ( 56)
+    (0x1f, 6),        // '9' ( 57)
+    (0x5c, 7),        // ':' ( 58)
+    (0xfb, 8),        // ';' ( 59)
+    (0x7ffc, 15),     // '<' ( 60)
+    (0x20, 6),        // '=' ( 61)
+    (0xffb, 12),      // '>' ( 62)
+    (0x3fc, 10),      // '?' ( 63)
+    (0x1ffa, 13),     // '@' ( 64)
+    (0x21, 6),        // 'A' ( 65)
+    (0x5d, 7),        // 'B' ( 66)
+    (0x5e, 7),        // 'C' ( 67)
+    (0x5f, 7),        // 'D' ( 68)
+    (0x60, 7),        // 'E' ( 69)
+    (0x61, 7),        // 'F' ( 70)
+    (0x62, 7),        // 'G' ( 71)
+    (0x63, 7),        // 'H' ( 72)
+    (0x64, 7),        // 'I' ( 73)
+    (0x65, 7),        // 'J' ( 74)
+    (0x66, 7),        // 'K' ( 75)
+    (0x67, 7),        // 'L' ( 76)
+    (0x68, 7),        // 'M' ( 77)
+    (0x69, 7),        // 'N' ( 78)
+    (0x6a, 7),        // 'O' ( 79)
+    (0x6b, 7),        // 'P' ( 80)
+    (0x6c, 7),        // 'Q' ( 81)
+    (0x6d, 7),        // 'R' ( 82)
+    (0x6e, 7),        // 'S' ( 83)
+    (0x6f, 7),        // 'T' ( 84)
+    (0x70, 7),        // 'U' ( 85)
+    (0x71, 7),        // 'V' ( 86)
+    (0x72, 7),        // 'W' ( 87)
+    (0xfc, 8),        // 'X' ( 88)
+    (0x73, 7),        // 'Y' ( 89)
+    (0xfd, 8),        // 'Z' ( 90)
+    (0x1ffb, 13),     // '[' ( 91)
+    (0x7fff0, 19),    // '\\' ( 92)
+    (0x1ffc, 13),     // ']' ( 93)
+    (0x3ffc, 14),     // '^' ( 94)
+    (0x22, 6),        // '_' ( 95)
+    (0x7ffd, 15),     // '`' ( 96)
+    (0x3, 5),         // 'a' ( 97)
+    (0x23, 6),        // 'b' ( 98)
+    (0x4, 5),         // 'c' ( 99)
+    (0x24, 6),        // 'd' (100)
+    (0x5, 5),         // 'e' (101)
+    (0x25, 6),        // 'f' (102)
+    (0x26, 6),        // 'g' (103)
+    (0x27, 6),        // 'h' (104)
+    (0x6, 5),         // 'i' (105)
+    (0x74, 7),        // 'j' (106)
+    (0x75, 7),        // 'k' (107)
+    (0x28, 6),        // 'l' (108)
+    (0x29, 6),        // 'm' (109)
+    (0x2a, 6),        // 'n' (110)
+    (0x7, 5),         // 'o' (111)
+    (0x2b, 6),        // 'p' (112)
+    (0x76, 7),        // 'q' (113)
+    (0x2c, 6),        // 'r' (114)
+    (0x8, 5),         // 's' (115)
+    (0x9, 5),         // 't' (116)
+    (0x2d, 6),        // 'u' (117)
+    (0x77, 7),        // 'v' (118)
+    (0x78, 7),        // 'w' (119)
+    (0x79, 7),        // 'x' (120)
+    (0x7a, 7),        // 'y' (121)
+    (0x7b, 7),        // 'z' (122)
+    (0x7ffe, 15),     // '{' (123)
+    (0x7fc, 11),      // '|' (124)
+    (0x3ffd, 14),     // '}' (125)
+    (0x1ffd, 13),     // '~' (126)
+    (0xffffffc, 28),  // (127)
+    (0xfffe6, 20),    // (128)
+    (0x3fffd2, 22),   // (129)
+    (0xfffe7, 20),    // (130)
+    (0xfffe8, 20),    // (131)
+    (0x3fffd3, 22),   // (132)
+    (0x3fffd4, 22),   // (133)
+    (0x3fffd5, 22),   // (134)
+    (0x7fffd9, 23),   // (135)
+    (0x3fffd6, 22),   // (136)
+    (0x7fffda, 23),   // (137)
+    (0x7fffdb, 23),   // (138)
+    (0x7fffdc, 23),   // (139)
+    (0x7fffdd, 23),   // (140)
+    (0x7fffde, 23),   // (141)
+    (0xffffeb, 24),   // (142)
+    (0x7fffdf, 23),   // (143)
+    (0xffffec, 24),   // (144)
+    (0xffffed, 24),   // (145)
+    (0x3fffd7, 22),   // (146)
+    (0x7fffe0, 23),   // (147)
+    (0xffffee, 24),   // (148)
+    (0x7fffe1, 23),   // (149)
+    (0x7fffe2, 23),   // (150)
+    (0x7fffe3, 23),   // (151)
+    (0x7fffe4, 23),   // (152)
+    (0x1fffdc, 21),   // (153)
+    (0x3fffd8, 22),   // (154)
+    (0x7fffe5, 23),   // (155)
+    (0x3fffd9, 22),   // (156)
+    (0x7fffe6, 23),   // (157)
+    (0x7fffe7, 23),   // (158)
+    (0xffffef, 24),   // (159)
+    (0x3fffda, 22),   // (160)
+    (0x1fffdd, 21),   // (161)
+    (0xfffe9, 20),    // (162)
+    (0x3fffdb, 22),   // (163)
+    (0x3fffdc, 22),   // (164)
+    (0x7fffe8, 23),   // (165)
+    (0x7fffe9, 23),   // (166)
+    (0x1fffde, 21),   // (167)
+    (0x7fffea, 23),   // (168)
+    (0x3fffdd, 22),   // (169)
+    (0x3fffde, 22),   // (170)
+    (0xfffff0, 24),   // (171)
+    (0x1fffdf, 21),   // (172)
+    (0x3fffdf, 22),   // (173)
+    (0x7fffeb, 23),   // (174)
+    (0x7fffec, 23),   // (175)
+    (0x1fffe0, 21),   // (176)
+    (0x1fffe1, 21),   // (177)
+    (0x3fffe0, 22),   // (178)
+    (0x1fffe2, 21),   // (179)
+    (0x7fffed, 23),   // (180)
+    (0x3fffe1, 22),   // (181)
+    (0x7fffee, 23),   // (182)
+    (0x7fffef, 23),   // (183)
+    (0xfffea, 20),    // (184)
+    (0x3fffe2, 22),   // (185)
+    (0x3fffe3, 22),   // (186)
+    (0x3fffe4, 22),   // (187)
+    (0x7ffff0, 23),   // (188)
+    (0x3fffe5, 22),   // (189)
+    (0x3fffe6, 22),   // (190)
+    (0x7ffff1, 23),   // (191)
+    (0x3ffffe0, 26),  // (192)
+    (0x3ffffe1, 26),  // (193)
+    (0xfffeb, 20),    // (194)
+    (0x7fff1, 19),    // (195)
+    (0x3fffe7, 22),   // (196)
+    (0x7ffff2, 23),   // (197)
+    (0x3fffe8, 22),   // (198)
+    (0x1ffffec, 25),  // (199)
+    (0x3ffffe2, 26),  // (200)
+    (0x3ffffe3, 26),  // (201)
+    (0x3ffffe4, 26),  // (202)
+    (0x7ffffde, 27),  // (203)
+    (0x7ffffdf, 27),  // (204)
+    (0x3ffffe5, 26),  // (205)
+    (0xfffff1, 24),   // (206)
+    (0x1ffffed, 25),  // (207)
+    (0x7fff2, 19),    // (208)
+    (0x1fffe3, 21),   // (209)
+    (0x3ffffe6, 26),  // (210)
+    (0x7ffffe0, 27),  // (211)
+    (0x7ffffe1, 27),  // (212)
+    (0x3ffffe7, 26),  // (213)
+    (0x7ffffe2, 27),  // (214)
+    (0xfffff2, 24),   // (215)
+    (0x1fffe4, 21),   // (216)
+    (0x1fffe5, 21),   // (217)
+    (0x3ffffe8, 26),  // (218)
+    (0x3ffffe9, 26),  // (219)
+    (0xffffffd, 28),  // (220)
+    (0x7ffffe3, 27),  // (221)
+    (0x7ffffe4, 27),  // (222)
+    (0x7ffffe5, 27),  // (223)
+    (0xfffec, 20),    // (224)
+    (0xfffff3, 24),   // (225)
+    (0xfffed, 20),    // (226)
+    (0x1fffe6, 21),   // (227)
+    (0x3fffe9, 22),   // (228)
+    (0x1fffe7, 21),   // (229)
+    (0x1fffe8, 21),   // (230)
+    (0x7ffff3, 23),   // (231)
+    (0x3fffea, 22),   // (232)
+    (0x3fffeb, 22),   // (233)
+    (0x1ffffee, 25),  // (234)
+    (0x1ffffef, 25),  // (235)
+    (0xfffff4, 24),   // (236)
+    (0xfffff5, 24),   // (237)
+    (0x3ffffea, 26),  // (238)
+    (0x7ffff4, 23),   // (239)
+    (0x3ffffeb, 26),  // (240)
+    (0x7ffffe6, 27),  // (241)
+    (0x3ffffec, 26),  // (242)
+    (0x3ffffed, 26),  // (243)
+    (0x7ffffe7, 27),  // (244)
+    (0x7ffffe8, 27),  // (245)
+    (0x7ffffe9, 27),  // (246)
+    (0x7ffffea, 27),  // (247)
+    (0x7ffffeb, 27),  // (248)
+    (0xffffffe, 28),  // (249)
+    (0x7ffffec, 27),  // (250)
+    (0x7ffffed, 27),  // (251)
+    (0x7ffffee, 27),  // (252)
+    (0x7ffffef, 27),  // (253)
+    (0x7fffff0, 27),  // (254)
+    (0x3ffffee, 26),  // (255)
+    (0x3fffffff, 30), // EOS
+];
+
+const EOS: usize = 256;
+
+/// A decoded field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    pub never_indexed: bool,
+}
+
+#[derive(Debug)]
+pub struct Decoder {
+    /// The limit set by the decoder, `SETTINGS_HEADER_TABLE_SIZE`.
+    limit: usize,
+    /// The maximum size of the table, as last set by the encoder.
+    max_size: usize,
+    /// Set when the limit went under the maximum size, in which case the next
+    /// block must start with a size update.
+    update_required: bool,
+    /// Newest entry first.
+    entries: VecDeque<(String, String)>,
+    size: usize,
+    huffman: Huffman,
+}
+
+/// The Huffman code of Appendix B, decoded as a canonical code.
+#[derive(Debug)]
+struct Huffman {
+    /// The first code of each length, and the index of its symbol.
+    first: Vec<(u32, usize)>,
+    /// The number of codes of each length.
+    count: Vec<usize>,
+    /// Symbols ordered by code.
+    symbols: Vec<usize>,
+}
+
+struct Reader<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+// ===== impl Decoder =====
+
+impl Decoder {
+    pub fn new(limit: usize) -> Decoder {
+        Decoder {
+            limit,
+            max_size: limit,
+            update_required: false,
+            entries: VecDeque::new(),
+            size: 0,
+            huffman: Huffman::new(),
+        }
+    }
+
+    /// Applies an acknowledged `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn set_limit(&mut self, limit: usize) {
+        if limit < self.max_size {
+            self.update_required = true;
+        }
+
+        self.limit = limit;
+    }
+
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<Field>, String> {
+        let mut src = Reader { src: block, pos: 0 };
+        let mut fields = vec![];
+
+        // Section 4.2, dynamic table size updates must come first.
+        while src.peek().map_or(false, |b| b & 0b1110_0000 == 0b0010_0000) {
+            let size = src.integer(5)?;
+
+            if size > self.limit {
+                return Err(format!("size update {} over limit {}", size, self.limit));
+            }
+
+            self.max_size = size;
+            self.update_required = false;
+            self.evict(0);
+        }
+
+        if self.update_required {
+            return Err("missing size update".to_string());
+        }
+
+        while let Some(b) = src.peek() {
+            if b & 0b1000_0000 != 0 {
+                // Section 6.1, indexed header field
+                let (name, value) = self.get(src.integer(7)?)?;
+                fields.push(Field {
+                    name,
+                    value,
+                    never_indexed: false,
+                });
+            } else if b & 0b0100_0000 != 0 {
+                // Section 6.2.1, literal with incremental indexing
+                let (name, value) = self.literal(&mut src, 6)?;
+                self.insert(name.clone(), value.clone());
+                fields.push(Field {
+                    name,
+                    value,
+                    never_indexed: false,
+                });
+            } else if b & 0b0010_0000 != 0 {
+                return Err("size update after a field".to_string());
+            } else {
+                // Section 6.2.2 and 6.2.3, literal without indexing and
+                // never indexed
+                let never_indexed = b & 0b0001_0000 != 0;
+                let (name, value) = self.literal(&mut src, 4)?;
+                fields.push(Field {
+                    name,
+                    value,
+                    never_indexed,
+                });
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// The number of entries in the dynamic table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The size of the dynamic table, in the sense of section 4.1.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn literal(&self, src: &mut Reader, prefix: u8) -> Result<(String, String), String> {
+        let name = match src.integer(prefix)? {
+            0 => self.string(src)?,
+            index => self.get(index)?.0,
+        };
+        let value = self.string(src)?;
+
+        Ok((name, value))
+    }
+
+    /// Section 5.2, string literal
+    fn string(&self, src: &mut Reader) -> Result<String, String> {
+        let huffman = src.peek().ok_or("missing string")? & 0b1000_0000 != 0;
+        let len = src.integer(7)?;
+        let raw = src.take(len)?;
+
+        let bytes = if huffman {
+            self.huffman.decode(raw)?
+        } else {
+            raw.to_vec()
+        };
+
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Section 2.3.3, index address space
+    fn get(&self, index: usize) -> Result<(String, String), String> {
+        if index == 0 {
+            return Err("index 0".to_string());
+        }
+
+        if let Some(&(name, value)) = STATIC_TABLE.get(index - 1) {
+            return Ok((name.to_string(), value.to_string()));
+        }
+
+        self.entries
+            .get(index - 1 - STATIC_TABLE.len())
+            .cloned()
+            .ok_or_else(|| format!("index {} out of range", index))
+    }
+
+    /// Section 4.4, entry addition
+    fn insert(&mut self, name: String, value: String) {
+        let size = name.len() + value.len() + 32;
+
+        if size > self.max_size {
+            // Adding an entry larger than the table empties it.
+            self.evict(self.max_size);
+            return;
+        }
+
+        self.evict(size);
+        self.entries.push_front((name, value));
+        self.size += size;
+    }
+
+    /// Section 4.3 and 4.4, evicts entries until `room` octets are free.
+    fn evict(&mut self, room: usize) {
+        while self.size + room > self.max_size {
+            let (name, value) = self.entries.pop_back().unwrap();
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+}
+
+// ===== impl Huffman =====
+
+impl Huffman {
+    fn new() -> Huffman {
+        let max_len = HUFFMAN_CODE.iter().map(|&(_, len)| len).max().unwrap();
+
+        let mut symbols: Vec<usize> = (0..HUFFMAN_CODE.len()).collect();
+        symbols.sort_by_key(|&sym| (HUFFMAN_CODE[sym].1, HUFFMAN_CODE[sym].0));
+
+        let mut first = vec![(0, 0); max_len + 1];
+        let mut count = vec![0; max_len + 1];
+
+        for (i, &sym) in symbols.iter().enumerate().rev() {
+            let (code, len) = HUFFMAN_CODE[sym];
+            first[len] = (code, i);
+            count[len] += 1;
+        }
+
+        Huffman {
+            first,
+            count,
+            symbols,
+        }
+    }
+
+    /// Section 5.2, decodes one bit at a time, most significant first.
+    fn decode(&self, src: &[u8]) -> Result<Vec<u8>, String> {
+        let mut dst = vec![];
+        let mut code = 0u32;
+        let mut len = 0;
+
+        for i in 0..src.len() * 8 {
+            let bit = (src[i / 8] >> (7 - i % 8)) & 1;
+            code = (code << 1) | bit as u32;
+            len += 1;
+
+            if len >= self.first.len() {
+                return Err("invalid Huffman code".to_string());
+            }
+
+            let (first, index) = self.first[len];
+
+            // Codes of the same length are consecutive in a canonical code.
+            if self.count[len] > 0 && code >= first && code - first < self.count[len] as u32 {
+                let sym = self.symbols[index + (code - first) as usize];
+
+                if sym == EOS {
+                    return Err("EOS in Huffman string".to_string());
+                }
+
+                dst.push(sym as u8);
+                code = 0;
+                len = 0;
+            }
+        }
+
+        // The padding is the most significant bits of EOS, all ones, and
+        // shorter than a byte.
+        if len > 7 || code != (1 << len) - 1 {
+            return Err("invalid Huffman padding".to_string());
+        }
+
+        Ok(dst)
+    }
+}
+
+// ===== impl Reader =====
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).cloned()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.src.len() - self.pos < n {
+            return Err("unexpected end of block".to_string());
+        }
+
+        let bytes = &self.src[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    /// Section 5.1, integer representation
+    fn integer(&mut self, prefix: u8) -> Result<usize, String> {
+        let mask = (1u8 << prefix) - 1;
+        let mut value = (self.take(1)?[0] & mask) as usize;
+
+        if value < mask as usize {
+            return Ok(value);
+        }
+
+        let mut shift = 0;
+
+        loop {
+            let b = self.take(1)?[0];
+
+            if shift > 28 {
+                return Err("integer overflow".to_string());
+            }
+
+            value += ((b & 0x7f) as usize) << shift;
+            shift += 7;
+
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+}
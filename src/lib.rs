@@ -18,6 +18,8 @@
 //!
 //! The crate is split into [`client`] and [`server`] modules. Types that are
 //! common to both clients and servers are located at the root of the crate.
+//! The [`wire`] module sends and receives individual frames, for programs
+//! that need to drive the protocol themselves.
 //!
 //! See module level documentation for more details on how to use `h2`.
 //!
@@ -70,6 +72,7 @@
 //! [futures]: https://docs.rs/futures/
 //! [`client`]: client/index.html
 //! [`server`]: server/index.html
+//! [`wire`]: wire/index.html
 //! [Flow control]: http://httpwg.org/specs/rfc7540.html#FlowControl
 //! [`ReleaseCapacity`]: struct.ReleaseCapacity.html
 //! [`SendStream`]: struct.SendStream.html
@@ -94,9 +97,17 @@ macro_rules! proto_err {
 #[cfg_attr(feature = "unstable", allow(missing_docs))]
 mod codec;
 mod error;
-mod hpack;
 mod proto;
 
+#[cfg(not(feature = "hpack"))]
+mod hpack;
+
+#[cfg(feature = "hpack")]
+pub mod hpack;
+
+#[cfg(feature = "qpack")]
+pub mod qpack;
+
 #[cfg(not(feature = "unstable"))]
 mod frame;
 
@@ -105,11 +116,24 @@ mod frame;
 pub mod frame;
 
 pub mod client;
+mod flush;
+mod observer;
+mod padding;
 pub mod server;
 mod share;
+mod timer;
+pub mod transcript;
+pub mod wire;
 
 pub use crate::error::{Error, Reason};
+pub use crate::flush::FlushStrategy;
+pub use crate::hpack::{
+    DefaultIndexingPolicy, HpackCounters, HpackStats, Huffman, Indexing, IndexingPolicy,
+};
+pub use crate::observer::{ConnectionState, FrameEvent, FrameKind, FrameObserver, HeaderFields};
+pub use crate::padding::Padding;
 pub use crate::share::{Ping, PingPong, Pong, RecvStream, ReleaseCapacity, SendStream, StreamId};
+pub use crate::timer::{Sleep, Timer};
 
 #[cfg(feature = "unstable")]
 pub use codec::{Codec, RecvError, SendError, UserError};
@@ -0,0 +1,253 @@
+use crate::frame::{self, Reason};
+use crate::share::StreamId;
+
+use http::header::{self, HeaderMap, HeaderValue};
+use std::{fmt, vec};
+
+/// Receives an event for every frame sent or received on a connection, and
+/// for every state transition of the connection.
+///
+/// An observer is set with [`client::Builder::frame_observer`] or
+/// [`server::Builder::frame_observer`]. It is meant for debugging and
+/// tracing: it is called from the task driving the connection, so it should
+/// return quickly and must not block.
+///
+/// A sent frame is observed once it is encoded to the write buffer, which
+/// may be some time before it is written to the I/O. A received frame is
+/// observed once it is decoded, including frames that the connection then
+/// ignores or rejects.
+///
+/// All methods do nothing by default.
+///
+/// # Examples
+///
+/// ```
+/// use h2::{FrameEvent, FrameObserver};
+///
+/// #[derive(Debug)]
+/// struct PrintFrames;
+///
+/// impl FrameObserver for PrintFrames {
+///     fn frame_sent(&self, event: &FrameEvent) {
+///         println!("-> {:?}", event);
+///     }
+///
+///     fn frame_received(&self, event: &FrameEvent) {
+///         println!("<- {:?}", event);
+///     }
+/// }
+/// ```
+///
+/// [`client::Builder::frame_observer`]: client/struct.Builder.html#method.frame_observer
+/// [`server::Builder::frame_observer`]: server/struct.Builder.html#method.frame_observer
+pub trait FrameObserver: fmt::Debug + Send + Sync + 'static {
+    /// Called for every frame sent, `CONTINUATION` frames included.
+    fn frame_sent(&self, _event: &FrameEvent) {}
+
+    /// Called for every frame received, `CONTINUATION` frames and frames of
+    /// an unknown type included.
+    fn frame_received(&self, _event: &FrameEvent) {}
+
+    /// Called when the connection enters a new state.
+    fn state_changed(&self, _state: ConnectionState) {}
+}
+
+/// A frame sent or received on a connection.
+///
+/// Besides the fields of the frame header, the event carries the decoded
+/// header fields of the frame that completes a header block.
+pub struct FrameEvent<'a> {
+    kind: FrameKind,
+    flags: u8,
+    stream_id: StreamId,
+    len: usize,
+    headers: Option<(&'a frame::Pseudo, &'a HeaderMap)>,
+}
+
+/// The type of a frame, as defined in [RFC 7540 section 6].
+///
+/// [RFC 7540 section 6]: https://tools.ietf.org/html/rfc7540#section-6
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum FrameKind {
+    /// `DATA`
+    Data,
+    /// `HEADERS`
+    Headers,
+    /// `PRIORITY`
+    Priority,
+    /// `RST_STREAM`
+    Reset,
+    /// `SETTINGS`
+    Settings,
+    /// `PUSH_PROMISE`
+    PushPromise,
+    /// `PING`
+    Ping,
+    /// `GOAWAY`
+    GoAway,
+    /// `WINDOW_UPDATE`
+    WindowUpdate,
+    /// `CONTINUATION`
+    Continuation,
+    /// A frame of an extension type, with its type code.
+    Unknown(u8),
+}
+
+/// The state of a connection, as reported to a [`FrameObserver`].
+///
+/// [`FrameObserver`]: trait.FrameObserver.html
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConnectionState {
+    /// The handshake was started and frames are being exchanged.
+    Open,
+
+    /// The connection is done, its remaining frames are being flushed
+    /// before the I/O is shut down.
+    Closing(Reason),
+
+    /// The I/O was shut down.
+    Closed(Reason),
+}
+
+/// An iterator over the header fields of a [`FrameEvent`].
+///
+/// Pseudo header fields come first, then the regular fields in the order
+/// they were sent or received.
+///
+/// [`FrameEvent`]: struct.FrameEvent.html
+#[derive(Debug)]
+pub struct HeaderFields<'a> {
+    pseudo: vec::IntoIter<(&'a str, &'a [u8])>,
+    fields: header::Iter<'a, HeaderValue>,
+}
+
+// ===== impl FrameEvent =====
+
+impl<'a> FrameEvent<'a> {
+    /// Creates the event of the frame whose 9 byte header is `head`.
+    pub(crate) fn new(head: &[u8], headers: Option<(&'a frame::Pseudo, &'a HeaderMap)>) -> Self {
+        let len = (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize;
+        let parsed = frame::Head::parse(head);
+
+        FrameEvent {
+            kind: FrameKind::from(head[3]),
+            flags: parsed.flag(),
+            stream_id: StreamId::from_internal(parsed.stream_id()),
+            len,
+            headers,
+        }
+    }
+
+    /// Returns the type of the frame.
+    pub fn kind(&self) -> FrameKind {
+        self.kind
+    }
+
+    /// Returns the flags of the frame.
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns the stream the frame belongs to.
+    ///
+    /// Connection level frames have the stream ID zero.
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id.clone()
+    }
+
+    /// Returns the length of the frame payload, padding included.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the frame has no payload.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the header fields of the header block completed by this frame.
+    ///
+    /// Only the `HEADERS`, `PUSH_PROMISE` or `CONTINUATION` frame carrying
+    /// the `END_HEADERS` flag has header fields. Received fields over the
+    /// max header list size are missing.
+    pub fn header_fields(&self) -> Option<HeaderFields<'a>> {
+        let (pseudo, fields) = self.headers?;
+        let mut list = Vec::new();
+
+        if let Some(ref method) = pseudo.method {
+            list.push((":method", method.as_str().as_bytes()));
+        }
+        if let Some(ref scheme) = pseudo.scheme {
+            list.push((":scheme", scheme.as_bytes()));
+        }
+        if let Some(ref authority) = pseudo.authority {
+            list.push((":authority", authority.as_bytes()));
+        }
+        if let Some(ref path) = pseudo.path {
+            list.push((":path", path.as_bytes()));
+        }
+        if let Some(ref status) = pseudo.status {
+            list.push((":status", status.as_str().as_bytes()));
+        }
+
+        Some(HeaderFields {
+            pseudo: list.into_iter(),
+            fields: fields.iter(),
+        })
+    }
+}
+
+impl<'a> fmt::Debug for FrameEvent<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = fmt.debug_struct("FrameEvent");
+        builder
+            .field("kind", &self.kind)
+            .field("flags", &format_args!("{:#x}", self.flags))
+            .field("stream_id", &self.stream_id)
+            .field("len", &self.len);
+
+        if let Some((pseudo, fields)) = self.headers {
+            builder.field("pseudo", pseudo).field("fields", fields);
+        }
+
+        builder.finish()
+    }
+}
+
+// ===== impl FrameKind =====
+
+impl From<u8> for FrameKind {
+    fn from(src: u8) -> FrameKind {
+        use self::FrameKind::*;
+
+        match src {
+            0 => Data,
+            1 => Headers,
+            2 => Priority,
+            3 => Reset,
+            4 => Settings,
+            5 => PushPromise,
+            6 => Ping,
+            7 => GoAway,
+            8 => WindowUpdate,
+            9 => Continuation,
+            _ => Unknown(src),
+        }
+    }
+}
+
+// ===== impl HeaderFields =====
+
+impl<'a> Iterator for HeaderFields<'a> {
+    type Item = (&'a str, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(field) = self.pseudo.next() {
+            return Some(field);
+        }
+
+        self.fields
+            .next()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+    }
+}
@@ -0,0 +1,119 @@
+use std::cmp;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Padding added to the `DATA` and `HEADERS` frames sent on a connection.
+///
+/// Padding obscures the size of messages, which can otherwise reveal what is
+/// being exchanged even when the connection is encrypted. It is set with
+/// [`client::Builder::padding`] and [`server::Builder::padding`].
+///
+/// A padded frame carries a Pad Length octet followed by up to 255 octets of
+/// padding. Frames are padded as much as the policy asks, up to the max frame
+/// size and, for `DATA` frames, up to the flow control window not yet
+/// assigned to streams. Padding is counted by flow control like data is.
+///
+/// # Examples
+///
+/// ```
+/// use h2::Padding;
+///
+/// // Round frame payloads up to a multiple of 64 octets.
+/// let padding = Padding::Bucket(64);
+/// # drop(padding);
+/// ```
+///
+/// [`client::Builder::padding`]: client/struct.Builder.html#method.padding
+/// [`server::Builder::padding`]: server/struct.Builder.html#method.padding
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Padding {
+    /// Pads every frame with the given number of octets.
+    Fixed(u8),
+
+    /// Pads frames so that their payload length, padding included, is a
+    /// multiple of the given number of octets.
+    ///
+    /// The bucket size must not be zero.
+    Bucket(usize),
+
+    /// Pads every frame with a random number of octets, in the given
+    /// inclusive range.
+    Random {
+        /// The minimum number of padding octets
+        min: u8,
+        /// The maximum number of padding octets
+        max: u8,
+    },
+}
+
+/// Computes the padding of frames according to a `Padding` policy.
+#[derive(Debug)]
+pub struct Padder {
+    padding: Padding,
+
+    /// State of the xorshift generator used for `Padding::Random`
+    rng: u64,
+}
+
+// ===== impl Padding =====
+
+impl Padding {
+    pub(crate) fn assert_valid(&self) {
+        match *self {
+            Padding::Fixed(_) => {}
+            Padding::Bucket(size) => assert!(size > 0, "padding bucket size is zero"),
+            Padding::Random { min, max } => assert!(min <= max, "invalid padding range"),
+        }
+    }
+}
+
+// ===== impl Padder =====
+
+impl Padder {
+    pub fn new(padding: Padding) -> Padder {
+        padding.assert_valid();
+
+        // Seed from the random keys of the std hasher, padding does not need
+        // a cryptographically secure generator.
+        let seed = RandomState::new().build_hasher().finish();
+
+        Padder {
+            padding,
+            rng: seed | 1,
+        }
+    }
+
+    /// Returns the number of padding octets of a frame with `len` octets of
+    /// payload, not counting the Pad Length octet.
+    ///
+    /// The padding is at most `max` octets.
+    pub fn pad_len(&mut self, len: usize, max: usize) -> u8 {
+        let pad = match self.padding {
+            Padding::Fixed(n) => n as usize,
+            Padding::Bucket(size) => (size - (len + 1) % size) % size,
+            Padding::Random { min, max } => {
+                let range = (max - min) as u64 + 1;
+                min as usize + (self.next_u64() % range) as usize
+            }
+        };
+
+        cmp::min(pad, cmp::min(max, 255)) as u8
+    }
+
+    /// Returns the largest padding the policy may ask for.
+    pub fn max_pad_len(&self) -> usize {
+        match self.padding {
+            Padding::Fixed(n) => n as usize,
+            Padding::Bucket(size) => cmp::min(size - 1, 255),
+            Padding::Random { max, .. } => max as usize,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
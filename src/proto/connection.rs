@@ -1,15 +1,22 @@
 use crate::codec::RecvError;
+use crate::flush::FlushStrategy;
 use crate::frame::{Reason, StreamId};
+use crate::hpack::HpackStats;
+use crate::observer::{ConnectionState, FrameObserver};
+use crate::padding::Padding;
 use crate::{client, frame, proto, server};
 
 use crate::frame::DEFAULT_INITIAL_WINDOW_SIZE;
 use crate::proto::*;
+use crate::timer::{Sleep, Timer};
 
 use bytes::{Bytes, IntoBuf};
 use futures::{ready, Stream};
+use std::future::Future;
 use std::io;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -38,12 +45,24 @@ where
     /// Ping/pong handler
     ping_pong: PingPong,
 
+    /// Sends keep-alive PINGs, if configured
+    keep_alive: Option<KeepAlive>,
+
+    /// Deadline and progress of a user requested drain, if any.
+    drain: Option<Drain>,
+
     /// Connection settings
     settings: Settings,
 
+    /// Handshake, SETTINGS ACK and idle timeouts
+    timeouts: Timeouts,
+
     /// Stream state handler
     streams: Streams<B::Buf, P>,
 
+    /// Observes the state transitions, if set
+    observer: Option<Arc<dyn FrameObserver>>,
+
     /// Client or server
     _phantom: PhantomData<P>,
 }
@@ -55,6 +74,14 @@ pub(crate) struct Config {
     pub reset_stream_duration: Duration,
     pub reset_stream_max: usize,
     pub settings: frame::Settings,
+    pub timer: Option<Arc<dyn Timer>>,
+    pub idle_timeout: Option<Duration>,
+    pub settings_ack_timeout: Option<Duration>,
+    pub keep_alive_interval: Option<Duration>,
+    pub keep_alive_timeout: Duration,
+    pub padding: Option<Padding>,
+    pub flush: FlushStrategy,
+    pub observer: Option<Arc<dyn FrameObserver>>,
 }
 
 #[derive(Debug)]
@@ -87,20 +114,42 @@ where
             local_push_enabled: config.settings.is_push_enabled(),
             local_reset_duration: config.reset_stream_duration,
             local_reset_max: config.reset_stream_max,
+            timer: config.timer.clone(),
             remote_init_window_sz: DEFAULT_INITIAL_WINDOW_SIZE,
             remote_max_initiated: config
                 .settings
                 .max_concurrent_streams()
                 .map(|max| max as usize),
+            padding: config.padding,
+            flush: config.flush,
         });
+        let keep_alive = match (&config.timer, config.keep_alive_interval) {
+            (Some(timer), Some(interval)) => Some(KeepAlive::new(
+                timer.clone(),
+                interval,
+                config.keep_alive_timeout,
+            )),
+            _ => None,
+        };
+        if let Some(ref observer) = config.observer {
+            observer.state_changed(ConnectionState::Open);
+        }
         Connection {
             state: State::Open,
             error: None,
             codec,
             go_away: GoAway::new(),
             ping_pong: PingPong::new(),
-            settings: Settings::new(),
+            keep_alive,
+            drain: None,
+            settings: Settings::new(config.settings),
+            timeouts: Timeouts::new(
+                config.timer,
+                config.idle_timeout,
+                config.settings_ack_timeout,
+            ),
             streams,
+            observer: config.observer,
             _phantom: PhantomData,
         }
     }
@@ -109,6 +158,12 @@ where
         self.streams.set_target_connection_window_size(size);
     }
 
+    /// Fails the connection if `sleep` completes before the remote's first
+    /// SETTINGS frame is received.
+    pub fn set_handshake_timeout(&mut self, sleep: Sleep) {
+        self.timeouts.set_handshake(sleep);
+    }
+
     /// Returns `Ready` when the connection is ready to receive a frame.
     ///
     /// Returns `RecvError` as this may raise errors that are caused by delayed
@@ -145,6 +200,41 @@ where
         self.go_away.go_away_now(frame);
     }
 
+    pub fn go_away_gracefully(&mut self) {
+        if self.go_away.is_going_away() {
+            // No reason to start a new one.
+            return;
+        }
+
+        if !P::is_server() {
+            // The only streams a server can initiate are pushed streams, which
+            // are reserved by a PUSH_PROMISE on a stream we opened. There are
+            // no requests in flight to wait for, so unlike a server, a single
+            // GOAWAY naming the last processed push is enough. The connection
+            // closes once the remaining streams complete.
+            let last_processed_id = self.streams.last_processed_id();
+            self.go_away(last_processed_id, Reason::NO_ERROR);
+            return;
+        }
+
+        // According to http://httpwg.org/specs/rfc7540.html#GOAWAY:
+        //
+        // > A server that is attempting to gracefully shut down a connection
+        // > SHOULD send an initial GOAWAY frame with the last stream
+        // > identifier set to 2^31-1 and a NO_ERROR code. This signals to the
+        // > client that a shutdown is imminent and that initiating further
+        // > requests is prohibited. After allowing time for any in-flight
+        // > stream creation (at least one round-trip time), the server can
+        // > send another GOAWAY frame with an updated last stream identifier.
+        // > This ensures that a connection can be cleanly shut down without
+        // > losing requests.
+        self.go_away(StreamId::MAX, Reason::NO_ERROR);
+
+        // We take the advice of waiting 1 RTT literally, and wait
+        // for a pong before proceeding.
+        self.ping_pong.ping_shutdown();
+    }
+
     pub fn go_away_from_user(&mut self, e: Reason) {
         let last_processed_id = self.streams.last_processed_id();
         let frame = frame::GoAway::new(last_processed_id, e);
@@ -154,6 +244,18 @@ where
         self.streams.recv_err(&proto::Error::Proto(e));
     }
 
+    fn set_state(&mut self, state: State) {
+        if let Some(ref observer) = self.observer {
+            observer.state_changed(match state {
+                State::Open => ConnectionState::Open,
+                State::Closing(reason) => ConnectionState::Closing(reason),
+                State::Closed(reason) => ConnectionState::Closed(reason),
+            });
+        }
+
+        self.state = state;
+    }
+
     fn take_error(&mut self, ours: Reason) -> Poll<Result<(), proto::Error>> {
         let reason = if let Some(theirs) = self.error.take() {
             match (ours, theirs) {
@@ -179,22 +281,148 @@ where
 
     /// Closes the connection by transitioning to a GOAWAY state
     /// iff there are no streams or references
-    pub fn maybe_close_connection_if_no_streams(&mut self) {
+    ///
+    /// Returns true if the connection is closing.
+    fn maybe_close_connection_if_no_streams(&mut self) -> bool {
         // If we poll() and realize that there are no streams or references
         // then we can close the connection by transitioning to GOAWAY
         if !self.streams.has_streams_or_other_references() {
             self.go_away_now(Reason::NO_ERROR);
+            return true;
         }
+
+        false
     }
 
     pub(crate) fn take_user_pings(&mut self) -> Option<UserPings> {
         self.ping_pong.take_user_pings()
     }
 
+    pub(crate) fn hpack_stats(&self) -> HpackStats {
+        self.codec.hpack_stats()
+    }
+
+    /// Bounds an in-progress graceful shutdown by `deadline`.
+    ///
+    /// Once `deadline` completes, the connection is shutdown abruptly with
+    /// `reason`, regardless of any remaining active streams.
+    pub fn drain<D>(&mut self, deadline: D, reason: Reason)
+    where
+        D: Future<Output = ()> + Send + 'static,
+    {
+        self.drain = Some(Drain::new(deadline, reason));
+    }
+
+    /// Returns `Ready` with the number of active streams each time it changes
+    /// while the connection is draining.
+    pub fn poll_drain_progress(&mut self) -> Poll<usize> {
+        match self.drain {
+            Some(ref mut drain) => drain.poll_progress(self.streams.num_open_streams()),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Polls the drain deadline, timeouts and keep-alive.
+    ///
+    /// Returns `Ready(Ok(()))` if any of them changed the state of the
+    /// connection, in which case the connection must be polled again.
+    fn poll_timers(&mut self, cx: &mut Context) -> Poll<Result<(), proto::Error>> {
+        let drain = self.poll_drain_deadline(cx);
+        let timeouts = self.poll_timeouts(cx);
+
+        if let Err(e) = self.poll_keep_alive(cx) {
+            // Reset all active streams
+            self.streams.recv_err(&e);
+            return Poll::Ready(Err(e));
+        }
+
+        if drain.is_ready() || timeouts.is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+
+        Poll::Pending
+    }
+
+    /// Escalates a drain to an abrupt shutdown once its deadline is reached.
+    fn poll_drain_deadline(&mut self, cx: &mut Context) -> Poll<()> {
+        let reason = match self.drain {
+            Some(ref mut drain) => ready!(drain.poll_deadline(cx)),
+            None => return Poll::Pending,
+        };
+
+        log::debug!(
+            "drain deadline reached; active streams={}; reason={:?}",
+            self.streams.num_open_streams(),
+            reason
+        );
+        self.go_away_from_user(reason);
+        Poll::Ready(())
+    }
+
+    /// Enforces the handshake, SETTINGS ACK and idle timeouts.
+    fn poll_timeouts(&mut self, cx: &mut Context) -> Poll<()> {
+        if self.timeouts.poll_handshake(cx).is_ready() {
+            log::debug!("handshake timed out; no SETTINGS received from remote");
+            self.go_away_on_timeout(Reason::SETTINGS_TIMEOUT);
+            return Poll::Ready(());
+        }
+
+        if self.timeouts.poll_settings_ack(cx).is_ready() {
+            // > If the sender of a SETTINGS frame does not receive an
+            // > acknowledgement within a reasonable amount of time, it MAY
+            // > issue a connection error of type SETTINGS_TIMEOUT.
+            log::debug!("SETTINGS ACK timed out");
+            self.go_away_on_timeout(Reason::SETTINGS_TIMEOUT);
+            return Poll::Ready(());
+        }
+
+        let is_idle = !self.streams.has_streams() && !self.go_away.is_going_away();
+        ready!(self.timeouts.poll_idle(cx, is_idle));
+        log::debug!("connection idle timeout; going away");
+        self.go_away_gracefully();
+        Poll::Ready(())
+    }
+
+    /// Sends keep-alive PINGs, failing the connection if one isn't
+    /// acknowledged in time.
+    fn poll_keep_alive(&mut self, cx: &mut Context) -> Result<(), proto::Error> {
+        let keep_alive = match self.keep_alive {
+            Some(ref mut keep_alive) => keep_alive,
+            None => return Ok(()),
+        };
+
+        loop {
+            match keep_alive.poll(cx) {
+                Poll::Ready(keep_alive::Event::SendPing) => {
+                    log::trace!("sending keep-alive PING");
+                    self.ping_pong.ping_keep_alive();
+                }
+                Poll::Ready(keep_alive::Event::TimedOut) => {
+                    log::debug!("keep-alive PING timed out");
+                    let e = io::Error::new(io::ErrorKind::TimedOut, "keep-alive timed out");
+                    return Err(e.into());
+                }
+                Poll::Pending => return Ok(()),
+            }
+        }
+    }
+
+    fn go_away_on_timeout(&mut self, reason: Reason) {
+        // Reset all active streams
+        self.streams.recv_err(&reason.into());
+        self.go_away_now(reason);
+    }
+
     /// Advances the internal state of the connection.
     pub fn poll(&mut self, cx: &mut Context) -> Poll<Result<(), proto::Error>> {
         use crate::codec::RecvError::*;
 
+        if let State::Open = self.state {
+            if let Poll::Ready(Err(e)) = self.poll_timers(cx) {
+                return Poll::Ready(Err(e));
+            }
+        }
+
         loop {
             // TODO: probably clean up this glob of code
             match self.state {
@@ -202,7 +430,7 @@ where
                 State::Open => {
                     match self.poll2(cx) {
                         // The connection has shutdown normally
-                        Poll::Ready(Ok(())) => self.state = State::Closing(Reason::NO_ERROR),
+                        Poll::Ready(Ok(())) => self.set_state(State::Closing(Reason::NO_ERROR)),
                         // The connection is not ready to make progress
                         Poll::Pending => {
                             // Ensure all window updates have been sent.
@@ -217,7 +445,21 @@ where
                                 continue;
                             }
 
-                            return Poll::Pending;
+                            // A client closes once the user dropped every
+                            // handle to it. This is checked after the frames
+                            // above were written, so resets of dropped or
+                            // canceled streams are sent before the GOAWAY.
+                            if !P::is_server()
+                                && !self.go_away.is_going_away()
+                                && self.maybe_close_connection_if_no_streams()
+                            {
+                                continue;
+                            }
+
+                            // Timers are polled again now that the frames have
+                            // been processed, so they observe the latest state.
+                            ready!(self.poll_timers(cx))?;
+                            continue;
                         }
                         // Attempting to read a frame resulted in a connection level
                         // error. This is handled by setting a GOAWAY frame followed by
@@ -230,7 +472,7 @@ where
                             if let Some(reason) = self.go_away.going_away_reason() {
                                 if reason == e {
                                     log::trace!("    -> already going away");
-                                    self.state = State::Closing(e);
+                                    self.set_state(State::Closing(e));
                                     continue;
                                 }
                             }
@@ -268,7 +510,7 @@ where
                     ready!(self.codec.shutdown(cx))?;
 
                     // Transition the state to error
-                    self.state = State::Closed(reason);
+                    self.set_state(State::Closed(reason));
                 }
                 State::Closed(reason) => return self.take_error(reason),
             }
@@ -281,7 +523,7 @@ where
         // This happens outside of the loop to prevent needing to do a clock
         // check and then comparison of the queue possibly multiple times a
         // second (and thus, the clock wouldn't have changed enough to matter).
-        self.clear_expired_reset_streams();
+        self.clear_expired_reset_streams(cx);
 
         loop {
             // First, ensure that the `Connection` is able to receive a frame
@@ -308,7 +550,13 @@ where
             }
             ready!(self.poll_ready(cx))?;
 
-            match ready!(Pin::new(&mut self.codec).poll_next(cx)?) {
+            let frame = ready!(Pin::new(&mut self.codec).poll_next(cx)?);
+
+            if let Some(ref frame) = frame {
+                self.settings.recv_frame(frame)?;
+            }
+
+            match frame {
                 Some(Headers(frame)) => {
                     log::trace!("recv HEADERS; frame={:?}", frame);
                     self.streams.recv_headers(frame)?;
@@ -327,7 +575,12 @@ where
                 }
                 Some(Settings(frame)) => {
                     log::trace!("recv SETTINGS; frame={:?}", frame);
-                    self.settings.recv_settings(frame);
+                    if frame.is_ack() {
+                        self.timeouts.recv_settings_ack();
+                    } else {
+                        self.timeouts.recv_settings();
+                    }
+                    self.settings.recv_settings(frame, &mut self.codec);
                 }
                 Some(GoAway(frame)) => {
                     log::trace!("recv GOAWAY; frame={:?}", frame);
@@ -349,6 +602,10 @@ where
 
                         let last_processed_id = self.streams.last_processed_id();
                         self.go_away(last_processed_id, Reason::NO_ERROR);
+                    } else if status.is_keep_alive() {
+                        if let Some(ref mut keep_alive) = self.keep_alive {
+                            keep_alive.recv_pong();
+                        }
                     }
                 }
                 Some(WindowUpdate(frame)) => {
@@ -359,6 +616,13 @@ where
                     log::trace!("recv PRIORITY; frame={:?}", frame);
                     // TODO: handle
                 }
+                Some(Unknown(frame)) => {
+                    // Frames of unknown types MUST be ignored
+                    log::trace!("recv unknown frame; frame={:?}", frame);
+                }
+                Some(Continuation(_)) => {
+                    unreachable!("CONTINUATION frames are merged by the codec");
+                }
                 None => {
                     log::trace!("codec closed");
                     self.streams.recv_eof(false).expect("mutex poisoned");
@@ -368,8 +632,15 @@ where
         }
     }
 
-    fn clear_expired_reset_streams(&mut self) {
-        self.streams.clear_expired_reset_streams();
+    fn clear_expired_reset_streams(&mut self, cx: &mut Context) {
+        // With a timer, the connection is woken up when the next stream
+        // expires, instead of waiting to be polled for some other reason.
+        loop {
+            let next = self.streams.clear_expired_reset_streams();
+            if self.timeouts.poll_reset_expiration(cx, next).is_pending() {
+                return;
+            }
+        }
     }
 }
 
@@ -392,31 +663,6 @@ where
     pub fn next_incoming(&mut self) -> Option<StreamRef<B::Buf>> {
         self.streams.next_incoming()
     }
-
-    // Graceful shutdown only makes sense for server peers.
-    pub fn go_away_gracefully(&mut self) {
-        if self.go_away.is_going_away() {
-            // No reason to start a new one.
-            return;
-        }
-
-        // According to http://httpwg.org/specs/rfc7540.html#GOAWAY:
-        //
-        // > A server that is attempting to gracefully shut down a connection
-        // > SHOULD send an initial GOAWAY frame with the last stream
-        // > identifier set to 2^31-1 and a NO_ERROR code. This signals to the
-        // > client that a shutdown is imminent and that initiating further
-        // > requests is prohibited. After allowing time for any in-flight
-        // > stream creation (at least one round-trip time), the server can
-        // > send another GOAWAY frame with an updated last stream identifier.
-        // > This ensures that a connection can be cleanly shut down without
-        // > losing requests.
-        self.go_away(StreamId::MAX, Reason::NO_ERROR);
-
-        // We take the advice of waiting 1 RTT literally, and wait
-        // for a pong before proceeding.
-        self.ping_pong.ping_shutdown();
-    }
 }
 
 impl<T, P, B> Drop for Connection<T, P, B>
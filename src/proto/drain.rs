@@ -0,0 +1,73 @@
+use crate::frame::Reason;
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Tracks a user requested drain of the connection.
+///
+/// A drain starts out as a graceful shutdown. If the deadline completes before
+/// all active streams have finished, the connection is shutdown abruptly with
+/// the configured reason.
+pub(super) struct Drain {
+    /// Completes once we should stop waiting for active streams.
+    ///
+    /// Set to `None` once the deadline has been reached.
+    deadline: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+
+    /// The reason sent to the peer if the deadline is reached.
+    reason: Reason,
+
+    /// The number of active streams last reported to the user.
+    reported: Option<usize>,
+}
+
+impl Drain {
+    pub fn new<D>(deadline: D, reason: Reason) -> Self
+    where
+        D: Future<Output = ()> + Send + 'static,
+    {
+        Drain {
+            deadline: Some(Box::pin(deadline)),
+            reason,
+            reported: None,
+        }
+    }
+
+    /// Polls the deadline, returning the reason to shutdown with once it has
+    /// been reached.
+    ///
+    /// Only returns `Ready` once.
+    pub fn poll_deadline(&mut self, cx: &mut Context) -> Poll<Reason> {
+        match self.deadline {
+            Some(ref mut deadline) => {
+                futures::ready!(deadline.as_mut().poll(cx));
+                self.deadline = None;
+                Poll::Ready(self.reason)
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    /// Returns `Ready` with the number of active streams if it changed since
+    /// the last time it was reported.
+    pub fn poll_progress(&mut self, active: usize) -> Poll<usize> {
+        if self.reported == Some(active) {
+            return Poll::Pending;
+        }
+
+        self.reported = Some(active);
+        Poll::Ready(active)
+    }
+}
+
+impl fmt::Debug for Drain {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Drain")
+            .field("expired", &self.deadline.is_none())
+            .field("reason", &self.reason)
+            .field("reported", &self.reported)
+            .finish()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::timer::{Sleep, Timer};
+
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Periodically checks that the remote is still responsive.
+///
+/// Every `interval`, a PING is sent to the remote. If it isn't acknowledged
+/// within `timeout`, the connection is considered dead.
+pub(super) struct KeepAlive {
+    timer: Arc<dyn Timer>,
+    interval: Duration,
+    timeout: Duration,
+    state: State,
+}
+
+enum State {
+    /// Waiting until it is time to send the next PING.
+    Idle(Sleep),
+
+    /// A PING has been sent, waiting for it to be acknowledged.
+    PingSent(Sleep),
+}
+
+#[derive(Debug)]
+pub(super) enum Event {
+    /// A PING should be sent.
+    SendPing,
+
+    /// The last PING was not acknowledged in time.
+    TimedOut,
+}
+
+impl KeepAlive {
+    pub fn new(timer: Arc<dyn Timer>, interval: Duration, timeout: Duration) -> Self {
+        let state = State::Idle(timer.sleep(interval));
+
+        KeepAlive {
+            timer,
+            interval,
+            timeout,
+            state,
+        }
+    }
+
+    /// Called once the keep-alive PING has been acknowledged.
+    pub fn recv_pong(&mut self) {
+        if let State::PingSent(_) = self.state {
+            self.state = State::Idle(self.timer.sleep(self.interval));
+        }
+    }
+
+    pub fn poll(&mut self, cx: &mut Context) -> Poll<Event> {
+        match self.state {
+            State::Idle(ref mut sleep) => {
+                futures::ready!(sleep.as_mut().poll(cx));
+                self.state = State::PingSent(self.timer.sleep(self.timeout));
+                Poll::Ready(Event::SendPing)
+            }
+            State::PingSent(ref mut sleep) => {
+                futures::ready!(sleep.as_mut().poll(cx));
+                Poll::Ready(Event::TimedOut)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for KeepAlive {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self.state {
+            State::Idle(_) => "Idle",
+            State::PingSent(_) => "PingSent",
+        };
+
+        fmt.debug_struct("KeepAlive")
+            .field("interval", &self.interval)
+            .field("timeout", &self.timeout)
+            .field("state", &state)
+            .finish()
+    }
+}
@@ -1,10 +1,13 @@
 mod connection;
+mod drain;
 mod error;
 mod go_away;
+mod keep_alive;
 mod peer;
 mod ping_pong;
 mod settings;
 mod streams;
+mod timeout;
 
 pub(crate) use self::connection::{Config, Connection};
 pub(crate) use self::error::Error;
@@ -15,9 +18,12 @@ pub(crate) use self::streams::{Open, PollReset, Prioritized};
 
 use crate::codec::Codec;
 
+use self::drain::Drain;
 use self::go_away::GoAway;
+use self::keep_alive::KeepAlive;
 use self::ping_pong::PingPong;
 use self::settings::Settings;
+use self::timeout::Timeouts;
 
 use crate::frame::{self, Frame};
 
@@ -33,3 +39,4 @@ pub type WindowSize = u32;
 pub const MAX_WINDOW_SIZE: WindowSize = (1 << 31) - 1;
 pub const DEFAULT_RESET_STREAM_MAX: usize = 10;
 pub const DEFAULT_RESET_STREAM_SECS: u64 = 30;
+pub const DEFAULT_KEEP_ALIVE_TIMEOUT_SECS: u64 = 20;
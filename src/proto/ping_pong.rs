@@ -16,6 +16,7 @@ pub(crate) struct PingPong {
     pending_ping: Option<PendingPing>,
     pending_pong: Option<PingPayload>,
     user_pings: Option<UserPingsRx>,
+    keep_alive: Option<PendingPing>,
 }
 
 #[derive(Debug)]
@@ -45,6 +46,7 @@ pub(crate) enum ReceivedPing {
     MustAck,
     Unknown,
     Shutdown,
+    KeepAlive,
 }
 
 /// No user ping pending.
@@ -66,6 +68,7 @@ impl PingPong {
             pending_ping: None,
             pending_pong: None,
             user_pings: None,
+            keep_alive: None,
         }
     }
 
@@ -93,6 +96,18 @@ impl PingPong {
         });
     }
 
+    /// Queues a keep-alive PING, unless one is already outstanding.
+    pub(crate) fn ping_keep_alive(&mut self) {
+        if self.keep_alive.is_some() {
+            return;
+        }
+
+        self.keep_alive = Some(PendingPing {
+            payload: Ping::KEEPALIVE,
+            sent: false,
+        });
+    }
+
     /// Process a ping
     pub(crate) fn recv_ping(&mut self, ping: Ping) -> ReceivedPing {
         // The caller should always check that `send_pongs` returns ready before
@@ -115,6 +130,15 @@ impl PingPong {
                 self.pending_ping = Some(pending);
             }
 
+            if let Some(pending) = self.keep_alive.take() {
+                if &pending.payload == ping.payload() {
+                    log::trace!("recv PING KEEPALIVE ack");
+                    return ReceivedPing::KeepAlive;
+                }
+
+                self.keep_alive = Some(pending);
+            }
+
             if let Some(ref users) = self.user_pings {
                 if ping.payload() == &Ping::USER && users.receive_pong() {
                     log::trace!("recv PING USER ack");
@@ -167,6 +191,18 @@ impl PingPong {
         T: AsyncWrite + Unpin,
         B: Buf + Unpin,
     {
+        if let Some(ref mut ping) = self.keep_alive {
+            if !ping.sent {
+                if !dst.poll_ready(cx)?.is_ready() {
+                    return Poll::Pending;
+                }
+
+                dst.buffer(Ping::new(ping.payload).into())
+                    .expect("invalid ping frame");
+                ping.sent = true;
+            }
+        }
+
         if let Some(ref mut ping) = self.pending_ping {
             if !ping.sent {
                 if !dst.poll_ready(cx)?.is_ready() {
@@ -205,6 +241,13 @@ impl ReceivedPing {
             _ => false,
         }
     }
+
+    pub(crate) fn is_keep_alive(&self) -> bool {
+        match *self {
+            ReceivedPing::KeepAlive => true,
+            _ => false,
+        }
+    }
 }
 
 // ===== impl UserPings =====
@@ -9,17 +9,65 @@ pub(crate) struct Settings {
     /// the socket first then the settings applied **before** receiving any
     /// further frames.
     pending: Option<frame::Settings>,
+
+    /// Our SETTINGS frame, sent during the handshake, waiting to be
+    /// acknowledged by the remote.
+    local: Option<frame::Settings>,
+
+    /// Whether the SETTINGS frame of the remote's preface was received.
+    recv_preface: bool,
 }
 
 impl Settings {
-    pub fn new() -> Self {
-        Settings { pending: None }
+    pub fn new(local: frame::Settings) -> Self {
+        Settings {
+            pending: None,
+            local: Some(local),
+            recv_preface: false,
+        }
+    }
+
+    /// Checks that `frame` may be received at this point of the remote's
+    /// preface.
+    pub fn recv_frame<T>(&mut self, frame: &frame::Frame<T>) -> Result<(), RecvError> {
+        if self.recv_preface {
+            return Ok(());
+        }
+
+        // > The server connection preface consists of a potentially empty
+        // > SETTINGS frame (Section 6.5) that MUST be the first frame the
+        // > server sends in the HTTP/2 connection.
+        //
+        // The client connection preface ends with a SETTINGS frame as well.
+        //
+        // > Clients and servers MUST treat an invalid connection preface as a
+        // > connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+        match frame {
+            frame::Frame::Settings(settings) if !settings.is_ack() => {
+                self.recv_preface = true;
+                Ok(())
+            }
+            // Unknown frames are ignored, wherever they are
+            frame::Frame::Unknown(_) => Ok(()),
+            _ => {
+                proto_err!(conn: "recv_frame: expected SETTINGS as first frame; frame={:?}", frame);
+                Err(RecvError::Connection(frame::Reason::PROTOCOL_ERROR))
+            }
+        }
     }
 
-    pub fn recv_settings(&mut self, frame: frame::Settings) {
+    pub fn recv_settings<T, B>(&mut self, frame: frame::Settings, codec: &mut Codec<T, B>) {
         if frame.is_ack() {
             log::debug!("received remote settings ack");
-        // TODO: handle acks
+
+            // Settings that only constrain the remote are enforced once the
+            // remote has acknowledged them.
+            if let Some(local) = self.local.take() {
+                if let Some(val) = local.header_table_size() {
+                    log::trace!("applying local header table size; size={}", val);
+                    codec.set_recv_header_table_size(val as usize);
+                }
+            }
         } else {
             assert!(self.pending.is_none());
             self.pending = Some(frame);
@@ -58,6 +106,10 @@ impl Settings {
                 dst.set_max_send_frame_size(val as usize);
             }
 
+            if let Some(val) = settings.header_table_size() {
+                dst.set_send_header_table_size(val as usize);
+            }
+
             streams.apply_remote_settings(settings)?;
         }
 
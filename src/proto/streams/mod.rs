@@ -24,10 +24,14 @@ use self::state::State;
 use self::store::Store;
 use self::stream::Stream;
 
+use crate::flush::FlushStrategy;
 use crate::frame::{StreamId, StreamIdOverflow};
+use crate::padding::Padding;
 use crate::proto::*;
+use crate::timer::Timer;
 
 use bytes::Bytes;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Debug)]
@@ -53,9 +57,18 @@ pub struct Config {
     /// Maximum number of locally reset streams to keep at a time
     pub local_reset_max: usize,
 
+    /// Clock used to expire locally reset streams
+    pub timer: Option<Arc<dyn Timer>>,
+
     /// Initial window size of remote initiated streams
     pub remote_init_window_sz: WindowSize,
 
     /// Maximum number of remote initiated streams
     pub remote_max_initiated: Option<usize>,
+
+    /// Padding of sent DATA frames
+    pub padding: Option<Padding>,
+
+    /// When to flush the frames buffered by the codec
+    pub flush: FlushStrategy,
 }
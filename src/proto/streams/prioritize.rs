@@ -1,7 +1,9 @@
 use super::store::Resolve;
 use super::*;
 
+use crate::flush::Flusher;
 use crate::frame::{Reason, StreamId};
+use crate::padding::Padder;
 
 use crate::codec::UserError;
 use crate::codec::UserError::*;
@@ -10,7 +12,7 @@ use bytes::buf::Take;
 use futures::ready;
 use std::io;
 use std::task::{Context, Poll, Waker};
-use std::{cmp, fmt, mem};
+use std::{cmp, fmt};
 
 /// # Warning
 ///
@@ -50,18 +52,26 @@ pub(super) struct Prioritize {
     /// Stream ID of the last stream opened.
     last_opened_id: StreamId,
 
-    /// What `DATA` frame is currently being sent in the codec.
-    in_flight_data_frame: InFlightData,
+    /// What `DATA` frames are currently being sent in the codec.
+    in_flight_data_frames: Vec<InFlightData>,
+
+    /// Pads sent `DATA` frames
+    padder: Option<Padder>,
+
+    /// Decides when the codec is flushed
+    flusher: Flusher,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 enum InFlightData {
-    /// There is no `DATA` frame in flight.
-    Nothing,
     /// There is a `DATA` frame in flight belonging to the given stream.
     DataFrame(store::Key),
+    /// There is a `DATA` frame in flight holding the rest of the given
+    /// stream's data. The stream cannot send anything else until it is
+    /// reclaimed.
+    SplitDataFrame(store::Key),
     /// There was a `DATA` frame, but the stream's queue was since cleared.
-    Drop,
+    Drop(store::Key),
 }
 
 pub(crate) struct Prioritized<B> {
@@ -93,7 +103,9 @@ impl Prioritize {
             pending_open: store::Queue::new(),
             flow,
             last_opened_id: StreamId::ZERO,
-            in_flight_data_frame: InFlightData::Nothing,
+            in_flight_data_frames: Vec::new(),
+            padder: config.padding.map(Padder::new),
+            flusher: Flusher::new(config.flush),
         }
     }
 
@@ -505,9 +517,16 @@ impl Prioritize {
                 Some(frame) => {
                     log::trace!("writing frame={:?}", frame);
 
-                    debug_assert_eq!(self.in_flight_data_frame, InFlightData::Nothing);
                     if let Frame::Data(ref frame) = frame {
-                        self.in_flight_data_frame = InFlightData::DataFrame(frame.payload().stream);
+                        let payload = frame.payload();
+
+                        self.in_flight_data_frames.push(
+                            if payload.inner.get_ref().remaining() > payload.remaining() {
+                                InFlightData::SplitDataFrame(payload.stream)
+                            } else {
+                                InFlightData::DataFrame(payload.stream)
+                            },
+                        );
                     }
                     dst.buffer(frame).expect("invalid frame");
 
@@ -518,6 +537,15 @@ impl Prioritize {
                     self.reclaim_frame(buffer, store, dst);
                 }
                 None => {
+                    if !self.flusher.should_flush(dst.buffered_len()) {
+                        log::trace!("deferring flush; buffered={}", dst.buffered_len());
+
+                        // Poll again soon, to flush the frames if nothing
+                        // else does.
+                        cx.waker().wake_by_ref();
+                        return Poll::Ready(Ok(()));
+                    }
+
                     // Try to flush the codec.
                     ready!(dst.flush(cx))?;
 
@@ -533,7 +561,7 @@ impl Prioritize {
         }
     }
 
-    /// Tries to reclaim a pending data frame from the codec.
+    /// Tries to reclaim the pending data frames from the codec.
     ///
     /// Returns true if a frame was reclaimed.
     ///
@@ -551,8 +579,10 @@ impl Prioritize {
     {
         log::trace!("try reclaim frame");
 
+        let mut reclaimed = false;
+
         // First check if there are any data chunks to take back
-        if let Some(frame) = dst.take_last_data_frame() {
+        while let Some(frame) = dst.take_written_data_frame() {
             log::trace!(
                 "  -> reclaimed; frame={:?}; sz={}",
                 frame,
@@ -562,15 +592,19 @@ impl Prioritize {
             let mut eos = false;
             let key = frame.payload().stream;
 
-            match mem::replace(&mut self.in_flight_data_frame, InFlightData::Nothing) {
-                InFlightData::Nothing => panic!("wasn't expecting a frame to reclaim"),
-                InFlightData::Drop => {
-                    log::trace!("not reclaiming frame for cancelled stream");
-                    return false;
-                }
-                InFlightData::DataFrame(k) => {
-                    debug_assert_eq!(k, key);
-                }
+            let pos = self
+                .in_flight_data_frames
+                .iter()
+                .position(|in_flight| match *in_flight {
+                    InFlightData::DataFrame(k)
+                    | InFlightData::SplitDataFrame(k)
+                    | InFlightData::Drop(k) => k == key,
+                })
+                .expect("wasn't expecting a frame to reclaim");
+
+            if let InFlightData::Drop(_) = self.in_flight_data_frames.remove(pos) {
+                log::trace!("not reclaiming frame for cancelled stream");
+                continue;
             }
 
             let mut frame = frame.map(|prioritized| {
@@ -588,11 +622,11 @@ impl Prioritize {
 
                 self.push_back_frame(frame.into(), buffer, &mut stream);
 
-                return true;
+                reclaimed = true;
             }
         }
 
-        false
+        reclaimed
     }
 
     /// Push the frame to the front of the stream's deque, scheduling the
@@ -623,10 +657,12 @@ impl Prioritize {
 
         stream.buffered_send_data = 0;
         stream.requested_send_capacity = 0;
-        if let InFlightData::DataFrame(key) = self.in_flight_data_frame {
-            if stream.key() == key {
+        for in_flight in &mut self.in_flight_data_frames {
+            if *in_flight == InFlightData::DataFrame(stream.key())
+                || *in_flight == InFlightData::SplitDataFrame(stream.key())
+            {
                 // This stream could get cleaned up now - don't allow the buffered frame to get reclaimed.
-                self.in_flight_data_frame = InFlightData::Drop;
+                *in_flight = InFlightData::Drop(stream.key());
             }
         }
     }
@@ -666,6 +702,16 @@ impl Prioritize {
                         stream.state
                     );
 
+                    if self
+                        .in_flight_data_frames
+                        .contains(&InFlightData::SplitDataFrame(stream.key()))
+                    {
+                        // The stream is scheduled again once the rest of its
+                        // data is reclaimed from the codec.
+                        log::trace!(" --> split data frame in flight; stream={:?}", stream.id);
+                        continue;
+                    }
+
                     // It's possible that this stream, besides having data to send,
                     // is also queued to send a reset, and thus is already in the queue
                     // to wait for "some time" after a reset.
@@ -679,6 +725,9 @@ impl Prioritize {
                         is_pending_reset
                     );
 
+                    // Whether the rest of a DATA frame is held by the codec
+                    let mut is_split = false;
+
                     let frame = match stream.pending_send.pop_front(buffer) {
                         Some(Frame::Data(mut frame)) => {
                             // Get the amount of capacity remaining for stream's
@@ -757,8 +806,44 @@ impl Prioritize {
                             let eos = frame.is_end_stream();
                             let len = len as usize;
 
+                            if let Some(ref mut padder) = self.padder {
+                                // Padding is counted by flow control. It only
+                                // takes the window that is assigned neither
+                                // to this stream nor to any other.
+                                let stream_room = stream.send_flow.window_size()
+                                    - cmp::min(
+                                        stream.send_flow.available().as_size(),
+                                        stream.send_flow.window_size(),
+                                    );
+                                let connection_room = cmp::min(
+                                    self.flow.available().as_size(),
+                                    self.flow.window_size(),
+                                );
+                                let room = cmp::min(stream_room, connection_room) as usize;
+                                let room = cmp::min(room, max_len - len);
+
+                                let pad_len = if room > 0 {
+                                    Some(padder.pad_len(len, room - 1))
+                                } else {
+                                    None
+                                };
+
+                                if let Some(pad_len) = pad_len {
+                                    let sz = pad_len as WindowSize + 1;
+
+                                    log::trace!(" --> padding data frame; pad_len={}", pad_len);
+
+                                    stream.send_flow.assign_capacity(sz);
+                                    stream.send_flow.send_data(sz);
+                                    self.flow.send_data(sz);
+                                }
+
+                                frame.set_pad_len(pad_len);
+                            }
+
                             if frame.payload().remaining() > len {
                                 frame.set_end_stream(false);
+                                is_split = true;
                             }
 
                             Frame::Data(frame.map(|buf| Prioritized {
@@ -796,12 +881,26 @@ impl Prioritize {
 
                     log::trace!("pop_frame; frame={:?}", frame);
 
+                    if let Frame::Reset(_) = frame {
+                        if stream.is_canceled {
+                            // Let the user who canceled the stream know the
+                            // reset has been written.
+                            stream.state.set_reset_sent();
+                            stream.notify_reset_sent();
+                        }
+                    }
+
                     if cfg!(debug_assertions) && stream.state.is_idle() {
                         debug_assert!(stream.id > self.last_opened_id);
                         self.last_opened_id = stream.id;
                     }
 
-                    if !stream.pending_send.is_empty() || stream.state.is_scheduled_reset() {
+                    // A split DATA frame is pushed back to the stream once the
+                    // codec has written it, which requeues the stream. Until
+                    // then, the rest of the stream's queue must wait.
+                    if (!is_split && !stream.pending_send.is_empty())
+                        || stream.state.is_scheduled_reset()
+                    {
                         // TODO: Only requeue the sender IF it is ready to send
                         // the next frame. i.e. don't requeue it if the next
                         // frame is a data frame and the stream does not have
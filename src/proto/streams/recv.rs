@@ -1,6 +1,7 @@
 use super::*;
 use crate::codec::{RecvError, UserError};
 use crate::frame::{Reason, DEFAULT_INITIAL_WINDOW_SIZE};
+use crate::timer::{self, Timer};
 use crate::{frame, proto};
 use std::task::Context;
 
@@ -8,6 +9,7 @@ use futures::ready;
 use http::{HeaderMap, Method, Request, Response};
 
 use std::io;
+use std::sync::Arc;
 use std::task::{Poll, Waker};
 use std::time::{Duration, Instant};
 
@@ -28,6 +30,9 @@ pub(super) struct Recv {
     /// The stream ID of the last processed stream
     last_processed_id: StreamId,
 
+    /// The stream ID of the last stream opened with a well-formed message
+    last_opened_id: StreamId,
+
     /// Any streams with a higher ID are ignored.
     ///
     /// This starts as MAX, but is lowered when a GOAWAY is received.
@@ -49,6 +54,9 @@ pub(super) struct Recv {
     /// How long locally reset streams should ignore received frames
     reset_duration: Duration,
 
+    /// Clock used to expire locally reset streams
+    timer: Option<Arc<dyn Timer>>,
+
     /// Holds frames that are waiting to be read
     buffer: Buffer<Event>,
 
@@ -57,6 +65,10 @@ pub(super) struct Recv {
 
     /// If push promises are allowed to be recevied.
     is_push_enabled: bool,
+
+    /// Set once the connection has ended. Nothing reaps locally reset
+    /// streams anymore, so they are no longer queued for expiration.
+    is_eof: bool,
 }
 
 #[derive(Debug)]
@@ -72,7 +84,7 @@ pub(super) enum RecvHeaderBlockError<T> {
     State(RecvError),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub(crate) enum Open {
     PushPromise,
     Headers,
@@ -103,16 +115,24 @@ impl Recv {
             next_stream_id: Ok(next_stream_id.into()),
             pending_window_updates: store::Queue::new(),
             last_processed_id: StreamId::ZERO,
+            last_opened_id: StreamId::ZERO,
             max_stream_id: StreamId::MAX,
             pending_accept: store::Queue::new(),
             pending_reset_expired: store::Queue::new(),
             reset_duration: config.local_reset_duration,
+            timer: config.timer.clone(),
             buffer: Buffer::new(),
             refused: None,
             is_push_enabled: config.local_push_enabled,
+            is_eof: false,
         }
     }
 
+    /// Returns the connection's timer, if one was configured.
+    pub fn timer(&self) -> Option<&Arc<dyn Timer>> {
+        self.timer.as_ref()
+    }
+
     /// Returns the initial receive window size
     pub fn init_window_sz(&self) -> WindowSize {
         self.init_window_sz
@@ -138,6 +158,25 @@ impl Recv {
 
         let next_id = self.next_stream_id()?;
         if id < next_id {
+            if let Open::Headers = mode {
+                // Closed streams are forgotten, so a lower stream ID is
+                // usually one that was implicitly closed by opening a
+                // higher one. The last stream the peer opened did exist
+                // though, and HEADERS on it is a frame on a closed stream.
+                // Streams that were reset for being malformed are left to
+                // the connection error below.
+                if id == self.last_opened_id {
+                    log::debug!(
+                        "stream error STREAM_CLOSED -- HEADERS frame on closed stream; stream={:?}",
+                        id
+                    );
+                    return Err(RecvError::Stream {
+                        id,
+                        reason: Reason::STREAM_CLOSED,
+                    });
+                }
+            }
+
             proto_err!(conn: "id ({:?}) < next_id ({:?})", id, next_id);
             return Err(RecvError::Connection(Reason::PROTOCOL_ERROR));
         }
@@ -162,6 +201,35 @@ impl Recv {
         counts: &mut Counts,
     ) -> Result<(), RecvHeaderBlockError<Option<frame::Headers>>> {
         log::trace!("opening stream; init_window={}", self.init_window_sz);
+
+        // > An HTTP message (request or response) consists of:
+        // > 1. for a response only, zero or more HEADERS frames (each
+        // >    followed by zero or more CONTINUATION frames) containing the
+        // >    message headers of informational (1xx) HTTP responses
+        //
+        // Informational responses are skipped, the stream keeps waiting for
+        // the final response. They cannot end the stream.
+        if !counts.peer().is_server() {
+            let is_informational = match frame.parts().0.status {
+                Some(status) => status.is_informational(),
+                None => false,
+            };
+
+            if is_informational {
+                if frame.is_end_stream() {
+                    proto_err!(stream: "recv_headers: informational response ends stream; stream={:?}", stream.id);
+                    return Err(RecvError::Stream {
+                        id: stream.id,
+                        reason: Reason::PROTOCOL_ERROR,
+                    }
+                    .into());
+                }
+
+                log::trace!("skipping informational response; stream={:?}", stream.id);
+                return Ok(());
+            }
+        }
+
         let is_initial = stream.state.recv_open(frame.is_end_stream())?;
 
         if is_initial {
@@ -231,6 +299,10 @@ impl Recv {
             .peer()
             .convert_poll_message(pseudo, fields, stream_id)?;
 
+        if is_initial && stream_id > self.last_opened_id {
+            self.last_opened_id = stream_id;
+        }
+
         // Push the frame onto the stream's recv buffer
         stream
             .pending_recv
@@ -382,7 +454,10 @@ impl Recv {
         // Assign capacity to stream
         stream.recv_flow.assign_capacity(capacity);
 
-        if stream.recv_flow.unclaimed_capacity().is_some() {
+        // A stream that no longer receives data has no use for a
+        // WINDOW_UPDATE frame, and queueing it would keep the stream alive
+        // once the connection is gone.
+        if stream.recv_flow.unclaimed_capacity().is_some() && stream.state.is_recv_streaming() {
             // Queue the stream for sending the WINDOW_UPDATE frame.
             self.pending_window_updates.push(stream);
 
@@ -469,7 +544,9 @@ impl Recv {
         frame: frame::Data,
         stream: &mut store::Ptr,
     ) -> Result<(), RecvError> {
-        let sz = frame.payload().len();
+        // Flow control counts the whole payload, padding included
+        let padding = frame.padding_len() as WindowSize;
+        let sz = frame.payload().len() + frame.padding_len();
 
         // This should have been enforced at the codec::FramedRead layer, so
         // this is just a sanity check.
@@ -479,10 +556,25 @@ impl Recv {
 
         let is_ignoring_frame = stream.state.is_local_reset();
 
-        if !is_ignoring_frame && !stream.state.is_recv_streaming() {
-            // TODO: There are cases where this can be a stream error of
-            // STREAM_CLOSED instead...
+        if !is_ignoring_frame && stream.state.is_recv_closed() {
+            // > If an endpoint receives additional frames, other than
+            // > WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+            // > this state, it MUST respond with a stream error (Section
+            // > 5.4.2) of type STREAM_CLOSED.
+            //
+            // The frame still counts against the connection window.
+            log::debug!(
+                "stream error STREAM_CLOSED -- DATA frame on closed stream; stream={:?}",
+                stream.id
+            );
+            self.consume_connection_window(sz)?;
+            return Err(RecvError::Stream {
+                id: stream.id,
+                reason: Reason::STREAM_CLOSED,
+            });
+        }
 
+        if !is_ignoring_frame && !stream.state.is_recv_streaming() {
             // Receiving a DATA frame when not expecting one is a protocol
             // error.
             proto_err!(conn: "unexpected DATA frame; stream={:?}", stream.id);
@@ -560,6 +652,13 @@ impl Recv {
         // Track the data as in-flight
         stream.in_flight_recv_data += sz;
 
+        // The padding is never handed to the user, so its capacity is
+        // released right away.
+        if padding > 0 {
+            self.release_capacity(padding, stream, &mut None)
+                .expect("padding is in flight");
+        }
+
         let event = Event::Data(frame.into_payload());
 
         // Push the frame onto the recv buffer
@@ -701,6 +800,18 @@ impl Recv {
         Ok(())
     }
 
+    /// Skips over `id`, a remotely initiated stream which was reset before
+    /// being opened.
+    pub fn maybe_reset_next_stream_id(&mut self, id: StreamId) {
+        if let Ok(next_id) = self.next_stream_id {
+            // Peer::is_local_init should have been called beforehand
+            debug_assert_eq!(id.is_server_initiated(), next_id.is_server_initiated());
+            if id >= next_id {
+                self.next_stream_id = id.next_id();
+            }
+        }
+    }
+
     /// Handle remote sending an explicit RST_STREAM.
     pub fn recv_reset(&mut self, frame: frame::Reset, stream: &mut Stream) {
         // Notify the stream
@@ -710,6 +821,7 @@ impl Recv {
 
         stream.notify_send();
         stream.notify_recv();
+        stream.notify_reset_sent();
     }
 
     /// Handle a received error
@@ -720,6 +832,7 @@ impl Recv {
         // If a receiver is waiting, notify it
         stream.notify_send();
         stream.notify_recv();
+        stream.notify_reset_sent();
     }
 
     pub fn go_away(&mut self, last_processed_id: StreamId) {
@@ -731,6 +844,7 @@ impl Recv {
         stream.state.recv_eof();
         stream.notify_send();
         stream.notify_recv();
+        stream.notify_reset_sent();
     }
 
     pub(super) fn clear_recv_buffer(&mut self, stream: &mut Stream) {
@@ -776,7 +890,7 @@ impl Recv {
 
     /// Add a locally reset stream to queue to be eventually reaped.
     pub fn enqueue_reset_expiration(&mut self, stream: &mut store::Ptr, counts: &mut Counts) {
-        if !stream.state.is_local_reset() || stream.is_pending_reset_expiration() {
+        if !stream.state.is_local_reset() || stream.is_pending_reset_expiration() || self.is_eof {
             return;
         }
 
@@ -793,6 +907,7 @@ impl Recv {
 
         if counts.can_inc_num_reset_streams() {
             counts.inc_num_reset_streams();
+            stream.reset_at = Some(timer::now(self.timer.as_ref()));
             self.pending_reset_expired.push(stream);
         }
     }
@@ -822,15 +937,27 @@ impl Recv {
         Poll::Ready(Ok(()))
     }
 
-    pub fn clear_expired_reset_streams(&mut self, store: &mut Store, counts: &mut Counts) {
-        let now = Instant::now();
+    /// Reaps expired locally reset streams.
+    ///
+    /// Returns when the next remaining stream expires, if any.
+    pub fn clear_expired_reset_streams(
+        &mut self,
+        store: &mut Store,
+        counts: &mut Counts,
+    ) -> Option<Instant> {
+        let now = timer::now(self.timer.as_ref());
         let reset_duration = self.reset_duration;
         while let Some(stream) = self.pending_reset_expired.pop_if(store, |stream| {
             let reset_at = stream.reset_at.expect("reset_at must be set if in queue");
-            now - reset_at > reset_duration
+            now - reset_at >= reset_duration
         }) {
             counts.transition_after(stream, true);
         }
+
+        self.pending_reset_expired.peek(store).map(|stream| {
+            let reset_at = stream.reset_at.expect("reset_at must be set if in queue");
+            reset_at + reset_duration
+        })
     }
 
     pub fn clear_queues(
@@ -839,6 +966,7 @@ impl Recv {
         store: &mut Store,
         counts: &mut Counts,
     ) {
+        self.is_eof = true;
         self.clear_stream_window_update_queue(store, counts);
         self.clear_all_reset_streams(store, counts);
 
@@ -23,6 +23,9 @@ pub(super) struct Send {
 
     /// Prioritization layer
     prioritize: Prioritize,
+
+    /// Set once we've sent a GOAWAY, after which no new streams are opened.
+    is_going_away: bool,
 }
 
 /// A value to detect which public API has called `poll_reset`.
@@ -39,6 +42,7 @@ impl Send {
             init_window_sz: config.remote_init_window_sz,
             next_stream_id: Ok(config.local_next_stream_id),
             prioritize: Prioritize::new(config),
+            is_going_away: false,
         }
     }
 
@@ -83,10 +87,6 @@ impl Send {
             }
         }
 
-        if frame.has_too_big_field() {
-            return Err(UserError::HeaderTooBig);
-        }
-
         let end_stream = frame.is_end_stream();
 
         // Update the state
@@ -216,10 +216,6 @@ impl Send {
             return Err(UserError::UnexpectedFrameType);
         }
 
-        if frame.has_too_big_field() {
-            return Err(UserError::HeaderTooBig);
-        }
-
         stream.state.send_close();
 
         log::trace!("send_trailers -- queuing; frame={:?}", frame);
@@ -449,6 +445,19 @@ impl Send {
         Ok(())
     }
 
+    /// Stops opening new streams, as we've told the peer we're going away.
+    pub fn go_away(&mut self) {
+        self.is_going_away = true;
+    }
+
+    pub fn ensure_not_going_away(&self) -> Result<(), UserError> {
+        if self.is_going_away {
+            return Err(UserError::ConnectionShuttingDown);
+        }
+
+        Ok(())
+    }
+
     pub fn ensure_next_stream_id(&self) -> Result<StreamId, UserError> {
         self.next_stream_id
             .map_err(|_| UserError::OverflowedStreamId)
@@ -463,4 +472,16 @@ impl Send {
             true
         }
     }
+
+    /// Skips over `id`, a locally initiated stream which was reset before
+    /// being opened.
+    pub fn maybe_reset_next_stream_id(&mut self, id: StreamId) {
+        if let Ok(next_id) = self.next_stream_id {
+            // Peer::is_local_init should have been called beforehand
+            debug_assert_eq!(id.is_server_initiated(), next_id.is_server_initiated());
+            if id >= next_id {
+                self.next_stream_id = id.next_id();
+            }
+        }
+    }
 }
@@ -78,6 +78,13 @@ enum Cause {
     LocallyReset(Reason),
     Io,
 
+    /// A local reset whose RST_STREAM frame has been written to the
+    /// connection.
+    ///
+    /// Until then, a local reset is either `LocallyReset` (the frame is in
+    /// the stream's send queue) or `Scheduled`.
+    ResetSent(Reason),
+
     /// This indicates to the connection that a reset frame must be sent out
     /// once the send queue has been flushed.
     ///
@@ -310,6 +317,25 @@ impl State {
         self.inner = Closed(Cause::Scheduled(reason));
     }
 
+    /// Records that the RST_STREAM frame for a local reset has been written.
+    pub fn set_reset_sent(&mut self) {
+        match self.inner {
+            Closed(Cause::LocallyReset(reason)) | Closed(Cause::Scheduled(reason)) => {
+                log::trace!("set_reset_sent; reason={:?}", reason);
+                self.inner = Closed(Cause::ResetSent(reason));
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true if a RST_STREAM frame for a local reset has been written.
+    pub fn is_reset_sent(&self) -> bool {
+        match self.inner {
+            Closed(Cause::ResetSent(..)) => true,
+            _ => false,
+        }
+    }
+
     pub fn get_scheduled_reset(&self) -> Option<Reason> {
         match self.inner {
             Closed(Cause::Scheduled(reason)) => Some(reason),
@@ -328,6 +354,7 @@ impl State {
         match self.inner {
             Closed(Cause::LocallyReset(_)) => true,
             Closed(Cause::Scheduled(..)) => true,
+            Closed(Cause::ResetSent(..)) => true,
             _ => false,
         }
     }
@@ -408,7 +435,8 @@ impl State {
         match self.inner {
             Closed(Cause::Proto(reason))
             | Closed(Cause::LocallyReset(reason))
-            | Closed(Cause::Scheduled(reason)) => Err(proto::Error::Proto(reason)),
+            | Closed(Cause::Scheduled(reason))
+            | Closed(Cause::ResetSent(reason)) => Err(proto::Error::Proto(reason)),
             Closed(Cause::Io) => Err(proto::Error::Io(io::ErrorKind::BrokenPipe.into())),
             Closed(Cause::EndStream) | HalfClosedRemote(..) => Ok(false),
             _ => Ok(true),
@@ -420,7 +448,8 @@ impl State {
         match self.inner {
             Closed(Cause::Proto(reason))
             | Closed(Cause::LocallyReset(reason))
-            | Closed(Cause::Scheduled(reason)) => Ok(Some(reason)),
+            | Closed(Cause::Scheduled(reason))
+            | Closed(Cause::ResetSent(reason)) => Ok(Some(reason)),
             Closed(Cause::Io) => Err(proto::Error::Io(io::ErrorKind::BrokenPipe.into()).into()),
             Open {
                 local: Streaming, ..
@@ -296,6 +296,16 @@ where
         None
     }
 
+    pub fn peek<'a, R>(&self, store: &'a mut R) -> Option<store::Ptr<'a>>
+    where
+        R: Resolve,
+    {
+        match self.indices {
+            Some(idxs) => Some(store.resolve(idxs.head)),
+            None => None,
+        }
+    }
+
     pub fn pop_if<'a, R, F>(&mut self, store: &'a mut R, f: F) -> Option<store::Ptr<'a>>
     where
         R: Resolve,
@@ -69,6 +69,14 @@ pub(super) struct Stream {
     /// Set to true when the stream is pending to be opened
     pub is_pending_open: bool,
 
+    /// Set to true when the user canceled the stream, so that the
+    /// RST_STREAM frame is tracked until it is written.
+    pub is_canceled: bool,
+
+    /// Task waiting for the RST_STREAM frame of the cancellation to be
+    /// written
+    pub reset_sent_task: Option<Waker>,
+
     // ===== Fields related to receiving =====
     /// Next node in the accept linked list
     pub next_pending_accept: Option<store::Key>,
@@ -90,6 +98,9 @@ pub(super) struct Stream {
     /// The time when this stream may have been locally reset.
     pub reset_at: Option<Instant>,
 
+    /// True if the stream is waiting to expire after a local reset
+    pub is_pending_reset_expire: bool,
+
     /// Next node in list of reset streams that should expire eventually
     pub next_reset_expire: Option<store::Key>,
 
@@ -164,6 +175,8 @@ impl Stream {
             next_pending_send_capacity: None,
             send_capacity_inc: false,
             is_pending_open: false,
+            is_canceled: false,
+            reset_sent_task: None,
             next_open: None,
 
             // ===== Fields related to receiving =====
@@ -174,6 +187,7 @@ impl Stream {
             next_window_update: None,
             is_pending_window_update: false,
             reset_at: None,
+            is_pending_reset_expire: false,
             next_reset_expire: None,
             pending_recv: buffer::Deque::new(),
             recv_task: None,
@@ -197,7 +211,7 @@ impl Stream {
     /// Returns true if stream is currently being held for some time because of
     /// a local reset.
     pub fn is_pending_reset_expiration(&self) -> bool {
-        self.reset_at.is_some()
+        self.is_pending_reset_expire
     }
 
     /// Returns true if the stream is closed
@@ -224,7 +238,7 @@ impl Stream {
             // The stream is not in any queue
             !self.is_pending_send && !self.is_pending_send_capacity &&
             !self.is_pending_accept && !self.is_pending_window_update &&
-            !self.is_pending_open && !self.reset_at.is_some()
+            !self.is_pending_open && !self.is_pending_reset_expire
     }
 
     /// Returns true when the consumer of the stream has dropped all handles
@@ -292,6 +306,12 @@ impl Stream {
             task.wake();
         }
     }
+
+    pub fn notify_reset_sent(&mut self) {
+        if let Some(task) = self.reset_sent_task.take() {
+            task.wake();
+        }
+    }
 }
 
 impl store::Next for NextAccept {
@@ -428,15 +448,15 @@ impl store::Next for NextResetExpire {
     }
 
     fn is_queued(stream: &Stream) -> bool {
-        stream.reset_at.is_some()
+        stream.is_pending_reset_expire
     }
 
     fn set_queued(stream: &mut Stream, val: bool) {
-        if val {
-            stream.reset_at = Some(Instant::now());
-        } else {
+        // `reset_at` is set before the stream is queued
+        if !val {
             stream.reset_at = None;
         }
+        stream.is_pending_reset_expire = val;
     }
 }
 
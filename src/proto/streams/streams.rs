@@ -4,6 +4,7 @@ use super::{Buffer, Config, Counts, Prioritized, Recv, Send, Stream, StreamId};
 use crate::codec::{Codec, RecvError, SendError, UserError};
 use crate::frame::{self, Frame, Reason};
 use crate::proto::{peer, Open, Peer, WindowSize};
+use crate::timer::Timer;
 use crate::{client, proto, server};
 
 use bytes::{Buf, Bytes};
@@ -14,6 +15,7 @@ use tokio_io::AsyncWrite;
 
 use crate::PollExt;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use std::{fmt, io};
 
 #[derive(Debug)]
@@ -135,7 +137,7 @@ where
 
         // The GOAWAY process has begun. All streams with a greater ID than
         // specified as part of GOAWAY should be ignored.
-        if id > me.actions.recv.max_stream_id() {
+        if me.actions.is_ignored_after_go_away::<P>(id) {
             log::trace!(
                 "id ({:?}) > max_stream_id ({:?}), ignoring HEADERS",
                 id,
@@ -231,6 +233,18 @@ where
                     Err(RecvHeaderBlockError::State(err)) => Err(err),
                 }
             } else {
+                if stream.state.is_recv_closed() {
+                    // A HEADERS frame after the end of the stream
+                    log::debug!(
+                        "stream error STREAM_CLOSED -- HEADERS frame on closed stream; stream={:?}",
+                        stream.id
+                    );
+                    return Err(RecvError::Stream {
+                        id: stream.id,
+                        reason: Reason::STREAM_CLOSED,
+                    });
+                }
+
                 if !frame.is_end_stream() {
                     // Receiving trailers that don't set EOS is a "malformed"
                     // message. Malformed messages are a stream error.
@@ -241,6 +255,16 @@ where
                     });
                 }
 
+                if !frame.parts().0.is_empty() {
+                    // Trailers cannot carry pseudo header fields, this is
+                    // also a malformed message.
+                    proto_err!(stream: "recv_headers: pseudo header in trailers; stream={:?}", stream.id);
+                    return Err(RecvError::Stream {
+                        id: stream.id,
+                        reason: Reason::PROTOCOL_ERROR,
+                    });
+                }
+
                 actions.recv.recv_trailers(frame, stream)
             };
 
@@ -259,7 +283,7 @@ where
             None => {
                 // The GOAWAY process has begun. All streams with a greater ID
                 // than specified as part of GOAWAY should be ignored.
-                if id > me.actions.recv.max_stream_id() {
+                if me.actions.is_ignored_after_go_away::<P>(id) {
                     log::trace!(
                         "id ({:?}) > max_stream_id ({:?}), ignoring DATA",
                         id,
@@ -322,7 +346,7 @@ where
 
         // The GOAWAY process has begun. All streams with a greater ID than
         // specified as part of GOAWAY should be ignored.
-        if id > me.actions.recv.max_stream_id() {
+        if me.actions.is_ignored_after_go_away::<P>(id) {
             log::trace!(
                 "id ({:?}) > max_stream_id ({:?}), ignoring RST_STREAM",
                 id,
@@ -482,17 +506,6 @@ where
         // First, ensure that the initiating stream is still in a valid state.
         let parent_key = match me.store.find_mut(&id) {
             Some(stream) => {
-                // The GOAWAY process has begun. All streams with a greater ID
-                // than specified as part of GOAWAY should be ignored.
-                if id > me.actions.recv.max_stream_id() {
-                    log::trace!(
-                        "id ({:?}) > max_stream_id ({:?}), ignoring PUSH_PROMISE",
-                        id,
-                        me.actions.recv.max_stream_id()
-                    );
-                    return Ok(());
-                }
-
                 // The stream must be receive open
                 stream.state.ensure_recv_open()?;
                 stream.key()
@@ -503,6 +516,18 @@ where
             }
         };
 
+        // If we've sent a GOAWAY, any stream promised after the last one we
+        // processed is refused by ignoring the PUSH_PROMISE. The peer learns
+        // about it from the last stream ID in our GOAWAY frame.
+        if promised_id > me.actions.recv.max_stream_id() {
+            log::trace!(
+                "promised_id ({:?}) > max_stream_id ({:?}), ignoring PUSH_PROMISE",
+                promised_id,
+                me.actions.recv.max_stream_id()
+            );
+            return Ok(());
+        }
+
         // TODO: Streams in the reserved states do not count towards the concurrency
         // limit. However, it seems like there should be a cap otherwise this
         // could grow in memory indefinitely.
@@ -604,12 +629,12 @@ where
         me.actions.recv.send_pending_refusal(cx, dst)
     }
 
-    pub fn clear_expired_reset_streams(&mut self) {
+    pub fn clear_expired_reset_streams(&mut self) -> Option<Instant> {
         let mut me = self.inner.lock().unwrap();
         let me = &mut *me;
         me.actions
             .recv
-            .clear_expired_reset_streams(&mut me.store, &mut me.counts);
+            .clear_expired_reset_streams(&mut me.store, &mut me.counts)
     }
 
     pub fn poll_complete<T>(
@@ -685,6 +710,7 @@ where
         let mut send_buffer = self.send_buffer.inner.lock().unwrap();
         let send_buffer = &mut *send_buffer;
 
+        me.actions.send.ensure_not_going_away()?;
         me.actions.ensure_no_conn_error()?;
         me.actions.send.ensure_next_stream_id()?;
 
@@ -759,6 +785,16 @@ where
         let key = match me.store.find_entry(id) {
             Entry::Occupied(e) => e.key(),
             Entry::Vacant(e) => {
+                // Resetting a stream we don't know about, either a request
+                // refused before being accepted, or a stream the peer should
+                // not have sent a frame on. Either way, the stream ID is used
+                // up and must not be opened later on.
+                if me.counts.peer().is_local_init(id) {
+                    me.actions.send.maybe_reset_next_stream_id(id);
+                } else {
+                    me.actions.recv.maybe_reset_next_stream_id(id);
+                }
+
                 let stream = Stream::new(id, 0, 0);
 
                 e.insert(stream)
@@ -777,6 +813,13 @@ where
         let me = &mut *me;
         let actions = &mut me.actions;
         actions.recv.go_away(last_processed_id);
+
+        // A client that sent GOAWAY stops opening new requests. A server
+        // keeps answering the requests it processed, and may still push
+        // streams for them.
+        if !me.counts.peer().is_server() {
+            actions.send.go_away();
+        }
     }
 }
 
@@ -792,6 +835,7 @@ where
         let mut me = self.inner.lock().unwrap();
         let me = &mut *me;
 
+        me.actions.send.ensure_not_going_away()?;
         me.actions.ensure_no_conn_error()?;
         me.actions.send.ensure_next_stream_id()?;
 
@@ -857,6 +901,11 @@ where
         me.counts.has_streams()
     }
 
+    pub fn num_open_streams(&self) -> usize {
+        let me = self.inner.lock().unwrap();
+        me.counts.num_open_streams()
+    }
+
     pub fn has_streams_or_other_references(&self) -> bool {
         let me = self.inner.lock().unwrap();
         me.counts.has_streams() || me.refs > 1
@@ -1156,6 +1205,62 @@ impl OpaqueStreamRef {
     pub fn stream_id(&self) -> StreamId {
         self.inner.lock().unwrap().store[self.key].id
     }
+
+    /// Resets the stream with `reason`, as if all references to it were
+    /// dropped.
+    ///
+    /// The RST_STREAM frame is sent once the stream's send queue has been
+    /// flushed. Does nothing if the stream is already closed.
+    pub fn cancel(&mut self, reason: Reason) {
+        let mut me = self.inner.lock().unwrap();
+        let me = &mut *me;
+
+        let stream = me.store.resolve(self.key);
+        let actions = &mut me.actions;
+
+        me.counts.transition(stream, |counts, stream| {
+            if !stream.state.is_closed() {
+                stream.is_canceled = true;
+            }
+
+            actions
+                .send
+                .schedule_implicit_reset(stream, reason, counts, &mut actions.task);
+            actions.recv.enqueue_reset_expiration(stream, counts);
+            stream.notify_recv();
+        });
+    }
+
+    /// Returns `Ready(true)` once the RST_STREAM frame of a local reset has
+    /// been written to the connection, or `Ready(false)` if none will be.
+    pub fn poll_reset_sent(&mut self, cx: &Context) -> Poll<bool> {
+        let mut me = self.inner.lock().unwrap();
+        let me = &mut *me;
+
+        let mut stream = me.store.resolve(self.key);
+
+        if stream.state.is_reset_sent() {
+            return Poll::Ready(true);
+        }
+
+        // A reset is still waiting to be written as long as it is scheduled
+        // or its frame is in the send queue.
+        let is_pending = stream.is_canceled
+            && (stream.state.is_scheduled_reset()
+                || (stream.state.is_local_reset() && stream.is_pending_send));
+
+        if !is_pending || me.actions.conn_error.is_some() {
+            return Poll::Ready(false);
+        }
+
+        stream.reset_sent_task = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Returns the timer the connection was configured with, if any.
+    pub fn timer(&self) -> Option<Arc<dyn Timer>> {
+        self.inner.lock().unwrap().actions.recv.timer().cloned()
+    }
 }
 
 impl fmt::Debug for OpaqueStreamRef {
@@ -1348,6 +1453,15 @@ impl Actions {
         }
     }
 
+    /// Check if frames on stream `id` must be ignored because we sent a
+    /// GOAWAY frame.
+    ///
+    /// The last stream ID of a GOAWAY frame only refers to streams initiated
+    /// by the peer, the streams we initiated are still processed.
+    fn is_ignored_after_go_away<P: Peer>(&self, id: StreamId) -> bool {
+        id > self.recv.max_stream_id() && !P::is_local_init(id)
+    }
+
     fn clear_queues(&mut self, clear_pending_accept: bool, store: &mut Store, counts: &mut Counts) {
         self.recv.clear_queues(clear_pending_accept, store, counts);
         self.send.clear_queues(store, counts);
@@ -0,0 +1,146 @@
+use crate::timer::{Sleep, Timer};
+
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Enforces the time based limits configured for the connection.
+pub(super) struct Timeouts {
+    /// Creates the sleeps below. Without one, no limits are enforced.
+    timer: Option<Arc<dyn Timer>>,
+
+    /// How long the connection may go without any streams.
+    idle_timeout: Option<Duration>,
+
+    /// Completes if the remote's first SETTINGS frame isn't received in time.
+    handshake: Option<Sleep>,
+
+    /// Completes if our SETTINGS frame isn't acknowledged in time.
+    settings_ack: Option<Sleep>,
+
+    /// Completes once the connection has been idle for `idle_timeout`.
+    idle: Option<Sleep>,
+
+    /// Completes when the next locally reset stream expires.
+    reset_expiration: Option<(Instant, Sleep)>,
+}
+
+impl Timeouts {
+    pub fn new(
+        timer: Option<Arc<dyn Timer>>,
+        idle_timeout: Option<Duration>,
+        settings_ack_timeout: Option<Duration>,
+    ) -> Self {
+        // Our SETTINGS frame is sent as part of the handshake.
+        let settings_ack = timer
+            .as_ref()
+            .and_then(|timer| settings_ack_timeout.map(|dur| timer.sleep(dur)));
+
+        Timeouts {
+            timer,
+            idle_timeout,
+            handshake: None,
+            settings_ack,
+            idle: None,
+            reset_expiration: None,
+        }
+    }
+
+    /// Bounds the time until the remote's first SETTINGS frame is received.
+    ///
+    /// The handshake is started before the connection exists, so the caller
+    /// hands over the sleep that has been running since then.
+    pub fn set_handshake(&mut self, sleep: Sleep) {
+        self.handshake = Some(sleep);
+    }
+
+    pub fn recv_settings(&mut self) {
+        self.handshake = None;
+    }
+
+    pub fn recv_settings_ack(&mut self) {
+        self.settings_ack = None;
+    }
+
+    /// Returns `Ready` once if the handshake did not complete in time.
+    pub fn poll_handshake(&mut self, cx: &mut Context) -> Poll<()> {
+        poll_sleep(&mut self.handshake, cx)
+    }
+
+    /// Returns `Ready` once if our SETTINGS were not acknowledged in time.
+    pub fn poll_settings_ack(&mut self, cx: &mut Context) -> Poll<()> {
+        poll_sleep(&mut self.settings_ack, cx)
+    }
+
+    /// Returns `Ready` once the connection has been idle for too long.
+    ///
+    /// The idle timer starts over every time `is_idle` changes to `true`.
+    pub fn poll_idle(&mut self, cx: &mut Context, is_idle: bool) -> Poll<()> {
+        if !is_idle {
+            self.idle = None;
+            return Poll::Pending;
+        }
+
+        if self.idle.is_none() {
+            self.idle = match (&self.timer, self.idle_timeout) {
+                (Some(timer), Some(dur)) => Some(timer.sleep(dur)),
+                _ => return Poll::Pending,
+            };
+        }
+
+        poll_sleep(&mut self.idle, cx)
+    }
+
+    /// Returns `Ready` once `next`, the time the next locally reset stream
+    /// expires, has been reached.
+    pub fn poll_reset_expiration(&mut self, cx: &mut Context, next: Option<Instant>) -> Poll<()> {
+        let (timer, next) = match (&self.timer, next) {
+            (Some(timer), Some(next)) => (timer, next),
+            _ => {
+                self.reset_expiration = None;
+                return Poll::Pending;
+            }
+        };
+
+        match self.reset_expiration {
+            Some((at, _)) if at == next => {}
+            _ => {
+                let sleep = timer.sleep(next.saturating_duration_since(timer.now()));
+                self.reset_expiration = Some((next, sleep));
+            }
+        }
+
+        let (_, sleep) = self.reset_expiration.as_mut().unwrap();
+        futures::ready!(sleep.as_mut().poll(cx));
+        self.reset_expiration = None;
+        Poll::Ready(())
+    }
+}
+
+impl fmt::Debug for Timeouts {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Timeouts")
+            .field("timer", &self.timer)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("handshake", &self.handshake.is_some())
+            .field("settings_ack", &self.settings_ack.is_some())
+            .field("idle", &self.idle.is_some())
+            .field(
+                "reset_expiration",
+                &self.reset_expiration.as_ref().map(|&(at, _)| at),
+            )
+            .finish()
+    }
+}
+
+fn poll_sleep(sleep: &mut Option<Sleep>, cx: &mut Context) -> Poll<()> {
+    match sleep {
+        Some(inner) => {
+            futures::ready!(inner.as_mut().poll(cx));
+            *sleep = None;
+            Poll::Ready(())
+        }
+        None => Poll::Pending,
+    }
+}
@@ -0,0 +1,192 @@
+//! Integer and string literal representations, as defined by RFC 9204
+//! section 4.1. They are the same as in HPACK, except that the string length
+//! prefix can be shorter than 7 bits and that integers may take up to 62
+//! bits.
+
+use super::Cause;
+use crate::hpack::huffman;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use std::io::Cursor;
+
+/// Encodes an integer with a `prefix` bits prefix, the high bits of the first
+/// octet being taken from `first`.
+pub(crate) fn encode_int(value: u64, prefix: u8, first: u8, dst: &mut Vec<u8>) {
+    let max = (1u64 << prefix) - 1;
+
+    if value < max {
+        dst.push(first | value as u8);
+        return;
+    }
+
+    dst.push(first | max as u8);
+
+    let mut value = value - max;
+
+    while value >= 128 {
+        dst.push(0x80 | value as u8);
+        value >>= 7;
+    }
+
+    dst.push(value as u8);
+}
+
+/// Encodes a string literal whose length has a `prefix` bits prefix. The
+/// Huffman flag is the bit just above the prefix.
+///
+/// The string is only Huffman encoded if that makes it shorter.
+pub(crate) fn encode_str(value: &[u8], prefix: u8, first: u8, dst: &mut Vec<u8>) {
+    let mut huff = Vec::with_capacity(value.len());
+    huffman::encode(value, &mut huff).expect("vectors grow as needed");
+
+    if huff.len() < value.len() {
+        encode_int(huff.len() as u64, prefix, first | 1 << prefix, dst);
+        dst.extend_from_slice(&huff);
+    } else {
+        encode_int(value.len() as u64, prefix, first, dst);
+        dst.extend_from_slice(value);
+    }
+}
+
+/// Decodes an integer with a `prefix` bits prefix.
+///
+/// Returns `None` if `buf` ends before the integer.
+pub(crate) fn decode_int(buf: &mut Cursor<&[u8]>, prefix: u8) -> Result<Option<u64>, Cause> {
+    const VARINT_MASK: u8 = 0b0111_1111;
+    const VARINT_FLAG: u8 = 0b1000_0000;
+
+    if !buf.has_remaining() {
+        return Ok(None);
+    }
+
+    let mask = ((1u16 << prefix) - 1) as u8;
+    let mut ret = u64::from(buf.get_u8() & mask);
+
+    if ret < u64::from(mask) {
+        return Ok(Some(ret));
+    }
+
+    let mut shift = 0;
+
+    while buf.has_remaining() {
+        let b = buf.get_u8();
+        let bits = u64::from(b & VARINT_MASK);
+
+        if shift > 56 {
+            return Err(Cause::IntegerOverflow);
+        }
+
+        ret += bits << shift;
+        shift += 7;
+
+        if ret >= 1 << 62 {
+            return Err(Cause::IntegerOverflow);
+        }
+
+        if b & VARINT_FLAG == 0 {
+            return Ok(Some(ret));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Decodes a string literal whose length has a `prefix` bits prefix.
+///
+/// Returns `None` if `buf` ends before the string.
+pub(crate) fn decode_str(buf: &mut Cursor<&[u8]>, prefix: u8) -> Result<Option<Bytes>, Cause> {
+    let huff = match peek_u8(buf) {
+        Some(b) => b & (1 << prefix) != 0,
+        None => return Ok(None),
+    };
+
+    let len = match decode_int(buf, prefix)? {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+
+    if len > buf.remaining() as u64 {
+        return Ok(None);
+    }
+
+    let len = len as usize;
+    let pos = buf.position() as usize;
+    let raw = &buf.get_ref()[pos..pos + len];
+
+    let ret = if huff {
+        let mut dst = BytesMut::new();
+        huffman::decode(raw, &mut dst)?.freeze()
+    } else {
+        Bytes::from(raw)
+    };
+
+    buf.advance(len);
+
+    Ok(Some(ret))
+}
+
+pub(crate) fn peek_u8(buf: &Cursor<&[u8]>) -> Option<u8> {
+    buf.get_ref().get(buf.position() as usize).cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(value: u64, prefix: u8) {
+        let mut dst = vec![];
+        encode_int(value, prefix, 0, &mut dst);
+
+        let mut buf = Cursor::new(&dst[..]);
+        assert_eq!(decode_int(&mut buf, prefix), Ok(Some(value)));
+        assert!(!buf.has_remaining());
+    }
+
+    #[test]
+    fn test_int_round_trip() {
+        for &prefix in &[3, 4, 5, 6, 7, 8] {
+            for &value in &[0, 1, 6, 7, 127, 128, 255, 1337, 1 << 40, (1 << 62) - 1] {
+                round_trip(value, prefix);
+            }
+        }
+    }
+
+    #[test]
+    fn test_int_overflow() {
+        // 2^62 with a 6 bit prefix
+        let mut dst = vec![];
+        encode_int((1 << 62) - 1, 6, 0, &mut dst);
+        *dst.last_mut().unwrap() += 1;
+        assert_eq!(
+            decode_int(&mut Cursor::new(&dst[..]), 6),
+            Err(Cause::IntegerOverflow)
+        );
+
+        let long = [0xff; 16];
+        assert_eq!(
+            decode_int(&mut Cursor::new(&long[..]), 8),
+            Err(Cause::IntegerOverflow)
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert_eq!(decode_int(&mut Cursor::new(&[0x3f, 0x80][..]), 6), Ok(None));
+        assert_eq!(decode_str(&mut Cursor::new(&[0x05, b'a'][..]), 7), Ok(None));
+    }
+
+    #[test]
+    fn test_str_huffman_only_when_shorter() {
+        let mut dst = vec![];
+        encode_str(b"www.example.com", 7, 0, &mut dst);
+        assert_eq!(dst[0], 0x80 | 12);
+
+        dst.clear();
+        encode_str(b"{}", 3, 0x20, &mut dst);
+        assert_eq!(dst, [0x22, b'{', b'}']);
+
+        let mut buf = Cursor::new(&dst[..]);
+        assert_eq!(decode_str(&mut buf, 3), Ok(Some(Bytes::from_static(b"{}"))));
+    }
+}
@@ -0,0 +1,483 @@
+use super::coding::{decode_int, decode_str, encode_int, peek_u8};
+use super::table::{get_static, DynamicTable, Table};
+use super::{Cause, Error};
+use crate::hpack::{Field, Header};
+
+use bytes::{Bytes, BytesMut};
+
+use std::io::Cursor;
+use std::{fmt, mem};
+
+/// Decodes QPACK field sections into header fields.
+///
+/// The decoder reads the instructions of the peer's encoder stream with
+/// [`on_encoder_stream`], and writes its own instructions, to be sent on the
+/// decoder stream, into a buffer drained with [`take_instructions`].
+///
+/// [`on_encoder_stream`]: #method.on_encoder_stream
+/// [`take_instructions`]: #method.take_instructions
+pub struct Decoder {
+    table: DynamicTable,
+    max_table_capacity: usize,
+    max_blocked_streams: usize,
+    // Insert count the encoder knows the decoder has received
+    known_received_count: u64,
+    blocked: Vec<u64>,
+    instructions: Vec<u8>,
+    // Partial encoder stream instruction
+    buf: BytesMut,
+}
+
+/// Encoder stream instructions, RFC 9204 section 4.3.
+#[derive(Debug)]
+enum Instruction {
+    SetCapacity(u64),
+    InsertWithStaticName(u64, Bytes),
+    InsertWithDynamicName(u64, Bytes),
+    InsertWithLiteralName(Bytes, Bytes),
+    Duplicate(u64),
+}
+
+impl Decoder {
+    /// Creates a decoder which only uses the static table.
+    ///
+    /// This is what a peer expects when `SETTINGS_QPACK_MAX_TABLE_CAPACITY`
+    /// is not sent.
+    pub fn new() -> Decoder {
+        Decoder::with_settings(0, 0)
+    }
+
+    /// Creates a decoder matching the `SETTINGS_QPACK_MAX_TABLE_CAPACITY` and
+    /// `SETTINGS_QPACK_BLOCKED_STREAMS` values sent to the peer.
+    pub fn with_settings(max_table_capacity: usize, max_blocked_streams: usize) -> Decoder {
+        Decoder {
+            table: DynamicTable::new(),
+            max_table_capacity,
+            max_blocked_streams,
+            known_received_count: 0,
+            blocked: vec![],
+            instructions: vec![],
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// Processes data received on the peer's encoder stream.
+    ///
+    /// `src` does not need to end on an instruction boundary, the end of a
+    /// partial instruction is expected in the next call.
+    pub fn on_encoder_stream(&mut self, src: &[u8]) -> Result<(), Error> {
+        self.buf.extend_from_slice(src);
+
+        loop {
+            let (instruction, pos) = {
+                let mut buf = Cursor::new(&self.buf[..]);
+
+                match parse_instruction(&mut buf).map_err(Error::EncoderStream)? {
+                    Some(instruction) => (instruction, buf.position() as usize),
+                    None => return Ok(()),
+                }
+            };
+
+            self.buf.advance(pos);
+            self.apply(instruction).map_err(Error::EncoderStream)?;
+        }
+    }
+
+    fn apply(&mut self, instruction: Instruction) -> Result<(), Cause> {
+        log::trace!("qpack encoder instruction; {:?}", instruction);
+
+        let header = match instruction {
+            Instruction::SetCapacity(capacity) => {
+                if capacity > self.max_table_capacity as u64 {
+                    return Err(Cause::InvalidCapacity);
+                }
+
+                self.table.set_capacity(capacity as usize);
+                return Ok(());
+            }
+            Instruction::InsertWithStaticName(index, value) => get_static(index)
+                .ok_or(Cause::InvalidTableIndex)?
+                .name()
+                .into_entry(value)?,
+            Instruction::InsertWithDynamicName(index, value) => {
+                // The referenced entry may be evicted by the insertion, so
+                // the new entry is built first.
+                self.relative(index)?.name().into_entry(value)?
+            }
+            Instruction::InsertWithLiteralName(name, value) => Header::new(name, value)?,
+            Instruction::Duplicate(index) => self.relative(index)?.clone(),
+        };
+
+        if header.len() > self.table.capacity() {
+            return Err(Cause::EntryTooLarge);
+        }
+
+        self.table.insert(header);
+        Ok(())
+    }
+
+    // Resolves an encoder stream relative index.
+    fn relative(&self, index: u64) -> Result<&Header, Cause> {
+        self.table
+            .insert_count()
+            .checked_sub(index + 1)
+            .and_then(|index| self.table.get(index))
+            .ok_or(Cause::InvalidTableIndex)
+    }
+
+    /// Decodes the field section of a HEADERS frame received on `stream_id`.
+    ///
+    /// Returns `None` if the field section references entries that have not
+    /// been received on the encoder stream yet. It must then be decoded again
+    /// once more encoder instructions have been processed.
+    ///
+    /// Fields encoded with the never indexed bit set are returned with a
+    /// sensitive value.
+    pub fn decode(&mut self, stream_id: u64, src: &[u8]) -> Result<Option<Vec<Field>>, Error> {
+        self.decode_section(stream_id, src)
+            .map_err(Error::DecompressionFailed)
+    }
+
+    fn decode_section(&mut self, stream_id: u64, src: &[u8]) -> Result<Option<Vec<Field>>, Cause> {
+        let mut buf = Cursor::new(src);
+
+        let encoded = need(decode_int(&mut buf, 8)?)?;
+        let required = self.required_insert_count(encoded)?;
+
+        let negative = peek_u8(&buf).map(|b| b & 0x80 != 0).unwrap_or(false);
+        let delta = need(decode_int(&mut buf, 7)?)?;
+
+        let base = if negative {
+            required.checked_sub(delta + 1).ok_or(Cause::InvalidBase)?
+        } else {
+            required + delta
+        };
+
+        if required > self.table.insert_count() {
+            if !self.blocked.contains(&stream_id) {
+                if self.blocked.len() >= self.max_blocked_streams {
+                    return Err(Cause::TooManyBlockedStreams);
+                }
+
+                self.blocked.push(stream_id);
+            }
+
+            return Ok(None);
+        }
+
+        self.blocked.retain(|&id| id != stream_id);
+
+        let mut fields = vec![];
+
+        while peek_u8(&buf).is_some() {
+            let header = self.decode_line(&mut buf, required, base)?;
+            fields.push(Field::from_inner(header));
+        }
+
+        if required > 0 {
+            // Section Acknowledgment
+            encode_int(stream_id, 7, 0b1000_0000, &mut self.instructions);
+
+            if required > self.known_received_count {
+                self.known_received_count = required;
+            }
+        }
+
+        Ok(Some(fields))
+    }
+
+    fn decode_line(
+        &self,
+        buf: &mut Cursor<&[u8]>,
+        required: u64,
+        base: u64,
+    ) -> Result<Header, Cause> {
+        const INDEXED: u8 = 0b1000_0000;
+        const LITERAL_WITH_NAME_REF: u8 = 0b0100_0000;
+        const LITERAL_WITH_LITERAL_NAME: u8 = 0b0010_0000;
+        const INDEXED_POST_BASE: u8 = 0b0001_0000;
+
+        let b = peek_u8(buf).expect("caller checks for remaining data");
+
+        let header = if b & INDEXED == INDEXED {
+            let index = need(decode_int(buf, 6)?)?;
+
+            if b & 0b0100_0000 != 0 {
+                get_static(index).ok_or(Cause::InvalidTableIndex)?
+            } else {
+                let index = base
+                    .checked_sub(index + 1)
+                    .ok_or(Cause::InvalidTableIndex)?;
+                self.dynamic(index, required)?.clone()
+            }
+        } else if b & LITERAL_WITH_NAME_REF == LITERAL_WITH_NAME_REF {
+            let index = need(decode_int(buf, 4)?)?;
+            let value = need(decode_str(buf, 7)?)?;
+
+            let name = if b & 0b0001_0000 != 0 {
+                get_static(index).ok_or(Cause::InvalidTableIndex)?
+            } else {
+                let index = base
+                    .checked_sub(index + 1)
+                    .ok_or(Cause::InvalidTableIndex)?;
+                self.dynamic(index, required)?.clone()
+            };
+
+            let mut header = name.name().into_entry(value)?;
+            header.set_sensitive(b & 0b0010_0000 != 0);
+            header
+        } else if b & LITERAL_WITH_LITERAL_NAME == LITERAL_WITH_LITERAL_NAME {
+            let name = need(decode_str(buf, 3)?)?;
+            let value = need(decode_str(buf, 7)?)?;
+
+            let mut header = Header::new(name, value)?;
+            header.set_sensitive(b & 0b0001_0000 != 0);
+            header
+        } else if b & INDEXED_POST_BASE == INDEXED_POST_BASE {
+            let index = need(decode_int(buf, 4)?)?;
+            self.dynamic(base + index, required)?.clone()
+        } else {
+            // Literal field line with post-base name reference
+            let index = need(decode_int(buf, 3)?)?;
+            let value = need(decode_str(buf, 7)?)?;
+
+            let name = self.dynamic(base + index, required)?;
+            let mut header = name.name().into_entry(value)?;
+            header.set_sensitive(b & 0b0000_1000 != 0);
+            header
+        };
+
+        Ok(header)
+    }
+
+    // Resolves an absolute index referenced by a field section.
+    fn dynamic(&self, index: u64, required: u64) -> Result<&Header, Cause> {
+        if index >= required {
+            return Err(Cause::InvalidTableIndex);
+        }
+
+        self.table.get(index).ok_or(Cause::InvalidTableIndex)
+    }
+
+    // RFC 9204 section 4.5.1.1.
+    fn required_insert_count(&self, encoded: u64) -> Result<u64, Cause> {
+        if encoded == 0 {
+            return Ok(0);
+        }
+
+        let max_entries = self.max_table_capacity as u64 / 32;
+        let full_range = 2 * max_entries;
+
+        if encoded > full_range {
+            return Err(Cause::InvalidRequiredInsertCount);
+        }
+
+        let max_value = self.table.insert_count() + max_entries;
+        let max_wrapped = (max_value / full_range) * full_range;
+        let mut required = max_wrapped + encoded - 1;
+
+        if required > max_value {
+            if required <= full_range {
+                return Err(Cause::InvalidRequiredInsertCount);
+            }
+
+            required -= full_range;
+        }
+
+        if required == 0 {
+            return Err(Cause::InvalidRequiredInsertCount);
+        }
+
+        Ok(required)
+    }
+
+    /// Signals that the field sections of `stream_id` will not be decoded,
+    /// because the stream was reset or reading it was abandoned.
+    pub fn cancel_stream(&mut self, stream_id: u64) {
+        self.blocked.retain(|&id| id != stream_id);
+
+        // Without a dynamic table, there is nothing to cancel.
+        if self.max_table_capacity > 0 {
+            encode_int(stream_id, 6, 0b0100_0000, &mut self.instructions);
+        }
+    }
+
+    /// Returns the instructions to send on the decoder stream.
+    ///
+    /// This includes an Insert Count Increment for the entries received since
+    /// the last call that were not acknowledged along with a field section.
+    pub fn take_instructions(&mut self) -> Bytes {
+        let increment = self.table.insert_count() - self.known_received_count;
+
+        if increment > 0 {
+            encode_int(increment, 6, 0, &mut self.instructions);
+            self.known_received_count += increment;
+        }
+
+        mem::replace(&mut self.instructions, vec![]).into()
+    }
+
+    /// Returns the number of streams whose field section is blocked.
+    pub fn blocked_streams(&self) -> usize {
+        self.blocked.len()
+    }
+
+    /// Returns a view of the dynamic table.
+    pub fn table(&self) -> Table<'_> {
+        Table::new(&self.table)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Decoder")
+            .field("table", &self.table())
+            .field("max_table_capacity", &self.max_table_capacity)
+            .field("blocked_streams", &self.blocked_streams())
+            .finish()
+    }
+}
+
+fn parse_instruction(buf: &mut Cursor<&[u8]>) -> Result<Option<Instruction>, Cause> {
+    let b = match peek_u8(buf) {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+
+    let instruction = if b & 0b1000_0000 != 0 {
+        let index = try_opt!(decode_int(buf, 6)?);
+        let value = try_opt!(decode_str(buf, 7)?);
+
+        if b & 0b0100_0000 != 0 {
+            Instruction::InsertWithStaticName(index, value)
+        } else {
+            Instruction::InsertWithDynamicName(index, value)
+        }
+    } else if b & 0b0100_0000 != 0 {
+        let name = try_opt!(decode_str(buf, 5)?);
+        let value = try_opt!(decode_str(buf, 7)?);
+        Instruction::InsertWithLiteralName(name, value)
+    } else if b & 0b0010_0000 != 0 {
+        Instruction::SetCapacity(try_opt!(decode_int(buf, 5)?))
+    } else {
+        Instruction::Duplicate(try_opt!(decode_int(buf, 5)?))
+    };
+
+    Ok(Some(instruction))
+}
+
+// Field sections are complete, so running out of data is an error.
+fn need<T>(val: Option<T>) -> Result<T, Cause> {
+    val.ok_or(Cause::Truncated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_static_only() {
+        let mut decoder = Decoder::new();
+
+        // RFC 9204 Appendix B.1
+        let src = b"\x00\x00\x51\x0b/index.html";
+        let fields = decoder.decode(0, src).unwrap().unwrap();
+
+        assert_eq!(
+            fields,
+            vec![Field::from_bytes(b":path", b"/index.html").unwrap()]
+        );
+        assert!(decoder.take_instructions().is_empty());
+    }
+
+    #[test]
+    fn test_decode_never_indexed_literal() {
+        let mut decoder = Decoder::new();
+
+        // Literal with static name reference 84 (authorization) and N set
+        let src = b"\x00\x00\x7f\x45\x03abc";
+        let fields = decoder.decode(0, src).unwrap().unwrap();
+
+        assert_eq!(fields[0].name(), "authorization");
+        assert!(fields[0].is_sensitive());
+    }
+
+    #[test]
+    fn test_dynamic_reference_without_table() {
+        let mut decoder = Decoder::new();
+
+        assert_eq!(
+            decoder.decode(0, &[0x02, 0x00, 0x80]),
+            Err(Error::DecompressionFailed(
+                Cause::InvalidRequiredInsertCount
+            ))
+        );
+        assert_eq!(
+            decoder.decode(0, &[0x00, 0x00, 0x80]),
+            Err(Error::DecompressionFailed(Cause::InvalidTableIndex))
+        );
+        assert_eq!(
+            decoder.decode(0, &[0x00, 0x00, 0x51]),
+            Err(Error::DecompressionFailed(Cause::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_blocked_streams() {
+        let mut decoder = Decoder::with_settings(220, 1);
+        decoder.on_encoder_stream(&[0x3f, 0xbd, 0x01]).unwrap();
+
+        // Required Insert Count of 1, indexed field with relative index 0
+        let section = [0x02, 0x00, 0x80];
+
+        assert_eq!(decoder.decode(4, &section), Ok(None));
+        assert_eq!(decoder.decode(4, &section), Ok(None));
+        assert_eq!(
+            decoder.decode(8, &section),
+            Err(Error::DecompressionFailed(Cause::TooManyBlockedStreams))
+        );
+        assert_eq!(decoder.blocked_streams(), 1);
+
+        // Insert With Literal Name, sent in two parts
+        decoder.on_encoder_stream(b"\x43ab").unwrap();
+        assert_eq!(decoder.table().insert_count(), 0);
+        decoder.on_encoder_stream(b"c\x01d").unwrap();
+        assert_eq!(decoder.table().insert_count(), 1);
+
+        let fields = decoder.decode(4, &section).unwrap().unwrap();
+        assert_eq!(fields, vec![Field::from_bytes(b"abc", b"d").unwrap()]);
+        assert_eq!(decoder.blocked_streams(), 0);
+
+        // Section Acknowledgment, the insert count is already known
+        assert_eq!(&decoder.take_instructions()[..], &[0x84]);
+    }
+
+    #[test]
+    fn test_encoder_stream_errors() {
+        let mut decoder = Decoder::with_settings(64, 0);
+
+        assert_eq!(
+            decoder.on_encoder_stream(&[0x3f, 0x22]),
+            Err(Error::EncoderStream(Cause::InvalidCapacity))
+        );
+
+        let mut decoder = Decoder::with_settings(64, 0);
+        decoder.on_encoder_stream(&[0x3f, 0x01]).unwrap();
+
+        // 32 + 3 + 1 octets do not fit in a table of 32 octets
+        assert_eq!(
+            decoder.on_encoder_stream(b"\x43abc\x01d"),
+            Err(Error::EncoderStream(Cause::EntryTooLarge))
+        );
+        assert_eq!(
+            decoder.on_encoder_stream(&[0x00]),
+            Err(Error::EncoderStream(Cause::InvalidTableIndex))
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use super::coding::{decode_int, encode_int, encode_str, peek_u8};
+use super::table::{find_static, DynamicTable, Table};
+use super::{Cause, Error};
+use crate::hpack::{DefaultIndexingPolicy, Field, Header, Indexing, IndexingPolicy};
+
+use bytes::{Bytes, BytesMut};
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+use std::sync::Arc;
+use std::{cmp, fmt, mem};
+
+/// Encodes header fields into QPACK field sections.
+///
+/// The encoder writes the instructions to be sent on its encoder stream into
+/// a buffer drained with [`take_instructions`], and reads the instructions of
+/// the peer's decoder stream with [`on_decoder_stream`].
+///
+/// Entries are only inserted into the dynamic table once its capacity has
+/// been set with [`set_table_capacity`].
+///
+/// [`take_instructions`]: #method.take_instructions
+/// [`on_decoder_stream`]: #method.on_decoder_stream
+/// [`set_table_capacity`]: #method.set_table_capacity
+pub struct Encoder {
+    table: DynamicTable,
+    max_table_capacity: usize,
+    max_blocked_streams: usize,
+    // Insert count the decoder is known to have received
+    known_received_count: u64,
+    // Unacknowledged field sections referencing the dynamic table
+    sections: HashMap<u64, VecDeque<Section>>,
+    policy: Arc<dyn IndexingPolicy>,
+    instructions: Vec<u8>,
+    // Partial decoder stream instruction
+    buf: BytesMut,
+}
+
+#[derive(Debug)]
+struct Section {
+    required_insert_count: u64,
+    // Smallest absolute index referenced by the section
+    min_index: u64,
+}
+
+/// Decoder stream instructions, RFC 9204 section 4.4.
+#[derive(Debug)]
+enum Instruction {
+    SectionAck(u64),
+    StreamCancel(u64),
+    InsertCountIncrement(u64),
+}
+
+/// A field line representation, RFC 9204 section 4.5.
+#[derive(Debug)]
+enum Line {
+    Static(u64),
+    Dynamic(u64),
+    StaticName(u64, Header, bool),
+    DynamicName(u64, Header, bool),
+    Literal(Header, bool),
+}
+
+impl Encoder {
+    /// Creates an encoder which only uses the static table.
+    pub fn new() -> Encoder {
+        Encoder::with_settings(0, 0)
+    }
+
+    /// Creates an encoder honoring the `SETTINGS_QPACK_MAX_TABLE_CAPACITY`
+    /// and `SETTINGS_QPACK_BLOCKED_STREAMS` values received from the peer.
+    pub fn with_settings(max_table_capacity: usize, max_blocked_streams: usize) -> Encoder {
+        Encoder {
+            table: DynamicTable::new(),
+            max_table_capacity,
+            max_blocked_streams,
+            known_received_count: 0,
+            sections: HashMap::new(),
+            policy: Arc::new(DefaultIndexingPolicy),
+            instructions: vec![],
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// Sets the capacity of the dynamic table, and queues the matching Set
+    /// Dynamic Table Capacity instruction.
+    ///
+    /// Returns `false`, leaving the capacity unchanged, if `capacity` is
+    /// larger than the maximum allowed by the peer, or if shrinking the table
+    /// would evict entries referenced by unacknowledged field sections.
+    pub fn set_table_capacity(&mut self, capacity: usize) -> bool {
+        if capacity > self.max_table_capacity || !self.table.can_resize(capacity, self.pinned()) {
+            return false;
+        }
+
+        self.table.set_capacity(capacity);
+        encode_int(capacity as u64, 5, 0b0010_0000, &mut self.instructions);
+        true
+    }
+
+    /// Sets the policy deciding which fields are inserted into the dynamic
+    /// table.
+    ///
+    /// Sensitive values are always encoded as never indexed literals.
+    pub fn set_indexing_policy<P: IndexingPolicy>(&mut self, policy: P) {
+        self.policy = Arc::new(policy);
+    }
+
+    /// Encodes the fields of a HEADERS frame sent on `stream_id` into `dst`.
+    ///
+    /// Fields may be inserted into the dynamic table, in which case the
+    /// instructions returned by the next call to `take_instructions` must be
+    /// sent on the encoder stream.
+    pub fn encode<I>(&mut self, stream_id: u64, fields: I, dst: &mut BytesMut)
+    where
+        I: IntoIterator<Item = Field>,
+    {
+        // Entries not yet acknowledged by the decoder may only be referenced
+        // if that does not block one more stream than allowed.
+        let may_block =
+            self.is_blocking(stream_id) || self.blocked_streams() < self.max_blocked_streams;
+
+        let mut min_index = u64::max_value();
+        let mut required = 0;
+        let mut lines = vec![];
+
+        for field in fields {
+            let line = self.encode_field(field.into_inner(), may_block, &mut min_index);
+
+            match line {
+                Line::Dynamic(index) | Line::DynamicName(index, ..) => {
+                    required = cmp::max(required, index + 1);
+                }
+                _ => {}
+            }
+
+            lines.push(line);
+        }
+
+        let mut buf = vec![];
+
+        // The Base is the Required Insert Count, so that all references are
+        // relative and the Delta Base is zero.
+        let encoded = if required == 0 {
+            0
+        } else {
+            let max_entries = self.max_table_capacity as u64 / 32;
+            required % (2 * max_entries) + 1
+        };
+
+        encode_int(encoded, 8, 0, &mut buf);
+        encode_int(0, 7, 0, &mut buf);
+
+        for line in &lines {
+            encode_line(line, required, &mut buf);
+        }
+
+        dst.extend_from_slice(&buf);
+
+        if required > 0 {
+            self.sections
+                .entry(stream_id)
+                .or_default()
+                .push_back(Section {
+                    required_insert_count: required,
+                    min_index,
+                });
+        }
+    }
+
+    fn encode_field(&mut self, header: Header, may_block: bool, min_index: &mut u64) -> Line {
+        let indexing = if header.is_sensitive() {
+            Indexing::NeverIndexed
+        } else {
+            self.policy
+                .indexing(header.name().as_str(), header.value_slice())
+        };
+
+        let never_index = indexing == Indexing::NeverIndexed;
+
+        let found_static = find_static(header.name().as_slice(), header.value_slice());
+
+        if let Some((index, true)) = found_static {
+            return Line::Static(index);
+        }
+
+        let found_dynamic = self
+            .table
+            .find(&header)
+            .filter(|&(index, _)| index < self.known_received_count || may_block);
+
+        if let Some((index, true)) = found_dynamic {
+            if !never_index {
+                *min_index = cmp::min(*min_index, index);
+                return Line::Dynamic(index);
+            }
+        }
+
+        let pinned = cmp::min(self.pinned(), *min_index);
+
+        if indexing == Indexing::Indexed && may_block && self.table.can_insert(header.len(), pinned)
+        {
+            self.encode_insert(&header, found_static, found_dynamic);
+            self.table.insert(header);
+
+            let index = self.table.insert_count() - 1;
+            *min_index = cmp::min(*min_index, index);
+            return Line::Dynamic(index);
+        }
+
+        if let Some((index, _)) = found_static {
+            Line::StaticName(index, header, never_index)
+        } else if let Some((index, _)) = found_dynamic {
+            *min_index = cmp::min(*min_index, index);
+            Line::DynamicName(index, header, never_index)
+        } else {
+            Line::Literal(header, never_index)
+        }
+    }
+
+    fn encode_insert(
+        &mut self,
+        header: &Header,
+        found_static: Option<(u64, bool)>,
+        found_dynamic: Option<(u64, bool)>,
+    ) {
+        let dst = &mut self.instructions;
+
+        if let Some((index, _)) = found_static {
+            encode_int(index, 6, 0b1100_0000, dst);
+        } else if let Some((index, _)) = found_dynamic {
+            let relative = self.table.insert_count() - index - 1;
+            encode_int(relative, 6, 0b1000_0000, dst);
+        } else {
+            encode_str(header.name().as_slice(), 5, 0b0100_0000, dst);
+        }
+
+        encode_str(header.value_slice(), 7, 0, dst);
+    }
+
+    /// Processes data received on the peer's decoder stream.
+    ///
+    /// `src` does not need to end on an instruction boundary, the end of a
+    /// partial instruction is expected in the next call.
+    pub fn on_decoder_stream(&mut self, src: &[u8]) -> Result<(), Error> {
+        self.buf.extend_from_slice(src);
+
+        loop {
+            let (instruction, pos) = {
+                let mut buf = Cursor::new(&self.buf[..]);
+
+                match parse_instruction(&mut buf).map_err(Error::DecoderStream)? {
+                    Some(instruction) => (instruction, buf.position() as usize),
+                    None => return Ok(()),
+                }
+            };
+
+            self.buf.advance(pos);
+            self.apply(instruction).map_err(Error::DecoderStream)?;
+        }
+    }
+
+    fn apply(&mut self, instruction: Instruction) -> Result<(), Cause> {
+        log::trace!("qpack decoder instruction; {:?}", instruction);
+
+        match instruction {
+            Instruction::SectionAck(stream_id) => {
+                let section = {
+                    let sections = self
+                        .sections
+                        .get_mut(&stream_id)
+                        .ok_or(Cause::UnknownStream)?;
+                    let section = sections.pop_front().ok_or(Cause::UnknownStream)?;
+
+                    if sections.is_empty() {
+                        self.sections.remove(&stream_id);
+                    }
+
+                    section
+                };
+
+                self.known_received_count =
+                    cmp::max(self.known_received_count, section.required_insert_count);
+            }
+            Instruction::StreamCancel(stream_id) => {
+                self.sections.remove(&stream_id);
+            }
+            Instruction::InsertCountIncrement(increment) => {
+                let count = self.known_received_count + increment;
+
+                if increment == 0 || count > self.table.insert_count() {
+                    return Err(Cause::InvalidIncrement);
+                }
+
+                self.known_received_count = count;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the instructions to send on the encoder stream.
+    pub fn take_instructions(&mut self) -> Bytes {
+        mem::replace(&mut self.instructions, vec![]).into()
+    }
+
+    /// Returns the number of streams with field sections that the decoder
+    /// may not be able to decode yet.
+    pub fn blocked_streams(&self) -> usize {
+        self.sections
+            .keys()
+            .filter(|&&stream_id| self.is_blocking(stream_id))
+            .count()
+    }
+
+    /// Returns a view of the dynamic table.
+    pub fn table(&self) -> Table<'_> {
+        Table::new(&self.table)
+    }
+
+    fn is_blocking(&self, stream_id: u64) -> bool {
+        self.sections.get(&stream_id).map_or(false, |sections| {
+            sections
+                .iter()
+                .any(|section| section.required_insert_count > self.known_received_count)
+        })
+    }
+
+    // Entries at or past this absolute index may not be evicted.
+    fn pinned(&self) -> u64 {
+        self.sections
+            .values()
+            .flat_map(|sections| sections.iter())
+            .map(|section| section.min_index)
+            .min()
+            .unwrap_or(u64::max_value())
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
+}
+
+impl fmt::Debug for Encoder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Encoder")
+            .field("table", &self.table())
+            .field("max_table_capacity", &self.max_table_capacity)
+            .field("blocked_streams", &self.blocked_streams())
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+fn encode_line(line: &Line, base: u64, dst: &mut Vec<u8>) {
+    match *line {
+        Line::Static(index) => encode_int(index, 6, 0b1100_0000, dst),
+        Line::Dynamic(index) => encode_int(base - index - 1, 6, 0b1000_0000, dst),
+        Line::StaticName(index, ref header, never_index) => {
+            let n = if never_index { 0b0010_0000 } else { 0 };
+            encode_int(index, 4, 0b0101_0000 | n, dst);
+            encode_str(header.value_slice(), 7, 0, dst);
+        }
+        Line::DynamicName(index, ref header, never_index) => {
+            let n = if never_index { 0b0010_0000 } else { 0 };
+            encode_int(base - index - 1, 4, 0b0100_0000 | n, dst);
+            encode_str(header.value_slice(), 7, 0, dst);
+        }
+        Line::Literal(ref header, never_index) => {
+            let n = if never_index { 0b0001_0000 } else { 0 };
+            encode_str(header.name().as_slice(), 3, 0b0010_0000 | n, dst);
+            encode_str(header.value_slice(), 7, 0, dst);
+        }
+    }
+}
+
+fn parse_instruction(buf: &mut Cursor<&[u8]>) -> Result<Option<Instruction>, Cause> {
+    let b = match peek_u8(buf) {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+
+    let instruction = if b & 0b1000_0000 != 0 {
+        Instruction::SectionAck(try_opt!(decode_int(buf, 7)?))
+    } else if b & 0b0100_0000 != 0 {
+        Instruction::StreamCancel(try_opt!(decode_int(buf, 6)?))
+    } else {
+        Instruction::InsertCountIncrement(try_opt!(decode_int(buf, 6)?))
+    };
+
+    Ok(Some(instruction))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn field(name: &str, value: &str) -> Field {
+        Field::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+    }
+
+    fn encode(encoder: &mut Encoder, stream_id: u64, fields: Vec<Field>) -> BytesMut {
+        let mut dst = BytesMut::new();
+        encoder.encode(stream_id, fields, &mut dst);
+        dst
+    }
+
+    #[test]
+    fn test_encode_static_only() {
+        let mut encoder = Encoder::new();
+
+        let res = encode(
+            &mut encoder,
+            0,
+            vec![field(":method", "GET"), field(":path", "/index.html")],
+        );
+
+        // Indexed static 17, then literal with static name reference 1 and a
+        // Huffman encoded value.
+        assert_eq!(&res[..5], &[0x00, 0x00, 0xd1, 0x51, 0x88]);
+        assert_eq!(res.len(), 13);
+        assert!(encoder.take_instructions().is_empty());
+        assert!(encoder.table().is_empty());
+    }
+
+    #[test]
+    fn test_set_table_capacity() {
+        let mut encoder = Encoder::with_settings(220, 0);
+
+        assert!(encoder.set_table_capacity(220));
+        assert!(!encoder.set_table_capacity(221));
+
+        // RFC 9204 Appendix B.2
+        assert_eq!(&encoder.take_instructions()[..], &[0x3f, 0xbd, 0x01]);
+        assert_eq!(encoder.table().capacity(), 220);
+    }
+
+    #[test]
+    fn test_blocked_streams_limit() {
+        let mut encoder = Encoder::with_settings(220, 1);
+        encoder.set_table_capacity(220);
+        encoder.take_instructions();
+
+        let authority = || vec![field(":authority", "www.example.com")];
+
+        // Insert with static name reference 0, then a reference to it.
+        let res = encode(&mut encoder, 4, authority());
+        assert_eq!(&res[..], &[0x02, 0x00, 0x80]);
+        assert_eq!(&encoder.take_instructions()[..2], &[0xc0, 0x8c]);
+        assert_eq!(encoder.blocked_streams(), 1);
+
+        // Referencing the entry would block a second stream.
+        let res = encode(&mut encoder, 8, authority());
+        assert_eq!(&res[..3], &[0x00, 0x00, 0x50]);
+        assert!(encoder.take_instructions().is_empty());
+
+        // Section Acknowledgment for stream 4
+        encoder.on_decoder_stream(&[0x84]).unwrap();
+        assert_eq!(encoder.blocked_streams(), 0);
+
+        let res = encode(&mut encoder, 12, authority());
+        assert_eq!(&res[..], &[0x02, 0x00, 0x80]);
+        assert_eq!(encoder.blocked_streams(), 0);
+    }
+
+    #[test]
+    fn test_referenced_entries_are_not_evicted() {
+        let mut encoder = Encoder::with_settings(64, 2);
+        encoder.set_table_capacity(64);
+
+        // 32 + 3 + 1 = 36 octets, so only one entry fits.
+        encode(&mut encoder, 4, vec![field("x-a", "1")]);
+        assert_eq!(encoder.table().len(), 1);
+
+        let res = encode(&mut encoder, 8, vec![field("x-b", "2")]);
+        assert_eq!(res[2] & 0b1110_0000, 0b0010_0000);
+        assert!(!encoder.set_table_capacity(0));
+
+        // Once stream 4 is acknowledged, its entry can be evicted.
+        encoder.on_decoder_stream(&[0x84]).unwrap();
+
+        let res = encode(&mut encoder, 12, vec![field("x-b", "2")]);
+        assert_eq!(&res[..], &[0x03, 0x00, 0x80]);
+        assert_eq!(encoder.table().evictions(), 1);
+    }
+
+    #[test]
+    fn test_never_indexed() {
+        let mut encoder = Encoder::with_settings(220, 1);
+        encoder.set_table_capacity(220);
+
+        let mut token = http::HeaderValue::from_static("secret");
+        token.set_sensitive(true);
+
+        let res = encode(
+            &mut encoder,
+            4,
+            vec![Field::new(http::header::AUTHORIZATION, token)],
+        );
+
+        // Literal with static name reference 84 and the N bit set
+        assert_eq!(&res[..4], &[0x00, 0x00, 0x7f, 0x45]);
+        assert!(encoder.table().is_empty());
+    }
+
+    #[test]
+    fn test_decoder_stream_errors() {
+        let mut encoder = Encoder::with_settings(220, 1);
+
+        assert_eq!(
+            encoder.on_decoder_stream(&[0x84]),
+            Err(Error::DecoderStream(Cause::UnknownStream))
+        );
+        assert_eq!(
+            encoder.on_decoder_stream(&[0x00]),
+            Err(Error::DecoderStream(Cause::InvalidIncrement))
+        );
+        assert_eq!(
+            encoder.on_decoder_stream(&[0x01]),
+            Err(Error::DecoderStream(Cause::InvalidIncrement))
+        );
+    }
+}
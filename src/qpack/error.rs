@@ -0,0 +1,120 @@
+use crate::hpack::DecoderError;
+
+use std::{error, fmt};
+
+/// A QPACK error, which must be treated as a connection error of the
+/// corresponding HTTP/3 error code.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    /// A field section could not be decoded (`QPACK_DECOMPRESSION_FAILED`).
+    DecompressionFailed(Cause),
+
+    /// An instruction received on the encoder stream could not be processed
+    /// (`QPACK_ENCODER_STREAM_ERROR`).
+    EncoderStream(Cause),
+
+    /// An instruction received on the decoder stream could not be processed
+    /// (`QPACK_DECODER_STREAM_ERROR`).
+    DecoderStream(Cause),
+}
+
+/// The reason for a QPACK [`Error`].
+///
+/// [`Error`]: enum.Error.html
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Cause {
+    /// The Required Insert Count of a field section could not be decoded.
+    InvalidRequiredInsertCount,
+
+    /// The Base of a field section is negative.
+    InvalidBase,
+
+    /// A reference to an entry that is not in the static or dynamic table,
+    /// or that is past the Required Insert Count of the field section.
+    InvalidTableIndex,
+
+    /// The dynamic table capacity was set above the advertised maximum.
+    InvalidCapacity,
+
+    /// An entry larger than the dynamic table capacity was inserted.
+    EntryTooLarge,
+
+    /// More field sections are blocked than advertised in
+    /// `SETTINGS_QPACK_BLOCKED_STREAMS`.
+    TooManyBlockedStreams,
+
+    /// A Section Acknowledgment for a stream without unacknowledged field
+    /// sections.
+    UnknownStream,
+
+    /// An Insert Count Increment of zero or past the number of inserted
+    /// entries.
+    InvalidIncrement,
+
+    /// A field section ends in the middle of a field line.
+    Truncated,
+
+    /// An integer does not fit in 62 bits.
+    IntegerOverflow,
+
+    /// A field name or value could not be decoded.
+    Field(DecoderError),
+}
+
+impl Error {
+    /// Returns the HTTP/3 error code of this error.
+    pub fn code(&self) -> u64 {
+        match *self {
+            Error::DecompressionFailed(_) => 0x200,
+            Error::EncoderStream(_) => 0x201,
+            Error::DecoderStream(_) => 0x202,
+        }
+    }
+
+    /// Returns the reason for this error.
+    pub fn cause(&self) -> Cause {
+        match *self {
+            Error::DecompressionFailed(cause)
+            | Error::EncoderStream(cause)
+            | Error::DecoderStream(cause) => cause,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let what = match *self {
+            Error::DecompressionFailed(_) => "failed to decode field section",
+            Error::EncoderStream(_) => "invalid encoder stream instruction",
+            Error::DecoderStream(_) => "invalid decoder stream instruction",
+        };
+
+        write!(fmt, "{}: {}", what, self.cause())
+    }
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Cause::InvalidRequiredInsertCount => fmt.write_str("invalid required insert count"),
+            Cause::InvalidBase => fmt.write_str("invalid base"),
+            Cause::InvalidTableIndex => fmt.write_str("invalid table index"),
+            Cause::InvalidCapacity => fmt.write_str("dynamic table capacity exceeds the maximum"),
+            Cause::EntryTooLarge => fmt.write_str("entry larger than the dynamic table"),
+            Cause::TooManyBlockedStreams => fmt.write_str("too many blocked streams"),
+            Cause::UnknownStream => fmt.write_str("no field section to acknowledge"),
+            Cause::InvalidIncrement => fmt.write_str("invalid insert count increment"),
+            Cause::Truncated => fmt.write_str("truncated field section"),
+            Cause::IntegerOverflow => fmt.write_str("integer overflow"),
+            Cause::Field(ref e) => fmt::Display::fmt(e, fmt),
+        }
+    }
+}
+
+impl From<DecoderError> for Cause {
+    fn from(src: DecoderError) -> Cause {
+        Cause::Field(src)
+    }
+}
@@ -0,0 +1,81 @@
+//! QPACK field compression.
+//!
+//! This module implements [QPACK], the field compression format of HTTP/3.
+//! It shares the Huffman code of the [`hpack`] module, and accounts for the
+//! size of dynamic table entries the same way.
+//!
+//! An [`Encoder`] turns a list of [`Field`]s into a field section, and a
+//! [`Decoder`] turns a field section back into fields. Unlike HPACK, the
+//! dynamic table is updated by instructions sent on dedicated unidirectional
+//! streams: the encoder stream carries the insertions made by the encoder,
+//! and the decoder stream carries the acknowledgments of the decoder. Both
+//! sides return the instructions they want to send from their
+//! `take_instructions` method, and process the instructions they receive
+//! with `on_encoder_stream` or `on_decoder_stream`.
+//!
+//! A decoder created with `Decoder::new`, and an encoder created with
+//! `Encoder::new`, only use the static table and never exchange
+//! instructions.
+//!
+//! # Examples
+//!
+//! ```
+//! use bytes::BytesMut;
+//! use h2::qpack::{Decoder, Encoder, Field};
+//!
+//! // The settings the decoder sent to the encoder.
+//! let mut encoder = Encoder::with_settings(4_096, 16);
+//! let mut decoder = Decoder::with_settings(4_096, 16);
+//!
+//! encoder.set_table_capacity(4_096);
+//!
+//! let fields = vec![
+//!     Field::from_bytes(b":method", b"GET").unwrap(),
+//!     Field::from_bytes(b":authority", b"example.com").unwrap(),
+//! ];
+//!
+//! let mut section = BytesMut::new();
+//! encoder.encode(0, fields.clone(), &mut section);
+//!
+//! // Until the encoder stream data is received, the section is blocked.
+//! assert_eq!(decoder.decode(0, &section).unwrap(), None);
+//!
+//! decoder.on_encoder_stream(&encoder.take_instructions()).unwrap();
+//! assert_eq!(decoder.decode(0, &section).unwrap(), Some(fields));
+//!
+//! encoder.on_decoder_stream(&decoder.take_instructions()).unwrap();
+//! assert_eq!(encoder.blocked_streams(), 0);
+//! ```
+//!
+//! This module is only available with the `qpack` cargo feature.
+//!
+//! [QPACK]: https://www.rfc-editor.org/rfc/rfc9204
+//! [`hpack`]: ../hpack/index.html
+//! [`Encoder`]: struct.Encoder.html
+//! [`Decoder`]: struct.Decoder.html
+//! [`Field`]: struct.Field.html
+
+// Returns early with `Ok(None)` when more data is needed.
+macro_rules! try_opt {
+    ($e:expr) => {
+        match $e {
+            Some(val) => val,
+            None => return Ok(None),
+        }
+    };
+}
+
+mod coding;
+mod decoder;
+mod encoder;
+mod error;
+mod table;
+
+#[cfg(test)]
+mod test;
+
+pub use self::decoder::Decoder;
+pub use self::encoder::Encoder;
+pub use self::error::{Cause, Error};
+pub use self::table::Table;
+pub use crate::hpack::Field;
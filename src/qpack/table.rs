@@ -0,0 +1,421 @@
+use crate::hpack::{DynamicEntries, Field, Header};
+
+use bytes::Bytes;
+
+use std::fmt;
+
+/// The QPACK dynamic table.
+///
+/// Entries are addressed by their absolute index, which is the number of
+/// entries inserted before them. The entries and their size are kept the
+/// same way as by the HPACK decoder.
+#[derive(Debug)]
+pub(crate) struct DynamicTable {
+    entries: DynamicEntries,
+}
+
+/// A read-only view of the dynamic table of an `Encoder` or a `Decoder`.
+#[derive(Clone, Copy)]
+pub struct Table<'a> {
+    inner: &'a DynamicTable,
+}
+
+/// The QPACK static table, as defined by RFC 9204 Appendix A.
+const STATIC_TABLE: [(&str, &str); 99] = [
+    (":authority", ""),
+    (":path", "/"),
+    ("age", "0"),
+    ("content-disposition", ""),
+    ("content-length", "0"),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("referer", ""),
+    ("set-cookie", ""),
+    (":method", "CONNECT"),
+    (":method", "DELETE"),
+    (":method", "GET"),
+    (":method", "HEAD"),
+    (":method", "OPTIONS"),
+    (":method", "POST"),
+    (":method", "PUT"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "103"),
+    (":status", "200"),
+    (":status", "304"),
+    (":status", "404"),
+    (":status", "503"),
+    ("accept", "*/*"),
+    ("accept", "application/dns-message"),
+    ("accept-encoding", "gzip, deflate, br"),
+    ("accept-ranges", "bytes"),
+    ("access-control-allow-headers", "cache-control"),
+    ("access-control-allow-headers", "content-type"),
+    ("access-control-allow-origin", "*"),
+    ("cache-control", "max-age=0"),
+    ("cache-control", "max-age=2592000"),
+    ("cache-control", "max-age=604800"),
+    ("cache-control", "no-cache"),
+    ("cache-control", "no-store"),
+    ("cache-control", "public, max-age=31536000"),
+    ("content-encoding", "br"),
+    ("content-encoding", "gzip"),
+    ("content-type", "application/dns-message"),
+    ("content-type", "application/javascript"),
+    ("content-type", "application/json"),
+    ("content-type", "application/x-www-form-urlencoded"),
+    ("content-type", "image/gif"),
+    ("content-type", "image/jpeg"),
+    ("content-type", "image/png"),
+    ("content-type", "text/css"),
+    ("content-type", "text/html; charset=utf-8"),
+    ("content-type", "text/plain"),
+    ("content-type", "text/plain;charset=utf-8"),
+    ("range", "bytes=0-"),
+    ("strict-transport-security", "max-age=31536000"),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includesubdomains",
+    ),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includesubdomains; preload",
+    ),
+    ("vary", "accept-encoding"),
+    ("vary", "origin"),
+    ("x-content-type-options", "nosniff"),
+    ("x-xss-protection", "1; mode=block"),
+    (":status", "100"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "302"),
+    (":status", "400"),
+    (":status", "403"),
+    (":status", "421"),
+    (":status", "425"),
+    (":status", "500"),
+    ("accept-language", ""),
+    ("access-control-allow-credentials", "FALSE"),
+    ("access-control-allow-credentials", "TRUE"),
+    ("access-control-allow-headers", "*"),
+    ("access-control-allow-methods", "get"),
+    ("access-control-allow-methods", "get, post, options"),
+    ("access-control-allow-methods", "options"),
+    ("access-control-expose-headers", "content-length"),
+    ("access-control-request-headers", "content-type"),
+    ("access-control-request-method", "get"),
+    ("access-control-request-method", "post"),
+    ("alt-svc", "clear"),
+    ("authorization", ""),
+    (
+        "content-security-policy",
+        "script-src 'none'; object-src 'none'; base-uri 'none'",
+    ),
+    ("early-data", "1"),
+    ("expect-ct", ""),
+    ("forwarded", ""),
+    ("if-range", ""),
+    ("origin", ""),
+    ("purpose", "prefetch"),
+    ("server", ""),
+    ("timing-allow-origin", "*"),
+    ("upgrade-insecure-requests", "1"),
+    ("user-agent", ""),
+    ("x-forwarded-for", ""),
+    ("x-frame-options", "deny"),
+    ("x-frame-options", "sameorigin"),
+];
+
+/// Returns the static table entry at `index`.
+pub(crate) fn get_static(index: u64) -> Option<Header> {
+    if index >= STATIC_TABLE.len() as u64 {
+        return None;
+    }
+
+    let (name, value) = STATIC_TABLE[index as usize];
+    let header = Header::new(
+        Bytes::from_static(name.as_bytes()),
+        Bytes::from_static(value.as_bytes()),
+    )
+    .expect("static table entries are valid");
+
+    Some(header)
+}
+
+/// Searches the static table for a field.
+///
+/// Returns the index of an entry matching both the name and the value if
+/// there is one, otherwise of the first entry matching the name. The boolean
+/// is true if the value matched.
+pub(crate) fn find_static(name: &[u8], value: &[u8]) -> Option<(u64, bool)> {
+    let mut found = None;
+
+    for (index, &(n, v)) in STATIC_TABLE.iter().enumerate() {
+        if n.as_bytes() != name {
+            continue;
+        }
+
+        if v.as_bytes() == value {
+            return Some((index as u64, true));
+        }
+
+        if found.is_none() {
+            found = Some((index as u64, false));
+        }
+    }
+
+    found
+}
+
+// ===== impl DynamicTable =====
+
+impl DynamicTable {
+    pub fn new() -> DynamicTable {
+        DynamicTable {
+            entries: DynamicEntries::new(0),
+        }
+    }
+
+    /// Returns the total number of entries ever inserted.
+    pub fn insert_count(&self) -> u64 {
+        self.dropped() + self.len() as u64
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.size()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.max_size()
+    }
+
+    /// Returns the number of evicted entries, which is also the absolute
+    /// index of the oldest entry still in the table.
+    pub fn dropped(&self) -> u64 {
+        self.entries.evictions() as u64
+    }
+
+    /// Returns the entry at the given absolute index, if it was not evicted.
+    pub fn get(&self, index: u64) -> Option<&Header> {
+        let index = index.checked_sub(self.dropped())?;
+
+        if index >= self.len() as u64 {
+            return None;
+        }
+
+        self.entries.get(index as usize)
+    }
+
+    /// Searches the table for a field, from the most recent entry.
+    ///
+    /// Returns the absolute index of an entry matching both the name and the
+    /// value if there is one, otherwise of the most recent entry matching the
+    /// name. The boolean is true if the value matched.
+    pub fn find(&self, header: &Header) -> Option<(u64, bool)> {
+        let name = header.name();
+        let name = name.as_slice();
+        let mut found = None;
+
+        for (i, entry) in self.entries.iter().enumerate().rev() {
+            if entry.name().as_slice() != name {
+                continue;
+            }
+
+            let index = self.dropped() + i as u64;
+
+            if entry.value_slice() == header.value_slice() {
+                return Some((index, true));
+            }
+
+            if found.is_none() {
+                found = Some((index, false));
+            }
+        }
+
+        found
+    }
+
+    /// Returns true if an entry of `size` octets can be inserted without
+    /// evicting any entry with an absolute index of `pinned` or more.
+    pub fn can_insert(&self, size: usize, pinned: u64) -> bool {
+        if size > self.capacity() {
+            return false;
+        }
+
+        let mut available = self.capacity() - self.size();
+
+        for (index, entry) in (self.dropped()..).zip(self.entries.iter()) {
+            if available >= size {
+                break;
+            }
+
+            if index >= pinned {
+                return false;
+            }
+
+            available += entry.len();
+        }
+
+        available >= size
+    }
+
+    /// Returns true if the capacity can be set to `capacity` without evicting
+    /// any entry with an absolute index of `pinned` or more.
+    pub fn can_resize(&self, capacity: usize, pinned: u64) -> bool {
+        let mut size = self.size();
+
+        for (index, entry) in (self.dropped()..).zip(self.entries.iter()) {
+            if size <= capacity {
+                break;
+            }
+
+            if index >= pinned {
+                return false;
+            }
+
+            size -= entry.len();
+        }
+
+        size <= capacity
+    }
+
+    /// Inserts an entry, evicting older entries to make room for it.
+    ///
+    /// The caller must ensure the entry fits in the table.
+    pub fn insert(&mut self, header: Header) {
+        debug_assert!(header.len() <= self.capacity());
+
+        self.entries.insert(header);
+    }
+
+    /// Sets the capacity of the table, evicting entries that no longer fit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.entries.set_max_size(capacity);
+    }
+}
+
+// ===== impl Table =====
+
+impl<'a> Table<'a> {
+    pub(crate) fn new(inner: &'a DynamicTable) -> Table<'a> {
+        Table { inner }
+    }
+
+    /// Returns the number of entries in the table.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the sum of the sizes of the entries, in octets.
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Returns the current capacity of the table, in octets.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Returns the total number of entries inserted into the table, including
+    /// the evicted ones.
+    pub fn insert_count(&self) -> u64 {
+        self.inner.insert_count()
+    }
+
+    /// Returns the number of entries evicted from the table.
+    pub fn evictions(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    /// Returns the entry at the given absolute index, if it was not evicted.
+    pub fn get(&self, index: u64) -> Option<Field> {
+        self.inner.get(index).cloned().map(Field::from_inner)
+    }
+}
+
+impl<'a> fmt::Debug for Table<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Table")
+            .field("len", &self.len())
+            .field("size", &self.size())
+            .field("capacity", &self.capacity())
+            .field("insert_count", &self.insert_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn header(name: &'static str, value: &'static str) -> Header {
+        Header::new(
+            Bytes::from_static(name.as_bytes()),
+            Bytes::from_static(value.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_static_table() {
+        assert_eq!(STATIC_TABLE.len(), 99);
+
+        for index in 0..STATIC_TABLE.len() as u64 {
+            assert!(get_static(index).is_some());
+        }
+
+        assert!(get_static(99).is_none());
+
+        assert_eq!(find_static(b":method", b"GET"), Some((17, true)));
+        assert_eq!(find_static(b":status", b"500"), Some((71, true)));
+        assert_eq!(find_static(b":status", b"201"), Some((24, false)));
+        assert_eq!(find_static(b"x-unknown", b""), None);
+    }
+
+    #[test]
+    fn test_insert_evicts_oldest_entries() {
+        let mut table = DynamicTable::new();
+        table.set_capacity(100);
+
+        // 32 + 5 + 1 = 38 octets each
+        table.insert(header("x-one", "1"));
+        table.insert(header("x-two", "2"));
+        assert_eq!(table.size(), 76);
+
+        assert!(table.can_insert(38, 2));
+        assert!(!table.can_insert(38, 0));
+        assert!(!table.can_insert(101, 2));
+
+        table.insert(header("x-thr", "3"));
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.insert_count(), 3);
+        assert_eq!(table.dropped(), 1);
+        assert!(table.get(0).is_none());
+        assert_eq!(table.get(2).unwrap().value_slice(), b"3");
+        assert!(table.get(3).is_none());
+
+        assert_eq!(table.find(&header("x-two", "2")), Some((1, true)));
+        assert_eq!(table.find(&header("x-two", "9")), Some((1, false)));
+
+        assert!(table.can_resize(40, 2));
+        assert!(!table.can_resize(40, 1));
+        table.set_capacity(40);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.dropped(), 2);
+    }
+}
@@ -0,0 +1,289 @@
+use super::{Decoder, Encoder, Field};
+
+use bytes::BytesMut;
+use hex::FromHex;
+use serde_json::Value;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::mem;
+use std::path::Path;
+use walkdir::WalkDir;
+
+fn load(path: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures/qpack")
+        .join(path);
+
+    let mut file = File::open(path).unwrap();
+    let mut data = String::new();
+    file.read_to_string(&mut data).unwrap();
+
+    serde_json::from_str(&data).unwrap()
+}
+
+fn wire(step: &Value) -> Vec<u8> {
+    let wire = step["wire"].as_str().unwrap();
+    FromHex::from_hex(wire.as_bytes()).unwrap()
+}
+
+fn headers(step: &Value) -> Vec<Field> {
+    step["headers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| {
+            let (name, value) = h.as_object().unwrap().iter().next().unwrap();
+            Field::from_bytes(name.as_bytes(), value.as_str().unwrap().as_bytes()).unwrap()
+        })
+        .collect()
+}
+
+fn test_decoder(story: &Value) {
+    let capacity = story["max_table_capacity"].as_u64().unwrap() as usize;
+    let mut decoder = Decoder::with_settings(capacity, 16);
+
+    for step in story["steps"].as_array().unwrap() {
+        let section = step["section"].as_str().unwrap();
+
+        match step["stream"].as_str() {
+            Some("encoder") => {
+                decoder.on_encoder_stream(&wire(step)).unwrap();
+
+                if let Some(table) = step.get("table") {
+                    let entries = table["entries"].as_array().unwrap();
+
+                    assert_eq!(decoder.table().len(), entries.len(), "{}", section);
+                    assert_eq!(
+                        decoder.table().size() as u64,
+                        table["size"].as_u64().unwrap(),
+                        "{}",
+                        section
+                    );
+
+                    for entry in entries {
+                        let index = entry[0].as_u64().unwrap();
+                        let name = entry[1].as_str().unwrap();
+                        let value = entry[2].as_str().unwrap();
+
+                        let field = decoder.table().get(index).unwrap();
+                        assert_eq!(field.name(), name, "{}", section);
+                        assert_eq!(field.value(), value.as_bytes(), "{}", section);
+                    }
+                }
+            }
+            Some("decoder") => {
+                let mut sent = decoder.take_instructions();
+
+                if let Some(stream_id) = step.get("cancel") {
+                    decoder.cancel_stream(stream_id.as_u64().unwrap());
+                    sent = decoder.take_instructions();
+                }
+
+                assert_eq!(&sent[..], &wire(step)[..], "{}", section);
+            }
+            _ => {
+                let stream_id = step["stream"].as_u64().unwrap();
+                let fields = decoder.decode(stream_id, &wire(step)).unwrap();
+
+                assert_eq!(fields, Some(headers(step)), "{}", section);
+            }
+        }
+    }
+}
+
+fn test_encoder(story: &Value) {
+    let capacity = story["max_table_capacity"].as_u64().unwrap() as usize;
+    let mut encoder = Encoder::with_settings(capacity, 16);
+    let mut decoder = Decoder::with_settings(capacity, 16);
+
+    assert!(encoder.set_table_capacity(capacity));
+
+    for step in story["steps"].as_array().unwrap() {
+        let stream_id = match step["stream"].as_u64() {
+            Some(stream_id) => stream_id,
+            None => continue,
+        };
+
+        let mut dst = BytesMut::new();
+        encoder.encode(stream_id, headers(step), &mut dst);
+
+        decoder
+            .on_encoder_stream(&encoder.take_instructions())
+            .unwrap();
+        assert_eq!(
+            decoder.decode(stream_id, &dst).unwrap(),
+            Some(headers(step))
+        );
+
+        encoder
+            .on_decoder_stream(&decoder.take_instructions())
+            .unwrap();
+        assert_eq!(encoder.blocked_streams(), 0);
+    }
+
+    assert_eq!(
+        encoder.table().insert_count(),
+        decoder.table().insert_count()
+    );
+    assert_eq!(encoder.table().size(), decoder.table().size());
+}
+
+#[test]
+fn rfc9204_appendix_b_decode() {
+    test_decoder(&load("rfc9204/appendix-b.json"));
+}
+
+#[test]
+fn rfc9204_appendix_b_encode() {
+    test_encoder(&load("rfc9204/appendix-b.json"));
+}
+
+/// Runs the QPACK interop vectors of `fixtures/qpack/qifs`, laid out like
+/// https://github.com/qpackers/qifs. See `fixtures/qpack/README.md`.
+#[test]
+fn qifs_interop() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/qpack/qifs");
+    let mut num_files = 0;
+
+    for entry in WalkDir::new(root.join("qifs")) {
+        let entry = entry.unwrap();
+
+        if entry.path().extension().map_or(false, |ext| ext == "qif") {
+            test_qif_encoder(&load_qif(entry.path()));
+        }
+    }
+
+    for entry in WalkDir::new(root.join("encoded")) {
+        let entry = entry.unwrap();
+        let name = entry.file_name().to_str().unwrap();
+
+        // <qif>.out.<table capacity>.<blocked streams>.<immediate ack>
+        let pos = match name.find(".out.") {
+            Some(pos) => pos,
+            None => continue,
+        };
+
+        let settings: Vec<usize> = name[pos + 5..]
+            .split('.')
+            .map(|n| n.parse().unwrap())
+            .collect();
+
+        let qif = root.join("qifs").join(format!("{}.qif", &name[..pos]));
+
+        test_qif_decoder(entry.path(), &load_qif(&qif), settings[0], settings[1]);
+        num_files += 1;
+    }
+
+    assert!(num_files > 0, "the qifs corpus is missing from {:?}", root);
+}
+
+/// Loads the field sections of a QIF file: one `name\tvalue` field per line,
+/// with sections separated by empty lines.
+fn load_qif(path: &Path) -> Vec<Vec<Field>> {
+    let data = fs::read_to_string(path).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+
+    let mut sections = vec![];
+    let mut fields = vec![];
+
+    for line in data.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if line.is_empty() {
+            if !fields.is_empty() {
+                sections.push(mem::replace(&mut fields, vec![]));
+            }
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '\t');
+        let name = parts.next().unwrap();
+        let value = parts.next().unwrap_or("");
+
+        fields.push(Field::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+    }
+
+    if !fields.is_empty() {
+        sections.push(fields);
+    }
+
+    sections
+}
+
+/// Decodes a file encoded by another implementation. It is a sequence of
+/// blocks made of a 64 bit stream ID, a 32 bit length and the data; stream 0
+/// is the encoder stream and stream N carries the Nth section of the QIF.
+fn test_qif_decoder(path: &Path, sections: &[Vec<Field>], capacity: usize, blocked: usize) {
+    let data = fs::read(path).unwrap();
+    let mut decoder = Decoder::with_settings(capacity, blocked);
+    let mut pending: Vec<(u64, &[u8])> = vec![];
+    let mut decoded = 0;
+
+    let mut check = |stream_id: u64, fields: Vec<Field>| {
+        assert_eq!(fields, sections[stream_id as usize - 1], "{:?}", path);
+        decoded += 1;
+    };
+
+    let mut buf = &data[..];
+
+    while !buf.is_empty() {
+        let stream_id = buf[..8].iter().fold(0, |n, &b| n << 8 | u64::from(b));
+        let len = buf[8..12].iter().fold(0, |n, &b| n << 8 | b as usize);
+        let block = &buf[12..12 + len];
+        buf = &buf[12 + len..];
+
+        if stream_id == 0 {
+            decoder.on_encoder_stream(block).unwrap();
+
+            // Blocked sections may now be decoded
+            let mut i = 0;
+            while i < pending.len() {
+                let (stream_id, block) = pending[i];
+
+                match decoder.decode(stream_id, block).unwrap() {
+                    Some(fields) => {
+                        check(stream_id, fields);
+                        pending.remove(i);
+                    }
+                    None => i += 1,
+                }
+            }
+        } else {
+            match decoder.decode(stream_id, block).unwrap() {
+                Some(fields) => check(stream_id, fields),
+                None => pending.push((stream_id, block)),
+            }
+        }
+    }
+
+    assert!(pending.is_empty(), "{:?}", path);
+    assert_eq!(decoded, sections.len(), "{:?}", path);
+}
+
+fn test_qif_encoder(sections: &[Vec<Field>]) {
+    let mut encoder = Encoder::with_settings(4_096, 16);
+    let mut decoder = Decoder::with_settings(4_096, 16);
+
+    assert!(encoder.set_table_capacity(4_096));
+
+    for (i, fields) in sections.iter().enumerate() {
+        let stream_id = i as u64 + 1;
+
+        let mut dst = BytesMut::new();
+        encoder.encode(stream_id, fields.clone(), &mut dst);
+
+        decoder
+            .on_encoder_stream(&encoder.take_instructions())
+            .unwrap();
+        assert_eq!(
+            decoder.decode(stream_id, &dst).unwrap().as_ref(),
+            Some(fields)
+        );
+
+        encoder
+            .on_decoder_stream(&decoder.take_instructions())
+            .unwrap();
+    }
+}
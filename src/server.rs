@@ -54,8 +54,11 @@
 //!
 //! # Shutting down the server
 //!
-//! Graceful shutdown of the server is [not yet
-//! implemented](https://github.com/hyperium/h2/issues/69).
+//! [`Connection::graceful_shutdown`] stops the client from initiating new
+//! streams and closes the connection once all active streams have completed.
+//! To bound how long this may take, use [`Connection::drain`] instead, which
+//! escalates to an abrupt shutdown once a deadline is reached. The number of
+//! remaining streams can be observed with [`Connection::drain_progress`].
 //!
 //! # Example
 //!
@@ -110,22 +113,29 @@
 //! [`Connection`]: struct.Connection.html
 //! [`Connection::poll`]: struct.Connection.html#method.poll
 //! [`Connection::poll_close`]: struct.Connection.html#method.poll_close
+//! [`Connection::graceful_shutdown`]: struct.Connection.html#method.graceful_shutdown
+//! [`Connection::drain`]: struct.Connection.html#method.drain
+//! [`Connection::drain_progress`]: struct.Connection.html#method.drain_progress
 //! [`futures::Stream`]: https://docs.rs/futures/0.1/futures/stream/trait.Stream.html
 //! [`http::Request<RecvStream>`]: ../struct.RecvStream.html
 //! [`RecvStream`]: ../struct.RecvStream.html
 //! [`SendStream`]: ../struct.SendStream.html
 //! [`TcpListener`]: https://docs.rs/tokio-core/0.1/tokio_core/net/struct.TcpListener.html
 
-use crate::codec::{Codec, RecvError};
+use crate::codec::{Codec, RecvError, UserError};
 use crate::frame::{self, Pseudo, Reason, Settings, StreamId};
 use crate::proto::{self, Config, Prioritized};
-use crate::{PingPong, RecvStream, ReleaseCapacity, SendStream};
+use crate::{
+    FlushStrategy, FrameObserver, HpackStats, Huffman, IndexingPolicy, Padding, PingPong,
+    RecvStream, ReleaseCapacity, SendStream, Sleep, Timer,
+};
 
 use bytes::{Buf, Bytes, IntoBuf};
 use futures::ready;
 use http::{HeaderMap, Request, Response};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{convert, fmt, io, mem};
@@ -151,6 +161,8 @@ pub struct Handshake<T, B: IntoBuf = Bytes> {
     builder: Builder,
     /// The current state of the handshake.
     state: Handshaking<T, B>,
+    /// Completes if the handshake takes longer than `handshake_timeout`.
+    timeout: Option<Sleep>,
 }
 
 /// Accepts inbound HTTP/2.0 streams on a connection.
@@ -240,8 +252,41 @@ pub struct Builder {
     /// Initial `Settings` frame to send as part of the handshake.
     settings: Settings,
 
+    /// Decides how sent headers are indexed by the HPACK encoder.
+    indexing_policy: Option<Arc<dyn IndexingPolicy>>,
+
+    /// When the HPACK encoder Huffman encodes sent string literals.
+    huffman: Huffman,
+
+    /// Padding of sent frames.
+    padding: Option<Padding>,
+
+    /// When buffered frames are flushed.
+    flush: FlushStrategy,
+
+    /// Observes the sent and received frames.
+    frame_observer: Option<Arc<dyn FrameObserver>>,
+
     /// Initial target window size for new connections.
     initial_target_connection_window_size: Option<u32>,
+
+    /// Provides timers for the time based options below.
+    timer: Option<Arc<dyn Timer>>,
+
+    /// Maximum time to receive the preface and first SETTINGS frame.
+    handshake_timeout: Option<Duration>,
+
+    /// Maximum time the connection may go without any streams.
+    idle_timeout: Option<Duration>,
+
+    /// Maximum time to wait for the client to acknowledge our SETTINGS.
+    settings_ack_timeout: Option<Duration>,
+
+    /// How often to send keep-alive PINGs.
+    keep_alive_interval: Option<Duration>,
+
+    /// Maximum time to wait for a keep-alive PING to be acknowledged.
+    keep_alive_timeout: Duration,
 }
 
 /// Send a response back to the client
@@ -349,6 +394,20 @@ where
             codec.set_max_recv_header_list_size(max as usize);
         }
 
+        if let Some(ref policy) = builder.indexing_policy {
+            codec.set_indexing_policy(policy.clone());
+        }
+
+        codec.set_huffman(builder.huffman);
+
+        if let Some(padding) = builder.padding {
+            codec.set_padding(padding);
+        }
+
+        if let Some(ref observer) = builder.frame_observer {
+            codec.set_frame_observer(observer.clone());
+        }
+
         // Send initial settings frame.
         codec
             .buffer(builder.settings.clone().into())
@@ -357,7 +416,16 @@ where
         // Create the handshake future.
         let state = Handshaking::from(codec);
 
-        Handshake { builder, state }
+        let timeout = match (&builder.timer, builder.handshake_timeout) {
+            (Some(timer), Some(dur)) => Some(timer.sleep(dur)),
+            _ => None,
+        };
+
+        Handshake {
+            builder,
+            state,
+            timeout,
+        }
     }
 
     /// Accept the next incoming request on this connection.
@@ -469,6 +537,65 @@ where
         self.connection.go_away_gracefully();
     }
 
+    /// Starts a [graceful shutdown][1] process that is bounded by `deadline`.
+    ///
+    /// This behaves like [`graceful_shutdown`], but if `deadline` completes
+    /// before all active streams have finished, the connection is shutdown
+    /// as if [`abrupt_shutdown`] had been called with `reason`.
+    ///
+    /// `deadline` is usually a timer future from the runtime in use. It is
+    /// only polled while the connection itself is being polled.
+    ///
+    /// Must continue being polled to close connection. Use
+    /// [`drain_progress`] to observe the number of remaining streams.
+    ///
+    /// [1]: http://httpwg.org/specs/rfc7540.html#GOAWAY
+    /// [`graceful_shutdown`]: #method.graceful_shutdown
+    /// [`abrupt_shutdown`]: #method.abrupt_shutdown
+    /// [`drain_progress`]: #method.drain_progress
+    pub fn drain<D>(&mut self, deadline: D, reason: Reason)
+    where
+        D: Future<Output = ()> + Send + 'static,
+    {
+        self.connection.go_away_gracefully();
+        self.connection.drain(deadline, reason);
+    }
+
+    /// Waits for the number of active streams to change while draining.
+    ///
+    /// The first call after [`drain`] yields the current number of active
+    /// streams, each subsequent call yields once that number changes. Returns
+    /// `None` once the connection has closed.
+    ///
+    /// This function will advance the internal connection state. Requests
+    /// that were already in flight when the drain started are still queued to
+    /// be returned by [`accept`].
+    ///
+    /// [`drain`]: #method.drain
+    /// [`accept`]: #method.accept
+    pub async fn drain_progress(&mut self) -> Option<Result<usize, crate::Error>> {
+        futures::future::poll_fn(move |cx| self.poll_drain_progress(cx)).await
+    }
+
+    #[doc(hidden)]
+    pub fn poll_drain_progress(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<usize, crate::Error>>> {
+        let closed = self.poll_closed(cx)?;
+
+        // Report the final count before signaling the connection is closed.
+        if let Poll::Ready(active) = self.connection.poll_drain_progress() {
+            return Poll::Ready(Some(Ok(active)));
+        }
+
+        if closed.is_ready() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+
     /// Takes a `PingPong` instance from the connection.
     ///
     /// # Note
@@ -477,6 +604,15 @@ where
     pub fn ping_pong(&mut self) -> Option<PingPong> {
         self.connection.take_user_pings().map(PingPong::new)
     }
+
+    /// Returns the HPACK header compression statistics of the connection.
+    ///
+    /// The counters cover the header blocks sent and received since the
+    /// connection was established, and the current state of both dynamic
+    /// tables.
+    pub fn hpack_stats(&self) -> HpackStats {
+        self.connection.hpack_stats()
+    }
 }
 
 #[cfg(feature = "stream")]
@@ -539,7 +675,18 @@ impl Builder {
             reset_stream_duration: Duration::from_secs(proto::DEFAULT_RESET_STREAM_SECS),
             reset_stream_max: proto::DEFAULT_RESET_STREAM_MAX,
             settings: Settings::default(),
+            indexing_policy: None,
+            huffman: Huffman::default(),
+            padding: None,
+            flush: FlushStrategy::Immediate,
+            frame_observer: None,
             initial_target_connection_window_size: None,
+            timer: None,
+            handshake_timeout: None,
+            idle_timeout: None,
+            settings_ack_timeout: None,
+            keep_alive_interval: None,
+            keep_alive_timeout: Duration::from_secs(proto::DEFAULT_KEEP_ALIVE_TIMEOUT_SECS),
         }
     }
 
@@ -683,6 +830,246 @@ impl Builder {
         self
     }
 
+    /// Sets the maximum size of the HPACK dynamic table used to decode
+    /// received headers.
+    ///
+    /// This setting informs the peer of the maximum size, in octets, of the
+    /// header compression table it may use when encoding headers sent to us.
+    /// A smaller table uses less memory per connection, while a larger one
+    /// may compress repetitive headers better.
+    ///
+    /// The new size is enforced once the peer has acknowledged the
+    /// `SETTINGS` frame. The default value is 4,096.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .header_table_size(1024)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    pub fn header_table_size(&mut self, size: u32) -> &mut Self {
+        self.settings.set_header_table_size(Some(size));
+        self
+    }
+
+    /// Sets the policy deciding how sent headers are indexed.
+    ///
+    /// For every header field sent on the connection, the [`IndexingPolicy`]
+    /// chooses whether the HPACK encoder may add it to the dynamic table,
+    /// must send it as a literal, or must send it as a never indexed literal.
+    /// Never indexing credentials protects them against compression based
+    /// attacks, and not indexing high cardinality values keeps them from
+    /// evicting more useful entries.
+    ///
+    /// Values marked as sensitive are always sent as never indexed literals.
+    /// The default is the [`DefaultIndexingPolicy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Indexing, IndexingPolicy};
+    /// #
+    /// #[derive(Debug)]
+    /// struct NeverIndexAll;
+    ///
+    /// impl IndexingPolicy for NeverIndexAll {
+    ///     fn indexing(&self, _: &str, _: &[u8]) -> Indexing {
+    ///         Indexing::NeverIndexed
+    ///     }
+    /// }
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .indexing_policy(NeverIndexAll)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`IndexingPolicy`]: ../trait.IndexingPolicy.html
+    /// [`DefaultIndexingPolicy`]: ../struct.DefaultIndexingPolicy.html
+    pub fn indexing_policy<P: IndexingPolicy>(&mut self, policy: P) -> &mut Self {
+        self.indexing_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sets when the HPACK encoder Huffman encodes sent string literals.
+    ///
+    /// The default is [`Huffman::WhenShorter`]: a string is only Huffman
+    /// encoded if that makes it shorter. [`Huffman::Never`] saves the cost of
+    /// encoding values that rarely compress, such as base64 tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::Huffman;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .huffman(Huffman::Never)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Huffman::WhenShorter`]: ../enum.Huffman.html#variant.WhenShorter
+    /// [`Huffman::Never`]: ../enum.Huffman.html#variant.Never
+    pub fn huffman(&mut self, huffman: Huffman) -> &mut Self {
+        self.huffman = huffman;
+        self
+    }
+
+    /// Sets the padding of sent `DATA` and `HEADERS` frames.
+    ///
+    /// Padding hides the exact size of requests and responses from an
+    /// observer of the encrypted connection. See [`Padding`] for the
+    /// available policies and how padding interacts with flow control.
+    ///
+    /// By default, frames are not padded.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the bucket size of `Padding::Bucket` is zero,
+    /// or if the range of `Padding::Random` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::Padding;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .padding(Padding::Bucket(64))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Padding`]: ../enum.Padding.html
+    pub fn padding(&mut self, padding: Padding) -> &mut Self {
+        padding.assert_valid();
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Sets when the frames buffered by the connection are flushed.
+    ///
+    /// Deferring the flush lets frames sent by many streams share fewer,
+    /// larger writes, at the cost of latency. See [`FlushStrategy`] for the
+    /// available strategies.
+    ///
+    /// By default, frames are flushed as soon as there are no more frames to
+    /// send.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::FlushStrategy;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .flush_strategy(FlushStrategy::Coalesce(4 * 1024))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FlushStrategy`]: ../enum.FlushStrategy.html
+    pub fn flush_strategy(&mut self, strategy: FlushStrategy) -> &mut Self {
+        self.flush = strategy;
+        self
+    }
+
+    /// Sets an observer of the frames sent and received on the connection.
+    ///
+    /// The observer is told about every frame, with its header fields once
+    /// decoded, and about the state transitions of the connection. It is
+    /// meant for debugging and tracing tools. See [`FrameObserver`] for
+    /// details.
+    ///
+    /// By default, there is no observer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{FrameEvent, FrameObserver};
+    /// #
+    /// #[derive(Debug)]
+    /// struct PrintFrames;
+    ///
+    /// impl FrameObserver for PrintFrames {
+    ///     fn frame_received(&self, event: &FrameEvent) {
+    ///         println!("<- {:?}", event);
+    ///     }
+    /// }
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .frame_observer(PrintFrames)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FrameObserver`]: ../trait.FrameObserver.html
+    pub fn frame_observer<O: FrameObserver>(&mut self, observer: O) -> &mut Self {
+        self.frame_observer = Some(Arc::new(observer));
+        self
+    }
+
     /// Sets the maximum number of concurrent streams.
     ///
     /// The maximum concurrent streams setting only controls the maximum number
@@ -824,6 +1211,273 @@ impl Builder {
         self
     }
 
+    /// Sets the [`Timer`] used by the time based options of the connection.
+    ///
+    /// A timer is required to use [`handshake_timeout`], [`idle_timeout`],
+    /// [`settings_ack_timeout`] or [`keep_alive_interval`]. When set, it is
+    /// also used to expire locally reset streams; see
+    /// [`reset_stream_duration`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .idle_timeout(Duration::from_secs(60))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Timer`]: ../trait.Timer.html
+    /// [`handshake_timeout`]: #method.handshake_timeout
+    /// [`idle_timeout`]: #method.idle_timeout
+    /// [`settings_ack_timeout`]: #method.settings_ack_timeout
+    /// [`keep_alive_interval`]: #method.keep_alive_interval
+    /// [`reset_stream_duration`]: #method.reset_stream_duration
+    pub fn timer<T: Timer>(&mut self, timer: T) -> &mut Self {
+        self.timer = Some(Arc::new(timer));
+        self
+    }
+
+    /// Sets the maximum amount of time to complete the HTTP/2.0 handshake.
+    ///
+    /// The handshake is complete once both the client connection preface and
+    /// the client's first `SETTINGS` frame have been received. If the preface
+    /// is not received in time, the [`Handshake`] future fails with an I/O
+    /// error of kind `TimedOut`. If the `SETTINGS` frame is not received in
+    /// time, the connection is closed with `SETTINGS_TIMEOUT`.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .handshake_timeout(Duration::from_secs(10))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Handshake`]: struct.Handshake.html
+    /// [`timer`]: #method.timer
+    pub fn handshake_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.handshake_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the maximum amount of time the connection may go without any
+    /// active streams.
+    ///
+    /// Once the connection has been idle for `dur`, a [graceful shutdown] is
+    /// started. The timer starts over whenever the last active stream
+    /// completes.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .idle_timeout(Duration::from_secs(60))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [graceful shutdown]: struct.Connection.html#method.graceful_shutdown
+    /// [`timer`]: #method.timer
+    pub fn idle_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.idle_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the maximum amount of time to wait for the client to acknowledge
+    /// the `SETTINGS` frame sent during the handshake.
+    ///
+    /// If no acknowledgement is received in time, the connection is closed
+    /// with [`SETTINGS_TIMEOUT`], as allowed by section 6.5.3 of the
+    /// specification.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, there is no timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .settings_ack_timeout(Duration::from_secs(5))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`SETTINGS_TIMEOUT`]: ../struct.Reason.html#associatedconstant.SETTINGS_TIMEOUT
+    /// [`timer`]: #method.timer
+    pub fn settings_ack_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.settings_ack_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the interval at which keep-alive PINGs are sent to the client.
+    ///
+    /// If a keep-alive PING isn't acknowledged within the
+    /// [`keep_alive_timeout`], the connection is closed with an I/O error of
+    /// kind `TimedOut`, failing all active streams.
+    ///
+    /// Requires a [`timer`] to be set, otherwise the handshake fails. By
+    /// default, no keep-alive PINGs are sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .keep_alive_interval(Duration::from_secs(30))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`keep_alive_timeout`]: #method.keep_alive_timeout
+    /// [`timer`]: #method.timer
+    pub fn keep_alive_interval(&mut self, dur: Duration) -> &mut Self {
+        self.keep_alive_interval = Some(dur);
+        self
+    }
+
+    /// Sets the maximum amount of time to wait for a keep-alive PING to be
+    /// acknowledged.
+    ///
+    /// Only has an effect if a [`keep_alive_interval`] is set. The default
+    /// value is 20 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::{Sleep, Timer};
+    /// # use std::time::Duration;
+    /// #
+    /// # struct MyTimer;
+    /// # impl Timer for MyTimer {
+    /// #     fn sleep(&self, _: Duration) -> Sleep { unimplemented!() }
+    /// # }
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .timer(MyTimer)
+    ///     .keep_alive_interval(Duration::from_secs(30))
+    ///     .keep_alive_timeout(Duration::from_secs(10))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`keep_alive_interval`]: #method.keep_alive_interval
+    pub fn keep_alive_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.keep_alive_timeout = dur;
+        self
+    }
+
     /// Creates a new configured HTTP/2.0 server backed by `io`.
     ///
     /// It is expected that `io` already be in an appropriate state to commence
@@ -880,6 +1534,13 @@ impl Builder {
     /// #
     /// # pub fn main() {}
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// The handshake fails with a user error if a timeout or a keep-alive
+    /// interval has been configured without a [`timer`].
+    ///
+    /// [`timer`]: #method.timer
     pub fn handshake<T, B>(&self, io: T) -> Handshake<T, B>
     where
         T: AsyncRead + AsyncWrite + Unpin,
@@ -888,6 +1549,15 @@ impl Builder {
     {
         Connection::handshake2(io, self.clone())
     }
+
+    /// Whether a timeout or keep-alive interval is set without a timer.
+    fn is_missing_timer(&self) -> bool {
+        self.timer.is_none()
+            && (self.handshake_timeout.is_some()
+                || self.idle_timeout.is_some()
+                || self.settings_ack_timeout.is_some()
+                || self.keep_alive_interval.is_some())
+    }
 }
 
 impl Default for Builder {
@@ -1059,6 +1729,21 @@ where
         log::trace!("Handshake::poll(); state={:?};", self.state);
         use crate::server::Handshaking::*;
 
+        if self.builder.is_missing_timer() {
+            return Poll::Ready(Err(UserError::MissingTimer.into()));
+        }
+
+        if let Some(ref mut timeout) = self.timeout {
+            if timeout.as_mut().poll(cx).is_ready() {
+                log::debug!("Handshake::poll(); timed out");
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "handshake timed out",
+                )
+                .into()));
+            }
+        }
+
         self.state = if let Flushing(ref mut flush) = self.state {
             // We're currently flushing a pending SETTINGS frame. Poll the
             // flush future, and, if it's completed, advance our state to wait
@@ -1094,7 +1779,7 @@ where
             unreachable!("Handshake::poll() state was not advanced completely!")
         };
         poll?.map(|codec| {
-            let connection = proto::Connection::new(
+            let mut connection = proto::Connection::new(
                 codec,
                 Config {
                     next_stream_id: 2.into(),
@@ -1103,9 +1788,22 @@ where
                     reset_stream_duration: self.builder.reset_stream_duration,
                     reset_stream_max: self.builder.reset_stream_max,
                     settings: self.builder.settings.clone(),
+                    timer: self.builder.timer.clone(),
+                    idle_timeout: self.builder.idle_timeout,
+                    settings_ack_timeout: self.builder.settings_ack_timeout,
+                    keep_alive_interval: self.builder.keep_alive_interval,
+                    keep_alive_timeout: self.builder.keep_alive_timeout,
+                    padding: self.builder.padding,
+                    flush: self.builder.flush,
+                    observer: self.builder.frame_observer.clone(),
                 },
             );
 
+            // The client's first SETTINGS frame is still part of the handshake.
+            if let Some(timeout) = self.timeout.take() {
+                connection.set_handshake_timeout(timeout);
+            }
+
             log::trace!("Handshake::poll(); connection established!");
             let mut c = Connection { connection };
             if let Some(sz) = self.builder.initial_target_connection_window_size {
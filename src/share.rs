@@ -392,7 +392,30 @@ impl StreamId {
     pub(crate) fn from_internal(id: crate::frame::StreamId) -> Self {
         StreamId(id.into())
     }
+
+    pub(crate) fn into_internal(self) -> crate::frame::StreamId {
+        crate::frame::StreamId::from(self.0)
+    }
+}
+
+impl From<u32> for StreamId {
+    /// Returns the `StreamId` corresponding to `src`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the most significant bit of `src` is set, stream identifiers
+    /// are 31 bits.
+    fn from(src: u32) -> Self {
+        StreamId::from_internal(crate::frame::StreamId::from(src))
+    }
+}
+
+impl From<StreamId> for u32 {
+    fn from(src: StreamId) -> Self {
+        src.0
+    }
 }
+
 // ===== impl RecvStream =====
 
 impl RecvStream {
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A future that completes once a [`Timer`] fires.
+///
+/// [`Timer`]: trait.Timer.html
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Provides timers for the time based features of a connection.
+///
+/// `h2` is not tied to any particular runtime, so it has no timer of its own.
+/// Options such as [`client::Builder::idle_timeout`] and
+/// [`server::Builder::idle_timeout`] require a `Timer` to be set on the
+/// builder, usually one backed by the runtime the connection is running on.
+///
+/// When a `Timer` is set, it is also the connection's clock: locally reset
+/// streams are expired according to [`now`], and the connection is woken up
+/// to reap them instead of waiting to be polled for some other reason. Tests
+/// can provide a manually advanced clock to make these features
+/// deterministic.
+///
+/// # Examples
+///
+/// ```
+/// use h2::{Sleep, Timer};
+/// use std::time::{Duration, Instant};
+///
+/// struct TokioTimer;
+///
+/// impl Timer for TokioTimer {
+///     fn sleep(&self, duration: Duration) -> Sleep {
+///         Box::pin(tokio::timer::Delay::new(Instant::now() + duration))
+///     }
+/// }
+/// ```
+///
+/// [`now`]: #method.now
+/// [`client::Builder::idle_timeout`]: client/struct.Builder.html#method.idle_timeout
+/// [`server::Builder::idle_timeout`]: server/struct.Builder.html#method.idle_timeout
+pub trait Timer: Send + Sync + 'static {
+    /// Returns a future that completes once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Sleep;
+
+    /// Returns the current time.
+    ///
+    /// Defaults to `Instant::now()`. Must not go backwards, and must agree
+    /// with the futures returned by [`sleep`].
+    ///
+    /// [`sleep`]: #tymethod.sleep
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Returns the current time according to `timer`, or the system clock if
+/// there isn't one.
+pub(crate) fn now(timer: Option<&Arc<dyn Timer>>) -> Instant {
+    match timer {
+        Some(timer) => timer.now(),
+        None => Instant::now(),
+    }
+}
+
+impl fmt::Debug for dyn Timer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Timer").finish()
+    }
+}
@@ -0,0 +1,455 @@
+//! Records the bytes exchanged on a connection.
+//!
+//! A [`Recorder`] wraps the I/O handle of a connection, before it is passed
+//! to [`client::handshake`] or [`server::handshake`], and captures every
+//! chunk of bytes read from or written to it with the time it was seen. The
+//! resulting [`Transcript`] can be saved to a file, to be attached to a bug
+//! report or replayed against a connection in a regression test.
+//!
+//! # File format
+//!
+//! Transcripts are saved as text, one chunk per line. The first line is the
+//! `h2-transcript 1` header. Each following line is a direction, `>` for the
+//! bytes written by the recorded connection and `<` for the bytes it read,
+//! the number of seconds since the recording started, and the bytes in hex:
+//!
+//! ```text
+//! h2-transcript 1
+//! > 0.000012 505249202a20485454502f322e300d0a0d0a534d0d0a0d0a
+//! < 0.001208 000000040000000000
+//! ```
+//!
+//! Empty lines and lines starting with `#` are ignored, so transcripts can
+//! be annotated by hand.
+//!
+//! # Examples
+//!
+//! ```
+//! # use tokio_io::*;
+//! # use h2::client::*;
+//! # use h2::transcript::Recorder;
+//! #
+//! # async fn doc<T: AsyncRead + AsyncWrite + Unpin>(my_io: T)
+//! # -> Result<(), Box<dyn std::error::Error>>
+//! # {
+//! let recorder = Recorder::new();
+//!
+//! let (client, connection) = handshake(recorder.wrap(my_io)).await?;
+//!
+//! // Use the connection...
+//! # drop(client);
+//! connection.await?;
+//!
+//! // Save what was exchanged
+//! let file = std::fs::File::create("connection.h2t")?;
+//! recorder.transcript().write_to(file)?;
+//! # Ok(())
+//! # }
+//! #
+//! # pub fn main() {}
+//! ```
+//!
+//! [`Recorder`]: struct.Recorder.html
+//! [`Transcript`]: struct.Transcript.html
+//! [`client::handshake`]: ../client/fn.handshake.html
+//! [`server::handshake`]: ../server/fn.handshake.html
+
+use bytes::{Buf, Bytes, BytesMut};
+use futures::ready;
+use iovec::IoVec;
+use std::io::{self, BufRead, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, str};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// The bytes exchanged on a connection, in the order they were seen.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Transcript {
+    records: Vec<Record>,
+}
+
+/// A chunk of bytes read or written in a single call.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Record {
+    direction: Direction,
+    elapsed: Duration,
+    data: Bytes,
+}
+
+/// Which way the bytes of a [`Record`] went, from the point of view of the
+/// recorded connection.
+///
+/// [`Record`]: struct.Record.html
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Direction {
+    /// Written by the connection.
+    Sent,
+
+    /// Read by the connection.
+    Received,
+}
+
+/// Records the bytes read from and written to I/O handles.
+///
+/// All the handles wrapped by a recorder, and its clones, add to the same
+/// transcript.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    inner: Arc<Mutex<Transcript>>,
+    start: Instant,
+}
+
+/// An I/O handle whose reads and writes are recorded.
+///
+/// Returned by [`Recorder::wrap`]. Each read or write, vectored or not, is
+/// recorded as one chunk.
+///
+/// [`Recorder::wrap`]: struct.Recorder.html#method.wrap
+#[derive(Debug)]
+pub struct Recording<T> {
+    io: T,
+    recorder: Recorder,
+}
+
+/// Keeps a copy of the bytes consumed from a `Buf`.
+struct Consumed<'a, B> {
+    buf: &'a mut B,
+    data: BytesMut,
+}
+
+const HEADER: &str = "h2-transcript 1";
+
+// ===== impl Transcript =====
+
+impl Transcript {
+    /// Creates an empty transcript.
+    pub fn new() -> Transcript {
+        Transcript::default()
+    }
+
+    /// Appends a record to the transcript.
+    pub fn push(&mut self, record: Record) {
+        self.records.push(record);
+    }
+
+    /// Returns the records of the transcript, in the order they were seen.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Returns all the bytes that went in the given direction, concatenated.
+    pub fn bytes(&self, direction: Direction) -> Bytes {
+        let mut dst = BytesMut::new();
+
+        for record in &self.records {
+            if record.direction == direction {
+                dst.extend_from_slice(&record.data);
+            }
+        }
+
+        dst.freeze()
+    }
+
+    /// Writes the transcript in the text format described in the [module
+    /// documentation].
+    ///
+    /// [module documentation]: index.html#file-format
+    pub fn write_to<W: Write>(&self, dst: W) -> io::Result<()> {
+        let mut dst = io::BufWriter::new(dst);
+
+        writeln!(dst, "{}", HEADER)?;
+
+        for record in &self.records {
+            let direction = match record.direction {
+                Direction::Sent => '>',
+                Direction::Received => '<',
+            };
+
+            write!(
+                dst,
+                "{} {}.{:06} ",
+                direction,
+                record.elapsed.as_secs(),
+                record.elapsed.subsec_micros()
+            )?;
+
+            for byte in &record.data {
+                write!(dst, "{:02x}", byte)?;
+            }
+
+            writeln!(dst)?;
+        }
+
+        dst.flush()
+    }
+
+    /// Reads a transcript written by [`write_to`].
+    ///
+    /// Returns an error of kind `InvalidData` if the transcript is malformed.
+    ///
+    /// [`write_to`]: #method.write_to
+    pub fn read_from<R: BufRead>(src: R) -> io::Result<Transcript> {
+        let mut lines = src.lines();
+        let mut transcript = Transcript::new();
+
+        match lines.next().transpose()? {
+            Some(ref line) if line.trim_end() == HEADER => {}
+            _ => return Err(invalid("missing transcript header")),
+        }
+
+        for line in lines {
+            let line = line?;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            transcript.push(parse_record(line)?);
+        }
+
+        Ok(transcript)
+    }
+}
+
+fn parse_record(line: &str) -> io::Result<Record> {
+    let mut parts = line.split_whitespace();
+
+    let direction = match parts.next() {
+        Some(">") => Direction::Sent,
+        Some("<") => Direction::Received,
+        _ => return Err(invalid("invalid direction")),
+    };
+
+    let elapsed = parts
+        .next()
+        .and_then(parse_elapsed)
+        .ok_or_else(|| invalid("invalid timestamp"))?;
+
+    let hex = parts.next().unwrap_or("").as_bytes();
+
+    if parts.next().is_some() || hex.len() % 2 != 0 {
+        return Err(invalid("invalid data"));
+    }
+
+    let mut data = BytesMut::with_capacity(hex.len() / 2);
+
+    for pair in hex.chunks(2) {
+        let byte = str::from_utf8(pair)
+            .ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(|| invalid("invalid data"))?;
+
+        data.extend_from_slice(&[byte]);
+    }
+
+    Ok(Record::new(direction, elapsed, data.freeze()))
+}
+
+fn parse_elapsed(src: &str) -> Option<Duration> {
+    let mut parts = src.splitn(2, '.');
+    let secs = parts.next()?.parse().ok()?;
+    let micros = parts.next()?;
+
+    if micros.len() != 6 {
+        return None;
+    }
+
+    Some(Duration::new(secs, micros.parse::<u32>().ok()? * 1_000))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// ===== impl Record =====
+
+impl Record {
+    /// Creates a record of `data` going in `direction`, `elapsed` after the
+    /// recording started.
+    pub fn new(direction: Direction, elapsed: Duration, data: Bytes) -> Record {
+        Record {
+            direction,
+            elapsed,
+            data,
+        }
+    }
+
+    /// Returns which way the bytes went.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the time between the start of the recording and this record.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the bytes read or written.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+}
+
+// ===== impl Recorder =====
+
+impl Recorder {
+    /// Creates a recorder with an empty transcript.
+    ///
+    /// The timestamps of the records are relative to this call.
+    pub fn new() -> Recorder {
+        Recorder {
+            inner: Arc::new(Mutex::new(Transcript::new())),
+            start: Instant::now(),
+        }
+    }
+
+    /// Wraps `io` so that the bytes read from or written to it are recorded.
+    pub fn wrap<T>(&self, io: T) -> Recording<T> {
+        Recording {
+            io,
+            recorder: self.clone(),
+        }
+    }
+
+    /// Returns a copy of the transcript recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        self.inner.lock().unwrap().clone()
+    }
+
+    fn record(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        // Transcripts are saved with microsecond precision
+        let elapsed = self.start.elapsed();
+        let elapsed = Duration::new(elapsed.as_secs(), elapsed.subsec_micros() * 1_000);
+
+        let record = Record::new(direction, elapsed, Bytes::from(data));
+        self.inner.lock().unwrap().push(record);
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+// ===== impl Recording =====
+
+impl<T> Recording<T> {
+    /// Returns a reference to the wrapped I/O handle.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the wrapped I/O handle.
+    ///
+    /// Bytes read from or written to it directly are not recorded.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns the wrapped I/O handle.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Recording<T> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        self.io.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = ready!(Pin::new(&mut self.io).poll_read(cx, buf))?;
+        self.recorder.record(Direction::Received, &buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Recording<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = ready!(Pin::new(&mut self.io).poll_write(cx, buf))?;
+        self.recorder.record(Direction::Sent, &buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_buf<B: Buf>(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut B,
+    ) -> Poll<io::Result<usize>> {
+        let mut consumed = Consumed {
+            buf,
+            data: BytesMut::new(),
+        };
+
+        let n = ready!(Pin::new(&mut self.io).poll_write_buf(cx, &mut consumed))?;
+        self.recorder.record(Direction::Sent, &consumed.data);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+// ===== impl Consumed =====
+
+impl<'a, B: Buf> Buf for Consumed<'a, B> {
+    fn remaining(&self) -> usize {
+        self.buf.remaining()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.buf.bytes()
+    }
+
+    fn bytes_vec<'b>(&'b self, dst: &mut [&'b IoVec]) -> usize {
+        self.buf.bytes_vec(dst)
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        // The consumed bytes may span several chunks of `buf`
+        while cnt > 0 {
+            let n = cmp::min(cnt, self.buf.bytes().len());
+
+            if n == 0 {
+                // Past the end, let `buf` report it
+                self.buf.advance(cnt);
+                return;
+            }
+
+            self.data.extend_from_slice(&self.buf.bytes()[..n]);
+            self.buf.advance(n);
+            cnt -= n;
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Direction::Sent => fmt.write_str("sent"),
+            Direction::Received => fmt.write_str("received"),
+        }
+    }
+}
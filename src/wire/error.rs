@@ -0,0 +1,257 @@
+use crate::codec;
+use crate::frame::{self, Reason};
+use crate::share::StreamId;
+
+use std::{error, fmt, io};
+
+/// An error returned by [`Codec`] when receiving frames.
+///
+/// [`Codec`]: struct.Codec.html
+#[derive(Debug)]
+pub enum RecvError {
+    /// The peer violated the protocol in a way that affects the whole
+    /// connection.
+    ///
+    /// The connection should be closed with a `GOAWAY` frame carrying
+    /// `reason`. The codec is not in a usable state anymore.
+    Connection {
+        /// The error code to report to the peer.
+        reason: Reason,
+
+        /// What was wrong with the received frame.
+        cause: FrameError,
+    },
+
+    /// The peer violated the protocol in a way that only affects one stream.
+    ///
+    /// The stream should be reset with a `RST_STREAM` frame carrying
+    /// `reason`. The codec can still be used.
+    Stream {
+        /// The stream the frame was received on.
+        id: StreamId,
+
+        /// The error code to report to the peer.
+        reason: Reason,
+
+        /// What was wrong with the received frame.
+        cause: FrameError,
+    },
+
+    /// Reading from the I/O failed.
+    Io(io::Error),
+}
+
+/// What was wrong with a received frame.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum FrameError {
+    /// The frame is larger than the max frame size.
+    TooLarge,
+
+    /// The payload length does not match the frame type.
+    InvalidPayloadLength,
+
+    /// The padding is longer than the payload.
+    TooMuchPadding,
+
+    /// The frame was received on a stream it is not allowed on, such as a
+    /// `SETTINGS` frame on a stream other than zero.
+    InvalidStreamId,
+
+    /// A stream was made dependent on itself.
+    InvalidDependencyId,
+
+    /// A `SETTINGS` frame carries a value out of the allowed range.
+    InvalidSettingValue,
+
+    /// A `SETTINGS` frame with the `ACK` flag has a payload.
+    InvalidPayloadAckSettings,
+
+    /// A `WINDOW_UPDATE` frame has an increment of zero.
+    InvalidWindowUpdateValue,
+
+    /// The header block is not a valid request, response or trailers, such
+    /// as one with an unknown or repeated pseudo header field.
+    MalformedHeaders,
+
+    /// The header block could not be decoded by HPACK.
+    Hpack,
+
+    /// The header block is larger than the max header list size and the
+    /// codec gave up skipping it.
+    HeaderBlockTooLarge,
+
+    /// A frame other than `CONTINUATION` interrupted a header block.
+    ExpectedContinuation,
+
+    /// A `CONTINUATION` frame does not follow a header block.
+    UnexpectedContinuation,
+
+    /// A `CONTINUATION` frame is on a different stream than its header block.
+    ContinuationStreamMismatch,
+}
+
+/// An error returned by [`Codec`] when sending frames.
+///
+/// [`Codec`]: struct.Codec.html
+#[derive(Debug)]
+pub enum SendError {
+    /// The payload of a `DATA` frame, with its padding, is larger than the
+    /// max frame size of the peer.
+    PayloadTooBig,
+
+    /// Writing to the I/O failed.
+    Io(io::Error),
+}
+
+// ===== impl RecvError =====
+
+impl RecvError {
+    /// Returns the error code to report to the peer, if the error was caused
+    /// by the peer.
+    pub fn reason(&self) -> Option<Reason> {
+        match *self {
+            RecvError::Connection { reason, .. } => Some(reason),
+            RecvError::Stream { reason, .. } => Some(reason),
+            RecvError::Io(_) => None,
+        }
+    }
+
+    /// Returns what was wrong with the received frame, if the error was
+    /// caused by the peer.
+    pub fn cause(&self) -> Option<FrameError> {
+        match *self {
+            RecvError::Connection { cause, .. } => Some(cause),
+            RecvError::Stream { cause, .. } => Some(cause),
+            RecvError::Io(_) => None,
+        }
+    }
+
+    pub(crate) fn connection(reason: Reason, cause: FrameError) -> Self {
+        RecvError::Connection { reason, cause }
+    }
+
+    pub(crate) fn stream(id: frame::StreamId, reason: Reason, cause: FrameError) -> Self {
+        RecvError::Stream {
+            id: StreamId::from_internal(id),
+            reason,
+            cause,
+        }
+    }
+}
+
+impl From<io::Error> for RecvError {
+    fn from(src: io::Error) -> Self {
+        RecvError::Io(src)
+    }
+}
+
+impl From<RecvError> for codec::RecvError {
+    fn from(src: RecvError) -> Self {
+        match src {
+            RecvError::Connection { reason, .. } => codec::RecvError::Connection(reason),
+            RecvError::Stream { id, reason, .. } => codec::RecvError::Stream {
+                id: id.into_internal(),
+                reason,
+            },
+            RecvError::Io(e) => codec::RecvError::Io(e),
+        }
+    }
+}
+
+impl error::Error for RecvError {}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvError::Connection { reason, cause } => {
+                write!(fmt, "connection error {:?}: {}", reason, cause)
+            }
+            RecvError::Stream {
+                ref id,
+                reason,
+                cause,
+            } => write!(
+                fmt,
+                "stream error {:?} on stream {}: {}",
+                reason,
+                u32::from(id.clone()),
+                cause
+            ),
+            RecvError::Io(ref e) => fmt::Display::fmt(e, fmt),
+        }
+    }
+}
+
+// ===== impl FrameError =====
+
+impl From<frame::Error> for FrameError {
+    fn from(src: frame::Error) -> Self {
+        match src {
+            frame::Error::BadFrameSize | frame::Error::InvalidPayloadLength => {
+                FrameError::InvalidPayloadLength
+            }
+            frame::Error::TooMuchPadding => FrameError::TooMuchPadding,
+            frame::Error::InvalidSettingValue | frame::Error::InvalidInitialWindowSize => {
+                FrameError::InvalidSettingValue
+            }
+            frame::Error::InvalidWindowUpdateValue => FrameError::InvalidWindowUpdateValue,
+            frame::Error::InvalidPayloadAckSettings => FrameError::InvalidPayloadAckSettings,
+            frame::Error::InvalidStreamId => FrameError::InvalidStreamId,
+            frame::Error::MalformedMessage => FrameError::MalformedHeaders,
+            frame::Error::InvalidDependencyId => FrameError::InvalidDependencyId,
+            frame::Error::Hpack(_) => FrameError::Hpack,
+        }
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            FrameError::TooLarge => "frame larger than the max frame size",
+            FrameError::InvalidPayloadLength => "invalid payload length",
+            FrameError::TooMuchPadding => "padding longer than the payload",
+            FrameError::InvalidStreamId => "invalid stream ID",
+            FrameError::InvalidDependencyId => "stream depends on itself",
+            FrameError::InvalidSettingValue => "invalid setting value",
+            FrameError::InvalidPayloadAckSettings => "payload on a SETTINGS ACK",
+            FrameError::InvalidWindowUpdateValue => "window update of zero",
+            FrameError::MalformedHeaders => "malformed header block",
+            FrameError::Hpack => "HPACK decoding failed",
+            FrameError::HeaderBlockTooLarge => "header block larger than the max list size",
+            FrameError::ExpectedContinuation => "expected a CONTINUATION frame",
+            FrameError::UnexpectedContinuation => "unexpected CONTINUATION frame",
+            FrameError::ContinuationStreamMismatch => "CONTINUATION frame on another stream",
+        };
+
+        fmt.write_str(description)
+    }
+}
+
+// ===== impl SendError =====
+
+impl From<io::Error> for SendError {
+    fn from(src: io::Error) -> Self {
+        SendError::Io(src)
+    }
+}
+
+impl From<codec::UserError> for SendError {
+    fn from(src: codec::UserError) -> Self {
+        match src {
+            codec::UserError::PayloadTooBig => SendError::PayloadTooBig,
+            // The codec itself only checks the size of DATA frames
+            e => unreachable!("unexpected codec error; err={:?}", e),
+        }
+    }
+}
+
+impl error::Error for SendError {}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::PayloadTooBig => fmt.write_str("payload too big"),
+            SendError::Io(ref e) => fmt::Display::fmt(e, fmt),
+        }
+    }
+}
@@ -0,0 +1,835 @@
+use crate::frame::{self, Reason};
+use crate::share::StreamId;
+
+use bytes::Bytes;
+use http::{HeaderMap, Method, StatusCode, Uri};
+use string::String;
+
+use std::fmt;
+use std::io::Cursor;
+
+/// A frame sent or received by a [`Codec`].
+///
+/// `CONTINUATION` frames are never received: a header block split over
+/// several frames is received as a single `HEADERS` or `PUSH_PROMISE` frame.
+/// Header blocks are split as needed when sent, and [`Continuation`] frames
+/// can also be sent explicitly.
+///
+/// [`Codec`]: struct.Codec.html
+/// [`Continuation`]: struct.Continuation.html
+#[derive(Debug, Eq, PartialEq)]
+pub enum Frame {
+    /// A `DATA` frame.
+    Data(Data),
+    /// A `HEADERS` frame.
+    Headers(Headers),
+    /// A `PRIORITY` frame.
+    Priority(Priority),
+    /// A `PUSH_PROMISE` frame.
+    PushPromise(PushPromise),
+    /// A `SETTINGS` frame.
+    Settings(Settings),
+    /// A `PING` frame.
+    Ping(Ping),
+    /// A `GOAWAY` frame.
+    GoAway(GoAway),
+    /// A `WINDOW_UPDATE` frame.
+    WindowUpdate(WindowUpdate),
+    /// A `RST_STREAM` frame.
+    Reset(Reset),
+    /// A `CONTINUATION` frame, only sent.
+    Continuation(Continuation),
+    /// A frame of a type not defined by RFC 7540.
+    Unknown(Unknown),
+}
+
+/// A `DATA` frame, carrying part of the body of a message.
+#[derive(Eq, PartialEq)]
+pub struct Data {
+    inner: frame::Data,
+}
+
+/// A `HEADERS` frame, carrying the head or the trailers of a message.
+#[derive(Eq, PartialEq)]
+pub struct Headers {
+    inner: frame::Headers,
+}
+
+/// The pseudo header fields of a header block.
+///
+/// Requests carry `:method`, `:scheme`, `:authority` and `:path`, responses
+/// carry `:status`, and trailers carry none. The codec sends whichever fields
+/// are set, so it can also be used to send malformed header blocks.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Pseudo {
+    inner: frame::Pseudo,
+}
+
+/// A `PRIORITY` frame.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Priority {
+    inner: frame::Priority,
+}
+
+/// A `PUSH_PROMISE` frame, carrying the head of a request pushed by a
+/// server.
+#[derive(Eq, PartialEq)]
+pub struct PushPromise {
+    inner: frame::PushPromise,
+}
+
+/// A `SETTINGS` frame.
+///
+/// Settings that are not set are not sent, the peer keeps their current
+/// value.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Settings {
+    inner: frame::Settings,
+}
+
+/// A `PING` frame.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Ping {
+    inner: frame::Ping,
+}
+
+/// A `GOAWAY` frame.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GoAway {
+    inner: frame::GoAway,
+}
+
+/// A `WINDOW_UPDATE` frame.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct WindowUpdate {
+    inner: frame::WindowUpdate,
+}
+
+/// A `RST_STREAM` frame.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Reset {
+    inner: frame::Reset,
+}
+
+/// A `CONTINUATION` frame carrying a header block fragment.
+///
+/// The fragment is sent as is, it is not HPACK encoded by the codec. A
+/// fragment that changes the dynamic table leaves the encoder of the codec
+/// out of sync with the decoder of the peer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Continuation {
+    inner: frame::Fragment,
+}
+
+/// A frame of a type not defined by RFC 7540, such as an extension frame.
+///
+/// Endpoints ignore frames of types they do not know.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unknown {
+    inner: frame::Unknown,
+}
+
+// ===== impl Frame =====
+
+impl Frame {
+    /// Returns the stream the frame is sent on, zero for the frames that
+    /// apply to the whole connection.
+    pub fn stream_id(&self) -> StreamId {
+        let id = match *self {
+            Frame::Data(ref frame) => frame.inner.stream_id(),
+            Frame::Headers(ref frame) => frame.inner.stream_id(),
+            Frame::Priority(ref frame) => frame.inner.stream_id(),
+            Frame::PushPromise(ref frame) => frame.inner.stream_id(),
+            Frame::WindowUpdate(ref frame) => frame.inner.stream_id(),
+            Frame::Reset(ref frame) => frame.inner.stream_id(),
+            Frame::Continuation(ref frame) => frame.inner.stream_id(),
+            Frame::Unknown(ref frame) => frame.inner.stream_id(),
+            Frame::Settings(_) | Frame::Ping(_) | Frame::GoAway(_) => frame::StreamId::zero(),
+        };
+
+        StreamId::from_internal(id)
+    }
+
+    pub(crate) fn from_internal(src: frame::Frame) -> Frame {
+        match src {
+            frame::Frame::Data(inner) => Frame::Data(Data { inner }),
+            frame::Frame::Headers(inner) => Frame::Headers(Headers { inner }),
+            frame::Frame::Priority(inner) => Frame::Priority(Priority { inner }),
+            frame::Frame::PushPromise(inner) => Frame::PushPromise(PushPromise { inner }),
+            frame::Frame::Settings(inner) => Frame::Settings(Settings { inner }),
+            frame::Frame::Ping(inner) => Frame::Ping(Ping { inner }),
+            frame::Frame::GoAway(inner) => Frame::GoAway(GoAway { inner }),
+            frame::Frame::WindowUpdate(inner) => Frame::WindowUpdate(WindowUpdate { inner }),
+            frame::Frame::Reset(inner) => Frame::Reset(Reset { inner }),
+            frame::Frame::Continuation(inner) => Frame::Continuation(Continuation { inner }),
+            frame::Frame::Unknown(inner) => Frame::Unknown(Unknown { inner }),
+        }
+    }
+
+    pub(crate) fn into_internal(self) -> frame::Frame<Cursor<Bytes>> {
+        match self {
+            Frame::Data(frame) => frame.inner.map(Cursor::new).into(),
+            Frame::Headers(frame) => frame.inner.into(),
+            Frame::Priority(frame) => frame.inner.into(),
+            Frame::PushPromise(frame) => frame.inner.into(),
+            Frame::Settings(frame) => frame.inner.into(),
+            Frame::Ping(frame) => frame.inner.into(),
+            Frame::GoAway(frame) => frame.inner.into(),
+            Frame::WindowUpdate(frame) => frame.inner.into(),
+            Frame::Reset(frame) => frame.inner.into(),
+            Frame::Continuation(frame) => frame.inner.into(),
+            Frame::Unknown(frame) => frame.inner.into(),
+        }
+    }
+}
+
+macro_rules! from_frame {
+    ($($frame:ident),*) => {
+        $(
+            impl From<$frame> for Frame {
+                fn from(src: $frame) -> Frame {
+                    Frame::$frame(src)
+                }
+            }
+        )*
+    };
+}
+
+from_frame!(
+    Data,
+    Headers,
+    Priority,
+    PushPromise,
+    Settings,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Reset,
+    Continuation,
+    Unknown
+);
+
+// ===== impl Data =====
+
+impl Data {
+    /// Creates a `DATA` frame carrying `payload` on the given stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stream_id` is zero.
+    pub fn new(stream_id: StreamId, payload: Bytes) -> Data {
+        Data {
+            inner: frame::Data::new(stream_id.into_internal(), payload),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the payload of the frame, without padding.
+    pub fn payload(&self) -> &Bytes {
+        self.inner.payload()
+    }
+
+    /// Returns the payload of the frame, without padding.
+    pub fn into_payload(self) -> Bytes {
+        self.inner.into_payload()
+    }
+
+    /// Returns whether the `END_STREAM` flag is set.
+    pub fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    /// Sets or clears the `END_STREAM` flag.
+    pub fn set_end_stream(&mut self, val: bool) {
+        self.inner.set_end_stream(val)
+    }
+
+    /// Sets the number of padding octets to send after the payload.
+    ///
+    /// `None`, the default, sends the frame without the `PADDED` flag. The
+    /// padding of received frames is stripped.
+    pub fn set_pad_len(&mut self, pad_len: Option<u8>) {
+        self.inner.set_pad_len(pad_len)
+    }
+}
+
+impl fmt::Debug for Data {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, fmt)
+    }
+}
+
+// ===== impl Headers =====
+
+impl Headers {
+    /// Creates a `HEADERS` frame carrying a request or response head.
+    pub fn new(stream_id: StreamId, pseudo: Pseudo, fields: HeaderMap) -> Headers {
+        Headers {
+            inner: frame::Headers::new(stream_id.into_internal(), pseudo.inner, fields),
+        }
+    }
+
+    /// Creates a `HEADERS` frame carrying trailers, with the `END_STREAM`
+    /// flag set.
+    pub fn trailers(stream_id: StreamId, fields: HeaderMap) -> Headers {
+        Headers {
+            inner: frame::Headers::trailers(stream_id.into_internal(), fields),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the pseudo header fields.
+    pub fn pseudo(&self) -> &Pseudo {
+        Pseudo::from_ref(self.inner.parts().0)
+    }
+
+    /// Returns the regular header fields.
+    pub fn fields(&self) -> &HeaderMap {
+        self.inner.fields()
+    }
+
+    /// Returns the pseudo and regular header fields.
+    pub fn into_parts(self) -> (Pseudo, HeaderMap) {
+        let (pseudo, fields) = self.inner.into_parts();
+        (Pseudo { inner: pseudo }, fields)
+    }
+
+    /// Returns whether the `END_STREAM` flag is set.
+    pub fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    /// Sets the `END_STREAM` flag.
+    pub fn set_end_stream(&mut self) {
+        self.inner.set_end_stream()
+    }
+
+    /// Returns whether the `END_HEADERS` flag is set.
+    ///
+    /// It is set on received frames, which hold the whole header block.
+    pub fn is_end_headers(&self) -> bool {
+        self.inner.is_end_headers()
+    }
+
+    /// Sets or clears the `END_HEADERS` flag, which is set by default.
+    ///
+    /// Without it, the header block goes on in the [`Continuation`] frames
+    /// sent after this one.
+    ///
+    /// [`Continuation`]: struct.Continuation.html
+    pub fn set_end_headers(&mut self, val: bool) {
+        if val {
+            self.inner.set_end_headers()
+        } else {
+            self.inner.unset_end_headers()
+        }
+    }
+
+    /// Returns whether the header block was larger than the max header list
+    /// size.
+    ///
+    /// The fields of such a block are dropped, but the block is still
+    /// decoded to keep the HPACK state in sync with the peer.
+    pub fn is_over_size(&self) -> bool {
+        self.inner.is_over_size()
+    }
+}
+
+impl fmt::Debug for Headers {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, fmt)
+    }
+}
+
+// ===== impl Pseudo =====
+
+impl Pseudo {
+    /// Returns pseudo header fields with none of the fields set.
+    pub fn new() -> Pseudo {
+        Pseudo::default()
+    }
+
+    /// Returns the pseudo header fields of a request for `uri`.
+    ///
+    /// `:path` defaults to `/`, except for `OPTIONS` requests.
+    pub fn request(method: Method, uri: Uri) -> Pseudo {
+        Pseudo {
+            inner: frame::Pseudo::request(method, uri),
+        }
+    }
+
+    /// Returns the pseudo header fields of a response.
+    pub fn response(status: StatusCode) -> Pseudo {
+        Pseudo {
+            inner: frame::Pseudo::response(status),
+        }
+    }
+
+    /// Returns the `:method` field.
+    pub fn method(&self) -> Option<&Method> {
+        self.inner.method.as_ref()
+    }
+
+    /// Sets the `:method` field.
+    pub fn set_method(&mut self, method: Method) {
+        self.inner.method = Some(method);
+    }
+
+    /// Returns the `:scheme` field.
+    pub fn scheme(&self) -> Option<&str> {
+        self.inner.scheme.as_ref().map(|v| &v[..])
+    }
+
+    /// Sets the `:scheme` field.
+    pub fn set_scheme(&mut self, scheme: &str) {
+        self.inner.scheme = Some(to_string(scheme));
+    }
+
+    /// Returns the `:authority` field.
+    pub fn authority(&self) -> Option<&str> {
+        self.inner.authority.as_ref().map(|v| &v[..])
+    }
+
+    /// Sets the `:authority` field.
+    pub fn set_authority(&mut self, authority: &str) {
+        self.inner.authority = Some(to_string(authority));
+    }
+
+    /// Returns the `:path` field.
+    pub fn path(&self) -> Option<&str> {
+        self.inner.path.as_ref().map(|v| &v[..])
+    }
+
+    /// Sets the `:path` field.
+    pub fn set_path(&mut self, path: &str) {
+        self.inner.path = Some(to_string(path));
+    }
+
+    /// Returns the `:status` field.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.inner.status
+    }
+
+    /// Sets the `:status` field.
+    pub fn set_status(&mut self, status: StatusCode) {
+        self.inner.status = Some(status);
+    }
+
+    fn from_ref(inner: &frame::Pseudo) -> &Pseudo {
+        // `Pseudo` is a transparent wrapper around `frame::Pseudo`
+        unsafe { &*(inner as *const frame::Pseudo as *const Pseudo) }
+    }
+}
+
+fn to_string(src: &str) -> String<Bytes> {
+    // `src` is a `str`, so the bytes are valid UTF-8
+    unsafe { String::from_utf8_unchecked(Bytes::from(src)) }
+}
+
+// ===== impl Priority =====
+
+impl Priority {
+    /// Creates a `PRIORITY` frame making `stream_id` depend on `dependency`.
+    ///
+    /// `weight` is one less than the weight of the stream, as it is sent on
+    /// the wire: 15 is the default weight of 16.
+    pub fn new(stream_id: StreamId, dependency: StreamId, weight: u8, exclusive: bool) -> Priority {
+        let dependency =
+            frame::StreamDependency::new(dependency.into_internal(), weight, exclusive);
+
+        Priority {
+            inner: frame::Priority::new(stream_id.into_internal(), dependency),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the stream that the stream depends on.
+    pub fn dependency_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.dependency().dependency_id())
+    }
+
+    /// Returns the weight of the stream, minus one.
+    pub fn weight(&self) -> u8 {
+        self.inner.dependency().weight()
+    }
+
+    /// Returns whether the dependency is exclusive.
+    pub fn is_exclusive(&self) -> bool {
+        self.inner.dependency().is_exclusive()
+    }
+}
+
+// ===== impl PushPromise =====
+
+impl PushPromise {
+    /// Creates a `PUSH_PROMISE` frame reserving `promised_id` for a request
+    /// associated with `stream_id`.
+    pub fn new(
+        stream_id: StreamId,
+        promised_id: StreamId,
+        pseudo: Pseudo,
+        fields: HeaderMap,
+    ) -> PushPromise {
+        PushPromise {
+            inner: frame::PushPromise::new(
+                stream_id.into_internal(),
+                promised_id.into_internal(),
+                pseudo.inner,
+                fields,
+            ),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the stream reserved for the pushed request.
+    pub fn promised_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.promised_id())
+    }
+
+    /// Returns the pseudo header fields of the pushed request.
+    pub fn pseudo(&self) -> &Pseudo {
+        Pseudo::from_ref(self.inner.parts().0)
+    }
+
+    /// Returns the regular header fields of the pushed request.
+    pub fn fields(&self) -> &HeaderMap {
+        self.inner.parts().1
+    }
+
+    /// Returns the pseudo and regular header fields of the pushed request.
+    pub fn into_parts(self) -> (Pseudo, HeaderMap) {
+        let (pseudo, fields) = self.inner.into_parts();
+        (Pseudo { inner: pseudo }, fields)
+    }
+
+    /// Returns whether the `END_HEADERS` flag is set.
+    pub fn is_end_headers(&self) -> bool {
+        self.inner.is_end_headers()
+    }
+
+    /// Sets or clears the `END_HEADERS` flag, see
+    /// [`Headers::set_end_headers`].
+    ///
+    /// [`Headers::set_end_headers`]: struct.Headers.html#method.set_end_headers
+    pub fn set_end_headers(&mut self, val: bool) {
+        if val {
+            self.inner.set_end_headers()
+        } else {
+            self.inner.unset_end_headers()
+        }
+    }
+
+    /// Returns whether the header block was larger than the max header list
+    /// size, see [`Headers::is_over_size`].
+    ///
+    /// [`Headers::is_over_size`]: struct.Headers.html#method.is_over_size
+    pub fn is_over_size(&self) -> bool {
+        self.inner.is_over_size()
+    }
+}
+
+impl fmt::Debug for PushPromise {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, fmt)
+    }
+}
+
+// ===== impl Settings =====
+
+impl Settings {
+    /// Creates a `SETTINGS` frame with no settings.
+    pub fn new() -> Settings {
+        Settings::default()
+    }
+
+    /// Creates a `SETTINGS` frame with the `ACK` flag set.
+    pub fn ack() -> Settings {
+        Settings {
+            inner: frame::Settings::ack(),
+        }
+    }
+
+    /// Returns whether the `ACK` flag is set.
+    pub fn is_ack(&self) -> bool {
+        self.inner.is_ack()
+    }
+
+    /// Returns `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn header_table_size(&self) -> Option<u32> {
+        self.inner.header_table_size()
+    }
+
+    /// Sets `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn set_header_table_size(&mut self, size: Option<u32>) {
+        self.inner.set_header_table_size(size)
+    }
+
+    /// Returns whether `SETTINGS_ENABLE_PUSH` allows server push, which is
+    /// the default.
+    pub fn is_push_enabled(&self) -> bool {
+        self.inner.is_push_enabled()
+    }
+
+    /// Sets `SETTINGS_ENABLE_PUSH`.
+    pub fn set_enable_push(&mut self, enable: bool) {
+        self.inner.set_enable_push(enable)
+    }
+
+    /// Returns `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    pub fn max_concurrent_streams(&self) -> Option<u32> {
+        self.inner.max_concurrent_streams()
+    }
+
+    /// Sets `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    pub fn set_max_concurrent_streams(&mut self, max: Option<u32>) {
+        self.inner.set_max_concurrent_streams(max)
+    }
+
+    /// Returns `SETTINGS_INITIAL_WINDOW_SIZE`.
+    pub fn initial_window_size(&self) -> Option<u32> {
+        self.inner.initial_window_size()
+    }
+
+    /// Sets `SETTINGS_INITIAL_WINDOW_SIZE`.
+    pub fn set_initial_window_size(&mut self, size: Option<u32>) {
+        self.inner.set_initial_window_size(size)
+    }
+
+    /// Returns `SETTINGS_MAX_FRAME_SIZE`.
+    pub fn max_frame_size(&self) -> Option<u32> {
+        self.inner.max_frame_size()
+    }
+
+    /// Sets `SETTINGS_MAX_FRAME_SIZE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not within 16,384 and 16,777,215.
+    pub fn set_max_frame_size(&mut self, size: Option<u32>) {
+        self.inner.set_max_frame_size(size)
+    }
+
+    /// Returns `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub fn max_header_list_size(&self) -> Option<u32> {
+        self.inner.max_header_list_size()
+    }
+
+    /// Sets `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub fn set_max_header_list_size(&mut self, size: Option<u32>) {
+        self.inner.set_max_header_list_size(size)
+    }
+}
+
+impl fmt::Debug for Settings {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, fmt)
+    }
+}
+
+// ===== impl Ping =====
+
+impl Ping {
+    /// Creates a `PING` frame carrying `payload`.
+    pub fn new(payload: [u8; 8]) -> Ping {
+        Ping {
+            inner: frame::Ping::new(payload),
+        }
+    }
+
+    /// Creates a `PING` frame with the `ACK` flag set, answering a `PING`
+    /// that carried `payload`.
+    pub fn pong(payload: [u8; 8]) -> Ping {
+        Ping {
+            inner: frame::Ping::pong(payload),
+        }
+    }
+
+    /// Returns whether the `ACK` flag is set.
+    pub fn is_ack(&self) -> bool {
+        self.inner.is_ack()
+    }
+
+    /// Returns the opaque data of the frame.
+    pub fn payload(&self) -> [u8; 8] {
+        *self.inner.payload()
+    }
+}
+
+// ===== impl GoAway =====
+
+impl GoAway {
+    /// Creates a `GOAWAY` frame.
+    ///
+    /// `last_stream_id` is the last stream initiated by the peer that was or
+    /// may be processed.
+    pub fn new(last_stream_id: StreamId, reason: Reason) -> GoAway {
+        GoAway {
+            inner: frame::GoAway::new(last_stream_id.into_internal(), reason),
+        }
+    }
+
+    /// Creates a `GOAWAY` frame carrying additional debug data.
+    pub fn with_debug_data(last_stream_id: StreamId, reason: Reason, debug_data: Bytes) -> GoAway {
+        GoAway {
+            inner: frame::GoAway::with_debug_data(
+                last_stream_id.into_internal(),
+                reason,
+                debug_data,
+            ),
+        }
+    }
+
+    /// Returns the last stream initiated by the receiver of the frame that
+    /// was or may be processed.
+    pub fn last_stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.last_stream_id())
+    }
+
+    /// Returns the error code of the frame.
+    pub fn reason(&self) -> Reason {
+        self.inner.reason()
+    }
+
+    /// Returns the additional debug data of the frame, empty if there is
+    /// none.
+    pub fn debug_data(&self) -> &Bytes {
+        self.inner.debug_data()
+    }
+}
+
+// ===== impl WindowUpdate =====
+
+impl WindowUpdate {
+    /// Creates a `WINDOW_UPDATE` frame for a stream, or for the connection if
+    /// `stream_id` is zero.
+    pub fn new(stream_id: StreamId, size_increment: u32) -> WindowUpdate {
+        WindowUpdate {
+            inner: frame::WindowUpdate::new(stream_id.into_internal(), size_increment),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the number of octets added to the flow control window.
+    pub fn size_increment(&self) -> u32 {
+        self.inner.size_increment()
+    }
+}
+
+// ===== impl Reset =====
+
+impl Reset {
+    /// Creates a `RST_STREAM` frame.
+    pub fn new(stream_id: StreamId, reason: Reason) -> Reset {
+        Reset {
+            inner: frame::Reset::new(stream_id.into_internal(), reason),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the error code of the frame.
+    pub fn reason(&self) -> Reason {
+        self.inner.reason()
+    }
+}
+
+// ===== impl Continuation =====
+
+impl Continuation {
+    /// Creates a `CONTINUATION` frame carrying `fragment`, with the
+    /// `END_HEADERS` flag set.
+    pub fn new(stream_id: StreamId, fragment: Bytes) -> Continuation {
+        Continuation {
+            inner: frame::Fragment::new(stream_id.into_internal(), fragment),
+        }
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the header block fragment.
+    pub fn fragment(&self) -> &Bytes {
+        self.inner.fragment()
+    }
+
+    /// Returns whether the `END_HEADERS` flag is set.
+    pub fn is_end_headers(&self) -> bool {
+        self.inner.is_end_headers()
+    }
+
+    /// Sets or clears the `END_HEADERS` flag.
+    pub fn set_end_headers(&mut self, val: bool) {
+        if val {
+            self.inner.set_end_headers()
+        } else {
+            self.inner.unset_end_headers()
+        }
+    }
+}
+
+// ===== impl Unknown =====
+
+impl Unknown {
+    /// Creates a frame of type `kind`, carrying `payload`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kind` is a frame type defined by RFC 7540.
+    pub fn new(kind: u8, flags: u8, stream_id: StreamId, payload: Bytes) -> Unknown {
+        assert!(
+            frame::Kind::new(kind) == frame::Kind::Unknown,
+            "frame type 0x{:02x} is defined by RFC 7540",
+            kind
+        );
+
+        Unknown {
+            inner: frame::Unknown::new(kind, flags, stream_id.into_internal(), payload),
+        }
+    }
+
+    /// Returns the type of the frame.
+    pub fn kind(&self) -> u8 {
+        self.inner.kind()
+    }
+
+    /// Returns the flags of the frame.
+    pub fn flags(&self) -> u8 {
+        self.inner.flags()
+    }
+
+    /// Returns the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the payload of the frame.
+    pub fn payload(&self) -> &Bytes {
+        self.inner.payload()
+    }
+}
@@ -0,0 +1,325 @@
+//! Sends and receives individual HTTP/2.0 frames.
+//!
+//! The [`client`] and [`server`] modules implement the whole protocol: they
+//! manage the stream states and flow control, and answer `SETTINGS` and
+//! `PING` frames. This module is for programs that need to control every
+//! frame instead, such as load testers and conformance suites. A [`Codec`]
+//! turns frames into bytes and bytes into frames, and leaves the protocol to
+//! its user: any frame can be sent, in any order, and received frames are
+//! only checked against the frame format.
+//!
+//! # Preface
+//!
+//! The codec neither sends nor expects the connection preface. A client
+//! writes [`PREFACE`] to the I/O before wrapping it in a codec, a server reads
+//! it first.
+//!
+//! # Settings
+//!
+//! A codec starts with the default settings, and `SETTINGS` frames do not
+//! change them. Once a `SETTINGS` frame sent to the peer is acknowledged,
+//! apply it with [`apply_local_settings`]. When a `SETTINGS` frame is
+//! received, apply it with [`apply_remote_settings`] before acknowledging it.
+//! Each setting can also be changed on its own, to test how a peer reacts to
+//! an endpoint that does not honor its settings.
+//!
+//! # Examples
+//!
+//! A client that sends its settings and prints the frames of the server:
+//!
+//! ```
+//! use futures::{SinkExt, StreamExt};
+//! use h2::wire::{self, Codec, Frame, Settings};
+//! use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+//!
+//! async fn dump<T>(mut io: T) -> Result<(), Box<dyn std::error::Error>>
+//! where
+//!     T: AsyncRead + AsyncWrite + Unpin,
+//! {
+//!     io.write_all(wire::PREFACE).await?;
+//!
+//!     let mut codec = Codec::new(io);
+//!     codec.send(Settings::new().into()).await?;
+//!
+//!     while let Some(frame) = codec.next().await {
+//!         match frame? {
+//!             Frame::Settings(ref settings) if !settings.is_ack() => {
+//!                 codec.apply_remote_settings(settings);
+//!                 codec.send(Settings::ack().into()).await?;
+//!             }
+//!             frame => println!("{:?}", frame),
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! #
+//! # pub fn main() {}
+//! ```
+//!
+//! [`client`]: ../client/index.html
+//! [`server`]: ../server/index.html
+//! [`Codec`]: struct.Codec.html
+//! [`PREFACE`]: constant.PREFACE.html
+//! [`apply_local_settings`]: struct.Codec.html#method.apply_local_settings
+//! [`apply_remote_settings`]: struct.Codec.html#method.apply_remote_settings
+
+mod error;
+mod frame;
+
+pub use self::error::{FrameError, RecvError, SendError};
+pub use self::frame::{
+    Continuation, Data, Frame, GoAway, Headers, Ping, Priority, Pseudo, PushPromise, Reset,
+    Settings, Unknown, WindowUpdate,
+};
+
+use crate::codec;
+use crate::hpack::{HpackStats, Huffman, IndexingPolicy};
+use crate::observer::FrameObserver;
+use crate::padding::Padding;
+
+use bytes::Bytes;
+use futures::{ready, Sink, Stream};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use std::io::{self, Cursor};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// The connection preface sent by a client before its first frame.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Sends and receives frames over an I/O handle.
+///
+/// Received frames are yielded by the `Stream` implementation. Frames are
+/// sent with the `Sink` implementation, or with [`poll_ready`], [`buffer`]
+/// and [`poll_flush`].
+///
+/// [`poll_ready`]: #method.poll_ready
+/// [`buffer`]: #method.buffer
+/// [`poll_flush`]: #method.poll_flush
+#[derive(Debug)]
+pub struct Codec<T> {
+    inner: codec::Codec<T, Cursor<Bytes>>,
+}
+
+impl<T> Codec<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns a codec over `io`, with the default settings.
+    pub fn new(io: T) -> Codec<T> {
+        Codec {
+            inner: codec::Codec::new(io),
+        }
+    }
+}
+
+impl<T> Codec<T> {
+    /// Applies the settings sent to the peer, once acknowledged.
+    ///
+    /// This sets the max frame size, the max header list size and the header
+    /// table size used to decode received frames.
+    pub fn apply_local_settings(&mut self, settings: &Settings) {
+        if let Some(val) = settings.max_frame_size() {
+            self.set_max_recv_frame_size(val as usize);
+        }
+
+        if let Some(val) = settings.max_header_list_size() {
+            self.set_max_recv_header_list_size(val as usize);
+        }
+
+        if let Some(val) = settings.header_table_size() {
+            self.set_recv_header_table_size(val as usize);
+        }
+    }
+
+    /// Applies the settings received from the peer.
+    ///
+    /// This sets the max frame size and the header table size used to encode
+    /// sent frames.
+    pub fn apply_remote_settings(&mut self, settings: &Settings) {
+        if let Some(val) = settings.max_frame_size() {
+            self.set_max_send_frame_size(val as usize);
+        }
+
+        if let Some(val) = settings.header_table_size() {
+            self.set_send_header_table_size(val as usize);
+        }
+    }
+
+    /// Returns the largest frame that can be received.
+    pub fn max_recv_frame_size(&self) -> usize {
+        self.inner.max_recv_frame_size()
+    }
+
+    /// Sets the largest frame that can be received.
+    ///
+    /// Larger frames are rejected with a [`FrameError::TooLarge`] error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `val` is not within 16,384 and 16,777,215.
+    ///
+    /// [`FrameError::TooLarge`]: enum.FrameError.html#variant.TooLarge
+    pub fn set_max_recv_frame_size(&mut self, val: usize) {
+        self.inner.set_max_recv_frame_size(val)
+    }
+
+    /// Returns the largest `DATA` frame that can be sent.
+    pub fn max_send_frame_size(&self) -> usize {
+        self.inner.max_send_frame_size()
+    }
+
+    /// Sets the largest `DATA` frame that can be sent.
+    ///
+    /// Header blocks are split into `CONTINUATION` frames of this size.
+    pub fn set_max_send_frame_size(&mut self, val: usize) {
+        self.inner.set_max_send_frame_size(val)
+    }
+
+    /// Sets the largest header list that is decoded.
+    ///
+    /// The fields of larger header blocks are dropped, see
+    /// [`Headers::is_over_size`].
+    ///
+    /// [`Headers::is_over_size`]: struct.Headers.html#method.is_over_size
+    pub fn set_max_recv_header_list_size(&mut self, val: usize) {
+        self.inner.set_max_recv_header_list_size(val)
+    }
+
+    /// Sets the size of the HPACK table used to decode received header
+    /// blocks.
+    ///
+    /// The peer must acknowledge a smaller size with a dynamic table size
+    /// update before it is enforced.
+    pub fn set_recv_header_table_size(&mut self, val: usize) {
+        self.inner.set_recv_header_table_size(val)
+    }
+
+    /// Sets the size of the HPACK table used to encode sent header blocks.
+    ///
+    /// The next header block sent starts with a dynamic table size update.
+    pub fn set_send_header_table_size(&mut self, val: usize) {
+        self.inner.set_send_header_table_size(val)
+    }
+
+    /// Sets the policy deciding how sent header fields are indexed.
+    pub fn set_indexing_policy<P: IndexingPolicy>(&mut self, policy: P) {
+        self.inner.set_indexing_policy(Arc::new(policy))
+    }
+
+    /// Sets when string literals of sent header fields are Huffman encoded.
+    pub fn set_huffman(&mut self, huffman: Huffman) {
+        self.inner.set_huffman(huffman)
+    }
+
+    /// Sets the padding of sent `HEADERS` and `PUSH_PROMISE` frames.
+    pub fn set_padding(&mut self, padding: Padding) {
+        padding.assert_valid();
+        self.inner.set_padding(padding)
+    }
+
+    /// Sets an observer of the frames sent and received, `CONTINUATION`
+    /// frames included.
+    pub fn set_frame_observer<O: FrameObserver>(&mut self, observer: O) {
+        self.inner.set_frame_observer(Arc::new(observer))
+    }
+
+    /// Returns the HPACK statistics of the sent and received header blocks.
+    pub fn hpack_stats(&self) -> HpackStats {
+        self.inner.hpack_stats()
+    }
+
+    /// Returns a reference to the I/O handle.
+    pub fn get_ref(&self) -> &T {
+        self.inner.get_ref()
+    }
+
+    /// Returns a mutable reference to the I/O handle.
+    ///
+    /// Reading from or writing to it directly corrupts the stream of frames.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl<T> Codec<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    /// Returns `Ready` when the codec can buffer a frame.
+    ///
+    /// This may flush the frames buffered so far.
+    pub fn poll_ready(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    /// Encodes a frame to the write buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `poll_ready` did not return `Ready` since the last frame was
+    /// buffered.
+    pub fn buffer(&mut self, frame: Frame) -> Result<(), SendError> {
+        let res = self.inner.buffer(frame.into_internal());
+        self.release_data_frames();
+        Ok(res?)
+    }
+
+    /// Writes the buffered frames to the I/O and flushes it.
+    pub fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        let res = self.inner.flush(cx);
+        self.release_data_frames();
+        res
+    }
+
+    /// Flushes the buffered frames, then shuts down the write half of the I/O.
+    pub fn poll_shutdown(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        let res = self.inner.shutdown(cx);
+        self.release_data_frames();
+        res
+    }
+
+    fn release_data_frames(&mut self) {
+        // The connection reclaims the written `DATA` frames to release their
+        // flow control capacity, there is nothing to reclaim here.
+        while self.inner.take_written_data_frame().is_some() {}
+    }
+}
+
+impl<T> Stream for Codec<T>
+where
+    T: AsyncRead + Unpin,
+{
+    type Item = Result<Frame, RecvError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let frame = ready!(self.inner.poll_frame(cx));
+        Poll::Ready(frame.map(|res| res.map(Frame::from_internal)))
+    }
+}
+
+impl<T> Sink<Frame> for Codec<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    type Error = SendError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        Codec::poll_ready(&mut self, cx).map_err(Into::into)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Frame) -> Result<(), SendError> {
+        self.buffer(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        Codec::poll_flush(&mut self, cx).map_err(Into::into)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.poll_shutdown(cx).map_err(Into::into)
+    }
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use h2_fuzz::frames::Script;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|script: Script| {
+    h2_fuzz::drive(script.run(), 100_000);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use h2_fuzz::io::MockIo;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    h2_fuzz::drive(h2_fuzz::server::run(MockIo::new(data)), 100_000);
+});
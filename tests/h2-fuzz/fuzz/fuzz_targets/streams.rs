@@ -0,0 +1,8 @@
+#![no_main]
+
+use h2_fuzz::streams::Script;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|script: Script| {
+    script.run();
+});
@@ -0,0 +1,23 @@
+//! Writes the HPACK fixture stories out as a seed corpus for the `hpack`
+//! fuzz target.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    let fixtures = args.get(1).expect("usage: hpack-seeds [FIXTURES] [CORPUS]");
+    let corpus = args.get(2).expect("usage: hpack-seeds [FIXTURES] [CORPUS]");
+    let corpus = Path::new(corpus);
+
+    fs::create_dir_all(corpus).unwrap();
+
+    let seeds = h2_fuzz::hpack::seeds(Path::new(fixtures)).unwrap();
+    for (name, input) in &seeds {
+        fs::write(corpus.join(name), input).unwrap();
+    }
+
+    println!("wrote {} seeds to {}", seeds.len(), corpus.display());
+}
@@ -0,0 +1,407 @@
+//! Structured frame sequences.
+//!
+//! Instead of raw bytes, the fuzzer generates a list of [`Frame`]s which are
+//! then written out with valid frame headers and HPACK encoded header
+//! blocks. Stream ids, error codes, settings and header fields are mostly
+//! drawn from small pools, so that the generated frames refer to each other
+//! and reach the stream state machine instead of failing to parse.
+//!
+//! [`Frame`]: enum.Frame.html
+
+use crate::io::Pipe;
+use arbitrary::{Arbitrary, Unstructured};
+use bytes::BytesMut;
+use h2::hpack;
+
+/// The client connection preface.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DATA: u8 = 0;
+const HEADERS: u8 = 1;
+const PRIORITY: u8 = 2;
+const RST_STREAM: u8 = 3;
+const SETTINGS: u8 = 4;
+const PUSH_PROMISE: u8 = 5;
+const PING: u8 = 6;
+const GOAWAY: u8 = 7;
+const WINDOW_UPDATE: u8 = 8;
+const CONTINUATION: u8 = 9;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY_FLAG: u8 = 0x20;
+
+/// Which side of the connection is under test.
+#[derive(Arbitrary, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// The frames sent to a connection, after its preface.
+#[derive(Arbitrary, Debug)]
+pub struct Script {
+    pub role: Role,
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum Frame {
+    Data {
+        stream: StreamId,
+        #[arbitrary(with = payload_len)]
+        len: usize,
+        pad: Option<u8>,
+        end_stream: bool,
+    },
+    Headers {
+        stream: StreamId,
+        fields: Vec<Field>,
+        priority: Option<Priority>,
+        pad: Option<u8>,
+        end_stream: bool,
+        end_headers: bool,
+    },
+    Priority {
+        stream: StreamId,
+        priority: Priority,
+    },
+    RstStream {
+        stream: StreamId,
+        reason: Reason,
+    },
+    Settings {
+        ack: bool,
+        settings: Vec<Setting>,
+    },
+    PushPromise {
+        stream: StreamId,
+        promised: StreamId,
+        fields: Vec<Field>,
+        end_headers: bool,
+    },
+    Ping {
+        ack: bool,
+        payload: [u8; 8],
+    },
+    GoAway {
+        last_stream: StreamId,
+        reason: Reason,
+    },
+    WindowUpdate {
+        stream: StreamId,
+        increment: u32,
+    },
+    Continuation {
+        stream: StreamId,
+        fields: Vec<Field>,
+        end_headers: bool,
+    },
+    Unknown {
+        kind: u8,
+        flags: u8,
+        stream: StreamId,
+        payload: Vec<u8>,
+    },
+}
+
+/// A stream identifier, usually one of the first few streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamId(pub u32);
+
+/// An error code, usually one defined by RFC 7540.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reason(pub u32);
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub struct Priority {
+    pub dependency: StreamId,
+    pub weight: u8,
+    pub exclusive: bool,
+}
+
+/// A setting, usually one defined by RFC 7540.
+#[derive(Debug, Clone, Copy)]
+pub struct Setting {
+    pub id: u16,
+    pub value: u32,
+}
+
+/// A header field, usually a well known one.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+const FIELDS: &[(&str, &str)] = &[
+    (":method", "GET"),
+    (":method", "POST"),
+    (":method", "CONNECT"),
+    (":scheme", "https"),
+    (":authority", "example.com"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":path", ""),
+    (":status", "200"),
+    (":status", "100"),
+    (":status", "404"),
+    (":protocol", "websocket"),
+    ("content-length", "0"),
+    ("content-length", "10"),
+    ("te", "trailers"),
+    ("connection", "close"),
+    ("cookie", "a=b"),
+    ("grpc-status", "0"),
+    ("x-custom", "value"),
+];
+
+/// Writes frames, keeping the HPACK state shared by their header blocks.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    hpack: hpack::Encoder,
+}
+
+// ===== impl Script =====
+
+impl Script {
+    /// Writes out the connection preface expected from the peer of `role`,
+    /// followed by the frames.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut dst = vec![];
+        if self.role == Role::Server {
+            dst.extend_from_slice(PREFACE);
+        }
+        head(SETTINGS, 0, 0, 0, &mut dst);
+
+        let mut encoder = Encoder::default();
+        for frame in &self.frames {
+            encoder.encode(frame, &mut dst);
+        }
+        dst
+    }
+
+    /// Runs the harness for `role` against the frames, until the connection
+    /// ends.
+    pub async fn run(&self) -> Result<(), h2::Error> {
+        let io = Pipe::closed(self.to_bytes());
+        match self.role {
+            Role::Client => crate::client::run(io).await,
+            Role::Server => crate::server::run(io).await,
+        }
+    }
+}
+
+// ===== impl Encoder =====
+
+impl Encoder {
+    pub fn encode(&mut self, frame: &Frame, dst: &mut Vec<u8>) {
+        match *frame {
+            Frame::Data {
+                stream,
+                len,
+                pad,
+                end_stream,
+            } => {
+                let mut flags = 0;
+                if end_stream {
+                    flags |= END_STREAM;
+                }
+                let payload = padded(vec![0; len], pad, &mut flags);
+                write(DATA, flags, stream.0, &payload, dst);
+            }
+            Frame::Headers {
+                stream,
+                ref fields,
+                priority,
+                pad,
+                end_stream,
+                end_headers,
+            } => {
+                let mut flags = 0;
+                if end_stream {
+                    flags |= END_STREAM;
+                }
+                if end_headers {
+                    flags |= END_HEADERS;
+                }
+                let mut payload = vec![];
+                if let Some(priority) = priority {
+                    flags |= PRIORITY_FLAG;
+                    priority.encode(&mut payload);
+                }
+                payload.extend_from_slice(&self.block(fields));
+                let payload = padded(payload, pad, &mut flags);
+                write(HEADERS, flags, stream.0, &payload, dst);
+            }
+            Frame::Priority { stream, priority } => {
+                let mut payload = vec![];
+                priority.encode(&mut payload);
+                write(PRIORITY, 0, stream.0, &payload, dst);
+            }
+            Frame::RstStream { stream, reason } => {
+                write(RST_STREAM, 0, stream.0, &reason.0.to_be_bytes(), dst);
+            }
+            Frame::Settings { ack, ref settings } => {
+                let mut payload = vec![];
+                for setting in settings {
+                    payload.extend_from_slice(&setting.id.to_be_bytes());
+                    payload.extend_from_slice(&setting.value.to_be_bytes());
+                }
+                write(SETTINGS, if ack { ACK } else { 0 }, 0, &payload, dst);
+            }
+            Frame::PushPromise {
+                stream,
+                promised,
+                ref fields,
+                end_headers,
+            } => {
+                let mut payload = promised.0.to_be_bytes().to_vec();
+                payload.extend_from_slice(&self.block(fields));
+                let flags = if end_headers { END_HEADERS } else { 0 };
+                write(PUSH_PROMISE, flags, stream.0, &payload, dst);
+            }
+            Frame::Ping { ack, payload } => {
+                write(PING, if ack { ACK } else { 0 }, 0, &payload, dst);
+            }
+            Frame::GoAway {
+                last_stream,
+                reason,
+            } => {
+                let mut payload = last_stream.0.to_be_bytes().to_vec();
+                payload.extend_from_slice(&reason.0.to_be_bytes());
+                write(GOAWAY, 0, 0, &payload, dst);
+            }
+            Frame::WindowUpdate { stream, increment } => {
+                write(WINDOW_UPDATE, 0, stream.0, &increment.to_be_bytes(), dst);
+            }
+            Frame::Continuation {
+                stream,
+                ref fields,
+                end_headers,
+            } => {
+                let payload = self.block(fields);
+                let flags = if end_headers { END_HEADERS } else { 0 };
+                write(CONTINUATION, flags, stream.0, &payload, dst);
+            }
+            Frame::Unknown {
+                kind,
+                flags,
+                stream,
+                ref payload,
+            } => {
+                write(kind, flags, stream.0, payload, dst);
+            }
+        }
+    }
+
+    fn block(&mut self, fields: &[Field]) -> BytesMut {
+        // Names which aren't valid header names are skipped, the decoder is
+        // exercised with arbitrary blocks by the `hpack` target instead.
+        let fields = fields
+            .iter()
+            .filter_map(|field| hpack::Field::from_bytes(&field.name, &field.value).ok());
+
+        let mut block = BytesMut::new();
+        self.hpack.encode(fields, &mut block);
+        block
+    }
+}
+
+// ===== impl Priority =====
+
+impl Priority {
+    fn encode(&self, dst: &mut Vec<u8>) {
+        let mut dependency = self.dependency.0 & !(1 << 31);
+        if self.exclusive {
+            dependency |= 1 << 31;
+        }
+        dst.extend_from_slice(&dependency.to_be_bytes());
+        dst.push(self.weight);
+    }
+}
+
+// ===== impl Arbitrary =====
+
+impl<'a> Arbitrary<'a> for StreamId {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        match u.int_in_range(0..=12)? {
+            12 => Ok(StreamId(u.arbitrary()?)),
+            id => Ok(StreamId(id)),
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for Reason {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        match u.int_in_range(0..=14)? {
+            14 => Ok(Reason(u.arbitrary()?)),
+            code => Ok(Reason(code)),
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for Setting {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let id = match u.int_in_range(0..=7)? {
+            7 => u.arbitrary()?,
+            id => id,
+        };
+        let value = match u.int_in_range(0..=3)? {
+            0 => 0,
+            1 => u.int_in_range(0..=1 << 16)?,
+            2 => u.int_in_range(16_384..=(1 << 24) - 1)?,
+            _ => u.arbitrary()?,
+        };
+        Ok(Setting { id, value })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Field {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.ratio(1, 8)? {
+            return Ok(Field {
+                name: u.arbitrary()?,
+                value: u.arbitrary()?,
+            });
+        }
+
+        let (name, value) = u.choose(FIELDS)?;
+        Ok(Field {
+            name: name.as_bytes().to_vec(),
+            value: value.as_bytes().to_vec(),
+        })
+    }
+}
+
+fn payload_len(u: &mut Unstructured) -> arbitrary::Result<usize> {
+    // Mostly fits in the default frame size, sometimes doesn't.
+    u.int_in_range(0..=16_400)
+}
+
+fn padded(payload: Vec<u8>, pad: Option<u8>, flags: &mut u8) -> Vec<u8> {
+    match pad {
+        Some(pad) => {
+            *flags |= PADDED;
+            let mut dst = vec![pad];
+            dst.extend_from_slice(&payload);
+            dst.resize(dst.len() + pad as usize, 0);
+            dst
+        }
+        None => payload,
+    }
+}
+
+fn head(kind: u8, flags: u8, stream: u32, len: usize, dst: &mut Vec<u8>) {
+    dst.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+    dst.push(kind);
+    dst.push(flags);
+    dst.extend_from_slice(&stream.to_be_bytes());
+}
+
+fn write(kind: u8, flags: u8, stream: u32, payload: &[u8], dst: &mut Vec<u8>) {
+    head(kind, flags, stream, payload.len(), dst);
+    dst.extend_from_slice(payload);
+}
@@ -0,0 +1,152 @@
+//! HPACK differential fuzzing.
+//!
+//! The input is a sequence of header blocks, as a peer would send them.
+//! Every block accepted by the decoder is encoded again by the encoder, and a
+//! second decoder must get back exactly the same fields. The encoder is also
+//! checked to be in sync with the decoder of its blocks.
+//!
+//! The input starts with a flags byte, its two low bits set to 1 making the
+//! encoder always use Huffman coding, and to 2 never. Each block follows as a two byte maximum table size, with
+//! `0xffff` leaving it unchanged, a two byte length and the block itself.
+//! [`seeds`] turns the stories in `fixtures/hpack` into such inputs.
+//!
+//! [`seeds`]: fn.seeds.html
+
+use bytes::BytesMut;
+use h2::hpack::{Decoder, Encoder, Huffman};
+use hex::FromHex;
+use serde_json::Value;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const NO_SIZE_UPDATE: u16 = 0xffff;
+
+pub fn run(input: &[u8]) {
+    let (&flags, mut input) = match input.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    let mut decoder = Decoder::new();
+    let mut encoder = Encoder::new();
+    let mut check = Decoder::new();
+
+    encoder.set_huffman(match flags & 0b11 {
+        1 => Huffman::Always,
+        2 => Huffman::Never,
+        _ => Huffman::WhenShorter,
+    });
+
+    while input.len() >= 4 {
+        let size = u16::from_be_bytes([input[0], input[1]]);
+        let len = u16::from_be_bytes([input[2], input[3]]) as usize;
+        input = &input[4..];
+
+        let block = &input[..len.min(input.len())];
+        input = &input[block.len()..];
+
+        if size != NO_SIZE_UPDATE {
+            decoder.set_max_table_size(size as usize);
+        }
+
+        let fields = match decoder.decode(block) {
+            Ok(fields) => fields,
+            // The decoder state is undefined after an error, as the
+            // connection is closed.
+            Err(_) => return,
+        };
+
+        if size != NO_SIZE_UPDATE {
+            encoder.update_max_table_size(size as usize);
+            check.set_max_table_size(size as usize);
+        }
+
+        let mut encoded = BytesMut::new();
+        encoder.encode(fields.clone(), &mut encoded);
+
+        let decoded = check
+            .decode(&encoded)
+            .unwrap_or_else(|e| panic!("failed to decode {:?}: {:?}", fields, e));
+
+        assert_eq!(fields, decoded);
+        for (field, decoded) in fields.iter().zip(&decoded) {
+            assert_eq!(field.is_sensitive(), decoded.is_sensitive());
+        }
+
+        assert_eq!(encoder.table().size(), check.table().size());
+        assert_eq!(encoder.table().len(), check.table().len());
+    }
+}
+
+/// Builds an input from a fixture story, with the given encoder flags.
+///
+/// Returns `None` if the story does not record the wire encoding.
+pub fn story_to_input(story: &str, flags: u8) -> Option<Vec<u8>> {
+    let story: Value = serde_json::from_str(story).ok()?;
+    let mut cases: Vec<_> = story.get("cases")?.as_array()?.iter().collect();
+    cases.sort_by_key(|case| case.get("seqno").and_then(Value::as_u64));
+
+    let mut input = vec![flags];
+
+    for case in cases {
+        let size = case
+            .get("header_table_size")
+            .and_then(Value::as_u64)
+            .map(|size| size as u16)
+            .unwrap_or(NO_SIZE_UPDATE);
+        let wire = case.get("wire")?.as_str()?;
+        let wire: Vec<u8> = FromHex::from_hex(wire.as_bytes()).ok()?;
+
+        input.extend_from_slice(&size.to_be_bytes());
+        input.extend_from_slice(&(wire.len() as u16).to_be_bytes());
+        input.extend_from_slice(&wire);
+    }
+
+    Some(input)
+}
+
+/// Builds inputs from every story found under `root`, usually
+/// `fixtures/hpack`, with each of the encoder's Huffman coding modes.
+///
+/// Each input is named after its story, such as `go-hpack-story_00-huffman`.
+pub fn seeds(root: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut seeds = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                // Raw data only lists headers, without any wire encoding.
+                if !path.ends_with("raw-data") {
+                    dirs.push(path);
+                }
+                continue;
+            }
+
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+
+            let name = path
+                .strip_prefix(root)
+                .unwrap()
+                .with_extension("")
+                .to_string_lossy()
+                .replace('/', "-");
+            let story = fs::read_to_string(&path)?;
+
+            for &(flags, suffix) in &[(0, "plain"), (1, "huffman"), (2, "raw")] {
+                if let Some(input) = story_to_input(&story, flags) {
+                    seeds.push((format!("{}-{}", name, suffix), input));
+                }
+            }
+        }
+    }
+
+    seeds.sort();
+    Ok(seeds)
+}
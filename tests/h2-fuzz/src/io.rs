@@ -0,0 +1,211 @@
+//! Transports used to feed fuzzer input to a connection.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A transport entirely scripted by the fuzzer input.
+///
+/// Every read and write starts by taking a two byte length from the input,
+/// which decides how many bytes are read or written. A length of zero makes
+/// the operation return `Pending`. The bytes read are taken from the input
+/// too, and the connection ends once the input runs out.
+pub struct MockIo<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> MockIo<'a> {
+    pub fn new(input: &'a [u8]) -> MockIo<'a> {
+        MockIo { input }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        if let Some(&c) = self.input.first() {
+            self.input = &self.input[1..];
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_byte().unwrap_or(0) as u32) << 8 | self.next_byte().unwrap_or(0) as u32
+    }
+}
+
+impl<'a> AsyncRead for MockIo<'a> {
+    unsafe fn prepare_uninitialized_buffer(&self, _buf: &mut [u8]) -> bool {
+        false
+    }
+
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut len = self.next_u32() as usize;
+        if self.input.is_empty() {
+            Poll::Ready(Ok(0))
+        } else if len == 0 {
+            cx.waker().clone().wake();
+            Poll::Pending
+        } else {
+            if len > self.input.len() {
+                len = self.input.len();
+            }
+
+            if len > buf.len() {
+                len = buf.len();
+            }
+            buf[0..len].copy_from_slice(&self.input[0..len]);
+            self.input = &self.input[len..];
+            Poll::Ready(Ok(len))
+        }
+    }
+}
+
+impl<'a> AsyncWrite for MockIo<'a> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let len = std::cmp::min(self.next_u32() as usize, buf.len());
+        if len == 0 {
+            if self.input.is_empty() {
+                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+            } else {
+                cx.waker().clone().wake();
+                Poll::Pending
+            }
+        } else {
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// An in-memory transport whose incoming bytes are supplied by a [`Peer`].
+///
+/// Reads return `Pending` while nothing has been sent and the peer is still
+/// open, without registering the waker: the harness is expected to poll the
+/// connection again after each step. Everything written is accepted and
+/// discarded.
+///
+/// [`Peer`]: struct.Peer.html
+pub struct Pipe {
+    shared: Arc<Mutex<Shared>>,
+}
+
+/// The remote end of a [`Pipe`].
+///
+/// [`Pipe`]: struct.Pipe.html
+#[derive(Clone)]
+pub struct Peer {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    buf: Vec<u8>,
+    pos: usize,
+    closed: bool,
+    written: usize,
+}
+
+impl Pipe {
+    pub fn new() -> (Pipe, Peer) {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let peer = Peer {
+            shared: shared.clone(),
+        };
+        (Pipe { shared }, peer)
+    }
+
+    /// A pipe which reads `input` and then the end of the stream.
+    pub fn closed(input: Vec<u8>) -> Pipe {
+        let (pipe, peer) = Pipe::new();
+        peer.send(&input);
+        peer.close();
+        pipe
+    }
+}
+
+impl Peer {
+    /// Queues `bytes` to be read by the pipe.
+    pub fn send(&self, bytes: &[u8]) {
+        self.shared.lock().unwrap().buf.extend_from_slice(bytes);
+    }
+
+    /// Ends the stream once the queued bytes have been read.
+    pub fn close(&self) {
+        self.shared.lock().unwrap().closed = true;
+    }
+
+    /// Returns the number of bytes written to the pipe so far.
+    pub fn written(&self) -> usize {
+        self.shared.lock().unwrap().written
+    }
+}
+
+impl AsyncRead for Pipe {
+    unsafe fn prepare_uninitialized_buffer(&self, _buf: &mut [u8]) -> bool {
+        false
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut shared = self.shared.lock().unwrap();
+        let remaining = shared.buf.len() - shared.pos;
+
+        if remaining == 0 {
+            if shared.closed {
+                return Poll::Ready(Ok(0));
+            }
+            return Poll::Pending;
+        }
+
+        let len = std::cmp::min(remaining, buf.len());
+        let pos = shared.pos;
+        buf[..len].copy_from_slice(&shared.buf[pos..pos + len]);
+        shared.pos += len;
+
+        // Don't keep the whole script around once it has been read.
+        if shared.pos == shared.buf.len() {
+            shared.buf.clear();
+            shared.pos = 0;
+        }
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for Pipe {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.shared.lock().unwrap().written += buf.len();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
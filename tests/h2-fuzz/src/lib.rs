@@ -0,0 +1,33 @@
+//! Fuzzing harnesses for `h2`.
+//!
+//! Each module drives connections from fuzzer input in a different way, and
+//! is shared by the honggfuzz binary of this crate and the cargo-fuzz targets
+//! in `fuzz/`.
+
+pub mod client;
+pub mod frames;
+pub mod hpack;
+pub mod io;
+pub mod server;
+pub mod streams;
+
+use std::future::Future;
+use std::task::{Context, Poll};
+
+/// Polls `future` until it completes, at most `max_polls` times.
+///
+/// The harness transports never wait on anything outside of the process, so
+/// a future that doesn't complete within the budget is stuck, and is dropped
+/// instead of hanging the fuzzer.
+pub fn drive<F: Future>(future: F, max_polls: usize) -> Option<F::Output> {
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    futures::pin_mut!(future);
+
+    for _ in 0..max_polls {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+    }
+
+    None
+}
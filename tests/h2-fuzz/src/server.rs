@@ -0,0 +1,66 @@
+//! A server connection driven by arbitrary client bytes.
+
+use futures::future;
+use futures::stream::FuturesUnordered;
+use futures::Stream;
+use h2::server::SendResponse;
+use h2::RecvStream;
+use http::{HeaderMap, Request, Response};
+use std::pin::Pin;
+use std::task::Poll;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Accepts every request, reads its body and trailers, and answers with a
+/// body and trailers of its own, until the connection ends.
+pub async fn run<T>(io: T) -> Result<(), h2::Error>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut h2 = h2::server::handshake(io).await?;
+    let mut handlers = FuturesUnordered::new();
+    let future = future::poll_fn(|cx| {
+        loop {
+            match h2.poll_accept(cx) {
+                Poll::Ready(Some(Ok((request, respond)))) => {
+                    if handlers.len() < 128 {
+                        handlers.push(handle(request, respond));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => break,
+            }
+        }
+        loop {
+            match Pin::new(&mut handlers).poll_next(cx) {
+                Poll::Pending | Poll::Ready(None) => break,
+                Poll::Ready(Some(r)) => {
+                    log::debug!("{:?}", r);
+                }
+            }
+        }
+        Poll::Pending
+    });
+    future.await
+}
+
+async fn handle(
+    request: Request<RecvStream>,
+    mut respond: SendResponse<bytes::Bytes>,
+) -> Result<(), h2::Error> {
+    let mut body = request.into_body();
+
+    while let Some(data) = body.data().await {
+        let data = data?;
+        body.release_capacity().release_capacity(data.len())?;
+    }
+    body.trailers().await?;
+
+    let response = Response::builder().status(200).body(()).unwrap();
+    let mut send = respond.send_response(response, false)?;
+    send.send_data(vec![0u8; 1024].into(), false)?;
+
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", "0".parse().unwrap());
+    send.send_trailers(trailers)
+}
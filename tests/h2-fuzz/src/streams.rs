@@ -0,0 +1,291 @@
+//! Stream state machine fuzzing.
+//!
+//! A client connection is driven one step at a time. Each step is either a
+//! frame received from the server or a call to the client API on one of the
+//! streams opened so far, so that local and remote state transitions are
+//! interleaved in every possible order.
+
+use crate::frames::{self, Frame, Reason};
+use crate::io::{Peer, Pipe};
+use arbitrary::Arbitrary;
+use bytes::Bytes;
+use futures::task::noop_waker_ref;
+use h2::client::{self, Connection, PushPromises, ResponseFuture, SendRequest};
+use h2::{RecvStream, SendStream};
+use http::{HeaderMap, Method, Request};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The number of times the connection is polled once the script is done.
+const FINAL_POLLS: usize = 64;
+
+/// The calls made to the client API, interleaved with server frames.
+#[derive(Arbitrary, Debug)]
+pub struct Script {
+    pub actions: Vec<Action>,
+}
+
+/// A single step. Streams are picked by index among the opened ones.
+#[derive(Arbitrary, Debug)]
+pub enum Action {
+    /// Polls the connection.
+    Poll,
+    /// Receives a frame from the server.
+    Recv(Frame),
+    SendRequest {
+        end_stream: bool,
+    },
+    ReserveCapacity {
+        stream: u8,
+        capacity: u16,
+    },
+    PollCapacity {
+        stream: u8,
+    },
+    SendData {
+        stream: u8,
+        len: u16,
+        end_stream: bool,
+    },
+    SendTrailers {
+        stream: u8,
+    },
+    SendReset {
+        stream: u8,
+        reason: Reason,
+    },
+    Cancel {
+        stream: u8,
+        reason: Reason,
+    },
+    PollResponse {
+        stream: u8,
+    },
+    PollPushPromise {
+        stream: u8,
+    },
+    PollData {
+        stream: u8,
+    },
+    PollTrailers {
+        stream: u8,
+    },
+    ReleaseCapacity {
+        stream: u8,
+        len: u16,
+    },
+    DropSendStream {
+        stream: u8,
+    },
+    DropResponse {
+        stream: u8,
+    },
+    GracefulShutdown,
+}
+
+/// The handles of an opened stream.
+#[derive(Default)]
+struct Slot {
+    send: Option<SendStream<Bytes>>,
+    response: Option<ResponseFuture>,
+    push_promises: Option<PushPromises>,
+    body: Option<RecvStream>,
+}
+
+struct Harness {
+    peer: Peer,
+    encoder: frames::Encoder,
+    send_request: SendRequest<Bytes>,
+    connection: Option<Connection<Pipe, Bytes>>,
+    slots: Vec<Slot>,
+}
+
+impl Script {
+    pub fn run(&self) {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let (io, peer) = Pipe::new();
+
+        // The client writes its preface without waiting for the server.
+        let mut handshake = Box::pin(client::handshake(io));
+        let (send_request, connection) = match handshake.as_mut().poll(&mut cx) {
+            Poll::Ready(Ok(parts)) => parts,
+            _ => return,
+        };
+
+        let mut encoder = frames::Encoder::default();
+        let mut settings = vec![];
+        encoder.encode(
+            &Frame::Settings {
+                ack: false,
+                settings: vec![],
+            },
+            &mut settings,
+        );
+        peer.send(&settings);
+
+        let mut harness = Harness {
+            peer,
+            encoder,
+            send_request,
+            connection: Some(connection),
+            slots: vec![],
+        };
+
+        for action in &self.actions {
+            harness.apply(action, &mut cx);
+            harness.poll(&mut cx);
+        }
+
+        harness.peer.close();
+        for _ in 0..FINAL_POLLS {
+            if harness.connection.is_none() {
+                break;
+            }
+            harness.poll(&mut cx);
+        }
+    }
+}
+
+impl Harness {
+    fn poll(&mut self, cx: &mut Context) {
+        if let Some(ref mut connection) = self.connection {
+            if let Poll::Ready(res) = Pin::new(connection).poll(cx) {
+                log::debug!("connection closed: {:?}", res);
+                self.connection = None;
+            }
+        }
+    }
+
+    fn slot(&mut self, index: u8) -> Option<&mut Slot> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let len = self.slots.len();
+        self.slots.get_mut(index as usize % len)
+    }
+
+    fn apply(&mut self, action: &Action, cx: &mut Context) {
+        match *action {
+            Action::Poll => {}
+            Action::Recv(ref frame) => {
+                let mut dst = vec![];
+                self.encoder.encode(frame, &mut dst);
+                self.peer.send(&dst);
+            }
+            Action::SendRequest { end_stream } => {
+                if let Poll::Ready(Ok(())) = self.send_request.poll_ready(cx) {
+                    let request = Request::builder()
+                        .method(Method::POST)
+                        .uri("https://example.com/")
+                        .body(())
+                        .unwrap();
+                    if let Ok((response, send)) =
+                        self.send_request.send_request(request, end_stream)
+                    {
+                        self.slots.push(Slot {
+                            send: Some(send),
+                            response: Some(response),
+                            ..Slot::default()
+                        });
+                    }
+                }
+            }
+            Action::ReserveCapacity { stream, capacity } => {
+                if let Some(send) = self.slot(stream).and_then(|s| s.send.as_mut()) {
+                    send.reserve_capacity(capacity as usize);
+                }
+            }
+            Action::PollCapacity { stream } => {
+                if let Some(send) = self.slot(stream).and_then(|s| s.send.as_mut()) {
+                    let _ = send.poll_capacity(cx);
+                }
+            }
+            Action::SendData {
+                stream,
+                len,
+                end_stream,
+            } => {
+                if let Some(send) = self.slot(stream).and_then(|s| s.send.as_mut()) {
+                    let _ = send.send_data(vec![0; len as usize].into(), end_stream);
+                }
+            }
+            Action::SendTrailers { stream } => {
+                if let Some(send) = self.slot(stream).and_then(|s| s.send.as_mut()) {
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("grpc-status", "0".parse().unwrap());
+                    let _ = send.send_trailers(trailers);
+                }
+            }
+            Action::SendReset { stream, reason } => {
+                if let Some(send) = self.slot(stream).and_then(|s| s.send.as_mut()) {
+                    send.send_reset(h2::Reason::from(reason.0));
+                }
+            }
+            Action::Cancel { stream, reason } => {
+                if let Some(response) = self.slot(stream).and_then(|s| s.response.as_mut()) {
+                    response.cancel(h2::Reason::from(reason.0));
+                }
+            }
+            Action::PollResponse { stream } => {
+                if let Some(slot) = self.slot(stream) {
+                    if let Some(ref mut response) = slot.response {
+                        if let Poll::Ready(res) = Pin::new(response).poll(cx) {
+                            slot.response = None;
+                            if let Ok(response) = res {
+                                slot.body = Some(response.into_body());
+                            }
+                        }
+                    }
+                }
+            }
+            Action::PollPushPromise { stream } => {
+                if let Some(slot) = self.slot(stream) {
+                    if slot.push_promises.is_none() {
+                        slot.push_promises = slot.response.as_mut().map(|r| r.push_promises());
+                    }
+                    if let Some(ref mut push_promises) = slot.push_promises {
+                        if let Poll::Ready(Some(Ok(push_promise))) =
+                            push_promises.poll_push_promise(cx)
+                        {
+                            let (_, mut response) = push_promise.into_parts();
+                            let _ = Pin::new(&mut response).poll(cx);
+                        }
+                    }
+                }
+            }
+            Action::PollData { stream } => {
+                if let Some(body) = self.slot(stream).and_then(|s| s.body.as_mut()) {
+                    let _ = body.poll_data(cx);
+                }
+            }
+            Action::PollTrailers { stream } => {
+                if let Some(body) = self.slot(stream).and_then(|s| s.body.as_mut()) {
+                    let _ = body.poll_trailers(cx);
+                }
+            }
+            Action::ReleaseCapacity { stream, len } => {
+                if let Some(body) = self.slot(stream).and_then(|s| s.body.as_mut()) {
+                    let _ = body.release_capacity().release_capacity(len as usize);
+                }
+            }
+            Action::DropSendStream { stream } => {
+                if let Some(slot) = self.slot(stream) {
+                    slot.send = None;
+                }
+            }
+            Action::DropResponse { stream } => {
+                if let Some(slot) = self.slot(stream) {
+                    slot.response = None;
+                    slot.push_promises = None;
+                    slot.body = None;
+                }
+            }
+            Action::GracefulShutdown => {
+                if let Some(ref mut connection) = self.connection {
+                    connection.graceful_shutdown();
+                }
+            }
+        }
+    }
+}
@@ -137,6 +137,11 @@ impl Mock<frame::Headers> {
         Mock(frame)
     }
 
+    pub fn pad_len(mut self, pad_len: u8) -> Self {
+        self.0.set_pad_len(Some(pad_len));
+        self
+    }
+
     pub fn scheme(self, value: &str) -> Self {
         let (id, mut pseudo, fields) = self.into_parts();
         let value = value.parse().unwrap();
@@ -184,6 +189,11 @@ impl Mock<frame::Data> {
         self
     }
 
+    pub fn pad_len(mut self, pad_len: u8) -> Self {
+        self.0.set_pad_len(Some(pad_len));
+        self
+    }
+
     pub fn eos(mut self) -> Self {
         self.0.set_end_stream(true);
         self
@@ -195,12 +205,16 @@ impl From<Mock<frame::Data>> for SendFrame {
         let id = src.0.stream_id();
         let eos = src.0.is_end_stream();
         let is_padded = src.0.is_padded();
+        let pad_len = src.0.pad_len();
         let payload = src.0.into_payload();
         let mut frame = frame::Data::new(id, payload.into_buf());
         frame.set_end_stream(eos);
         if is_padded {
             frame.set_padded();
         }
+        if pad_len.is_some() {
+            frame.set_pad_len(pad_len);
+        }
         Frame::Data(frame)
     }
 }
@@ -349,6 +363,11 @@ impl Mock<frame::Settings> {
         self.0.set_max_header_list_size(Some(val));
         self
     }
+
+    pub fn header_table_size(mut self, val: u32) -> Self {
+        self.0.set_header_table_size(Some(val));
+        self
+    }
 }
 
 impl From<Mock<frame::Settings>> for frame::Settings {
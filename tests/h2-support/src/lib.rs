@@ -9,6 +9,8 @@ pub mod frames;
 pub mod mock;
 pub mod mock_io;
 pub mod prelude;
+pub mod replay;
+pub mod timer;
 pub mod util;
 
 mod client_ext;
@@ -53,6 +53,9 @@ struct Inner {
     /// Task to notify when write capacity becomes available.
     tx_rem_task: Option<Waker>,
 
+    /// Number of writes made by the `h2` library.
+    num_writes: usize,
+
     /// True when the pipe is closed.
     closed: bool,
 }
@@ -73,6 +76,7 @@ pub fn new_with_write_capacity(cap: usize) -> (Mock, Handle) {
         tx_task: None,
         tx_rem: cap,
         tx_rem_task: None,
+        num_writes: 0,
         closed: false,
     }));
 
@@ -97,6 +101,11 @@ impl Handle {
         &mut self.codec
     }
 
+    /// Returns the number of writes made by the `h2` library so far.
+    pub fn num_writes(&self) -> usize {
+        self.codec.get_ref().inner.lock().unwrap().num_writes
+    }
+
     /// Send a frame
     pub async fn send(&mut self, item: SendFrame) -> Result<(), SendError> {
         // Queue the frame
@@ -251,6 +260,77 @@ impl Handle {
         self.recv_frame(crate::frames::ping(payload).pong()).await;
     }
 
+    /// Checks that the peer still answers a `PING`, skipping the frames it
+    /// sends before the `PING` ACK.
+    pub async fn assert_no_error(&mut self) {
+        const PAYLOAD: [u8; 8] = *b"h2spec\0\0";
+
+        self.send_frame(crate::frames::ping(PAYLOAD)).await;
+
+        loop {
+            match self.next().await {
+                Some(Ok(Frame::Ping(ref ping))) if ping.is_ack() => {
+                    assert_eq!(ping.payload(), &PAYLOAD);
+                    return;
+                }
+                Some(Ok(Frame::GoAway(frame))) => panic!("unexpected GOAWAY; frame={:?}", frame),
+                Some(Ok(Frame::Reset(frame))) => panic!("unexpected RST_STREAM; frame={:?}", frame),
+                Some(Ok(_)) => {}
+                res => panic!("unexpected result; res={:?}", res),
+            }
+        }
+    }
+
+    /// Expects a `GOAWAY` frame with one of `reasons`, then the end of the
+    /// connection, skipping the frames the peer sends before it.
+    pub async fn assert_connection_error(&mut self, reasons: &[frame::Reason]) {
+        loop {
+            match self.next().await {
+                Some(Ok(Frame::GoAway(frame))) => {
+                    assert!(
+                        reasons.contains(&frame.reason()),
+                        "unexpected GOAWAY; frame={:?}",
+                        frame
+                    );
+                    break;
+                }
+                Some(Ok(Frame::Reset(frame))) => panic!("unexpected RST_STREAM; frame={:?}", frame),
+                Some(Ok(_)) => {}
+                res => panic!("unexpected result; res={:?}", res),
+            }
+        }
+
+        self.recv_eof().await;
+    }
+
+    /// Expects a `RST_STREAM` frame on stream `id`, or a `GOAWAY` frame, with
+    /// one of `reasons`, skipping the frames the peer sends before it.
+    pub async fn assert_stream_error(&mut self, id: u32, reasons: &[frame::Reason]) {
+        loop {
+            match self.next().await {
+                Some(Ok(Frame::Reset(frame))) => {
+                    assert_eq!(frame.stream_id(), id, "unexpected RST_STREAM");
+                    assert!(
+                        reasons.contains(&frame.reason()),
+                        "unexpected RST_STREAM; frame={:?}",
+                        frame
+                    );
+                    return;
+                }
+                Some(Ok(Frame::GoAway(frame))) => {
+                    assert!(
+                        reasons.contains(&frame.reason()),
+                        "unexpected GOAWAY; frame={:?}",
+                        frame
+                    );
+                    return;
+                }
+                Some(Ok(_)) => {}
+                res => panic!("unexpected result; res={:?}", res),
+            }
+        }
+    }
+
     pub async fn buffer_bytes(&mut self, num: usize) {
         // Set tx_rem to num
         {
@@ -397,6 +477,7 @@ impl AsyncWrite for Mock {
 
         me.tx.extend(buf);
         me.tx_rem -= buf.len();
+        me.num_writes += 1;
 
         if let Some(task) = me.tx_task.take() {
             task.wake();
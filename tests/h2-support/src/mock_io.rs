@@ -111,6 +111,7 @@ enum Action {
 struct Inner {
     actions: VecDeque<Action>,
     waiting: Option<Instant>,
+    num_writes: usize,
 }
 
 impl Builder {
@@ -163,6 +164,7 @@ impl Builder {
             inner: Inner {
                 actions: src.actions,
                 waiting: None,
+                num_writes: 0,
             },
             tokio: tokio,
         };
@@ -173,6 +175,15 @@ impl Builder {
     }
 }
 
+impl Mock {
+    /// Returns the number of write calls that wrote data.
+    ///
+    /// A vectored write of several buffers counts as a single call.
+    pub fn num_writes(&self) -> usize {
+        self.inner.num_writes
+    }
+}
+
 impl Handle {
     /// Sequence a `read` operation.
     ///
@@ -310,8 +321,10 @@ impl Inner {
 mod tokio_ {
     use super::*;
 
+    use bytes::Buf;
     use futures::channel::mpsc;
     use futures::{ready, FutureExt, Stream};
+    use iovec::IoVec;
     use std::task::{Context, Poll, Waker};
     use tokio::io::{AsyncRead, AsyncWrite};
     use tokio::timer::Delay;
@@ -464,6 +477,7 @@ mod tokio_ {
                         }
                     }
                     ret => {
+                        self.inner.num_writes += 1;
                         self.maybe_wakeup_reader();
                         return Poll::Ready(ret);
                     }
@@ -471,6 +485,33 @@ mod tokio_ {
             }
         }
 
+        fn poll_write_buf<B: Buf>(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut B,
+        ) -> Poll<Result<usize, io::Error>> {
+            if !buf.has_remaining() {
+                return Poll::Ready(Ok(0));
+            }
+
+            // Gather the buffer's slices, like a `writev` would. The
+            // placeholder slices are overwritten by `bytes_vec`.
+            let placeholder: &IoVec = (&[0][..]).into();
+            let mut iovs = [placeholder; 64];
+            let n = buf.bytes_vec(&mut iovs);
+
+            let mut src = vec![];
+
+            for iov in &iovs[..n] {
+                src.extend_from_slice(iov);
+            }
+
+            let n = ready!(self.poll_write(cx, &src))?;
+            buf.advance(n);
+
+            Poll::Ready(Ok(n))
+        }
+
         fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
             Poll::Ready(Ok(()))
         }
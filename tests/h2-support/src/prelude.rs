@@ -9,9 +9,18 @@ pub use h2::*;
 // Re-export mock
 pub use super::mock::{self, idle_ms};
 
+// Re-export timer helpers
+pub use super::timer::MockTimer;
+
 // Re-export frames helpers
 pub use super::frames;
 
+// Re-export raw frame helpers
+pub use super::raw;
+
+// Re-export transcript helpers
+pub use super::replay;
+
 // Re-export utility mod
 pub use super::util;
 
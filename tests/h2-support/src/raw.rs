@@ -50,3 +50,86 @@ impl Chunk for Vec<u8> {
         dst.extend(self.iter())
     }
 }
+
+// ===== Encode frames by hand =====
+
+// Frame types
+pub const DATA: u8 = 0x0;
+pub const HEADERS: u8 = 0x1;
+pub const PRIORITY: u8 = 0x2;
+pub const RST_STREAM: u8 = 0x3;
+pub const SETTINGS: u8 = 0x4;
+pub const PUSH_PROMISE: u8 = 0x5;
+pub const PING: u8 = 0x6;
+pub const GOAWAY: u8 = 0x7;
+pub const WINDOW_UPDATE: u8 = 0x8;
+pub const CONTINUATION: u8 = 0x9;
+
+// Frame flags
+pub const END_STREAM: u8 = 0x1;
+pub const ACK: u8 = 0x1;
+pub const END_HEADERS: u8 = 0x4;
+pub const PADDED: u8 = 0x8;
+pub const PRIORITY_FLAG: u8 = 0x20;
+
+/// Encode a frame without checking that it is valid.
+///
+/// `stream_id` is written as is, reserved bit included.
+pub fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+    let len = payload.len();
+    assert!(len < 1 << 24, "payload too large; len={}", len);
+
+    let mut dst = Vec::with_capacity(9 + len);
+    dst.extend_from_slice(&[(len >> 16) as u8, (len >> 8) as u8, len as u8]);
+    dst.extend_from_slice(&[kind, flags]);
+    dst.extend_from_slice(&stream_id.to_be_bytes());
+    dst.extend_from_slice(payload);
+    dst
+}
+
+/// Encode a header block of literal fields.
+///
+/// The fields are neither indexed nor Huffman encoded, so the block decodes
+/// the same whatever the state of the decoder. Names are not checked, which
+/// allows encoding malformed fields.
+pub fn header_block(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut dst = vec![];
+
+    for &(name, value) in fields {
+        // Literal header field without indexing, new name
+        dst.extend_from_slice(&[0]);
+        string(name.as_bytes(), &mut dst);
+        string(value.as_bytes(), &mut dst);
+    }
+
+    dst
+}
+
+/// The fields of a `GET https://example.com/` request.
+pub const GET: &[(&str, &str)] = &[
+    (":method", "GET"),
+    (":scheme", "https"),
+    (":authority", "example.com"),
+    (":path", "/"),
+];
+
+fn string(src: &[u8], dst: &mut Vec<u8>) {
+    // Length, with a 7-bit prefix and the Huffman bit unset
+    let mut len = src.len();
+
+    if len < 127 {
+        dst.extend_from_slice(&[len as u8]);
+    } else {
+        dst.extend_from_slice(&[127]);
+        len -= 127;
+
+        while len >= 128 {
+            dst.extend_from_slice(&[0x80 | (len & 0x7f) as u8]);
+            len >>= 7;
+        }
+
+        dst.extend_from_slice(&[len as u8]);
+    }
+
+    dst.extend_from_slice(src);
+}
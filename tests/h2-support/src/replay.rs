@@ -0,0 +1,154 @@
+//! Replays recorded transcripts against a connection.
+
+use crate::mock::{self, Handle};
+use crate::timer::MockTimer;
+
+use h2::frame::Frame;
+use h2::transcript::{Direction, Transcript};
+
+use futures::StreamExt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+const PREFACE: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Load a transcript saved with `Transcript::write_to`.
+pub fn load<P: AsRef<Path>>(path: P) -> Transcript {
+    let path = path.as_ref();
+    let file = File::open(path).unwrap_or_else(|e| panic!("open {:?}; err={:?}", path, e));
+
+    Transcript::read_from(BufReader::new(file))
+        .unwrap_or_else(|e| panic!("load {:?}; err={:?}", path, e))
+}
+
+/// Play the peer of the connection recorded in `transcript`.
+///
+/// The bytes the recorded connection received are written to `handle`, in
+/// order. The frames it sent are read from `handle` and each must match one
+/// of the recorded frames not matched yet, so the connection may order or
+/// split its writes differently than when it was recorded.
+///
+/// Each chunk is written once the connection has sent as many frames as it
+/// had when the chunk was recorded, so that it is never sent a reply to a
+/// frame it did not send yet.
+pub async fn replay(handle: &mut Handle, transcript: &Transcript) {
+    replay2(handle, transcript, None).await
+}
+
+/// Play the peer of the connection recorded in `transcript`, moving `timer`
+/// forward to the time of each record.
+///
+/// This replays the timeouts of a connection using `timer`, see `replay`.
+pub async fn replay_with_timer(handle: &mut Handle, transcript: &Transcript, timer: &MockTimer) {
+    replay2(handle, transcript, Some(timer)).await
+}
+
+async fn replay2(handle: &mut Handle, transcript: &Transcript, timer: Option<&MockTimer>) {
+    let sent = transcript.bytes(Direction::Sent);
+
+    // A client starts with the connection preface, which isn't a frame
+    let start = if sent.starts_with(PREFACE) {
+        handle.read_preface().await.unwrap();
+        PREFACE.len()
+    } else {
+        0
+    };
+
+    let mut expected = decode(&sent[start..]).await;
+    let ends = frame_ends(&sent, start);
+    let total = expected.len();
+
+    // Number of bytes sent by the connection before the current record
+    let mut offset = 0;
+    let mut now = Duration::default();
+
+    for (i, record) in transcript.records().iter().enumerate() {
+        let num_sent = ends.iter().take_while(|&&end| end <= offset).count();
+
+        while total - expected.len() < num_sent {
+            recv_expected(handle, &mut expected, i).await;
+        }
+
+        if let Some(timer) = timer {
+            if record.elapsed() > now {
+                timer.advance(record.elapsed() - now);
+                now = record.elapsed();
+            }
+        }
+
+        match record.direction() {
+            Direction::Received => handle.send_bytes(record.data()).await,
+            Direction::Sent => offset += record.data().len(),
+        }
+    }
+
+    while !expected.is_empty() {
+        recv_expected(handle, &mut expected, transcript.records().len()).await;
+    }
+}
+
+/// Reads the next frame sent by the connection, which must be one of the
+/// `expected` frames, and removes it from them.
+async fn recv_expected(handle: &mut Handle, expected: &mut Vec<Frame>, record: usize) {
+    let frame = match handle.next().await {
+        Some(Ok(frame)) => frame,
+        res => panic!(
+            "record {}; expected one of {:?}; got {:?}",
+            record, expected, res
+        ),
+    };
+
+    match expected.iter().position(|f| *f == frame) {
+        Some(pos) => {
+            expected.remove(pos);
+        }
+        None => panic!(
+            "record {}; unexpected frame {:?}; expected one of {:?}",
+            record, frame, expected
+        ),
+    }
+}
+
+/// Decodes the frames of a recorded byte stream.
+async fn decode(src: &[u8]) -> Vec<Frame> {
+    let (mut io, mut handle) = mock::new();
+
+    io.write_all(src).await.unwrap();
+    drop(io);
+
+    let mut frames = vec![];
+
+    while let Some(frame) = handle.next().await {
+        frames.push(frame.unwrap_or_else(|e| panic!("invalid recorded frame; err={:?}", e)));
+    }
+
+    frames
+}
+
+/// Returns the offset of the end of each frame decoded by `decode`, the
+/// frames of `src` starting at `start`.
+fn frame_ends(src: &[u8], start: usize) -> Vec<usize> {
+    const END_HEADERS: u8 = 0x4;
+
+    let mut ends = vec![];
+    let mut pos = start;
+
+    while pos + 9 <= src.len() {
+        let len = (src[pos] as usize) << 16 | (src[pos + 1] as usize) << 8 | src[pos + 2] as usize;
+        let (kind, flags) = (src[pos + 3], src[pos + 4]);
+
+        pos += 9 + len;
+
+        // A header block is decoded as one frame, once complete
+        let is_header_block = kind == 0x1 || kind == 0x5 || kind == 0x9;
+
+        if !is_header_block || flags & END_HEADERS == END_HEADERS {
+            ends.push(pos);
+        }
+    }
+
+    ends
+}
@@ -0,0 +1,80 @@
+use h2::{Sleep, Timer};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// A `Timer` whose clock only moves when `advance` is called.
+///
+/// Clones share the same clock, so a test can keep a handle to advance time
+/// after passing the timer to a `Builder`.
+#[derive(Debug, Clone)]
+pub struct MockTimer {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    now: Instant,
+    wakers: Vec<Waker>,
+}
+
+struct MockSleep {
+    inner: Arc<Mutex<Inner>>,
+    deadline: Instant,
+}
+
+impl MockTimer {
+    pub fn new() -> Self {
+        MockTimer {
+            inner: Arc::new(Mutex::new(Inner {
+                now: Instant::now(),
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Moves the clock forward, waking any sleeps that may have completed.
+    pub fn advance(&self, duration: Duration) {
+        let wakers = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.now += duration;
+            std::mem::replace(&mut inner.wakers, Vec::new())
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl Timer for MockTimer {
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let deadline = self.now() + duration;
+        Box::pin(MockSleep {
+            inner: self.inner.clone(),
+            deadline,
+        })
+    }
+
+    fn now(&self) -> Instant {
+        self.inner.lock().unwrap().now
+    }
+}
+
+impl Future for MockSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.now >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        inner.wakers.push(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -1,6 +1,6 @@
 use futures::future::{join, ready, select, Either};
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use h2_support::prelude::*;
 use std::pin::Pin;
 use std::task::Context;
@@ -1169,6 +1169,847 @@ async fn malformed_response_headers_dont_unlink_stream() {
     join(srv, client).await;
 }
 
+#[tokio::test]
+async fn recv_malformed_response_pseudo_headers() {
+    let _ = env_logger::try_init();
+
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "http://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.recv_frame(
+            frames::headers(3)
+                .request("GET", "http://example.com/")
+                .eos(),
+        )
+        .await;
+        // A response needs a `:status`
+        srv.send_frame(frames::headers(1)).await;
+        srv.recv_frame(frames::reset(1).protocol_error()).await;
+        // and cannot carry request pseudo-header fields
+        srv.send_frame(frames::headers(3).request("GET", "http://example.com/"))
+            .await;
+        srv.recv_frame(frames::reset(3).protocol_error()).await;
+    };
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .uri("http://example.com/")
+            .body(())
+            .unwrap()
+    }
+
+    let client = async move {
+        let (mut client, mut conn) = client::handshake(io).await.expect("handshake");
+
+        let (resp1, _) = client.send_request(request(), true).unwrap();
+        let (resp2, _) = client.send_request(request(), true).unwrap();
+
+        let err = conn.drive(resp1).await.unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+        let err = conn.drive(resp2).await.unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+
+        drop(client);
+        conn.await.expect("h2");
+    };
+
+    join(srv, client).await;
+}
+
+#[tokio::test]
+async fn recv_informational_response_is_skipped() {
+    let _ = env_logger::try_init();
+
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "http://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.recv_frame(
+            frames::headers(3)
+                .request("GET", "http://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(100)).await;
+        srv.send_frame(frames::headers(1).response(103)).await;
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+        // An informational response cannot end the stream
+        srv.send_frame(frames::headers(3).response(100).eos()).await;
+        srv.recv_frame(frames::reset(3).protocol_error()).await;
+    };
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .uri("http://example.com/")
+            .body(())
+            .unwrap()
+    }
+
+    let client = async move {
+        let (mut client, mut conn) = client::handshake(io).await.expect("handshake");
+
+        let (resp1, _) = client.send_request(request(), true).unwrap();
+        let (resp2, _) = client.send_request(request(), true).unwrap();
+
+        let resp = conn.drive(resp1).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let err = conn.drive(resp2).await.unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+
+        drop(client);
+        conn.await.expect("h2");
+    };
+
+    join(srv, client).await;
+}
+
+#[tokio::test]
+async fn graceful_shutdown_waits_for_active_streams() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let (settled_tx, settled_rx) = futures::channel::oneshot::channel();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        settled_tx.send(()).unwrap();
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        // Shut down once the SETTINGS exchange is over
+        h2.drive(settled_rx).await.unwrap();
+
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (response, _) = client.send_request(request, true).unwrap();
+
+        h2.graceful_shutdown();
+
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // `client` is still alive, but the connection closes anyway.
+        h2.await.expect("h2");
+        drop(client);
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn send_request_after_graceful_shutdown_errors() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let (settled_tx, settled_rx) = futures::channel::oneshot::channel();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        settled_tx.send(()).unwrap();
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_frame(frames::headers(1).request("POST", "https://example.com/"))
+            .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.recv_frame(frames::data(1, "hello").eos()).await;
+        srv.send_frame(frames::data(1, "world").eos()).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        h2.drive(settled_rx).await.unwrap();
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (response, mut stream) = client.send_request(request, false).unwrap();
+
+        h2.graceful_shutdown();
+
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let err = client.send_request(request, true).unwrap_err();
+        assert_eq!(err.to_string(), "user error: connection is shutting down");
+
+        let err = poll_fn(|cx| client.poll_ready(cx)).await.unwrap_err();
+        assert_eq!(err.to_string(), "user error: connection is shutting down");
+
+        // The stream opened before the shutdown still completes.
+        let response = h2.drive(response).await.unwrap();
+        stream.send_data("hello".into(), true).unwrap();
+        let body = h2.drive(response.into_body().try_concat()).await.unwrap();
+        assert_eq!(body, "world");
+
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn drain_deadline_resets_active_streams() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let (settled_tx, settled_rx) = futures::channel::oneshot::channel();
+    let (deadline_tx, deadline_rx) = futures::channel::oneshot::channel();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        settled_tx.send(()).unwrap();
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        deadline_tx.send(()).unwrap();
+        srv.recv_frame(frames::go_away(0).internal_error()).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        h2.drive(settled_rx).await.unwrap();
+
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (response, _) = client.send_request(request, true).unwrap();
+
+        let deadline = async move {
+            deadline_rx.await.expect("deadline_rx");
+        };
+        h2.drain(deadline, Reason::INTERNAL_ERROR);
+
+        let conn = async move {
+            assert_eq!(h2.drain_progress().await.unwrap().unwrap(), 1);
+            assert_eq!(h2.drain_progress().await.unwrap().unwrap(), 0);
+            assert!(h2.drain_progress().await.is_none());
+        };
+        let response = async move {
+            let err = response.await.expect_err("response");
+            assert_eq!(err.reason(), Some(Reason::INTERNAL_ERROR));
+        };
+
+        join(conn, response).await;
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn idle_timeout_shuts_down_gracefully() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        // No request was sent
+        srv_timer.advance(Duration::from_secs(10));
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (client, h2) = client::Builder::new()
+            .timer(timer)
+            .idle_timeout(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        h2.await.expect("h2");
+        drop(client);
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn idle_timeout_without_timer_errors() {
+    let _ = env_logger::try_init();
+    let (io, _srv) = mock::new();
+
+    let err = client::Builder::new()
+        .idle_timeout(Duration::from_secs(10))
+        .handshake::<_, Bytes>(io)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "user error: timeout without a timer");
+}
+
+#[tokio::test]
+async fn handshake_timeout_without_settings() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        srv.read_preface().await.unwrap();
+        srv.recv_frame(frames::settings()).await;
+        // The server never sends its SETTINGS
+        srv_timer.advance(Duration::from_secs(10));
+        srv.recv_frame(frames::go_away(0).reason(Reason::SETTINGS_TIMEOUT))
+            .await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (client, h2) = client::Builder::new()
+            .timer(timer)
+            .handshake_timeout(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let err = h2.await.unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::SETTINGS_TIMEOUT));
+        drop(client);
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn cancel_sends_reset_with_reason() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.recv_frame(frames::reset(1).internal_error()).await;
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (mut response, _) = client.send_request(request, true).unwrap();
+
+        response.cancel(Reason::INTERNAL_ERROR);
+        let sent = h2.drive(poll_fn(|cx| response.poll_canceled(cx))).await;
+        assert!(sent, "RST_STREAM should be written");
+
+        let err = response.await.expect_err("response");
+        assert_eq!(err.reason(), Some(Reason::INTERNAL_ERROR));
+
+        drop(client);
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn poll_canceled_ignores_resets_not_from_cancel() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        // A response needs a `:status`
+        srv.send_frame(frames::headers(1)).await;
+        srv.recv_frame(frames::reset(1).protocol_error()).await;
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (mut response, _) = client.send_request(request, true).unwrap();
+
+        let err = h2.drive(&mut response).await.expect_err("response");
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+
+        // The stream was reset by the connection, not canceled
+        let sent = h2.drive(poll_fn(|cx| response.poll_canceled(cx))).await;
+        assert!(!sent);
+
+        drop((client, response));
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn poll_canceled_from_another_task() {
+    use futures::channel::oneshot;
+
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.recv_frame(frames::reset(1).cancel()).await;
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (mut response, _) = client.send_request(request, true).unwrap();
+
+        response.cancel(Reason::CANCEL);
+
+        // The task waiting for the reset is not the one driving the
+        // connection, so it must be woken when the frame is written.
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let sent = poll_fn(|cx| response.poll_canceled(cx)).await;
+            tx.send(sent).unwrap();
+        });
+        util::yield_once().await;
+
+        let sent = h2.drive(rx).await.unwrap();
+        assert!(sent, "RST_STREAM should be written");
+
+        drop(client);
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn set_timeout_without_timer_errors() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (mut response, _) = client.send_request(request, true).unwrap();
+
+        let err = response
+            .set_timeout(Duration::from_secs(5), Reason::CANCEL)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "user error: timeout without a timer");
+
+        // The request is unaffected
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn request_timeout_resets_stream() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv_timer.advance(Duration::from_secs(30));
+        srv.recv_frame(frames::reset(1).cancel()).await;
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .timer(timer)
+            .request_timeout(Duration::from_secs(30))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (mut response, _) = client.send_request(request, true).unwrap();
+
+        let err = h2
+            .drive(poll_fn(|cx| Pin::new(&mut response).poll(cx)))
+            .await
+            .expect_err("response");
+        assert_eq!(err.get_io().unwrap().kind(), std::io::ErrorKind::TimedOut);
+
+        let sent = h2.drive(poll_fn(|cx| response.poll_canceled(cx))).await;
+        assert!(sent, "RST_STREAM should be written");
+
+        drop(response);
+        drop(client);
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn set_timeout_resets_stream_with_reason() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv_timer.advance(Duration::from_secs(5));
+        srv.recv_frame(frames::reset(1).reason(Reason::REFUSED_STREAM))
+            .await;
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .timer(timer)
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+        let (mut response, _) = client.send_request(request, true).unwrap();
+        response
+            .set_timeout(Duration::from_secs(5), Reason::REFUSED_STREAM)
+            .unwrap();
+
+        let err = h2.drive(response).await.expect_err("response");
+        assert_eq!(err.get_io().unwrap().kind(), std::io::ErrorKind::TimedOut);
+
+        drop(client);
+        h2.await.expect("h2");
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn header_table_size_requires_size_update() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_frame_eq(settings, frames::settings().header_table_size(0));
+        // The mock's encoder never emits the size update required after the
+        // table was shrunk, so the client must reject the header block as a
+        // decoding error (RFC 7541 §4.2).
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+        srv.recv_frame(frames::go_away(0).reason(Reason::COMPRESSION_ERROR))
+            .await;
+    };
+
+    let h2 = async move {
+        let (mut client, h2) = client::Builder::new()
+            .header_table_size(0)
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let request = Request::builder()
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+
+        let (response, _) = client.send_request(request, true).unwrap();
+        let err = h2.await.expect_err("h2");
+        assert_eq!(err.reason(), Some(Reason::COMPRESSION_ERROR));
+        drop(response);
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn header_table_size_accepts_size_update() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_frame_eq(settings, frames::settings().header_table_size(0));
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        // The client acknowledged the SETTINGS, so the header block starts
+        // with the size update it requires.
+        srv.codec_mut().set_send_header_table_size(0);
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .header_table_size(0)
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let request = Request::builder()
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+
+        let (response, _) = client.send_request(request, true).unwrap();
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        drop(client);
+        h2.await.unwrap();
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn header_table_size_allows_larger_table() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_frame_eq(settings, frames::settings().header_table_size(8_192));
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        // Growing the table past the default 4,096 octets is a compression
+        // error unless the client allowed it.
+        srv.codec_mut().set_send_header_table_size(8_192);
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .header_table_size(8_192)
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let request = Request::builder()
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+
+        let (response, _) = client.send_request(request, true).unwrap();
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        drop(client);
+        h2.await.unwrap();
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn recv_huge_header_table_size() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv
+            .assert_client_handshake_with_settings(frames::settings().header_table_size(u32::MAX))
+            .await;
+        assert_default_settings!(settings);
+        // The client caps the table it uses, and the size update it sends
+        // must still be encodable.
+        srv.codec_mut()
+            .set_recv_header_table_size(u32::MAX as usize);
+        for &id in &[1, 3] {
+            srv.recv_frame(
+                frames::headers(id)
+                    .request("GET", "https://http2.akamai.com/")
+                    .eos(),
+            )
+            .await;
+            srv.send_frame(frames::headers(id).response(200).eos())
+                .await;
+        }
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.expect("handshake");
+
+        for _ in 0..2 {
+            let res = h2.drive(client.get("https://http2.akamai.com/")).await;
+            assert_eq!(res.unwrap().status(), StatusCode::OK);
+        }
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn hpack_stats_count_headers() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+
+        for &id in &[1, 3] {
+            srv.recv_frame(
+                frames::headers(id)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+            srv.send_frame(frames::headers(id).response(200).eos())
+                .await;
+        }
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.unwrap();
+        assert_eq!(h2.hpack_stats(), HpackStats::default());
+
+        for _ in 0..2 {
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let (response, _) = client.send_request(request, true).unwrap();
+            let response = h2.drive(response).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // Only `:authority` is not in the static table, it is inserted by the
+        // first request and indexed by the second.
+        let stats = h2.hpack_stats();
+        let sent = stats.sent();
+        assert_eq!(sent.field_hits(), 1);
+        assert_eq!(sent.name_hits(), 0);
+        assert_eq!(sent.table_size(), 32 + 10 + 11);
+        assert_eq!(sent.header_bytes(), 2 * (10 + 12 + 21 + 6));
+        assert!(sent.encoded_bytes() < sent.header_bytes());
+
+        // `:status: 200` is a single static table index.
+        let received = stats.received();
+        assert_eq!(received.header_bytes(), 2 * (7 + 3));
+        assert_eq!(received.encoded_bytes(), 2);
+        assert_eq!(received.field_hits(), 0);
+        assert_eq!(received.table_size(), 0);
+
+        drop(client);
+        h2.await.unwrap();
+    };
+
+    join(srv, h2).await;
+}
+
 const SETTINGS: &'static [u8] = &[0, 0, 0, 4, 0, 0, 0, 0, 0];
 const SETTINGS_ACK: &'static [u8] = &[0, 0, 0, 4, 1, 0, 0, 0, 0];
 
@@ -51,3 +51,294 @@ async fn write_continuation_frames() {
 
     join(srv, client).await;
 }
+
+#[tokio::test]
+async fn write_header_block_spanning_many_frames() {
+    use tokio::io::AsyncReadExt;
+
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let mut codec = Codec::new(io);
+
+    // A single field larger than any frame
+    let cookie = "a=b; ".repeat(20_000);
+
+    let frame = frames::headers(1)
+        .request("GET", "https://http2.akamai.com/")
+        .field("cookie", &cookie[..])
+        .eos();
+
+    codec.buffer(frame.into()).unwrap();
+    poll_fn(|cx| codec.flush(cx)).await.unwrap();
+
+    let mut frames = 0;
+
+    loop {
+        let mut head = [0; 9];
+        srv.read_exact(&mut head).await.unwrap();
+
+        let len = (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize;
+        assert!(len <= codec.max_send_frame_size());
+
+        // HEADERS, then CONTINUATION frames
+        assert_eq!(head[3], if frames == 0 { 1 } else { 9 });
+
+        let mut payload = vec![0; len];
+        srv.read_exact(&mut payload).await.unwrap();
+
+        frames += 1;
+
+        // END_HEADERS
+        if head[4] & 0x4 == 0x4 {
+            break;
+        }
+    }
+
+    assert!(frames > 4, "frames={}", frames);
+}
+
+#[tokio::test]
+async fn write_large_header_field() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let cookie = "a=b; ".repeat(20_000);
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .field("cookie", &cookie[..])
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(204).eos()).await;
+    };
+
+    let client = async move {
+        let (mut client, mut conn) = client::handshake(io).await.expect("handshake");
+
+        let request = Request::builder()
+            .uri("https://http2.akamai.com/")
+            .header("cookie", &"a=b; ".repeat(20_000)[..])
+            .body(())
+            .unwrap();
+
+        let req = async {
+            let res = client
+                .send_request(request, true)
+                .expect("send_request1")
+                .0
+                .await;
+            let response = res.unwrap();
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        };
+
+        conn.drive(req).await;
+        conn.await.unwrap();
+    };
+
+    join(srv, client).await;
+}
+
+#[tokio::test]
+async fn write_padded_frames() {
+    use tokio::io::AsyncReadExt;
+
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let mut codec = Codec::new(io);
+
+    codec.set_padding(Padding::Bucket(64));
+
+    let headers = frames::headers(1).request("GET", "https://http2.akamai.com/");
+    codec.buffer(headers.into()).unwrap();
+
+    // Short payloads are copied to the write buffer, long ones are chained
+    // after it.
+    for &len in &[10, 1_000] {
+        let data = frames::data(1, vec![b'a'; len]).pad_len(7);
+        codec.buffer(data.into()).unwrap();
+        poll_fn(|cx| codec.flush(cx)).await.unwrap();
+    }
+
+    let mut frames = vec![];
+
+    for _ in 0..3 {
+        let mut head = [0; 9];
+        srv.read_exact(&mut head).await.unwrap();
+
+        let len = (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize;
+        let mut payload = vec![0; len];
+        srv.read_exact(&mut payload).await.unwrap();
+
+        // PADDED
+        assert_eq!(head[4] & 0x8, 0x8);
+
+        let pad_len = payload[0] as usize;
+        assert!(payload[len - pad_len..].iter().all(|&b| b == 0));
+
+        frames.push((head[3], len, pad_len));
+    }
+
+    // The HEADERS payload is rounded up to the bucket size
+    assert_eq!(frames[0].0, 1);
+    assert_eq!(frames[0].1 % 64, 0);
+
+    // DATA frames are padded as requested
+    assert_eq!(frames[1], (0, 1 + 10 + 7, 7));
+    assert_eq!(frames[2], (0, 1 + 1_000 + 7, 7));
+}
+
+#[tokio::test]
+async fn write_headers_with_pad_len() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let mut codec = Codec::new(io);
+
+    // Without a padding policy, the frame carries the padding it asks for
+    let headers = frames::headers(1)
+        .request("GET", "https://http2.akamai.com/")
+        .pad_len(3);
+    codec.buffer(headers.into()).unwrap();
+    poll_fn(|cx| codec.flush(cx)).await.unwrap();
+
+    srv.recv_frame(
+        frames::headers(1)
+            .request("GET", "https://http2.akamai.com/")
+            .pad_len(3),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn write_headers_without_huffman() {
+    use tokio::io::AsyncReadExt;
+
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let mut codec = Codec::new(io);
+
+    codec.set_huffman(Huffman::Never);
+
+    let headers = frames::headers(1).request("GET", "https://http2.akamai.com/");
+    codec.buffer(headers.into()).unwrap();
+    poll_fn(|cx| codec.flush(cx)).await.unwrap();
+
+    let mut head = [0; 9];
+    srv.read_exact(&mut head).await.unwrap();
+
+    let len = (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize;
+    let mut payload = vec![0; len];
+    srv.read_exact(&mut payload).await.unwrap();
+
+    // The `:authority` value is sent as raw octets, its length prefix
+    // without the Huffman flag
+    let authority = b"http2.akamai.com";
+    let pos = payload
+        .windows(authority.len())
+        .position(|w| w == authority)
+        .expect("raw authority");
+    assert_eq!(payload[pos - 1], authority.len() as u8);
+}
+
+#[tokio::test]
+async fn write_queued_frames_at_once() {
+    let _ = env_logger::try_init();
+
+    let mut expect = vec![];
+    let mut frames = vec![];
+
+    // Short payloads are copied to the write buffer, the others are queued
+    // after it. The padding follows the payload.
+    for (i, &(len, pad_len)) in [(300, 0), (10, 0), (5_000, 4), (16_384, 0)]
+        .iter()
+        .enumerate()
+    {
+        let id = 2 * i as u8 + 1;
+        let byte = b'a' + i as u8;
+
+        let (flags, frame_len) = if pad_len > 0 {
+            (0x8, len + 1 + pad_len)
+        } else {
+            (0, len)
+        };
+
+        expect.extend_from_slice(&[
+            (frame_len >> 16) as u8,
+            (frame_len >> 8) as u8,
+            frame_len as u8,
+            0,
+            flags,
+            0,
+            0,
+            0,
+            id,
+        ]);
+
+        let mut frame = frames::data(id as u32, vec![byte; len]);
+
+        if pad_len > 0 {
+            expect.push(pad_len as u8);
+            frame = frame.pad_len(pad_len as u8);
+        }
+
+        expect.extend(std::iter::repeat(byte).take(len));
+        expect.extend(std::iter::repeat(0).take(pad_len));
+
+        frames.push(frame);
+    }
+
+    let mock = mock_io::Builder::new().write(&expect).build();
+    let mut codec = Codec::new(mock);
+
+    for frame in frames {
+        poll_fn(|cx| codec.poll_ready(cx)).await.unwrap();
+        codec.buffer(frame.into()).unwrap();
+    }
+
+    poll_fn(|cx| codec.flush(cx)).await.unwrap();
+
+    // All of the frames were gathered in a single vectored write
+    assert_eq!(codec.get_ref().num_writes(), 1);
+}
+
+#[tokio::test]
+async fn write_payload_exposing_only_first_slice() {
+    use bytes::{Buf, Bytes};
+    use std::io::Cursor;
+
+    let _ = env_logger::try_init();
+
+    let mut expect = vec![];
+    let mut frames = vec![];
+
+    // `Take` only exposes the first slice of the chain to a vectored write,
+    // so the rest of the payload must be written before the next frame.
+    for (i, &len) in [600, 300].iter().enumerate() {
+        let id = 2 * i as u8 + 1;
+        let half = len / 2;
+
+        expect.extend_from_slice(&[0, (len >> 8) as u8, len as u8, 0, 0, 0, 0, 0, id]);
+        expect.extend(std::iter::repeat(b'a' + id).take(half));
+        expect.extend(std::iter::repeat(b'b' + id).take(len - half));
+
+        let first = Cursor::new(Bytes::from(vec![b'a' + id; half]));
+        let second = Cursor::new(Bytes::from(vec![b'b' + id; len - half]));
+        let payload = first.chain(second).take(len);
+
+        frames.push(frame::Data::new(StreamId::from(id as u32), payload));
+    }
+
+    let mock = mock_io::Builder::new().write(&expect).build();
+    let mut codec = h2::Codec::new(mock);
+
+    for frame in frames {
+        poll_fn(|cx| codec.poll_ready(cx)).await.unwrap();
+        codec.buffer(frame.into()).unwrap();
+    }
+
+    poll_fn(|cx| codec.flush(cx)).await.unwrap();
+}
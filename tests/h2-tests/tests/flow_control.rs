@@ -1371,3 +1371,130 @@ async fn data_padding() {
 
     join(srv, h2).await;
 }
+
+#[tokio::test]
+async fn send_data_padding() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let mut settings = frame::Settings::default();
+    settings.set_initial_window_size(Some(18));
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake_with_settings(settings).await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("POST", "https://http2.akamai.com/")
+                .pad_len(5),
+        )
+        .await;
+        // The padding uses 6 octets of the stream window
+        srv.recv_frame(frames::data(1, "hello").pad_len(5)).await;
+        // Only 2 octets of the window are left for the padding
+        srv.recv_frame(frames::data(1, "world").pad_len(1).eos())
+            .await;
+        srv.send_frame(frames::headers(1).response(204).eos()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .padding(Padding::Fixed(5))
+            .handshake::<_, Bytes>(io)
+            .await
+            .unwrap();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+
+        let (response, mut stream) = client.send_request(request, false).unwrap();
+
+        stream.reserve_capacity(5);
+        let mut stream = h2.drive(util::wait_for_capacity(stream, 5)).await;
+        stream.send_data("hello".into(), false).unwrap();
+
+        stream.reserve_capacity(5);
+        let mut stream = h2.drive(util::wait_for_capacity(stream, 5)).await;
+        stream.send_data("world".into(), true).unwrap();
+
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        h2.await.unwrap();
+    };
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn recv_data_padding_is_released() {
+    let _ = env_logger::try_init();
+
+    // With the Pad Length octet, each frame uses 16_384 octets of window.
+    let payload = vec![0u8; 16_128];
+    let payload_len = payload.len();
+
+    let (io, mut srv) = mock::new();
+
+    let mock = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.send_frame(frames::data(1, &payload[..]).pad_len(255))
+            .await;
+        srv.send_frame(frames::data(1, &payload[..]).pad_len(255))
+            .await;
+        srv.send_frame(frames::data(1, &payload[..]).pad_len(255))
+            .await;
+        // The released data plus the padding of the three frames
+        srv.recv_frame(frames::window_update(0, 32_256 + 768)).await;
+        srv.recv_frame(frames::window_update(1, 32_256 + 768)).await;
+        srv.send_frame(frames::data(1, &payload[..]).eos()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, h2) = client::handshake(io).await.unwrap();
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+
+        let req = async move {
+            let resp = client.send_request(request, true).unwrap().0.await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let mut body = resp.into_parts().1;
+
+            let buf = body.next().await.unwrap().unwrap();
+            assert_eq!(buf.len(), payload_len);
+
+            let buf = body.next().await.unwrap().unwrap();
+            assert_eq!(buf.len(), payload_len);
+
+            let buf = body.next().await.unwrap().unwrap();
+            assert_eq!(buf.len(), payload_len);
+            body.release_capacity()
+                .release_capacity(buf.len() * 2)
+                .unwrap();
+
+            let buf = body.next().await.unwrap().unwrap();
+            assert_eq!(buf.len(), payload_len);
+        };
+
+        join(
+            async move {
+                h2.await.unwrap();
+            },
+            req,
+        )
+        .await
+    };
+    join(mock, h2).await;
+}
@@ -0,0 +1,170 @@
+#![deny(warnings)]
+
+use futures::channel::oneshot;
+use futures::future::join;
+use futures::StreamExt;
+use h2_support::prelude::*;
+
+#[tokio::test]
+async fn coalesced_frames_are_flushed() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(frames::headers(1).request("POST", "https://http2.akamai.com/"))
+            .await;
+        srv.recv_frame(frames::data(1, "hello").eos()).await;
+        srv.send_frame(frames::headers(1).response(204).eos()).await;
+    };
+
+    let h2 = async move {
+        // Far less than this is ever buffered, so the frames are only
+        // flushed once the connection is polled again.
+        let (mut client, mut h2) = client::Builder::new()
+            .flush_strategy(FlushStrategy::Coalesce(1 << 20))
+            .handshake::<_, Bytes>(io)
+            .await
+            .unwrap();
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+
+        let (response, mut stream) = client.send_request(request, false).unwrap();
+        stream.send_data("hello".into(), true).unwrap();
+
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        h2.await.unwrap();
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn coalesce_small_writes_into_one_flush() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let (handshaken_tx, handshaken_rx) = oneshot::channel();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+
+        let num_writes = srv.num_writes();
+        handshaken_tx.send(()).unwrap();
+
+        for id in (1..7).step_by(2) {
+            srv.recv_frame(
+                frames::headers(id)
+                    .request("GET", "https://http2.akamai.com/")
+                    .eos(),
+            )
+            .await;
+        }
+
+        // The requests were queued on separate polls of the connection,
+        // but written at once.
+        assert_eq!(srv.num_writes(), num_writes + 1);
+
+        for id in (1..7).step_by(2) {
+            srv.send_frame(frames::headers(id).response(204).eos())
+                .await;
+        }
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .flush_strategy(FlushStrategy::Coalesce(1 << 20))
+            .handshake::<_, Bytes>(io)
+            .await
+            .unwrap();
+
+        // Only count the writes made after the handshake
+        h2.drive(handshaken_rx).await.unwrap();
+
+        let mut responses = vec![];
+
+        // Poll the connection after each request
+        h2.drive(async {
+            for _ in 0..3 {
+                let request = Request::builder()
+                    .uri("https://http2.akamai.com/")
+                    .body(())
+                    .unwrap();
+
+                let (response, _) = client.send_request(request, true).unwrap();
+                responses.push(response);
+
+                util::yield_once().await;
+            }
+        })
+        .await;
+
+        for response in responses {
+            let response = h2.drive(response).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        }
+
+        drop(client);
+        h2.await.unwrap();
+    };
+
+    join(srv, h2).await;
+}
+
+#[tokio::test]
+async fn flush_on_idle_sends_all_responses() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+
+        for id in (1..7).step_by(2) {
+            client
+                .send_frame(
+                    frames::headers(id)
+                        .request("GET", "https://example.com/")
+                        .eos(),
+                )
+                .await;
+        }
+
+        for id in (1..7).step_by(2) {
+            client
+                .recv_frame(frames::headers(id).response(200).eos())
+                .await;
+        }
+    };
+
+    let srv = async move {
+        let mut srv = server::Builder::new()
+            .flush_strategy(FlushStrategy::OnIdle)
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let mut streams = vec![];
+
+        for _ in 0..3 {
+            let (_, stream) = srv.next().await.unwrap().unwrap();
+            streams.push(stream);
+        }
+
+        for mut stream in streams {
+            let rsp = http::Response::builder().status(200).body(()).unwrap();
+            stream.send_response(rsp, true).unwrap();
+        }
+
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
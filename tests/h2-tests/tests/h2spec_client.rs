@@ -0,0 +1,2163 @@
+//! The h2spec cases, run against the client.
+//!
+//! The mock plays the server: once the handshake is complete, it reads a
+//! `GET https://example.com/` request on stream 1 and sends the frames of
+//! the case. Modules and tests are named and tagged as in `h2spec_server`,
+//! and run with `cargo test --test h2spec_client http2_6_5::`.
+//!
+//! As in h2spec, an error is expected as a `GOAWAY` or `RST_STREAM` frame
+//! carrying its code, and frames the client sends before it are skipped.
+
+#![deny(warnings)]
+
+use futures::future::join;
+use h2_support::prelude::*;
+
+/// A `200` response.
+const OK: &[(&str, &str)] = &[(":status", "200")];
+
+const PING: [u8; 8] = *b"h2spec\0\0";
+
+/// Runs `server` against a client with the default settings, once the client
+/// sent its request. Returns the response body, if the client received the
+/// whole response.
+async fn run<F, T>(server: F) -> Option<Bytes>
+where
+    F: FnOnce(mock::Handle) -> T,
+    T: Future<Output = ()>,
+{
+    run_with(client::Builder::new(), server).await
+}
+
+async fn run_with<F, T>(builder: client::Builder, server: F) -> Option<Bytes>
+where
+    F: FnOnce(mock::Handle) -> T,
+    T: Future<Output = ()>,
+{
+    let _ = env_logger::try_init();
+    let (io, mut handle) = mock::new();
+
+    let server = async move {
+        handle.assert_client_handshake().await;
+        handle
+            .recv_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        server(handle).await;
+    };
+
+    join(server, request(builder, io)).await.1
+}
+
+/// The client under test: it sends a single request and reads the whole
+/// response. The connection is kept open until the server closes it.
+async fn request(builder: client::Builder, io: mock::Mock) -> Option<Bytes> {
+    let (client, connection) = builder.handshake::<_, Bytes>(io).await.ok()?;
+    let mut send_request = client.clone();
+
+    let connection = async move {
+        let _ = connection.await;
+        drop(client);
+    };
+
+    let request = async move {
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+
+        let (response, _) = send_request.send_request(request, true).ok()?;
+        let mut body = response.await.ok()?.into_body();
+        let mut buf = vec![];
+
+        while let Some(res) = body.data().await {
+            let chunk = res.ok()?;
+            let _ = body.release_capacity().release_capacity(chunk.len());
+            buf.extend_from_slice(&chunk);
+        }
+
+        body.trailers().await.ok()?;
+        Some(Bytes::from(buf))
+    };
+
+    join(connection, request).await.1
+}
+
+/// Sends the response to the request on stream `id`, with a `hello` body.
+async fn send_response(server: &mut mock::Handle, id: u32) {
+    server.send_bytes(&response(id, 0)).await;
+    server.send_frame(frames::data(id, "hello").eos()).await;
+}
+
+/// Checks that the client received the response sent by `send_response`.
+fn assert_response(body: Option<Bytes>) {
+    assert_eq!(body.as_ref().map(|b| &b[..]), Some(&b"hello"[..]));
+}
+
+/// A complete `HEADERS` frame carrying `block`.
+fn headers(id: u32, flags: u8, block: &[u8]) -> Vec<u8> {
+    raw::frame(raw::HEADERS, raw::END_HEADERS | flags, id, block)
+}
+
+/// A `HEADERS` frame with a `200` response on stream `id`.
+fn response(id: u32, flags: u8) -> Vec<u8> {
+    headers(id, flags, &raw::header_block(OK))
+}
+
+/// A `200` response with `fields` added after the `:status` field, the body
+/// of which is still to be sent.
+///
+/// A malformed response must not end the stream, or the stream is closed by
+/// the time the client could reset it.
+fn response_with(id: u32, fields: &[(&str, &str)]) -> Vec<u8> {
+    let fields: Vec<_> = OK.iter().chain(fields).cloned().collect();
+    headers(id, 0, &raw::header_block(&fields))
+}
+
+/// A complete `PUSH_PROMISE` frame, promising stream `promised_id` for a
+/// request with `fields`.
+fn push_promise(id: u32, promised_id: u32, fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut payload = promised_id.to_be_bytes().to_vec();
+    payload.extend(raw::header_block(fields));
+    raw::frame(raw::PUSH_PROMISE, raw::END_HEADERS, id, &payload)
+}
+
+fn priority(dependency: u32, exclusive: bool, weight: u8) -> Vec<u8> {
+    let mut dst = dependency.to_be_bytes().to_vec();
+
+    if exclusive {
+        dst[0] |= 0x80;
+    }
+
+    dst.push(weight);
+    dst
+}
+
+fn settings(params: &[(u16, u32)]) -> Vec<u8> {
+    let mut payload = vec![];
+
+    for &(id, val) in params {
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(&val.to_be_bytes());
+    }
+
+    raw::frame(raw::SETTINGS, 0, 0, &payload)
+}
+
+fn rst_stream(id: u32, code: u32) -> Vec<u8> {
+    raw::frame(raw::RST_STREAM, 0, id, &code.to_be_bytes())
+}
+
+fn window_update(id: u32, increment: u32) -> Vec<u8> {
+    raw::frame(raw::WINDOW_UPDATE, 0, id, &increment.to_be_bytes())
+}
+
+/// RFC 7540 §3.5: HTTP/2 Connection Preface
+mod generic_1 {
+    use super::*;
+
+    /// generic/1/1: Sends a server connection preface
+    #[tokio::test]
+    async fn server_connection_preface() {
+        let body = run(|mut server| async move {
+            server.assert_no_error().await;
+            send_response(&mut server, 1).await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §5: Streams and Multiplexing
+mod generic_2 {
+    use super::*;
+
+    /// generic/2/1: Sends a PRIORITY frame on idle stream
+    #[tokio::test]
+    async fn priority_on_idle_stream() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 3, &priority(0, false, 15));
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/2: Sends a WINDOW_UPDATE frame on half-closed (local) stream
+    #[tokio::test]
+    async fn window_update_on_half_closed_local_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(1, 1)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/3: Sends a PRIORITY frame on half-closed (local) stream
+    #[tokio::test]
+    async fn priority_on_half_closed_local_stream() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/4: Sends a RST_STREAM frame on half-closed (local) stream
+    #[tokio::test]
+    async fn rst_stream_on_half_closed_local_stream() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&rst_stream(1, 0x8)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, None);
+    }
+
+    /// generic/2/5: Sends a PRIORITY frame on closed stream
+    #[tokio::test]
+    async fn priority_on_closed_stream() {
+        let body = run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §6.1: DATA
+mod generic_3_1 {
+    use super::*;
+
+    /// generic/3.1/1: Sends a DATA frame
+    #[tokio::test]
+    async fn data_frame() {
+        let body = run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// generic/3.1/2: Sends multiple DATA frames
+    #[tokio::test]
+    async fn multiple_data_frames() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_frame(frames::data(1, "hel")).await;
+            server.send_frame(frames::data(1, "lo").eos()).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// generic/3.1/3: Sends a DATA frame with padding
+    #[tokio::test]
+    async fn data_frame_with_padding() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            let frame = raw::frame(
+                raw::DATA,
+                raw::END_STREAM | raw::PADDED,
+                1,
+                &[4, b'h', b'e', b'l', b'l', b'o', 0, 0, 0, 0],
+            );
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §6.2: HEADERS
+mod generic_3_2 {
+    use super::*;
+
+    /// generic/3.2/1: Sends a HEADERS frame
+    #[tokio::test]
+    async fn headers_frame() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, raw::END_STREAM)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+
+    /// generic/3.2/2: Sends a HEADERS frame with padding
+    #[tokio::test]
+    async fn headers_frame_with_padding() {
+        let body = run(|mut server| async move {
+            let mut payload = vec![4];
+            payload.extend(raw::header_block(OK));
+            payload.extend(&[0; 4]);
+            let frame = headers(1, raw::END_STREAM | raw::PADDED, &payload);
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+
+    /// generic/3.2/3: Sends a HEADERS frame with priority
+    #[tokio::test]
+    async fn headers_frame_with_priority() {
+        let body = run(|mut server| async move {
+            let mut payload = priority(0, false, 255);
+            payload.extend(raw::header_block(OK));
+            let frame = headers(1, raw::END_STREAM | raw::PRIORITY_FLAG, &payload);
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+}
+
+/// RFC 7540 §6.3: PRIORITY
+mod generic_3_3 {
+    use super::*;
+
+    /// generic/3.3/1: Sends a PRIORITY frame with priority 1
+    #[tokio::test]
+    async fn priority_1() {
+        let body = run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 0));
+            server.send_bytes(&frame).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// generic/3.3/2: Sends a PRIORITY frame with priority 256
+    #[tokio::test]
+    async fn priority_256() {
+        let body = run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 255));
+            server.send_bytes(&frame).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// generic/3.3/3: Sends a PRIORITY frame with stream dependency
+    #[tokio::test]
+    async fn priority_with_dependency() {
+        let body = run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 3, &priority(1, false, 15));
+            server.send_bytes(&frame).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// generic/3.3/4: Sends a PRIORITY frame with exclusive
+    #[tokio::test]
+    async fn priority_exclusive() {
+        let body = run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 3, &priority(1, true, 15));
+            server.send_bytes(&frame).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §6.4: RST_STREAM
+mod generic_3_4 {
+    use super::*;
+
+    /// generic/3.4/1: Sends a RST_STREAM frame
+    #[tokio::test]
+    async fn rst_stream_frame() {
+        let body = run(|mut server| async move {
+            server.send_frame(frames::reset(1).cancel()).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, None);
+    }
+}
+
+/// RFC 7540 §6.5: SETTINGS
+mod generic_3_5 {
+    use super::*;
+
+    /// generic/3.5/1: Sends a SETTINGS frame
+    #[tokio::test]
+    async fn settings_frame() {
+        let body = run(|mut server| async move {
+            // SETTINGS_ENABLE_PUSH is only sent by clients.
+            server.send_bytes(&settings(&[(0x3, 100)])).await;
+            server.recv_frame(frames::settings_ack()).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §6.6: PUSH_PROMISE
+mod generic_3_6 {
+    use super::*;
+
+    /// generic/3.6/1: Sends a PUSH_PROMISE frame
+    #[tokio::test]
+    async fn push_promise_frame() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_bytes(&push_promise(1, 2, raw::GET)).await;
+            server.send_frame(frames::data(1, "hello").eos()).await;
+            server.send_bytes(&response(2, raw::END_STREAM)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §6.7: PING
+mod generic_3_7 {
+    use super::*;
+
+    /// generic/3.7/1: Sends a PING frame
+    #[tokio::test]
+    async fn ping_frame() {
+        run(|mut server| async move {
+            server.ping_pong(PING).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.8: GOAWAY
+mod generic_3_8 {
+    use super::*;
+
+    /// generic/3.8/1: Sends a GOAWAY frame
+    #[tokio::test]
+    async fn go_away_frame() {
+        let body = run(|mut server| async move {
+            server.send_frame(frames::go_away(1)).await;
+            send_response(&mut server, 1).await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §6.9: WINDOW_UPDATE
+mod generic_3_9 {
+    use super::*;
+
+    /// generic/3.9/1: Sends a WINDOW_UPDATE frame with stream ID 0
+    #[tokio::test]
+    async fn window_update_on_connection() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(0, 1)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/3.9/2: Sends a WINDOW_UPDATE frame with arbitrary stream ID
+    #[tokio::test]
+    async fn window_update_on_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(1, 1)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.10: CONTINUATION
+mod generic_3_10 {
+    use super::*;
+
+    /// generic/3.10/1: Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn continuation_frame() {
+        let body = run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "200"), ("x-dummy", "dummy")]);
+            let (head, tail) = block.split_at(4);
+            let frame = raw::frame(raw::HEADERS, raw::END_STREAM, 1, head);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, tail);
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+
+    /// generic/3.10/2: Sends multiple CONTINUATION frames
+    #[tokio::test]
+    async fn multiple_continuation_frames() {
+        let body = run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "200"), ("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block[..4]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, 0, 1, &block[4..8]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block[8..]);
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+}
+
+/// RFC 7540 §8.1: HTTP Request/Response Exchange
+mod generic_4 {
+    use super::*;
+
+    /// generic/4/1: Sends a response without a body
+    #[tokio::test]
+    async fn response_without_body() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, raw::END_STREAM)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+
+    /// generic/4/2: Sends a response with a body
+    #[tokio::test]
+    async fn response_with_body() {
+        let body = run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// generic/4/3: Sends a response with trailers
+    #[tokio::test]
+    async fn response_with_trailers() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_frame(frames::data(1, "hello")).await;
+            let block = raw::header_block(&[("x-trailer", "trailer")]);
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7541 §6: Binary Format
+mod generic_5 {
+    use super::*;
+
+    /// `:status: 200`, from the static table.
+    const INDEXED: &[u8] = &[0x88];
+
+    /// `custom-key` and `custom-value`, Huffman encoded (RFC 7541 §C.4.3).
+    const HUFFMAN_CUSTOM: &[u8] = &[
+        0x88, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f, 0x89, 0x25, 0xa8, 0x49, 0xe9, 0x5b,
+        0xb8, 0xe8, 0xb4, 0xbf,
+    ];
+
+    /// `no-cache`, Huffman encoded (RFC 7541 §C.4.2).
+    const HUFFMAN_NO_CACHE: &[u8] = &[0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf];
+
+    /// `custom-key: custom-value`, without Huffman coding.
+    const CUSTOM: &[u8] = b"\x0acustom-key\x0ccustom-value";
+
+    async fn send_response(block: &[&[u8]]) {
+        let body = run(|mut server| async move {
+            let frame = headers(1, raw::END_STREAM, &block.concat());
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+
+    /// generic/5/1: Sends a indexed header field representation
+    #[tokio::test]
+    async fn indexed() {
+        send_response(&[INDEXED]).await;
+    }
+
+    /// generic/5/2: Sends a literal header field with incremental indexing -
+    /// indexed name
+    #[tokio::test]
+    async fn incremental_indexing_indexed_name() {
+        // `cache-control` (index 24)
+        send_response(&[INDEXED, b"\x58\x08no-cache"]).await;
+    }
+
+    /// generic/5/3: Sends a literal header field with incremental indexing -
+    /// indexed name (huffman encoded)
+    #[tokio::test]
+    async fn incremental_indexing_indexed_name_huffman() {
+        send_response(&[INDEXED, &[0x58], HUFFMAN_NO_CACHE]).await;
+    }
+
+    /// generic/5/4: Sends a literal header field with incremental indexing -
+    /// new name
+    #[tokio::test]
+    async fn incremental_indexing_new_name() {
+        send_response(&[INDEXED, &[0x40], CUSTOM]).await;
+    }
+
+    /// generic/5/5: Sends a literal header field with incremental indexing -
+    /// new name (huffman encoded)
+    #[tokio::test]
+    async fn incremental_indexing_new_name_huffman() {
+        send_response(&[INDEXED, &[0x40], HUFFMAN_CUSTOM]).await;
+    }
+
+    /// generic/5/6: Sends a literal header field without indexing - indexed
+    /// name
+    #[tokio::test]
+    async fn without_indexing_indexed_name() {
+        send_response(&[INDEXED, b"\x0f\x09\x08no-cache"]).await;
+    }
+
+    /// generic/5/7: Sends a literal header field without indexing - indexed
+    /// name (huffman encoded)
+    #[tokio::test]
+    async fn without_indexing_indexed_name_huffman() {
+        send_response(&[INDEXED, &[0x0f, 0x09], HUFFMAN_NO_CACHE]).await;
+    }
+
+    /// generic/5/8: Sends a literal header field without indexing - new name
+    #[tokio::test]
+    async fn without_indexing_new_name() {
+        send_response(&[INDEXED, &[0x00], CUSTOM]).await;
+    }
+
+    /// generic/5/9: Sends a literal header field without indexing - new name
+    /// (huffman encoded)
+    #[tokio::test]
+    async fn without_indexing_new_name_huffman() {
+        send_response(&[INDEXED, &[0x00], HUFFMAN_CUSTOM]).await;
+    }
+
+    /// generic/5/10: Sends a literal header field never indexed - indexed
+    /// name
+    #[tokio::test]
+    async fn never_indexed_indexed_name() {
+        send_response(&[INDEXED, b"\x1f\x09\x08no-cache"]).await;
+    }
+
+    /// generic/5/11: Sends a literal header field never indexed - indexed
+    /// name (huffman encoded)
+    #[tokio::test]
+    async fn never_indexed_indexed_name_huffman() {
+        send_response(&[INDEXED, &[0x1f, 0x09], HUFFMAN_NO_CACHE]).await;
+    }
+
+    /// generic/5/12: Sends a literal header field never indexed - new name
+    #[tokio::test]
+    async fn never_indexed_new_name() {
+        send_response(&[INDEXED, &[0x10], CUSTOM]).await;
+    }
+
+    /// generic/5/13: Sends a literal header field never indexed - new name
+    /// (huffman encoded)
+    #[tokio::test]
+    async fn never_indexed_new_name_huffman() {
+        send_response(&[INDEXED, &[0x10], HUFFMAN_CUSTOM]).await;
+    }
+
+    /// generic/5/14: Sends a dynamic table size update
+    #[tokio::test]
+    async fn dynamic_table_size_update() {
+        // Size 0
+        send_response(&[&[0x20], INDEXED]).await;
+    }
+
+    /// generic/5/15: Sends multiple dynamic table size update
+    #[tokio::test]
+    async fn multiple_dynamic_table_size_updates() {
+        // Size 0, then 4,096
+        send_response(&[&[0x20, 0x3f, 0xe1, 0x1f], INDEXED]).await;
+    }
+}
+
+/// RFC 7540 §3.5: HTTP/2 Connection Preface
+mod http2_3_5 {
+    use super::*;
+
+    /// http2/3.5/1: Sends server connection preface
+    #[tokio::test]
+    async fn server_connection_preface() {
+        run(|mut server| async move {
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/3.5/2: Sends invalid connection preface
+    #[tokio::test]
+    async fn invalid_connection_preface() {
+        let _ = env_logger::try_init();
+        let (io, mut server) = mock::new();
+
+        let server = async move {
+            server.read_preface().await.unwrap();
+            // The server preface must be a SETTINGS frame.
+            server.send_frame(frames::ping(PING)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        };
+
+        join(server, request(client::Builder::new(), io)).await;
+    }
+}
+
+/// RFC 7540 §4.1: Frame Format
+mod http2_4_1 {
+    use super::*;
+
+    /// http2/4.1/1: Sends a frame with unknown type
+    #[tokio::test]
+    async fn unknown_type() {
+        run(|mut server| async move {
+            server.send_bytes(&raw::frame(0x16, 0, 0, b"unknown")).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/4.1/2: Sends a frame with undefined flag
+    #[tokio::test]
+    async fn undefined_flag() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&raw::frame(raw::PING, 0x16, 0, &PING))
+                .await;
+            server.recv_frame(frames::ping(PING).pong()).await;
+        })
+        .await;
+    }
+
+    /// http2/4.1/3: Sends a frame with reserved field bit
+    #[tokio::test]
+    async fn reserved_bit() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&raw::frame(raw::PING, 0, 1 << 31, &PING))
+                .await;
+            server.recv_frame(frames::ping(PING).pong()).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §4.2: Frame Size
+mod http2_4_2 {
+    use super::*;
+
+    /// http2/4.2/1: Sends a DATA frame with 2^14 octets in length
+    #[tokio::test]
+    async fn data_frame_of_max_size() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            let frame = raw::frame(raw::DATA, raw::END_STREAM, 1, &[b'x'; 16_384]);
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body.map(|b| b.len()), Some(16_384));
+    }
+
+    /// http2/4.2/2: Sends a large size DATA frame that exceeds the
+    /// SETTINGS_MAX_FRAME_SIZE
+    #[tokio::test]
+    async fn data_frame_too_large() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            let frame = raw::frame(raw::DATA, raw::END_STREAM, 1, &[b'x'; 16_385]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_stream_error(1, &[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/4.2/3: Sends a large size HEADERS frame that exceeds the
+    /// SETTINGS_MAX_FRAME_SIZE
+    #[tokio::test]
+    async fn headers_frame_too_large() {
+        run(|mut server| async move {
+            let value = "x".repeat(16_384);
+            server
+                .send_bytes(&response_with(1, &[("x-dummy", &value)]))
+                .await;
+            server
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §4.3: Header Compression and Decompression
+mod http2_4_3 {
+    use super::*;
+
+    /// http2/4.3/1: Sends invalid header block fragment
+    #[tokio::test]
+    async fn invalid_header_block_fragment() {
+        run(|mut server| async move {
+            // A literal header field with incremental indexing, without the
+            // name and the value.
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x40]))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/4.3/2: Sends a PRIORITY frame while sending the header blocks
+    #[tokio::test]
+    async fn priority_in_header_block() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            server
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/4.3/3: Sends a PUSH_PROMISE frame to another stream while
+    /// sending the header blocks
+    #[tokio::test]
+    async fn push_promise_in_header_block() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            server
+                .send_bytes(&raw::frame(raw::HEADERS, 0, 1, &block))
+                .await;
+            server.send_bytes(&push_promise(1, 2, raw::GET)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.1: Stream States
+mod http2_5_1 {
+    use super::*;
+
+    /// http2/5.1/1: idle: Sends a DATA frame
+    #[tokio::test]
+    async fn idle_data() {
+        run(|mut server| async move {
+            server.send_frame(frames::data(3, "test")).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/2: idle: Sends a RST_STREAM frame
+    #[tokio::test]
+    async fn idle_rst_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&rst_stream(3, 0x8)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/3: idle: Sends a WINDOW_UPDATE frame
+    #[tokio::test]
+    async fn idle_window_update() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(3, 100)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/4: idle: Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn idle_continuation() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 3, &block);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/5: reserved (remote): Sends a DATA frame
+    #[tokio::test]
+    async fn reserved_remote_data() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_bytes(&push_promise(1, 2, raw::GET)).await;
+            server.send_frame(frames::data(2, "test")).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/6: half closed (local): Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn half_closed_local_continuation() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            let block = raw::header_block(&[("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/7: closed: Sends a DATA frame
+    #[tokio::test]
+    async fn closed_data() {
+        run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            server.send_frame(frames::data(1, "test")).await;
+            server
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/8: closed: Sends a HEADERS frame
+    #[tokio::test]
+    async fn closed_headers() {
+        run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            server.send_bytes(&response(1, raw::END_STREAM)).await;
+            server
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/9: closed: Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn closed_continuation() {
+        run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            let block = raw::header_block(&[("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.1.1: Stream Identifiers
+mod http2_5_1_1 {
+    use super::*;
+
+    /// http2/5.1.1/1: Sends odd-numbered promised stream identifier
+    #[tokio::test]
+    async fn odd_promised_stream_id() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_bytes(&push_promise(1, 3, raw::GET)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1.1/2: Sends promised stream identifier that is numerically
+    /// smaller than previous
+    #[tokio::test]
+    async fn decreasing_promised_stream_id() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_bytes(&push_promise(1, 4, raw::GET)).await;
+            server.send_bytes(&push_promise(1, 2, raw::GET)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.4.1: Connection Error Handling
+mod http2_5_4_1 {
+    use super::*;
+
+    /// http2/5.4.1/1: Sends an invalid PING frame for connection close
+    #[tokio::test]
+    async fn invalid_ping_closes_connection() {
+        run(|mut server| async move {
+            server.send_bytes(&raw::frame(raw::PING, 0, 3, &PING)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.4.1/2: Sends an invalid PING frame to receive GOAWAY frame
+    #[tokio::test]
+    async fn invalid_ping_sends_go_away() {
+        let body = run(|mut server| async move {
+            send_response(&mut server, 1).await;
+            server.send_bytes(&raw::frame(raw::PING, 0, 3, &PING)).await;
+            server.recv_frame(frames::go_away(0).protocol_error()).await;
+            server.recv_eof().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §5.5: Extending HTTP/2
+mod http2_5_5 {
+    use super::*;
+
+    /// http2/5.5/1: Sends an unknown extension frame
+    #[tokio::test]
+    async fn unknown_extension_frame() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&raw::frame(0xff, 0, 0, b"unknown")).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// http2/5.5/2: Sends an unknown extension frame in the middle of a header
+    /// block
+    #[tokio::test]
+    async fn unknown_extension_frame_in_header_block() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            server
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            server.send_bytes(&raw::frame(0xff, 0, 0, b"unknown")).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.1: DATA
+mod http2_6_1 {
+    use super::*;
+
+    /// http2/6.1/1: Sends a DATA frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn data_on_stream_0() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server
+                .send_bytes(&raw::frame(raw::DATA, 0, 0, b"test"))
+                .await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.1/2: Sends a DATA frame on the stream that is not in "open" or
+    /// "half-closed (local)" state
+    #[tokio::test]
+    async fn data_before_response() {
+        run(|mut server| async move {
+            // The response has not started, the stream cannot carry data yet.
+            server.send_frame(frames::data(1, "test")).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.1/3: Sends a DATA frame with invalid pad length
+    #[tokio::test]
+    async fn data_with_invalid_pad_length() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            // Pad Length of 6 for a payload of 5 octets.
+            let frame = raw::frame(
+                raw::DATA,
+                raw::END_STREAM | raw::PADDED,
+                1,
+                &[6, 0, 0, 0, 0],
+            );
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.2: HEADERS
+mod http2_6_2 {
+    use super::*;
+
+    /// http2/6.2/1: Sends a HEADERS frame without the END_HEADERS flag, and a
+    /// PRIORITY frame
+    #[tokio::test]
+    async fn priority_after_headers_without_end_headers() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            server
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.2/2: Sends a HEADERS frame to another stream while sending a
+    /// HEADERS frame
+    #[tokio::test]
+    async fn headers_to_another_stream_in_header_block() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            server
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            server.send_bytes(&response(3, raw::END_STREAM)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.2/3: Sends a HEADERS frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn headers_on_stream_0() {
+        run(|mut server| async move {
+            server.send_bytes(&response(0, raw::END_STREAM)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.2/4: Sends a HEADERS frame with invalid pad length
+    #[tokio::test]
+    async fn headers_with_invalid_pad_length() {
+        run(|mut server| async move {
+            let block = raw::header_block(OK);
+            let mut payload = vec![block.len() as u8 + 1];
+            payload.extend(block);
+            let frame = headers(1, raw::END_STREAM | raw::PADDED, &payload);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.3: PRIORITY
+mod http2_6_3 {
+    use super::*;
+
+    /// http2/6.3/1: Sends a PRIORITY frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn priority_on_stream_0() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 0, &priority(1, false, 15));
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.3/2: Sends a PRIORITY frame with a length other than 5 octets
+    #[tokio::test]
+    async fn priority_with_bad_length() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &[0x80, 0, 0, 0]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_stream_error(1, &[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.4: RST_STREAM
+mod http2_6_4 {
+    use super::*;
+
+    /// http2/6.4/1: Sends a RST_STREAM frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn rst_stream_on_stream_0() {
+        run(|mut server| async move {
+            server.send_bytes(&rst_stream(0, 0x8)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.4/2: Sends a RST_STREAM frame on a idle stream
+    #[tokio::test]
+    async fn rst_stream_on_idle_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&rst_stream(3, 0x8)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.4/3: Sends a RST_STREAM frame with a length other than 4
+    /// octets
+    #[tokio::test]
+    async fn rst_stream_with_bad_length() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::RST_STREAM, 0, 1, &[0, 0, 0]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5: SETTINGS
+mod http2_6_5 {
+    use super::*;
+
+    /// http2/6.5/1: Sends a SETTINGS frame with ACK flag and payload
+    #[tokio::test]
+    async fn settings_ack_with_payload() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::SETTINGS, raw::ACK, 0, &[0, 0x3, 0, 0, 0, 100]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5/2: Sends a SETTINGS frame with a stream identifier other
+    /// than 0x0
+    #[tokio::test]
+    async fn settings_on_stream() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::SETTINGS, 0, 1, &[0, 0x3, 0, 0, 0, 100]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5/3: Sends a SETTINGS frame with a length other than a
+    /// multiple of 6 octets
+    #[tokio::test]
+    async fn settings_with_bad_length() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::SETTINGS, 0, 0, &[0, 0x3, 0, 0, 100]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5.2: Defined SETTINGS Parameters
+mod http2_6_5_2 {
+    use super::*;
+
+    /// http2/6.5.2/1: SETTINGS_ENABLE_PUSH (0x2): Sends the value other than
+    /// 0 or 1
+    #[tokio::test]
+    async fn enable_push_invalid() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x2, 2)])).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/2: SETTINGS_INITIAL_WINDOW_SIZE (0x4): Sends the value
+    /// above the maximum flow control window size
+    #[tokio::test]
+    async fn initial_window_size_too_large() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x4, 1 << 31)])).await;
+            server
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/3: SETTINGS_MAX_FRAME_SIZE (0x5): Sends the value below
+    /// the initial value
+    #[tokio::test]
+    async fn max_frame_size_too_small() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x5, 16_383)])).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/4: SETTINGS_MAX_FRAME_SIZE (0x5): Sends the value above
+    /// the maximum allowed frame size
+    #[tokio::test]
+    async fn max_frame_size_too_large() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x5, 1 << 24)])).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/5: Sends a SETTINGS frame with unknown identifier
+    #[tokio::test]
+    async fn unknown_identifier() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0xff, 1)])).await;
+            server.recv_frame(frames::settings_ack()).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5.3: Settings Synchronization
+mod http2_6_5_3 {
+    use super::*;
+
+    /// http2/6.5.3/1: Sends multiple values of SETTINGS_INITIAL_WINDOW_SIZE
+    #[tokio::test]
+    async fn multiple_initial_window_sizes() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x4, 100), (0x4, 1)])).await;
+            server.recv_frame(frames::settings_ack()).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.3/2: Sends a SETTINGS frame without ACK flag
+    #[tokio::test]
+    async fn settings_is_acked() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x3, 100)])).await;
+            server.recv_frame(frames::settings_ack()).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.7: PING
+mod http2_6_7 {
+    use super::*;
+
+    /// http2/6.7/1: Sends a PING frame
+    #[tokio::test]
+    async fn ping() {
+        run(|mut server| async move {
+            server.ping_pong(PING).await;
+        })
+        .await;
+    }
+
+    /// http2/6.7/2: Sends a PING frame with ACK
+    #[tokio::test]
+    async fn ping_ack() {
+        run(|mut server| async move {
+            // An unsolicited ACK is not answered.
+            server.send_frame(frames::ping(*b"unsolici").pong()).await;
+            server.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/6.7/3: Sends a PING frame with a stream identifier field value
+    /// other than 0x0
+    #[tokio::test]
+    async fn ping_on_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&raw::frame(raw::PING, 0, 1, &PING)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.7/4: Sends a PING frame with a length field value other than 8
+    #[tokio::test]
+    async fn ping_with_bad_length() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&raw::frame(raw::PING, 0, 0, &PING[..6]))
+                .await;
+            server
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.8: GOAWAY
+mod http2_6_8 {
+    use super::*;
+
+    /// http2/6.8/1: Sends a GOAWAY frame with a stream identifier other than
+    /// 0x0
+    #[tokio::test]
+    async fn go_away_on_stream() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::GOAWAY, 0, 1, &[0; 8]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9: WINDOW_UPDATE
+mod http2_6_9 {
+    use super::*;
+
+    /// http2/6.9/1: Sends a WINDOW_UPDATE frame with a flow control window
+    /// increment of 0
+    #[tokio::test]
+    async fn zero_increment_on_connection() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(0, 0)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.9/2: Sends a WINDOW_UPDATE frame with a flow control window
+    /// increment of 0 on a stream
+    #[tokio::test]
+    async fn zero_increment_on_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(1, 0)).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.9/3: Sends a WINDOW_UPDATE frame with a length other than 4
+    /// octets
+    #[tokio::test]
+    async fn window_update_with_bad_length() {
+        run(|mut server| async move {
+            let frame = raw::frame(raw::WINDOW_UPDATE, 0, 0, &[0, 0, 1]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9.1: The Flow-Control Window
+mod http2_6_9_1 {
+    use super::*;
+
+    /// http2/6.9.1/1: Sends multiple WINDOW_UPDATE frames increasing the flow
+    /// control window to above 2^31-1
+    #[tokio::test]
+    async fn connection_window_overflow() {
+        run(|mut server| async move {
+            server.send_bytes(&window_update(0, (1 << 31) - 1)).await;
+            server
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.9.1/2: Sends a DATA frame that exceeds the flow control window
+    /// of the client
+    #[tokio::test]
+    async fn data_exceeds_window() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            // Four frames of the maximum size exceed the initial window of
+            // 65,535 octets.
+            for _ in 0..4 {
+                let frame = raw::frame(raw::DATA, 0, 1, &[b'x'; 16_384]);
+                server.send_bytes(&frame).await;
+            }
+            server
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9.2: Initial Flow-Control Window Size
+mod http2_6_9_2 {
+    use super::*;
+
+    /// http2/6.9.2/1: Sends a SETTINGS_INITIAL_WINDOW_SIZE settings with an
+    /// exceeded maximum window size value
+    #[tokio::test]
+    async fn initial_window_size_too_large() {
+        run(|mut server| async move {
+            server.send_bytes(&settings(&[(0x4, 1 << 31)])).await;
+            server
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.10: CONTINUATION
+mod http2_6_10 {
+    use super::*;
+
+    /// http2/6.10/1: Sends multiple CONTINUATION frames preceded by a HEADERS
+    /// frame
+    #[tokio::test]
+    async fn multiple_continuation_frames() {
+        let body = run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "200"), ("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block[..4]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, 0, 1, &block[4..8]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block[8..]);
+            server.send_bytes(&frame).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, Some(Bytes::new()));
+    }
+
+    /// http2/6.10/2: Sends a CONTINUATION frame followed by any frame other
+    /// than CONTINUATION
+    #[tokio::test]
+    async fn continuation_followed_by_data() {
+        run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "200"), ("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::HEADERS, 0, 1, &block[..4]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, 0, 1, &block[4..]);
+            server.send_bytes(&frame).await;
+            server.send_frame(frames::data(1, "test").eos()).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/3: Sends a CONTINUATION frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn continuation_on_stream_0() {
+        run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "200"), ("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block[..4]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 0, &block[4..]);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/4: Sends a CONTINUATION frame preceded by a HEADERS frame
+    /// with END_HEADERS flag
+    #[tokio::test]
+    async fn continuation_after_end_headers() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            let block = raw::header_block(&[("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/5: Sends a CONTINUATION frame preceded by a CONTINUATION
+    /// frame with END_HEADERS flag
+    #[tokio::test]
+    async fn continuation_after_continuation_with_end_headers() {
+        run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "200"), ("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::HEADERS, 0, 1, &block[..4]);
+            server.send_bytes(&frame).await;
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block[4..]);
+            server.send_bytes(&frame).await;
+            let block = raw::header_block(&[("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/6: Sends a CONTINUATION frame preceded by a DATA frame
+    #[tokio::test]
+    async fn continuation_after_data() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_frame(frames::data(1, "test")).await;
+            let block = raw::header_block(&[("x-dummy", "dummy")]);
+            let frame = raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block);
+            server.send_bytes(&frame).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §7: Error Codes
+mod http2_7 {
+    use super::*;
+
+    /// http2/7/1: Sends a GOAWAY frame with unknown error code
+    #[tokio::test]
+    async fn go_away_with_unknown_error_code() {
+        let body = run(|mut server| async move {
+            let frame = raw::frame(raw::GOAWAY, 0, 0, &[0, 0, 0, 1, 0, 0, 0, 0xff]);
+            server.send_bytes(&frame).await;
+            send_response(&mut server, 1).await;
+        })
+        .await;
+        assert_response(body);
+    }
+
+    /// http2/7/2: Sends a RST_STREAM frame with unknown error code
+    #[tokio::test]
+    async fn rst_stream_with_unknown_error_code() {
+        let body = run(|mut server| async move {
+            server.send_bytes(&rst_stream(1, 0xff)).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_eq!(body, None);
+    }
+}
+
+/// RFC 7540 §8.1: HTTP Request/Response Exchange
+mod http2_8_1 {
+    use super::*;
+
+    /// http2/8.1/1: Sends a second HEADERS frame without the END_STREAM flag
+    #[tokio::test]
+    async fn trailers_without_end_stream() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_frame(frames::data(1, "hello")).await;
+            let block = raw::header_block(&[("x-trailer", "trailer")]);
+            server.send_bytes(&headers(1, 0, &block)).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1/2: Sends an informational response before the final response
+    #[tokio::test]
+    async fn informational_response() {
+        let body = run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "100")]);
+            server.send_bytes(&headers(1, 0, &block)).await;
+            send_response(&mut server, 1).await;
+            server.assert_no_error().await;
+        })
+        .await;
+        assert_response(body);
+    }
+}
+
+/// RFC 7540 §8.1.2: HTTP Header Fields
+mod http2_8_1_2 {
+    use super::*;
+
+    /// http2/8.1.2/1: Sends a HEADERS frame that contains the header field
+    /// name in uppercase letters
+    #[tokio::test]
+    async fn uppercase_field_name() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&response_with(1, &[("X-TEST", "ok")]))
+                .await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.1: Pseudo-Header Fields
+mod http2_8_1_2_1 {
+    use super::*;
+
+    /// http2/8.1.2.1/1: Sends a HEADERS frame that contains a unknown
+    /// pseudo-header field
+    #[tokio::test]
+    async fn unknown_pseudo_header() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&response_with(1, &[(":test", "ok")]))
+                .await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.1/2: Sends a HEADERS frame that contains the pseudo-header
+    /// field defined for request
+    #[tokio::test]
+    async fn request_pseudo_header() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&response_with(1, &[(":path", "/")]))
+                .await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.1/3: Sends a HEADERS frame that contains a pseudo-header
+    /// field as trailers
+    #[tokio::test]
+    async fn pseudo_header_in_trailers() {
+        run(|mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_frame(frames::data(1, "hello")).await;
+            server.send_bytes(&response(1, raw::END_STREAM)).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.1/4: Sends a HEADERS frame that contains a pseudo-header
+    /// field that appears in a header block after a regular header field
+    #[tokio::test]
+    async fn pseudo_header_after_regular_field() {
+        run(|mut server| async move {
+            let block = raw::header_block(&[("x-test", "ok"), (":status", "200")]);
+            server.send_bytes(&headers(1, 0, &block)).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.2: Connection-Specific Header Fields
+mod http2_8_1_2_2 {
+    use super::*;
+
+    /// http2/8.1.2.2/1: Sends a HEADERS frame that contains the
+    /// connection-specific header field
+    #[tokio::test]
+    async fn connection_specific_field() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&response_with(1, &[("connection", "keep-alive")]))
+                .await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.4: Response Pseudo-Header Fields
+mod http2_8_1_2_4 {
+    use super::*;
+
+    /// http2/8.1.2.4/1: Sends a HEADERS frame that omits ":status"
+    #[tokio::test]
+    async fn missing_status() {
+        run(|mut server| async move {
+            let block = raw::header_block(&[("x-test", "ok")]);
+            server.send_bytes(&headers(1, 0, &block)).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.4/2: Sends a HEADERS frame with duplicated ":status"
+    #[tokio::test]
+    async fn duplicated_status() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&response_with(1, &[(":status", "200")]))
+                .await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.4/3: Sends a HEADERS frame with an invalid ":status"
+    #[tokio::test]
+    async fn invalid_status() {
+        run(|mut server| async move {
+            let block = raw::header_block(&[(":status", "twohundred")]);
+            server.send_bytes(&headers(1, 0, &block)).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.6: Malformed Requests and Responses
+mod http2_8_1_2_6 {
+    use super::*;
+
+    /// http2/8.1.2.6/1: Sends a HEADERS frame with the "content-length"
+    /// header field which does not equal the DATA frame payload length
+    #[tokio::test]
+    async fn content_length_mismatch() {
+        run(|mut server| async move {
+            let frame = headers(
+                1,
+                0,
+                &raw::header_block(&[(":status", "200"), ("content-length", "1")]),
+            );
+            server.send_bytes(&frame).await;
+            server.send_frame(frames::data(1, "hello").eos()).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.6/2: Sends a HEADERS frame with the "content-length"
+    /// header field which does not equal the sum of the multiple DATA frames
+    /// payload length
+    #[tokio::test]
+    async fn content_length_mismatch_multiple_data_frames() {
+        run(|mut server| async move {
+            let frame = headers(
+                1,
+                0,
+                &raw::header_block(&[(":status", "200"), ("content-length", "4")]),
+            );
+            server.send_bytes(&frame).await;
+            server.send_frame(frames::data(1, "hel")).await;
+            server.send_frame(frames::data(1, "lo").eos()).await;
+            server
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.2: Server Push
+mod http2_8_2 {
+    use super::*;
+
+    /// http2/8.2/1: Sends a PUSH_PROMISE frame when push is disabled
+    #[tokio::test]
+    async fn push_promise_when_disabled() {
+        let mut builder = client::Builder::new();
+        builder.enable_push(false);
+
+        run_with(builder, |mut server| async move {
+            server.send_bytes(&response(1, 0)).await;
+            server.send_bytes(&push_promise(1, 2, raw::GET)).await;
+            server
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.2/2: Sends a PUSH_PROMISE frame for a request that is not safe
+    #[tokio::test]
+    async fn push_promise_for_unsafe_method() {
+        run(|mut server| async move {
+            let post = &[
+                (":method", "POST"),
+                (":scheme", "https"),
+                (":authority", "example.com"),
+                (":path", "/"),
+            ];
+            server.send_bytes(&response(1, 0)).await;
+            server.send_bytes(&push_promise(1, 2, post)).await;
+            server
+                .assert_stream_error(2, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §2.3.3: Index Address Space
+mod hpack_2_3_3 {
+    use super::*;
+
+    /// hpack/2.3.3/1: Sends a indexed header field representation with
+    /// invalid index
+    #[tokio::test]
+    async fn indexed_with_invalid_index() {
+        run(|mut server| async move {
+            // Index 70, with an empty dynamic table
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x88, 0xc6]))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// hpack/2.3.3/2: Sends a literal header field representation with
+    /// invalid index
+    #[tokio::test]
+    async fn literal_with_invalid_index() {
+        run(|mut server| async move {
+            // Name index 70, with an empty dynamic table
+            let block = [0x88, 0x7f, 0x07, 0x04, b't', b'e', b's', b't'];
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §4.2: Maximum Table Size
+mod hpack_4_2 {
+    use super::*;
+
+    /// hpack/4.2/1: Sends a dynamic table size update larger than the value
+    /// of SETTINGS_HEADER_TABLE_SIZE
+    #[tokio::test]
+    async fn size_update_above_settings() {
+        run(|mut server| async move {
+            // Size 4,097
+            let mut block = vec![0x3f, 0xe2, 0x1f];
+            block.extend(raw::header_block(OK));
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §5.2: String Literal Representation
+mod hpack_5_2 {
+    use super::*;
+
+    /// Sends a `200` response with an `x-test` field, the value of which is
+    /// the Huffman encoded `value`.
+    async fn send_response(value: &[u8]) {
+        let mut block = raw::header_block(OK);
+        block.extend(b"\x00\x06x-test");
+        block.push(0x80 | value.len() as u8);
+        block.extend(value);
+
+        run(|mut server| async move {
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// hpack/5.2/1: Sends a Huffman-encoded string literal representation
+    /// with padding longer than 7 bits
+    #[tokio::test]
+    async fn padding_longer_than_7_bits() {
+        // `a` (00011), then 11 bits of padding
+        send_response(&[0x1f, 0xff]).await;
+    }
+
+    /// hpack/5.2/2: Sends a Huffman-encoded string literal representation
+    /// padded by zero
+    #[tokio::test]
+    async fn padding_with_zeros() {
+        // `a` (00011), then 3 bits of zeros
+        send_response(&[0x18]).await;
+    }
+
+    /// hpack/5.2/3: Sends a Huffman-encoded string literal representation
+    /// containing the EOS symbol
+    #[tokio::test]
+    async fn eos_symbol() {
+        // The EOS symbol is 30 bits of ones
+        send_response(&[0xff, 0xff, 0xff, 0xff]).await;
+    }
+}
+
+/// RFC 7541 §6.1: Indexed Header Field Representation
+mod hpack_6_1 {
+    use super::*;
+
+    /// hpack/6.1/1: Sends a indexed header field representation with index 0
+    #[tokio::test]
+    async fn index_0() {
+        run(|mut server| async move {
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x88, 0x80]))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §6.3: Dynamic Table Size Update
+mod hpack_6_3 {
+    use super::*;
+
+    /// hpack/6.3/1: Sends a dynamic table size update at the end of header
+    /// block
+    #[tokio::test]
+    async fn size_update_at_end_of_block() {
+        run(|mut server| async move {
+            let mut block = raw::header_block(OK);
+            block.push(0x20);
+            server
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            server
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
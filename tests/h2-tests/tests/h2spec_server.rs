@@ -0,0 +1,2439 @@
+//! The h2spec cases, run against the server.
+//!
+//! Each module holds the cases of one h2spec group, named after the section
+//! of RFC 7540 (`http2_*`) or RFC 7541 (`hpack_*`) they check. Each test is
+//! tagged with the h2spec case it ports, e.g. `http2/6.5/1` is the first case
+//! of RFC 7540 section 6.5, and runs with
+//! `cargo test --test h2spec_server http2_6_5::`.
+//!
+//! As in h2spec, an error is expected as a `GOAWAY` or `RST_STREAM` frame
+//! carrying its code, and frames the server sends before it, such as the
+//! response to a request, are skipped.
+
+#![deny(warnings)]
+
+use futures::future::join;
+use futures::StreamExt;
+use h2::frame::Frame;
+use h2_support::prelude::*;
+
+/// A `POST https://example.com/` request.
+const POST: &[(&str, &str)] = &[
+    (":method", "POST"),
+    (":scheme", "https"),
+    (":authority", "example.com"),
+    (":path", "/"),
+];
+
+const PING: [u8; 8] = *b"h2spec\0\0";
+
+/// Runs `client` against a server with the default settings, once the
+/// handshake is complete.
+async fn run<F, T>(client: F)
+where
+    F: FnOnce(mock::Handle) -> T,
+    T: Future<Output = ()>,
+{
+    run_with(server::Builder::new(), client).await
+}
+
+async fn run_with<F, T>(builder: server::Builder, client: F)
+where
+    F: FnOnce(mock::Handle) -> T,
+    T: Future<Output = ()>,
+{
+    let _ = env_logger::try_init();
+    let (io, mut handle) = mock::new();
+
+    let client = async move {
+        handle.assert_server_handshake().await;
+        client(handle).await;
+    };
+
+    join(serve(builder, io), client).await;
+}
+
+/// The server under test: every request is answered with a `200` response
+/// and a `hello` body, once the request is complete.
+async fn serve(builder: server::Builder, io: mock::Mock) {
+    let mut srv = match builder.handshake::<_, Bytes>(io).await {
+        Ok(srv) => srv,
+        Err(_) => return,
+    };
+
+    while let Some(res) = srv.accept().await {
+        match res {
+            Ok((request, respond)) => {
+                tokio::spawn(respond_to(request, respond));
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+async fn respond_to(request: Request<RecvStream>, mut respond: server::SendResponse<Bytes>) {
+    let is_head = request.method() == Method::HEAD;
+    let mut body = request.into_body();
+
+    while let Some(res) = body.data().await {
+        match res {
+            Ok(chunk) => {
+                let _ = body.release_capacity().release_capacity(chunk.len());
+            }
+            Err(_) => return,
+        }
+    }
+
+    if body.trailers().await.is_err() {
+        return;
+    }
+
+    let response = Response::builder().status(200).body(()).unwrap();
+
+    if let Ok(mut stream) = respond.send_response(response, is_head) {
+        if !is_head {
+            let _ = stream.send_data(Bytes::from_static(b"hello"), true);
+        }
+    }
+}
+
+/// Reads the response to the request on stream `id`.
+async fn recv_response(client: &mut mock::Handle, id: u32) {
+    client.recv_frame(frames::headers(id).response(200)).await;
+    client.recv_frame(frames::data(id, "hello").eos()).await;
+}
+
+/// A complete `HEADERS` frame carrying `block`.
+fn headers(id: u32, flags: u8, block: &[u8]) -> Vec<u8> {
+    raw::frame(raw::HEADERS, raw::END_HEADERS | flags, id, block)
+}
+
+/// A `HEADERS` frame opening stream `id` with a `GET` request.
+fn get(id: u32) -> Vec<u8> {
+    headers(id, raw::END_STREAM, &raw::header_block(raw::GET))
+}
+
+/// A `HEADERS` frame opening stream `id` with a `POST` request, the body of
+/// which is still to be sent.
+fn post(id: u32) -> Vec<u8> {
+    headers(id, 0, &raw::header_block(POST))
+}
+
+/// A `GET` request with `fields` added after the pseudo-header fields.
+fn get_with(id: u32, fields: &[(&str, &str)]) -> Vec<u8> {
+    let fields: Vec<_> = raw::GET.iter().chain(fields).cloned().collect();
+    headers(id, raw::END_STREAM, &raw::header_block(&fields))
+}
+
+fn priority(dependency: u32, exclusive: bool, weight: u8) -> Vec<u8> {
+    let mut dst = dependency.to_be_bytes().to_vec();
+
+    if exclusive {
+        dst[0] |= 0x80;
+    }
+
+    dst.push(weight);
+    dst
+}
+
+fn settings(params: &[(u16, u32)]) -> Vec<u8> {
+    let mut payload = vec![];
+
+    for &(id, val) in params {
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(&val.to_be_bytes());
+    }
+
+    raw::frame(raw::SETTINGS, 0, 0, &payload)
+}
+
+fn rst_stream(id: u32, code: u32) -> Vec<u8> {
+    raw::frame(raw::RST_STREAM, 0, id, &code.to_be_bytes())
+}
+
+fn window_update(id: u32, increment: u32) -> Vec<u8> {
+    raw::frame(raw::WINDOW_UPDATE, 0, id, &increment.to_be_bytes())
+}
+
+/// RFC 7540 §3.5: HTTP/2 Connection Preface
+mod generic_1 {
+    use super::*;
+
+    /// generic/1/1: Sends a client connection preface
+    #[tokio::test]
+    async fn client_connection_preface() {
+        run(|mut client| async move {
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5: Streams and Multiplexing
+mod generic_2 {
+    use super::*;
+
+    /// generic/2/1: Sends a PRIORITY frame on idle stream
+    #[tokio::test]
+    async fn priority_on_idle_stream() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            client.send_bytes(&frame).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/2: Sends a WINDOW_UPDATE frame on half-closed (remote) stream
+    #[tokio::test]
+    async fn window_update_on_half_closed_remote_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            client.send_bytes(&window_update(1, 1)).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/3: Sends a PRIORITY frame on half-closed (remote) stream
+    #[tokio::test]
+    async fn priority_on_half_closed_remote_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            client.send_bytes(&frame).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/4: Sends a RST_STREAM frame on half-closed (remote) stream
+    #[tokio::test]
+    async fn rst_stream_on_half_closed_remote_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/2/5: Sends a PRIORITY frame on closed stream
+    #[tokio::test]
+    async fn priority_on_closed_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            client.send_bytes(&frame).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.1: DATA
+mod generic_3_1 {
+    use super::*;
+
+    /// generic/3.1/1: Sends a DATA frame
+    #[tokio::test]
+    async fn data_frame() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.1/2: Sends multiple DATA frames
+    #[tokio::test]
+    async fn multiple_data_frames() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_frame(frames::data(1, "test")).await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.1/3: Sends a DATA frame with padding
+    #[tokio::test]
+    async fn data_frame_with_padding() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(
+                raw::DATA,
+                raw::END_STREAM | raw::PADDED,
+                1,
+                &[4, b't', b'e', b's', b't', 0, 0, 0, 0],
+            );
+            client.send_bytes(&frame).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.2: HEADERS
+mod generic_3_2 {
+    use super::*;
+
+    /// generic/3.2/1: Sends a HEADERS frame
+    #[tokio::test]
+    async fn headers_frame() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.2/2: Sends a HEADERS frame with padding
+    #[tokio::test]
+    async fn headers_frame_with_padding() {
+        run(|mut client| async move {
+            let mut payload = vec![4];
+            payload.extend(raw::header_block(raw::GET));
+            payload.extend(&[0; 4]);
+            let frame = headers(1, raw::END_STREAM | raw::PADDED, &payload);
+            client.send_bytes(&frame).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.2/3: Sends a HEADERS frame with priority
+    #[tokio::test]
+    async fn headers_frame_with_priority() {
+        run(|mut client| async move {
+            let mut payload = priority(0, false, 255);
+            payload.extend(raw::header_block(raw::GET));
+            let frame = headers(1, raw::END_STREAM | raw::PRIORITY_FLAG, &payload);
+            client.send_bytes(&frame).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.3: PRIORITY
+mod generic_3_3 {
+    use super::*;
+
+    /// generic/3.3/1: Sends a PRIORITY frame with priority 1
+    #[tokio::test]
+    async fn priority_1() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 0));
+            client.send_bytes(&frame).await;
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.3/2: Sends a PRIORITY frame with priority 256
+    #[tokio::test]
+    async fn priority_256() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 255));
+            client.send_bytes(&frame).await;
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.3/3: Sends a PRIORITY frame with stream dependency
+    #[tokio::test]
+    async fn priority_with_dependency() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::PRIORITY, 0, 3, &priority(1, false, 15));
+            client.send_bytes(&frame).await;
+            client.send_bytes(&get(3)).await;
+            recv_response(&mut client, 3).await;
+        })
+        .await;
+    }
+
+    /// generic/3.3/4: Sends a PRIORITY frame with exclusive
+    #[tokio::test]
+    async fn priority_exclusive() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::PRIORITY, 0, 3, &priority(1, true, 15));
+            client.send_bytes(&frame).await;
+            client.send_bytes(&get(3)).await;
+            recv_response(&mut client, 3).await;
+        })
+        .await;
+    }
+
+    /// generic/3.3/5: Sends a PRIORITY frame for an idle stream, then send a
+    /// HEADER frame for a lower stream ID
+    #[tokio::test]
+    async fn priority_on_idle_stream_then_lower_stream() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 3, &priority(0, false, 15));
+            client.send_bytes(&frame).await;
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.4: RST_STREAM
+mod generic_3_4 {
+    use super::*;
+
+    /// generic/3.4/1: Sends a RST_STREAM frame
+    #[tokio::test]
+    async fn rst_stream_frame() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5: SETTINGS
+mod generic_3_5 {
+    use super::*;
+
+    /// generic/3.5/1: Sends a SETTINGS frame
+    #[tokio::test]
+    async fn settings_frame() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x3, 100)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.7: PING
+mod generic_3_7 {
+    use super::*;
+
+    /// generic/3.7/1: Sends a PING frame
+    #[tokio::test]
+    async fn ping_frame() {
+        run(|mut client| async move {
+            client.ping_pong(PING).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.8: GOAWAY
+mod generic_3_8 {
+    use super::*;
+
+    /// generic/3.8/1: Sends a GOAWAY frame
+    #[tokio::test]
+    async fn go_away_frame() {
+        run(|mut client| async move {
+            client.send_frame(frames::go_away(0)).await;
+
+            // The server may close the connection, as it has no streams left.
+            client.send_frame(frames::ping(PING)).await;
+
+            match client.next().await {
+                Some(Ok(Frame::Ping(ref ping))) if ping.is_ack() => {}
+                Some(Ok(Frame::GoAway(ref frame))) if frame.reason() == Reason::NO_ERROR => {}
+                None => {}
+                res => panic!("unexpected result; res={:?}", res),
+            }
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9: WINDOW_UPDATE
+mod generic_3_9 {
+    use super::*;
+
+    /// generic/3.9/1: Sends a WINDOW_UPDATE frame with stream ID 0
+    #[tokio::test]
+    async fn window_update_on_connection() {
+        run(|mut client| async move {
+            client.send_bytes(&window_update(0, 1)).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// generic/3.9/2: Sends a WINDOW_UPDATE frame with arbitrary stream ID
+    #[tokio::test]
+    async fn window_update_on_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&window_update(1, 1)).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.10: CONTINUATION
+mod generic_3_10 {
+    use super::*;
+
+    /// generic/3.10/1: Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn continuation_frame() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            let (head, tail) = block.split_at(block.len() / 2);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, head))
+                .await;
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, tail))
+                .await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/3.10/2: Sends multiple CONTINUATION frames
+    #[tokio::test]
+    async fn multiple_continuation_frames() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            let mut chunks = block.chunks(8);
+            let first = chunks.next().unwrap();
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, first))
+                .await;
+
+            let chunks: Vec<_> = chunks.collect();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let flags = if i == chunks.len() - 1 {
+                    raw::END_HEADERS
+                } else {
+                    0
+                };
+                client
+                    .send_bytes(&raw::frame(raw::CONTINUATION, flags, 1, chunk))
+                    .await;
+            }
+
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1: HTTP Request/Response Exchange
+mod generic_4 {
+    use super::*;
+
+    /// generic/4/1: Sends a GET request
+    #[tokio::test]
+    async fn get_request() {
+        run(|mut client| async move {
+            client
+                .send_frame(
+                    frames::headers(1)
+                        .request("GET", "https://example.com/")
+                        .eos(),
+                )
+                .await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/4/2: Sends a HEAD request
+    #[tokio::test]
+    async fn head_request() {
+        run(|mut client| async move {
+            client
+                .send_frame(
+                    frames::headers(1)
+                        .request("HEAD", "https://example.com/")
+                        .eos(),
+                )
+                .await;
+            client
+                .recv_frame(frames::headers(1).response(200).eos())
+                .await;
+        })
+        .await;
+    }
+
+    /// generic/4/3: Sends a POST request
+    #[tokio::test]
+    async fn post_request() {
+        run(|mut client| async move {
+            client
+                .send_frame(frames::headers(1).request("POST", "https://example.com/"))
+                .await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/4/4: Sends a POST request with trailers
+    #[tokio::test]
+    async fn post_request_with_trailers() {
+        run(|mut client| async move {
+            client
+                .send_frame(frames::headers(1).request("POST", "https://example.com/"))
+                .await;
+            client.send_frame(frames::data(1, "test")).await;
+            client
+                .send_frame(frames::headers(1).field("trailer", "test").eos())
+                .await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541: HPACK
+mod generic_5 {
+    use super::*;
+
+    /// `:method: GET`, `:scheme: https` and `:path: /`, from the static table.
+    const INDEXED: &[u8] = &[0x82, 0x87, 0x84];
+
+    /// `www.example.com`, Huffman encoded (RFC 7541 §C.4.1).
+    const HUFFMAN_AUTHORITY: &[u8] = &[
+        0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff,
+    ];
+
+    /// `custom-key` and `custom-value`, Huffman encoded (RFC 7541 §C.4.3).
+    const HUFFMAN_CUSTOM: &[u8] = &[
+        0x88, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f, 0x89, 0x25, 0xa8, 0x49, 0xe9, 0x5b,
+        0xb8, 0xe8, 0xb4, 0xbf,
+    ];
+
+    /// `:authority: example.com`, without indexing.
+    const AUTHORITY: &[u8] = b"\x01\x0bexample.com";
+
+    /// `custom-key: custom-value`, without Huffman coding.
+    const CUSTOM: &[u8] = b"\x0acustom-key\x0ccustom-value";
+
+    async fn send_request(block: &[&[u8]]) {
+        run(|mut client| async move {
+            let frame = headers(1, raw::END_STREAM, &block.concat());
+            client.send_bytes(&frame).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// generic/5/1: Sends a indexed header field representation
+    #[tokio::test]
+    async fn indexed() {
+        send_request(&[INDEXED, AUTHORITY]).await;
+    }
+
+    /// generic/5/2: Sends a literal header field with incremental indexing -
+    /// indexed name
+    #[tokio::test]
+    async fn incremental_indexing_indexed_name() {
+        send_request(&[INDEXED, b"\x41\x0bexample.com"]).await;
+    }
+
+    /// generic/5/3: Sends a literal header field with incremental indexing -
+    /// indexed name (with Huffman coding)
+    #[tokio::test]
+    async fn incremental_indexing_indexed_name_huffman() {
+        send_request(&[INDEXED, &[0x41], HUFFMAN_AUTHORITY]).await;
+    }
+
+    /// generic/5/4: Sends a literal header field with incremental indexing -
+    /// new name
+    #[tokio::test]
+    async fn incremental_indexing_new_name() {
+        send_request(&[INDEXED, AUTHORITY, &[0x40], CUSTOM]).await;
+    }
+
+    /// generic/5/5: Sends a literal header field with incremental indexing -
+    /// new name (with Huffman coding)
+    #[tokio::test]
+    async fn incremental_indexing_new_name_huffman() {
+        send_request(&[INDEXED, AUTHORITY, &[0x40], HUFFMAN_CUSTOM]).await;
+    }
+
+    /// generic/5/6: Sends a literal header field without indexing - indexed
+    /// name
+    #[tokio::test]
+    async fn without_indexing_indexed_name() {
+        send_request(&[INDEXED, b"\x01\x0fwww.example.com"]).await;
+    }
+
+    /// generic/5/7: Sends a literal header field without indexing - indexed
+    /// name (with Huffman coding)
+    #[tokio::test]
+    async fn without_indexing_indexed_name_huffman() {
+        send_request(&[INDEXED, &[0x01], HUFFMAN_AUTHORITY]).await;
+    }
+
+    /// generic/5/8: Sends a literal header field without indexing - new name
+    #[tokio::test]
+    async fn without_indexing_new_name() {
+        send_request(&[INDEXED, AUTHORITY, &[0x00], CUSTOM]).await;
+    }
+
+    /// generic/5/9: Sends a literal header field without indexing - new name
+    /// (huffman encoded)
+    #[tokio::test]
+    async fn without_indexing_new_name_huffman() {
+        send_request(&[INDEXED, AUTHORITY, &[0x00], HUFFMAN_CUSTOM]).await;
+    }
+
+    /// generic/5/10: Sends a literal header field never indexed - indexed
+    /// name
+    #[tokio::test]
+    async fn never_indexed_indexed_name() {
+        send_request(&[INDEXED, b"\x11\x0bexample.com"]).await;
+    }
+
+    /// generic/5/11: Sends a literal header field never indexed - indexed
+    /// name (huffman encoded)
+    #[tokio::test]
+    async fn never_indexed_indexed_name_huffman() {
+        send_request(&[INDEXED, &[0x11], HUFFMAN_AUTHORITY]).await;
+    }
+
+    /// generic/5/12: Sends a literal header field never indexed - new name
+    #[tokio::test]
+    async fn never_indexed_new_name() {
+        send_request(&[INDEXED, AUTHORITY, &[0x10], CUSTOM]).await;
+    }
+
+    /// generic/5/13: Sends a literal header field never indexed - new name
+    /// (huffman encoded)
+    #[tokio::test]
+    async fn never_indexed_new_name_huffman() {
+        send_request(&[INDEXED, AUTHORITY, &[0x10], HUFFMAN_CUSTOM]).await;
+    }
+
+    /// generic/5/14: Sends a dynamic table size update
+    #[tokio::test]
+    async fn dynamic_table_size_update() {
+        // Size 0
+        send_request(&[&[0x20], INDEXED, AUTHORITY]).await;
+    }
+
+    /// generic/5/15: Sends multiple dynamic table size update
+    #[tokio::test]
+    async fn multiple_dynamic_table_size_updates() {
+        // Size 0, then 4,096
+        send_request(&[&[0x20, 0x3f, 0xe1, 0x1f], INDEXED, AUTHORITY]).await;
+    }
+}
+
+/// RFC 7540 §3.5: HTTP/2 Connection Preface
+mod http2_3_5 {
+    use super::*;
+
+    /// http2/3.5/1: Sends client connection preface
+    #[tokio::test]
+    async fn client_connection_preface() {
+        run(|mut client| async move {
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/3.5/2: Sends invalid connection preface
+    #[tokio::test]
+    async fn invalid_connection_preface() {
+        let _ = env_logger::try_init();
+        let (io, mut client) = mock::new();
+
+        let client = async move {
+            client.send_bytes(b"INVALID CONNECTION PREFACE\r\n").await;
+
+            // The server sends its SETTINGS before reading the preface, the
+            // connection is then closed, with or without a GOAWAY frame.
+            loop {
+                match client.next().await {
+                    Some(Ok(Frame::Settings(_))) => {}
+                    Some(Ok(Frame::GoAway(frame))) => {
+                        assert_eq!(frame.reason(), Reason::PROTOCOL_ERROR);
+                    }
+                    None => break,
+                    res => panic!("unexpected result; res={:?}", res),
+                }
+            }
+        };
+
+        join(serve(server::Builder::new(), io), client).await;
+    }
+}
+
+/// RFC 7540 §4.1: Frame Format
+mod http2_4_1 {
+    use super::*;
+
+    /// http2/4.1/1: Sends a frame with unknown type
+    #[tokio::test]
+    async fn unknown_type() {
+        run(|mut client| async move {
+            client.send_bytes(&raw::frame(0x16, 0, 0, b"unknown")).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/4.1/2: Sends a frame with undefined flag
+    #[tokio::test]
+    async fn undefined_flag() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&raw::frame(raw::PING, 0x16, 0, &PING))
+                .await;
+            client.recv_frame(frames::ping(PING).pong()).await;
+        })
+        .await;
+    }
+
+    /// http2/4.1/3: Sends a frame with reserved field bit
+    #[tokio::test]
+    async fn reserved_bit() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&raw::frame(raw::PING, 0, 1 << 31, &PING))
+                .await;
+            client.recv_frame(frames::ping(PING).pong()).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §4.2: Frame Size
+mod http2_4_2 {
+    use super::*;
+
+    /// http2/4.2/1: Sends a DATA frame with 2^14 octets in length
+    #[tokio::test]
+    async fn data_frame_of_max_size() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::DATA, raw::END_STREAM, 1, &[b'x'; 16_384]);
+            client.send_bytes(&frame).await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// http2/4.2/2: Sends a large size DATA frame that exceeds the
+    /// SETTINGS_MAX_FRAME_SIZE
+    #[tokio::test]
+    async fn data_frame_too_large() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::DATA, raw::END_STREAM, 1, &[b'x'; 16_385]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_stream_error(1, &[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/4.2/3: Sends a large size HEADERS frame that exceeds the
+    /// SETTINGS_MAX_FRAME_SIZE
+    #[tokio::test]
+    async fn headers_frame_too_large() {
+        run(|mut client| async move {
+            let value = "x".repeat(16_384);
+            client
+                .send_bytes(&get_with(1, &[("x-dummy", &value)]))
+                .await;
+            client
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §4.3: Header Compression and Decompression
+mod http2_4_3 {
+    use super::*;
+
+    /// http2/4.3/1: Sends invalid header block fragment
+    #[tokio::test]
+    async fn invalid_header_block_fragment() {
+        run(|mut client| async move {
+            // A literal header field with incremental indexing, without the
+            // name and the value.
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x40]))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/4.3/2: Sends a PRIORITY frame while sending the header blocks
+    #[tokio::test]
+    async fn priority_in_header_block() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/4.3/3: Sends a HEADERS frame to another stream while sending the
+    /// header blocks
+    #[tokio::test]
+    async fn headers_in_header_block() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            client.send_bytes(&get(3)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.1: Stream States
+mod http2_5_1 {
+    use super::*;
+
+    /// http2/5.1/1: idle: Sends a DATA frame
+    #[tokio::test]
+    async fn idle_data() {
+        run(|mut client| async move {
+            client.send_frame(frames::data(1, "test").eos()).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/2: idle: Sends a RST_STREAM frame
+    #[tokio::test]
+    async fn idle_rst_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/3: idle: Sends a WINDOW_UPDATE frame
+    #[tokio::test]
+    async fn idle_window_update() {
+        run(|mut client| async move {
+            client.send_bytes(&window_update(1, 100)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/4: idle: Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn idle_continuation() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/5: half closed (remote): Sends a DATA frame
+    #[tokio::test]
+    async fn half_closed_remote_data() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            client.send_frame(frames::data(1, "test")).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/6: half closed (remote): Sends a HEADERS frame
+    #[tokio::test]
+    async fn half_closed_remote_headers() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            client.send_bytes(&get(1)).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/7: half closed (remote): Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn half_closed_remote_continuation() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            let reasons = [Reason::STREAM_CLOSED, Reason::PROTOCOL_ERROR];
+            client.assert_stream_error(1, &reasons).await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/8: closed: Sends a DATA frame after sending RST_STREAM frame
+    #[tokio::test]
+    async fn closed_data_after_rst_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            client.send_frame(frames::data(1, "test")).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/9: closed: Sends a HEADERS frame after sending RST_STREAM
+    /// frame
+    #[tokio::test]
+    async fn closed_headers_after_rst_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            client.send_bytes(&get(1)).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/10: closed: Sends a CONTINUATION frame after sending
+    /// RST_STREAM frame
+    #[tokio::test]
+    async fn closed_continuation_after_rst_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            let reasons = [Reason::STREAM_CLOSED, Reason::PROTOCOL_ERROR];
+            client.assert_stream_error(1, &reasons).await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/11: closed: Sends a DATA frame
+    #[tokio::test]
+    async fn closed_data() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+            client.send_frame(frames::data(1, "test")).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/12: closed: Sends a HEADERS frame
+    #[tokio::test]
+    async fn closed_headers() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+            client.send_bytes(&get(1)).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1/13: closed: Sends a CONTINUATION frame
+    #[tokio::test]
+    async fn closed_continuation() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            let reasons = [Reason::STREAM_CLOSED, Reason::PROTOCOL_ERROR];
+            client.assert_stream_error(1, &reasons).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.1.1: Stream Identifiers
+mod http2_5_1_1 {
+    use super::*;
+
+    /// http2/5.1.1/1: Sends even-numbered stream identifier
+    #[tokio::test]
+    async fn even_stream_id() {
+        run(|mut client| async move {
+            client.send_bytes(&get(2)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.1.1/2: Sends stream identifier that is numerically smaller
+    /// than previous
+    #[tokio::test]
+    async fn decreasing_stream_id() {
+        run(|mut client| async move {
+            client.send_bytes(&get(5)).await;
+            client.send_bytes(&get(3)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.1.2: Stream Concurrency
+mod http2_5_1_2 {
+    use super::*;
+
+    /// http2/5.1.2/1: Sends HEADERS frames that causes their advertised
+    /// concurrent stream limit to be exceeded
+    #[tokio::test]
+    async fn exceed_max_concurrent_streams() {
+        let mut builder = server::Builder::new();
+        builder.max_concurrent_streams(1);
+
+        run_with(builder, |mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&post(3)).await;
+            let reasons = [Reason::PROTOCOL_ERROR, Reason::REFUSED_STREAM];
+            client.assert_stream_error(3, &reasons).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.3.1: Stream Dependencies
+mod http2_5_3_1 {
+    use super::*;
+
+    /// http2/5.3.1/1: Sends HEADERS frame that depends on itself
+    #[tokio::test]
+    async fn headers_depending_on_itself() {
+        run(|mut client| async move {
+            let mut payload = priority(1, false, 15);
+            payload.extend(raw::header_block(raw::GET));
+            let frame = headers(1, raw::END_STREAM | raw::PRIORITY_FLAG, &payload);
+            client.send_bytes(&frame).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.3.1/2: Sends PRIORITY frame that depend on itself
+    #[tokio::test]
+    async fn priority_depending_on_itself() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(1, false, 15));
+            client.send_bytes(&frame).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.4.1: Connection Error Handling
+mod http2_5_4_1 {
+    use super::*;
+
+    /// http2/5.4.1/1: Sends an invalid PING frame for connection close
+    #[tokio::test]
+    async fn invalid_ping_closes_connection() {
+        run(|mut client| async move {
+            client.send_bytes(&raw::frame(raw::PING, 0, 3, &PING)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/5.4.1/2: Sends an invalid PING frame to receive GOAWAY frame
+    #[tokio::test]
+    async fn invalid_ping_sends_go_away() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            recv_response(&mut client, 1).await;
+            client.send_bytes(&raw::frame(raw::PING, 0, 3, &PING)).await;
+            client.recv_frame(frames::go_away(1).protocol_error()).await;
+            client.recv_eof().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §5.5: Extending HTTP/2
+mod http2_5_5 {
+    use super::*;
+
+    /// http2/5.5/1: Sends an unknown extension frame
+    #[tokio::test]
+    async fn unknown_extension_frame() {
+        run(|mut client| async move {
+            client.send_bytes(&raw::frame(0xff, 0, 0, b"unknown")).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/5.5/2: Sends an unknown extension frame in the middle of a header
+    /// block
+    #[tokio::test]
+    async fn unknown_extension_frame_in_header_block() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            client.send_bytes(&raw::frame(0xff, 0, 1, b"unknown")).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.1: DATA
+mod http2_6_1 {
+    use super::*;
+
+    /// http2/6.1/1: Sends a DATA frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn data_on_stream_0() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client
+                .send_bytes(&raw::frame(raw::DATA, raw::END_STREAM, 0, b"test"))
+                .await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.1/2: Sends a DATA frame on the stream that is not in "open" or
+    /// "half-closed (local)" state
+    #[tokio::test]
+    async fn data_on_half_closed_remote_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&get(1)).await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            client
+                .assert_stream_error(1, &[Reason::STREAM_CLOSED])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.1/3: Sends a DATA frame with invalid pad length
+    #[tokio::test]
+    async fn data_with_invalid_pad_length() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(
+                raw::DATA,
+                raw::END_STREAM | raw::PADDED,
+                1,
+                &[6, b't', b'e', b's', b't', 0],
+            );
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.2: HEADERS
+mod http2_6_2 {
+    use super::*;
+
+    /// http2/6.2/1: Sends a HEADERS frame without the END_HEADERS flag, and a
+    /// PRIORITY frame
+    #[tokio::test]
+    async fn priority_after_headers_without_end_headers() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &priority(0, false, 15));
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.2/2: Sends a HEADERS frame to another stream while sending a
+    /// HEADERS frame
+    #[tokio::test]
+    async fn headers_to_other_stream_after_headers_without_end_headers() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, &block))
+                .await;
+            client.send_bytes(&get(3)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.2/3: Sends a HEADERS frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn headers_on_stream_0() {
+        run(|mut client| async move {
+            client.send_bytes(&get(0)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.2/4: Sends a HEADERS frame with invalid pad length
+    #[tokio::test]
+    async fn headers_with_invalid_pad_length() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            let mut payload = vec![block.len() as u8 + 1];
+            payload.extend(block);
+            let frame = headers(1, raw::END_STREAM | raw::PADDED, &payload);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.3: PRIORITY
+mod http2_6_3 {
+    use super::*;
+
+    /// http2/6.3/1: Sends a PRIORITY frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn priority_on_stream_0() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::PRIORITY, 0, 0, &priority(1, false, 15));
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.3/2: Sends a PRIORITY frame with a length other than 5 octets
+    #[tokio::test]
+    async fn priority_with_invalid_length() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::PRIORITY, 0, 1, &[0, 0, 0, 0]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_stream_error(1, &[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.4: RST_STREAM
+mod http2_6_4 {
+    use super::*;
+
+    /// http2/6.4/1: Sends a RST_STREAM frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn rst_stream_on_stream_0() {
+        run(|mut client| async move {
+            client.send_bytes(&rst_stream(0, 0x8)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.4/2: Sends a RST_STREAM frame on a idle stream
+    #[tokio::test]
+    async fn rst_stream_on_idle_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&rst_stream(1, 0x8)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.4/3: Sends a RST_STREAM frame with a length other than 4
+    /// octets
+    #[tokio::test]
+    async fn rst_stream_with_invalid_length() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::RST_STREAM, 0, 1, &[0, 0, 0]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5: SETTINGS
+mod http2_6_5 {
+    use super::*;
+
+    /// http2/6.5/1: Sends a SETTINGS frame with ACK flag and payload
+    #[tokio::test]
+    async fn ack_with_payload() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::SETTINGS, raw::ACK, 0, &[0, 0x3, 0, 0, 0, 100]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5/2: Sends a SETTINGS frame with a stream identifier other
+    /// than 0x0
+    #[tokio::test]
+    async fn settings_on_stream() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::SETTINGS, 0, 1, &[0, 0x3, 0, 0, 0, 100]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5/3: Sends a SETTINGS frame with a length other than a
+    /// multiple of 6 octets
+    #[tokio::test]
+    async fn settings_with_invalid_length() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::SETTINGS, 0, 0, &[0, 0x3, 0, 0, 100]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5.2: Defined SETTINGS Parameters
+mod http2_6_5_2 {
+    use super::*;
+
+    /// http2/6.5.2/1: SETTINGS_ENABLE_PUSH (0x2): Sends the value other than
+    /// 0 or 1
+    #[tokio::test]
+    async fn invalid_enable_push() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x2, 2)])).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/2: SETTINGS_INITIAL_WINDOW_SIZE (0x4): Sends the value
+    /// above the maximum flow control window size
+    #[tokio::test]
+    async fn initial_window_size_too_large() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x4, 1 << 31)])).await;
+            client
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/3: SETTINGS_MAX_FRAME_SIZE (0x5): Sends the value below
+    /// the initial value
+    #[tokio::test]
+    async fn max_frame_size_too_small() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x5, 16_383)])).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/4: SETTINGS_MAX_FRAME_SIZE (0x5): Sends the value above
+    /// the maximum allowed frame size
+    #[tokio::test]
+    async fn max_frame_size_too_large() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x5, 1 << 24)])).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.2/5: Sends a SETTINGS frame with unknown identifier
+    #[tokio::test]
+    async fn unknown_identifier() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0xff, 1)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.5.3: Settings Synchronization
+mod http2_6_5_3 {
+    use super::*;
+
+    /// http2/6.5.3/1: Sends multiple values of SETTINGS_INITIAL_WINDOW_SIZE
+    #[tokio::test]
+    async fn last_value_is_used() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x4, 100), (0x4, 1)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.send_bytes(&get(1)).await;
+            client.recv_frame(frames::headers(1).response(200)).await;
+            client.recv_frame(frames::data(1, "h")).await;
+        })
+        .await;
+    }
+
+    /// http2/6.5.3/2: Sends a SETTINGS frame without ACK flag
+    #[tokio::test]
+    async fn settings_are_acknowledged() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x3, 100)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.7: PING
+mod http2_6_7 {
+    use super::*;
+
+    /// http2/6.7/1: Sends a PING frame
+    #[tokio::test]
+    async fn ping() {
+        run(|mut client| async move {
+            client.ping_pong(PING).await;
+        })
+        .await;
+    }
+
+    /// http2/6.7/2: Sends a PING frame with ACK
+    #[tokio::test]
+    async fn ping_with_ack() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&raw::frame(raw::PING, raw::ACK, 0, b"unsolici"))
+                .await;
+            // Only the second PING frame is answered.
+            client.ping_pong(PING).await;
+        })
+        .await;
+    }
+
+    /// http2/6.7/3: Sends a PING frame with a stream identifier field value
+    /// other than 0x0
+    #[tokio::test]
+    async fn ping_on_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&raw::frame(raw::PING, 0, 1, &PING)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.7/4: Sends a PING frame with a length field value other than 8
+    #[tokio::test]
+    async fn ping_with_invalid_length() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&raw::frame(raw::PING, 0, 0, &PING[..6]))
+                .await;
+            client
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.8: GOAWAY
+mod http2_6_8 {
+    use super::*;
+
+    /// http2/6.8/1: Sends a GOAWAY frame with a stream identifier other than
+    /// 0x0
+    #[tokio::test]
+    async fn go_away_on_stream() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::GOAWAY, 0, 1, &[0, 0, 0, 0, 0, 0, 0, 0]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9: WINDOW_UPDATE
+mod http2_6_9 {
+    use super::*;
+
+    /// http2/6.9/1: Sends a WINDOW_UPDATE frame with a flow control window
+    /// increment of 0
+    #[tokio::test]
+    async fn zero_increment_on_connection() {
+        run(|mut client| async move {
+            client.send_bytes(&window_update(0, 0)).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.9/2: Sends a WINDOW_UPDATE frame with a flow control window
+    /// increment of 0 on a stream
+    #[tokio::test]
+    async fn zero_increment_on_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&window_update(1, 0)).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.9/3: Sends a WINDOW_UPDATE frame with a length other than 4
+    /// octets
+    #[tokio::test]
+    async fn window_update_with_invalid_length() {
+        run(|mut client| async move {
+            let frame = raw::frame(raw::WINDOW_UPDATE, 0, 0, &[0, 0, 1]);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::FRAME_SIZE_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9.1: The Flow-Control Window
+mod http2_6_9_1 {
+    use super::*;
+
+    /// http2/6.9.1/1: Sends SETTINGS frame to set the initial window size to
+    /// 1 and sends HEADERS frame
+    #[tokio::test]
+    async fn initial_window_size_of_1() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x4, 1)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.send_bytes(&get(1)).await;
+            client.recv_frame(frames::headers(1).response(200)).await;
+            client.recv_frame(frames::data(1, "h")).await;
+        })
+        .await;
+    }
+
+    /// http2/6.9.1/2: Sends multiple WINDOW_UPDATE frames increasing the flow
+    /// control window to above 2^31-1
+    #[tokio::test]
+    async fn connection_window_overflow() {
+        run(|mut client| async move {
+            client.send_bytes(&window_update(0, (1 << 31) - 1)).await;
+            client.send_bytes(&window_update(0, (1 << 31) - 1)).await;
+            client
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.9.1/3: Sends multiple WINDOW_UPDATE frames increasing the flow
+    /// control window to above 2^31-1 on a stream
+    #[tokio::test]
+    async fn stream_window_overflow() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&window_update(1, (1 << 31) - 1)).await;
+            client.send_bytes(&window_update(1, (1 << 31) - 1)).await;
+            client
+                .assert_stream_error(1, &[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.9.2: Initial Flow-Control Window Size
+mod http2_6_9_2 {
+    use super::*;
+
+    /// http2/6.9.2/1: Changes SETTINGS_INITIAL_WINDOW_SIZE after sending
+    /// HEADERS frame
+    #[tokio::test]
+    async fn change_initial_window_size() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x4, 0)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.send_bytes(&get(1)).await;
+            client.recv_frame(frames::headers(1).response(200)).await;
+            client.send_bytes(&settings(&[(0x4, 1)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.recv_frame(frames::data(1, "h")).await;
+        })
+        .await;
+    }
+
+    /// http2/6.9.2/2: Sends a SETTINGS frame for window size to be negative
+    #[tokio::test]
+    async fn negative_window() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x4, 3)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.send_bytes(&get(1)).await;
+            client.recv_frame(frames::headers(1).response(200)).await;
+            client.recv_frame(frames::data(1, "hel")).await;
+
+            // The window of the stream is now -2
+            client.send_bytes(&settings(&[(0x4, 1)])).await;
+            client.recv_frame(frames::settings_ack()).await;
+            client.send_bytes(&window_update(1, 3)).await;
+            client.recv_frame(frames::data(1, "l")).await;
+        })
+        .await;
+    }
+
+    /// http2/6.9.2/3: Sends a SETTINGS_INITIAL_WINDOW_SIZE settings with an
+    /// exceeded maximum window size value
+    #[tokio::test]
+    async fn initial_window_size_too_large() {
+        run(|mut client| async move {
+            client.send_bytes(&settings(&[(0x4, 1 << 31)])).await;
+            client
+                .assert_connection_error(&[Reason::FLOW_CONTROL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §6.10: CONTINUATION
+mod http2_6_10 {
+    use super::*;
+
+    /// http2/6.10/1: Sends multiple CONTINUATION frames preceded by a HEADERS
+    /// frame
+    #[tokio::test]
+    async fn multiple_continuation_frames() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            let (head, tail) = block.split_at(block.len() / 2);
+            let (middle, tail) = tail.split_at(tail.len() / 2);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, head))
+                .await;
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, 0, 1, middle))
+                .await;
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, tail))
+                .await;
+            recv_response(&mut client, 1).await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/2: Sends a CONTINUATION frame followed by any frame other
+    /// than CONTINUATION
+    #[tokio::test]
+    async fn continuation_followed_by_data() {
+        run(|mut client| async move {
+            let block = raw::header_block(POST);
+            let (head, tail) = block.split_at(block.len() / 2);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, 0, 1, head))
+                .await;
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, 0, 1, tail))
+                .await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/3: Sends a CONTINUATION frame with 0x0 stream identifier
+    #[tokio::test]
+    async fn continuation_on_stream_0() {
+        run(|mut client| async move {
+            let block = raw::header_block(raw::GET);
+            let (head, tail) = block.split_at(block.len() / 2);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, raw::END_STREAM, 1, head))
+                .await;
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 0, tail))
+                .await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/4: Sends a CONTINUATION frame preceded by a HEADERS frame
+    /// with END_HEADERS flag
+    #[tokio::test]
+    async fn continuation_after_end_headers() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let block = raw::header_block(&[("x-test", "ok")]);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/5: Sends a CONTINUATION frame preceded by a CONTINUATION
+    /// frame with END_HEADERS flag
+    #[tokio::test]
+    async fn continuation_after_continuation_with_end_headers() {
+        run(|mut client| async move {
+            let block = raw::header_block(POST);
+            let (head, tail) = block.split_at(block.len() / 2);
+            client
+                .send_bytes(&raw::frame(raw::HEADERS, 0, 1, head))
+                .await;
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, tail))
+                .await;
+            let block = raw::header_block(&[("x-test", "ok")]);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/6.10/6: Sends a CONTINUATION frame preceded by a DATA frame
+    #[tokio::test]
+    async fn continuation_after_data() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_frame(frames::data(1, "test")).await;
+            let block = raw::header_block(&[("x-test", "ok")]);
+            client
+                .send_bytes(&raw::frame(raw::CONTINUATION, raw::END_HEADERS, 1, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §7: Error Codes
+mod http2_7 {
+    use super::*;
+
+    /// http2/7/1: Sends a GOAWAY frame with unknown error code
+    #[tokio::test]
+    async fn go_away_with_unknown_error_code() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let frame = raw::frame(raw::GOAWAY, 0, 0, &[0, 0, 0, 0, 0, 0, 0, 0xff]);
+            client.send_bytes(&frame).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+
+    /// http2/7/2: Sends a RST_STREAM frame with unknown error code
+    #[tokio::test]
+    async fn rst_stream_with_unknown_error_code() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_bytes(&rst_stream(1, 0xff)).await;
+            client.assert_no_error().await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1: HTTP Request/Response Exchange
+mod http2_8_1 {
+    use super::*;
+
+    /// http2/8.1/1: Sends a second HEADERS frame without the END_STREAM flag
+    #[tokio::test]
+    async fn second_headers_without_end_stream() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_frame(frames::data(1, "test")).await;
+            let block = raw::header_block(&[("x-test", "ok")]);
+            client.send_bytes(&headers(1, 0, &block)).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2: HTTP Header Fields
+mod http2_8_1_2 {
+    use super::*;
+
+    /// http2/8.1.2/1: Sends a HEADERS frame that contains the header field
+    /// name in uppercase letters
+    #[tokio::test]
+    async fn uppercase_field_name() {
+        run(|mut client| async move {
+            client.send_bytes(&get_with(1, &[("X-TEST", "ok")])).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.1: Pseudo-Header Fields
+mod http2_8_1_2_1 {
+    use super::*;
+
+    /// http2/8.1.2.1/1: Sends a HEADERS frame that contains a unknown
+    /// pseudo-header field
+    #[tokio::test]
+    async fn unknown_pseudo_header() {
+        run(|mut client| async move {
+            client.send_bytes(&get_with(1, &[(":test", "ok")])).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.1/2: Sends a HEADERS frame that contains the pseudo-header
+    /// field defined for response
+    #[tokio::test]
+    async fn response_pseudo_header() {
+        run(|mut client| async move {
+            client.send_bytes(&get_with(1, &[(":status", "200")])).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.1/3: Sends a HEADERS frame that contains a pseudo-header
+    /// field as trailers
+    #[tokio::test]
+    async fn pseudo_header_in_trailers() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            client.send_frame(frames::data(1, "test")).await;
+            let block = raw::header_block(&[(":method", "POST")]);
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.1/4: Sends a HEADERS frame that contains a pseudo-header
+    /// field that appears in a header block after a regular header field
+    #[tokio::test]
+    async fn pseudo_header_after_regular_field() {
+        run(|mut client| async move {
+            let block = raw::header_block(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                ("x-test", "ok"),
+                (":authority", "example.com"),
+                (":path", "/"),
+            ]);
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.2: Connection-Specific Header Fields
+mod http2_8_1_2_2 {
+    use super::*;
+
+    /// http2/8.1.2.2/1: Sends a HEADERS frame that contains the
+    /// connection-specific header field
+    #[tokio::test]
+    async fn connection_specific_field() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&get_with(1, &[("connection", "keep-alive")]))
+                .await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.2/2: Sends a HEADERS frame that contains the TE header
+    /// field with any value other than "trailers"
+    #[tokio::test]
+    async fn te_other_than_trailers() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&get_with(1, &[("te", "trailers, deflate")]))
+                .await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.3: Request Pseudo-Header Fields
+mod http2_8_1_2_3 {
+    use super::*;
+
+    /// Sends a `GET` request with the pseudo-header fields `fields`.
+    async fn send_request(fields: &[(&str, &str)]) {
+        let block = raw::header_block(fields);
+
+        run(|mut client| async move {
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.3/1: Sends a HEADERS frame with empty ":path"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn empty_path() {
+        send_request(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "example.com"),
+            (":path", ""),
+        ])
+        .await;
+    }
+
+    /// http2/8.1.2.3/2: Sends a HEADERS frame that omits ":method"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn missing_method() {
+        send_request(&[
+            (":scheme", "https"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ])
+        .await;
+    }
+
+    /// http2/8.1.2.3/3: Sends a HEADERS frame that omits ":scheme"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn missing_scheme() {
+        send_request(&[
+            (":method", "GET"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ])
+        .await;
+    }
+
+    /// http2/8.1.2.3/4: Sends a HEADERS frame that omits ":path"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn missing_path() {
+        send_request(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "example.com"),
+        ])
+        .await;
+    }
+
+    /// http2/8.1.2.3/5: Sends a HEADERS frame with duplicated ":method"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn duplicated_method() {
+        send_request(&[
+            (":method", "GET"),
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ])
+        .await;
+    }
+
+    /// http2/8.1.2.3/6: Sends a HEADERS frame with duplicated ":scheme"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn duplicated_scheme() {
+        send_request(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":scheme", "https"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ])
+        .await;
+    }
+
+    /// http2/8.1.2.3/7: Sends a HEADERS frame with duplicated ":path"
+    /// pseudo-header field
+    #[tokio::test]
+    async fn duplicated_path() {
+        send_request(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "example.com"),
+            (":path", "/"),
+            (":path", "/"),
+        ])
+        .await;
+    }
+}
+
+/// RFC 7540 §8.1.2.6: Malformed Requests and Responses
+mod http2_8_1_2_6 {
+    use super::*;
+
+    /// http2/8.1.2.6/1: Sends a HEADERS frame with the "content-length"
+    /// header field which does not equal the DATA frame payload length
+    #[tokio::test]
+    async fn content_length_mismatch() {
+        run(|mut client| async move {
+            let fields: Vec<_> = POST
+                .iter()
+                .chain(&[("content-length", "1")])
+                .cloned()
+                .collect();
+            client
+                .send_bytes(&headers(1, 0, &raw::header_block(&fields)))
+                .await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// http2/8.1.2.6/2: Sends a HEADERS frame with the "content-length"
+    /// header field which does not equal the sum of the multiple DATA frames
+    /// payload length
+    #[tokio::test]
+    async fn content_length_mismatch_over_multiple_data_frames() {
+        run(|mut client| async move {
+            let fields: Vec<_> = POST
+                .iter()
+                .chain(&[("content-length", "1")])
+                .cloned()
+                .collect();
+            client
+                .send_bytes(&headers(1, 0, &raw::header_block(&fields)))
+                .await;
+            client.send_frame(frames::data(1, "test")).await;
+            client.send_frame(frames::data(1, "test").eos()).await;
+            client
+                .assert_stream_error(1, &[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7540 §8.2: Server Push
+mod http2_8_2 {
+    use super::*;
+
+    /// http2/8.2/1: Sends a PUSH_PROMISE frame
+    #[tokio::test]
+    async fn push_promise_from_client() {
+        run(|mut client| async move {
+            client.send_bytes(&post(1)).await;
+            let mut payload = 2u32.to_be_bytes().to_vec();
+            payload.extend(raw::header_block(raw::GET));
+            let frame = raw::frame(raw::PUSH_PROMISE, raw::END_HEADERS, 1, &payload);
+            client.send_bytes(&frame).await;
+            client
+                .assert_connection_error(&[Reason::PROTOCOL_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §2.3.3: Index Address Space
+mod hpack_2_3_3 {
+    use super::*;
+
+    /// hpack/2.3.3/1: Sends a indexed header field representation with
+    /// invalid index
+    #[tokio::test]
+    async fn indexed_with_invalid_index() {
+        run(|mut client| async move {
+            // Index 62, the dynamic table is empty
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x82, 0xbe]))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// hpack/2.3.3/2: Sends a literal header field representation with
+    /// invalid index
+    #[tokio::test]
+    async fn literal_with_invalid_index() {
+        run(|mut client| async move {
+            // Name index 62, the dynamic table is empty
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x82, 0x7e, 0x01, b'a']))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §4.2: Maximum Table Size
+mod hpack_4_2 {
+    use super::*;
+
+    /// hpack/4.2/1: Sends a dynamic table size update larger than the value
+    /// of SETTINGS_HEADER_TABLE_SIZE
+    #[tokio::test]
+    async fn size_update_above_settings() {
+        run(|mut client| async move {
+            // Size 4,097
+            let mut block = vec![0x3f, 0xe2, 0x1f];
+            block.extend(raw::header_block(raw::GET));
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §5.2: String Literal Representation
+mod hpack_5_2 {
+    use super::*;
+
+    /// Sends a `GET` request with an `x-test` field, the value of which is
+    /// the Huffman encoded `value`.
+    async fn send_request(value: &[u8]) {
+        let mut block = raw::header_block(raw::GET);
+        block.extend(b"\x00\x06x-test");
+        block.push(0x80 | value.len() as u8);
+        block.extend(value);
+
+        run(|mut client| async move {
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+
+    /// hpack/5.2/1: Sends a Huffman-encoded string literal representation
+    /// with padding longer than 7 bits
+    #[tokio::test]
+    async fn padding_longer_than_7_bits() {
+        // `a` (00011), then 11 bits of padding
+        send_request(&[0x1f, 0xff]).await;
+    }
+
+    /// hpack/5.2/2: Sends a Huffman-encoded string literal representation
+    /// padded by zero
+    #[tokio::test]
+    async fn padding_with_zeros() {
+        // `a` (00011), then 3 bits of zeros
+        send_request(&[0x18]).await;
+    }
+
+    /// hpack/5.2/3: Sends a Huffman-encoded string literal representation
+    /// containing the EOS symbol
+    #[tokio::test]
+    async fn eos_symbol() {
+        // The EOS symbol is 30 bits of ones
+        send_request(&[0xff, 0xff, 0xff, 0xff]).await;
+    }
+}
+
+/// RFC 7541 §6.1: Indexed Header Field Representation
+mod hpack_6_1 {
+    use super::*;
+
+    /// hpack/6.1/1: Sends a indexed header field representation with index 0
+    #[tokio::test]
+    async fn index_0() {
+        run(|mut client| async move {
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &[0x82, 0x80]))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
+
+/// RFC 7541 §6.3: Dynamic Table Size Update
+mod hpack_6_3 {
+    use super::*;
+
+    /// hpack/6.3/1: Sends a dynamic table size update at the end of header
+    /// block
+    #[tokio::test]
+    async fn size_update_at_end_of_block() {
+        run(|mut client| async move {
+            let mut block = raw::header_block(raw::GET);
+            block.push(0x20);
+            client
+                .send_bytes(&headers(1, raw::END_STREAM, &block))
+                .await;
+            client
+                .assert_connection_error(&[Reason::COMPRESSION_ERROR])
+                .await;
+        })
+        .await;
+    }
+}
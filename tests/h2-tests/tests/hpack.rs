@@ -0,0 +1,167 @@
+use h2_support::prelude::*;
+
+use h2::hpack::{Decoder, DecoderError, Encoder, Field};
+use http::header::{HeaderName, HeaderValue};
+
+fn field(name: &'static str, value: &'static str) -> Field {
+    Field::new(
+        HeaderName::from_static(name),
+        HeaderValue::from_static(value),
+    )
+}
+
+fn request(path: &[u8]) -> Vec<Field> {
+    vec![
+        Field::from_bytes(b":method", b"GET").unwrap(),
+        Field::from_bytes(b":scheme", b"https").unwrap(),
+        Field::from_bytes(b":authority", b"example.com").unwrap(),
+        Field::from_bytes(b":path", path).unwrap(),
+        field("accept", "text/html"),
+        field("user-agent", "h2"),
+    ]
+}
+
+#[test]
+fn round_trip_shares_table() {
+    let mut encoder = Encoder::new();
+    let mut decoder = Decoder::new();
+
+    for path in &[&b"/"[..], &b"/index.html"[..]] {
+        let mut block = BytesMut::new();
+        encoder.encode(request(path), &mut block);
+
+        let fields = decoder.decode(&block).unwrap();
+        assert_eq!(fields, request(path));
+        assert!(fields[0].is_pseudo());
+        assert!(!fields[4].is_pseudo());
+    }
+
+    let encoded: Vec<_> = encoder
+        .table()
+        .entries()
+        .map(|e| (e.index(), e.name(), e.value()))
+        .collect();
+    let decoded: Vec<_> = decoder
+        .table()
+        .entries()
+        .map(|e| (e.index(), e.name(), e.value()))
+        .collect();
+
+    // `:path` is not indexed by the default policy.
+    assert_eq!(
+        encoded,
+        vec![
+            (62, "user-agent", &b"h2"[..]),
+            (63, "accept", &b"text/html"[..]),
+            (64, ":authority", &b"example.com"[..]),
+        ]
+    );
+    assert_eq!(encoded, decoded);
+    assert_eq!(encoder.table().size(), decoder.table().size());
+    assert_eq!(
+        decoder.table().get(63).unwrap().to_field(),
+        field("accept", "text/html")
+    );
+    assert!(decoder.table().get(65).is_none());
+}
+
+#[test]
+fn tables_count_evictions() {
+    let mut encoder = Encoder::with_max_table_size(64);
+    let mut decoder = Decoder::with_max_table_size(64);
+
+    // Each entry is 32 + 9 + 1 = 42 octets, so only one fits at a time.
+    for value in &["1", "2", "3"] {
+        let fields = vec![Field::new(
+            HeaderName::from_static("x-counter"),
+            HeaderValue::from_str(value).unwrap(),
+        )];
+
+        let mut block = BytesMut::new();
+        encoder.encode(fields.clone(), &mut block);
+        assert_eq!(decoder.decode(&block).unwrap(), fields);
+    }
+
+    for table in &[encoder.table(), decoder.table()] {
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.size(), 42);
+        assert_eq!(table.max_size(), 64);
+        assert_eq!(table.evictions(), 2);
+    }
+
+    // Shrinking the table evicts the remaining entry.
+    encoder.update_max_table_size(0);
+    decoder.set_max_table_size(0);
+
+    let mut block = BytesMut::new();
+    encoder.encode(vec![field("accept", "*/*")], &mut block);
+    assert_eq!(
+        decoder.decode(&block).unwrap(),
+        vec![field("accept", "*/*")]
+    );
+
+    for table in &[encoder.table(), decoder.table()] {
+        assert!(table.is_empty());
+        assert_eq!(table.max_size(), 0);
+        assert_eq!(table.evictions(), 3);
+    }
+}
+
+#[test]
+fn sensitive_values_are_never_indexed() {
+    let mut encoder = Encoder::new();
+    let mut decoder = Decoder::new();
+
+    let mut token = HeaderValue::from_static("Bearer secret");
+    token.set_sensitive(true);
+
+    let fields = vec![Field::new(http::header::AUTHORIZATION, token)];
+
+    let mut block = BytesMut::new();
+    encoder.encode(fields.clone(), &mut block);
+
+    // Literal header field never indexed, with the static name index 23.
+    assert_eq!(&block[..2], &[0x1f, 0x08]);
+
+    let decoded = decoder.decode(&block).unwrap();
+    assert_eq!(decoded, fields);
+    assert!(decoded[0].is_sensitive());
+
+    let (name, value) = decoded.into_iter().next().unwrap().into_header().unwrap();
+    assert_eq!(name, http::header::AUTHORIZATION);
+    assert!(value.is_sensitive());
+
+    assert!(encoder.table().is_empty());
+    assert!(decoder.table().is_empty());
+}
+
+#[test]
+fn encode_grows_buffer() {
+    let mut encoder = Encoder::new();
+    let mut decoder = Decoder::new();
+
+    let value = "x".repeat(8 * 1024);
+    let fields = vec![Field::new(
+        HeaderName::from_static("x-large"),
+        HeaderValue::from_str(&value).unwrap(),
+    )];
+
+    let mut block = BytesMut::new();
+    encoder.encode(fields.clone(), &mut block);
+
+    assert_eq!(decoder.decode(&block).unwrap(), fields);
+}
+
+#[test]
+fn decode_errors() {
+    let mut decoder = Decoder::new();
+
+    // Indexed field 127, which is past the end of the table.
+    assert_eq!(
+        decoder.decode(&[0xff, 0x00]),
+        Err(DecoderError::InvalidTableIndex)
+    );
+
+    assert!(Field::from_bytes(b":unknown", b"").is_err());
+    assert!(Field::from_bytes(b"Upper", b"").is_err());
+}
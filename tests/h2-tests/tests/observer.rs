@@ -0,0 +1,212 @@
+#![deny(warnings)]
+
+use futures::future::join;
+use futures::StreamExt;
+use h2_support::prelude::*;
+use std::sync::{Arc, Mutex};
+
+/// Records the events seen by a connection
+#[derive(Debug, Clone, Default)]
+struct Recorder {
+    sent: Arc<Mutex<Vec<Event>>>,
+    received: Arc<Mutex<Vec<Event>>>,
+    states: Arc<Mutex<Vec<ConnectionState>>>,
+}
+
+#[derive(Debug)]
+struct Event {
+    kind: FrameKind,
+    flags: u8,
+    stream_id: h2::StreamId,
+    len: usize,
+    fields: Option<Vec<(String, Vec<u8>)>>,
+}
+
+impl Event {
+    fn new(event: &FrameEvent) -> Event {
+        Event {
+            kind: event.kind(),
+            flags: event.flags(),
+            stream_id: event.stream_id(),
+            len: event.len(),
+            fields: event.header_fields().map(|fields| {
+                fields
+                    .map(|(name, value)| (name.to_string(), value.to_vec()))
+                    .collect()
+            }),
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&[u8]> {
+        self.fields
+            .as_ref()?
+            .iter()
+            .find(|field| field.0 == name)
+            .map(|field| &field.1[..])
+    }
+}
+
+impl FrameObserver for Recorder {
+    fn frame_sent(&self, event: &FrameEvent) {
+        self.sent.lock().unwrap().push(Event::new(event));
+    }
+
+    fn frame_received(&self, event: &FrameEvent) {
+        self.received.lock().unwrap().push(Event::new(event));
+    }
+
+    fn state_changed(&self, state: ConnectionState) {
+        self.states.lock().unwrap().push(state);
+    }
+}
+
+#[tokio::test]
+async fn observe_client_frames() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let recorder = Recorder::default();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+    };
+
+    let observer = recorder.clone();
+    let h2 = async move {
+        let (mut client, mut h2) = client::Builder::new()
+            .frame_observer(observer)
+            .handshake::<_, Bytes>(io)
+            .await
+            .unwrap();
+
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+
+        let (response, _) = client.send_request(request, true).unwrap();
+        let stream_id = response.stream_id();
+
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        drop(client);
+        h2.await.unwrap();
+        stream_id
+    };
+
+    let (_, stream_id) = join(srv, h2).await;
+
+    let sent = recorder.sent.lock().unwrap();
+    let headers = find(&sent, FrameKind::Headers);
+    assert_eq!(headers.stream_id, stream_id);
+    // END_STREAM | END_HEADERS
+    assert_eq!(headers.flags, 0x5);
+    assert_eq!(headers.field(":method"), Some(&b"GET"[..]));
+    assert_eq!(headers.field(":path"), Some(&b"/"[..]));
+
+    let settings: Vec<_> = sent
+        .iter()
+        .filter(|event| event.kind == FrameKind::Settings)
+        .collect();
+    assert_eq!(settings.len(), 2);
+    // ACK
+    assert_eq!(settings[1].flags, 0x1);
+    assert_eq!(settings[1].len, 0);
+    assert!(settings[1].fields.is_none());
+
+    let received = recorder.received.lock().unwrap();
+    let headers = find(&received, FrameKind::Headers);
+    assert_eq!(headers.stream_id, stream_id);
+    assert_eq!(headers.field(":status"), Some(&b"200"[..]));
+
+    let states = recorder.states.lock().unwrap();
+    assert_eq!(
+        *states,
+        [
+            ConnectionState::Open,
+            ConnectionState::Closing(Reason::NO_ERROR),
+            ConnectionState::Closed(Reason::NO_ERROR),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn observe_continuation_frames() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+    let recorder = Recorder::default();
+
+    // Huffman coding does not shorten `~`, so the fields span two frames
+    let value = "~".repeat(6_000);
+    let names = ["a0", "a1", "a2", "a3"];
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+
+        let mut response = frames::headers(1).response(200);
+        for name in &names {
+            response = response.field(*name, &value[..]);
+        }
+        client.recv_frame(response.eos()).await;
+    };
+
+    let observer = recorder.clone();
+    let srv = async move {
+        let mut srv = server::Builder::new()
+            .frame_observer(observer)
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let (_, mut stream) = srv.next().await.unwrap().unwrap();
+
+        let mut rsp = http::Response::builder();
+        rsp.status(200);
+        for name in &names {
+            rsp.header(*name, &"~".repeat(6_000)[..]);
+        }
+        stream.send_response(rsp.body(()).unwrap(), true).unwrap();
+
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+
+    let sent = recorder.sent.lock().unwrap();
+
+    let headers = find(&sent, FrameKind::Headers);
+    // END_STREAM
+    assert_eq!(headers.flags, 0x1);
+    assert!(headers.fields.is_none());
+    assert!(headers.len <= 16_384);
+
+    let continuation = find(&sent, FrameKind::Continuation);
+    assert_eq!(continuation.stream_id, headers.stream_id);
+    // END_HEADERS
+    assert_eq!(continuation.flags, 0x4);
+    assert_eq!(continuation.field(":status"), Some(&b"200"[..]));
+    assert_eq!(continuation.field("a3").map(<[u8]>::len), Some(6_000));
+}
+
+fn find(events: &[Event], kind: FrameKind) -> &Event {
+    let mut found = events.iter().filter(|event| event.kind == kind);
+    let event = found.next().expect("no event");
+    assert!(found.next().is_none(), "more than one {:?} event", kind);
+    event
+}
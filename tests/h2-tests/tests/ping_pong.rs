@@ -184,3 +184,44 @@ async fn user_notifies_when_connection_closes() {
         "broken pipe",
     );
 }
+
+#[tokio::test]
+async fn keep_alive_ping_timeout_closes_connection() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+
+        srv_timer.advance(Duration::from_secs(30));
+        srv.recv_frame(frames::ping(frame::Ping::KEEPALIVE)).await;
+        srv.send_frame(frames::ping(frame::Ping::KEEPALIVE).pong())
+            .await;
+        // ensure the client has seen the pong before moving the clock
+        srv.ping_pong([1; 8]).await;
+
+        srv_timer.advance(Duration::from_secs(30));
+        srv.recv_frame(frames::ping(frame::Ping::KEEPALIVE)).await;
+        // never acknowledge the second PING
+        srv_timer.advance(Duration::from_secs(20));
+        srv.recv_eof().await;
+    };
+
+    let client = async move {
+        let (client, conn) = client::Builder::new()
+            .timer(timer)
+            .keep_alive_interval(Duration::from_secs(30))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let err = conn.await.expect_err("client");
+        assert_eq!(err.get_io().unwrap().kind(), std::io::ErrorKind::TimedOut);
+        drop(client);
+    };
+
+    join(srv, client).await;
+}
@@ -61,6 +61,65 @@ async fn recv_push_works() {
     join(mock, h2).await;
 }
 
+#[tokio::test]
+async fn recv_push_promise_after_graceful_shutdown_is_refused() {
+    let _ = env_logger::try_init();
+
+    let (io, mut srv) = mock::new();
+    let mock = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.send_frame(
+            frames::push_promise(1, 2).request("GET", "https://http2.akamai.com/style.css"),
+        )
+        .await;
+        srv.send_frame(frames::headers(2).response(200).eos()).await;
+        srv.recv_frame(frames::go_away(2)).await;
+        // Promised after our GOAWAY, so it is ignored.
+        srv.send_frame(
+            frames::push_promise(1, 4).request("GET", "https://http2.akamai.com/style2.css"),
+        )
+        .await;
+        srv.send_frame(frames::headers(4).response(200).eos()).await;
+        srv.send_frame(frames::data(1, "").eos()).await;
+        srv.recv_eof().await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.unwrap();
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+        let (mut resp, _) = client.send_request(request, true).unwrap();
+        let mut pushed = resp.push_promises();
+
+        let push = h2.drive(pushed.next()).await.unwrap().unwrap();
+        let (_, push) = push.into_parts();
+        let push = h2.drive(push).await.unwrap();
+        assert_eq!(push.status(), StatusCode::OK);
+
+        h2.graceful_shutdown();
+
+        let resp = h2.drive(resp).await.unwrap();
+        let body = h2.drive(resp.into_body().try_concat()).await.unwrap();
+        assert!(body.is_empty());
+        assert!(pushed.next().await.is_none());
+
+        h2.await.expect("h2");
+    };
+
+    join(mock, h2).await;
+}
+
 #[tokio::test]
 async fn pushed_streams_arent_dropped_too_early() {
     // tests that by default, received push promises work
@@ -315,7 +374,7 @@ async fn recv_invalid_push_promise_headers_is_stream_protocol_error() {
                 .field(http::header::CONTENT_LENGTH, 0),
         )
         .await;
-        srv.send_frame(frames::headers(1).response(404).eos()).await;
+        srv.send_frame(frames::data(1, "").eos()).await;
         srv.recv_frame(frames::reset(2).protocol_error()).await;
         srv.recv_frame(frames::reset(4).protocol_error()).await;
         srv.send_frame(frames::headers(6).response(200).eos()).await;
@@ -455,3 +514,56 @@ async fn recv_push_promise_dup_stream_id() {
 
     join(mock, h2).await;
 }
+
+#[tokio::test]
+async fn recv_malformed_push_promise_is_stream_error() {
+    let _ = env_logger::try_init();
+
+    let (io, mut srv) = mock::new();
+    let mock = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        // A malformed header block promised on stream 1, which the client
+        // has open, is a stream error on stream 1.
+        let mut payload = 2u32.to_be_bytes().to_vec();
+        payload.extend(raw::header_block(&[
+            (":method", "GET"),
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "http2.akamai.com"),
+            (":path", "/style.css"),
+        ]));
+        let frame = raw::frame(raw::PUSH_PROMISE, raw::END_HEADERS, 1, &payload);
+        srv.send_bytes(&frame).await;
+        srv.recv_frame(frames::reset(1).protocol_error()).await;
+        // The connection is still usable.
+        srv.recv_frame(
+            frames::headers(3)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(3).response(200).eos()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.unwrap();
+
+        let err = h2
+            .drive(client.get("https://http2.akamai.com/"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+
+        let res = h2.drive(client.get("https://http2.akamai.com/")).await;
+        assert_eq!(res.unwrap().status(), 200);
+    };
+
+    join(mock, h2).await;
+}
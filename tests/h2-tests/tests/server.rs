@@ -136,6 +136,38 @@ async fn recv_invalid_authority() {
     join(client, srv).await;
 }
 
+#[tokio::test]
+async fn recv_malformed_headers_on_stream_0() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    // Malformed headers are a stream error, but there is no stream 0 to
+    // reset. The stream identifier is checked first.
+    let block = raw::header_block(&[
+        (":method", "GET"),
+        (":method", "GET"),
+        (":scheme", "https"),
+        (":authority", "example.com"),
+        (":path", "/"),
+    ]);
+    let frame = raw::frame(raw::HEADERS, raw::END_HEADERS | raw::END_STREAM, 0, &block);
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client.send_bytes(&frame).await;
+        client.recv_frame(frames::go_away(0).protocol_error()).await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let err = srv.next().await.unwrap().unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+    };
+
+    join(client, srv).await;
+}
+
 #[tokio::test]
 async fn recv_connection_header() {
     let _ = env_logger::try_init();
@@ -324,6 +356,95 @@ async fn graceful_shutdown() {
     join(client, srv).await;
 }
 
+#[tokio::test]
+async fn drain_reports_progress() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client.recv_frame(frames::go_away(2147483647)).await;
+        client.recv_frame(frames::ping(frame::Ping::SHUTDOWN)).await;
+        client
+            .recv_frame(frames::headers(1).response(200).eos())
+            .await;
+        client
+            .send_frame(frames::ping(frame::Ping::SHUTDOWN).pong())
+            .await;
+        client.recv_frame(frames::go_away(1)).await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let (_req, mut stream) = srv.next().await.unwrap().unwrap();
+
+        srv.drain(futures::future::pending(), Reason::INTERNAL_ERROR);
+        assert_eq!(srv.drain_progress().await.unwrap().unwrap(), 1);
+
+        let rsp = http::Response::builder().status(200).body(()).unwrap();
+        stream.send_response(rsp, true).unwrap();
+
+        assert_eq!(srv.drain_progress().await.unwrap().unwrap(), 0);
+        assert!(srv.drain_progress().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn drain_deadline_escalates_to_abrupt_shutdown() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+    let (deadline_tx, deadline_rx) = futures::channel::oneshot::channel();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client.recv_frame(frames::go_away(2147483647)).await;
+        client.recv_frame(frames::ping(frame::Ping::SHUTDOWN)).await;
+        client
+            .send_frame(frames::ping(frame::Ping::SHUTDOWN).pong())
+            .await;
+        client.recv_frame(frames::go_away(1)).await;
+        // The request is never responded to, so the deadline is reached.
+        deadline_tx.send(()).unwrap();
+        client.recv_frame(frames::go_away(1).internal_error()).await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let (_req, _stream) = srv.next().await.unwrap().unwrap();
+
+        let deadline = async move {
+            deadline_rx.await.expect("deadline_rx");
+        };
+        srv.drain(deadline, Reason::INTERNAL_ERROR);
+
+        assert_eq!(srv.drain_progress().await.unwrap().unwrap(), 1);
+        assert_eq!(srv.drain_progress().await.unwrap().unwrap(), 0);
+        assert!(srv.drain_progress().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
 #[tokio::test]
 async fn sends_reset_cancel_when_res_body_is_dropped() {
     let _ = env_logger::try_init();
@@ -631,3 +752,438 @@ async fn request_without_authority() {
 
     join(client, srv).await;
 }
+
+#[tokio::test]
+async fn idle_timeout_shuts_down_gracefully() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+    let timer = MockTimer::new();
+
+    let client_timer = timer.clone();
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        // No stream was opened
+        client_timer.advance(Duration::from_secs(10));
+        client.recv_frame(frames::go_away(2147483647)).await;
+        client.recv_frame(frames::ping(frame::Ping::SHUTDOWN)).await;
+        client
+            .send_frame(frames::ping(frame::Ping::SHUTDOWN).pong())
+            .await;
+        client.recv_frame(frames::go_away(0)).await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::Builder::new()
+            .timer(timer)
+            .idle_timeout(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn settings_ack_timeout_closes_connection() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+    let timer = MockTimer::new();
+
+    let client_timer = timer.clone();
+    let client = async move {
+        client.write_preface().await;
+        client.send_frame(frames::settings()).await;
+        client.recv_frame(frames::settings()).await;
+        client.recv_frame(frames::settings_ack()).await;
+        // The server's SETTINGS are never acknowledged.
+        client_timer.advance(Duration::from_secs(10));
+        client
+            .recv_frame(frames::go_away(0).reason(Reason::SETTINGS_TIMEOUT))
+            .await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::Builder::new()
+            .timer(timer)
+            .settings_ack_timeout(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+        let err = srv.next().await.unwrap().unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::SETTINGS_TIMEOUT));
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn idle_timeout_without_timer_errors() {
+    let _ = env_logger::try_init();
+    let (io, _client) = mock::new();
+
+    let err = server::Builder::new()
+        .idle_timeout(Duration::from_secs(10))
+        .handshake::<_, Bytes>(io)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "user error: timeout without a timer");
+}
+
+#[tokio::test]
+async fn handshake_timeout_without_preface() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+    let timer = MockTimer::new();
+
+    let client_timer = timer.clone();
+    let client = async move {
+        // The server sends its SETTINGS right away, but the connection
+        // preface is never sent.
+        client.recv_frame(frames::settings()).await;
+        client_timer.advance(Duration::from_secs(10));
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let err = server::Builder::new()
+            .timer(timer)
+            .handshake_timeout(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_io().unwrap().kind(), std::io::ErrorKind::TimedOut);
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_header_table_size_from_client() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client
+            .assert_server_handshake_with_settings(frames::settings().header_table_size(0))
+            .await;
+        assert_default_settings!(settings);
+        // Our SETTINGS were acknowledged, so the server's next header block
+        // must begin with a dynamic table size update.
+        client.codec_mut().set_recv_header_table_size(0);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client
+            .recv_frame(frames::headers(1).response(200).eos())
+            .await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let (req, mut stream) = srv.next().await.unwrap().unwrap();
+
+        assert_eq!(req.method(), &http::Method::GET);
+
+        let rsp = http::Response::builder().status(200).body(()).unwrap();
+        stream.send_response(rsp, true).unwrap();
+
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_never_indexed_field_is_sensitive() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+
+        // Sensitive values are sent as never indexed literals.
+        let mut token = http::HeaderValue::from_static("Bearer secret");
+        token.set_sensitive(true);
+
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .field("authorization", &token)
+                    .field("x-request-id", "42")
+                    .eos(),
+            )
+            .await;
+        client
+            .recv_frame(frames::headers(1).response(200).eos())
+            .await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let (req, mut stream) = srv.next().await.unwrap().unwrap();
+
+        assert!(req.headers()["authorization"].is_sensitive());
+        assert!(!req.headers()["x-request-id"].is_sensitive());
+
+        let rsp = http::Response::builder().status(200).body(()).unwrap();
+        stream.send_response(rsp, true).unwrap();
+
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_ping_with_bad_length_is_frame_size_error() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_bytes(&[
+                0, 0, 7, // length: 7, PING payloads are 8 octets
+                6, // type: PING
+                0, // flags
+                0, 0, 0, 0, // stream identifier: 0
+                0, 0, 0, 0, 0, 0, 0,
+            ])
+            .await;
+        client
+            .recv_frame(frames::go_away(0).reason(Reason::FRAME_SIZE_ERROR))
+            .await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let err = srv.next().await.unwrap().unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::FRAME_SIZE_ERROR));
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_too_big_initial_window_size_is_flow_control_error() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_bytes(&[
+                0, 0, 6, // length: 6
+                4, // type: SETTINGS
+                0, // flags
+                0, 0, 0, 0, // stream identifier: 0
+                0, 4, // SETTINGS_INITIAL_WINDOW_SIZE
+                0x80, 0, 0, 0, // 2^31, above the maximum window size
+            ])
+            .await;
+        client
+            .recv_frame(frames::go_away(0).reason(Reason::FLOW_CONTROL_ERROR))
+            .await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let err = srv.next().await.unwrap().unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::FLOW_CONTROL_ERROR));
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_go_away_on_stream_is_protocol_error() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_bytes(&[
+                0, 0, 8, // length: 8
+                7, // type: GOAWAY
+                0, // flags
+                0, 0, 0, 1, // stream identifier: 1, GOAWAY applies to the connection
+                0, 0, 0, 0, // last stream identifier: 0
+                0, 0, 0, 0, // error code: NO_ERROR
+            ])
+            .await;
+        client.recv_frame(frames::go_away(0).protocol_error()).await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let err = srv.next().await.unwrap().unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_preface_without_settings_is_protocol_error() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        client.write_preface().await;
+        // The preface must be followed by a SETTINGS frame.
+        client.send_frame(frames::ping([0; 8])).await;
+        client.recv_frame(frames::settings()).await;
+        client.recv_frame(frames::go_away(0).protocol_error()).await;
+        client.recv_eof().await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let err = srv.next().await.unwrap().unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_data_after_end_stream_is_stream_closed() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client.send_frame(frames::data(1, &b"hello"[..])).await;
+        client
+            .recv_frame(frames::reset(1).reason(Reason::STREAM_CLOSED))
+            .await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let (req, _stream) = srv.next().await.unwrap().unwrap();
+        assert_eq!(req.method(), &http::Method::GET);
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_headers_after_end_stream_is_stream_closed() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client
+            .send_frame(frames::headers(1).field("trailer", "value").eos())
+            .await;
+        client
+            .recv_frame(frames::reset(1).reason(Reason::STREAM_CLOSED))
+            .await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+        let (req, _stream) = srv.next().await.unwrap().unwrap();
+        assert_eq!(req.method(), &http::Method::GET);
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
+
+#[tokio::test]
+async fn recv_headers_on_released_stream_is_stream_closed() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let client = async move {
+        let settings = client.assert_server_handshake().await;
+        assert_default_settings!(settings);
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client
+            .recv_frame(frames::headers(1).response(200).eos())
+            .await;
+        // Stream 1 is closed and released, but it is the last stream opened,
+        // so it cannot be mistaken for a stream that was skipped.
+        client
+            .send_frame(
+                frames::headers(1)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client
+            .recv_frame(frames::reset(1).reason(Reason::STREAM_CLOSED))
+            .await;
+        // The connection is still usable.
+        client
+            .send_frame(
+                frames::headers(3)
+                    .request("GET", "https://example.com/")
+                    .eos(),
+            )
+            .await;
+        client
+            .recv_frame(frames::headers(3).response(200).eos())
+            .await;
+    };
+
+    let srv = async move {
+        let mut srv = server::handshake(io).await.expect("handshake");
+
+        for _ in 0..2 {
+            let (req, mut stream) = srv.next().await.unwrap().unwrap();
+            assert_eq!(req.method(), &http::Method::GET);
+
+            let rsp = http::Response::builder().status(200).body(()).unwrap();
+            stream.send_response(rsp, true).unwrap();
+        }
+
+        assert!(srv.next().await.is_none());
+    };
+
+    join(client, srv).await;
+}
@@ -193,6 +193,52 @@ async fn closed_streams_are_released() {
     join(srv, h2).await;
 }
 
+#[tokio::test]
+async fn releasing_capacity_of_closed_stream_does_not_keep_it_wired() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.unwrap();
+        let request = Request::get("https://example.com/").body(()).unwrap();
+
+        let (response, _) = client.send_request(request, true).unwrap();
+        let response = h2.drive(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (_, mut body) = response.into_parts();
+        for _ in 0..2 {
+            let data = h2.drive(body.data()).await.unwrap().unwrap();
+            assert_eq!(data.len(), 16_384);
+        }
+        assert!(body.is_end_stream());
+
+        // Enough to update the stream window, if the stream were still open
+        body.release_capacity()
+            .release_capacity(16_384 * 2)
+            .unwrap();
+        drop(body);
+
+        // The stream state is now free
+        assert_eq!(0, client.num_wired_streams());
+    };
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.send_frame(frames::data(1, vec![0; 16_384])).await;
+        srv.send_frame(frames::data(1, vec![0; 16_384]).eos()).await;
+    };
+    join(srv, h2).await;
+}
+
 #[tokio::test]
 async fn errors_if_recv_frame_exceeds_max_frame_size() {
     let _ = env_logger::try_init();
@@ -545,6 +591,116 @@ async fn rst_stream_expires() {
     join(srv, client).await;
 }
 
+#[tokio::test]
+async fn rst_stream_expires_with_timer() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.send_frame(frames::data(1, vec![0; 16_384])).await;
+        srv.recv_frame(frames::reset(1).cancel()).await;
+        // move the clock to the end of the configured duration
+        srv_timer.advance(Duration::from_secs(10));
+        srv.ping_pong([1; 8]).await;
+        // sending frame after canceled!
+        srv.send_frame(frames::data(1, vec![0; 16_384]).eos()).await;
+        // window capacity is returned
+        srv.recv_frame(frames::window_update(0, 16_384 * 2)).await;
+        // and then stream error
+        srv.recv_frame(frames::reset(1).stream_closed()).await;
+    };
+
+    let client = async move {
+        let (mut client, conn) = client::Builder::new()
+            .timer(timer)
+            .reset_stream_duration(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let req = async {
+            let resp = client.get("https://example.com/").await.expect("response");
+            assert_eq!(resp.status(), StatusCode::OK);
+            // drop resp will send a reset
+        };
+
+        // no connection error should happen
+        let mut conn = Box::pin(async move { conn.await.expect("client") });
+        conn.drive(req).await;
+        conn.await;
+        drop(client);
+    };
+
+    join(srv, client).await;
+}
+
+#[tokio::test]
+async fn rst_stream_expires_once_duration_elapsed() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.send_frame(frames::data(1, vec![0; 16])).await;
+        srv.recv_frame(frames::reset(1).cancel()).await;
+        // just before the end of the configured duration, the frame is
+        // still ignored
+        srv_timer.advance(Duration::from_secs(10) - Duration::from_millis(1));
+        srv.ping_pong([1; 8]).await;
+        srv.send_frame(frames::data(1, vec![0; 16])).await;
+        srv.ping_pong([2; 8]).await;
+        // the stream expires once the duration has fully elapsed
+        srv_timer.advance(Duration::from_millis(1));
+        srv.ping_pong([3; 8]).await;
+        srv.send_frame(frames::data(1, vec![0; 16]).eos()).await;
+        srv.recv_frame(frames::reset(1).stream_closed()).await;
+    };
+
+    let client = async move {
+        let (mut client, conn) = client::Builder::new()
+            .timer(timer)
+            .reset_stream_duration(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .expect("handshake");
+
+        let req = async {
+            let resp = client.get("https://example.com/").await.expect("response");
+            assert_eq!(resp.status(), StatusCode::OK);
+            // drop resp will send a reset
+        };
+
+        // no connection error should happen
+        let mut conn = Box::pin(async move { conn.await.expect("client") });
+        conn.drive(req).await;
+        conn.await;
+        drop(client);
+    };
+
+    join(srv, client).await;
+}
+
 #[tokio::test]
 async fn rst_stream_max() {
     let _ = env_logger::try_init();
@@ -944,7 +1100,7 @@ async fn srv_window_update_on_lower_stream_id() {
             frames::push_promise(7, 2).request("GET", "https://http2.akamai.com/style.css"),
         )
         .await;
-        srv.send_frame(frames::headers(7).eos()).await;
+        srv.send_frame(frames::headers(7).response(200).eos()).await;
         srv.recv_frame(frames::reset(2).cancel()).await;
         srv.send_frame(frames::window_update(5, 66666)).await;
     };
@@ -978,3 +1134,49 @@ async fn srv_window_update_on_lower_stream_id() {
     };
     join(srv, client).await;
 }
+
+#[tokio::test]
+async fn reset_after_connection_closed() {
+    // A stream reset once the connection has ended must not be queued for
+    // expiration, as nothing would ever reap it.
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+
+    // Rendezvous when the request has been received
+    let (tx, rx) = crate::futures::channel::oneshot::channel();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        tx.send(()).unwrap();
+        srv.recv_frame(frames::go_away(0)).await;
+        srv.recv_frame(frames::reset(1).cancel()).await;
+    };
+
+    let client = async move {
+        let (mut client, mut conn) = client::handshake(io).await.expect("handshake");
+        let request = Request::builder()
+            .uri("https://example.com/")
+            .body(())
+            .unwrap();
+
+        let (mut resp, mut stream) = client.send_request(request, true).expect("send_request");
+        conn.drive(rx).await.unwrap();
+        conn.graceful_shutdown();
+        stream.send_reset(Reason::CANCEL);
+        conn.await.expect("client");
+
+        resp.cancel(Reason::CANCEL);
+        drop(resp);
+        drop(stream);
+        drop(client);
+    };
+
+    join(srv, client).await;
+}
@@ -1,3 +1,4 @@
+use futures::future::join;
 use futures::StreamExt;
 use h2_support::prelude::*;
 
@@ -104,6 +105,50 @@ async fn send_trailers_immediately() {
     h2.await.unwrap();
 }
 
+#[tokio::test]
+async fn recv_trailers_with_pseudo_header_is_stream_error() {
+    let _ = env_logger::try_init();
+
+    let (io, mut srv) = mock::new();
+    let mock = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        // Trailers cannot carry a `:status`
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+        srv.recv_frame(frames::reset(1).protocol_error()).await;
+    };
+
+    let h2 = async move {
+        let (mut client, mut h2) = client::handshake(io).await.unwrap();
+        let request = Request::builder()
+            .uri("https://http2.akamai.com/")
+            .body(())
+            .unwrap();
+        let (response, _) = client.send_request(request, true).unwrap();
+
+        let response = h2.run(response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (_, mut body) = response.into_parts();
+        let err = h2
+            .run(poll_fn(|cx| body.poll_trailers(cx)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), Some(Reason::PROTOCOL_ERROR));
+
+        h2.await.unwrap();
+    };
+
+    join(mock, h2).await;
+}
+
 #[test]
 #[ignore]
 fn recv_trailers_without_eos() {
@@ -0,0 +1,198 @@
+#![deny(warnings)]
+
+use futures::future::join;
+use futures::StreamExt;
+use h2::transcript::{Direction, Recorder, Transcript};
+use h2_support::prelude::*;
+
+async fn get<T>(io: T)
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut client, mut h2) = client::handshake(io).await.unwrap();
+
+    let request = Request::builder()
+        .uri("https://example.com/")
+        .body(())
+        .unwrap();
+
+    let (response, _) = client.send_request(request, true).unwrap();
+    let response = h2.drive(response).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    drop(client);
+    h2.await.unwrap();
+}
+
+async fn serve<T>(io: T)
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut srv = server::handshake(io).await.expect("handshake");
+
+    while let Some(request) = srv.next().await {
+        let (request, mut stream) = request.unwrap();
+        assert_eq!(request.uri().path(), "/");
+
+        let rsp = http::Response::builder().status(200).body(()).unwrap();
+        let mut body = stream.send_response(rsp, false).unwrap();
+        body.send_data("hello".into(), true).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn record_and_replay_client() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let recorder = Recorder::new();
+
+    // Nothing is sent to the client before it is done writing, so that the
+    // order of its frames doesn't depend on scheduling when replayed.
+    let srv = async move {
+        srv.read_preface().await.unwrap();
+        srv.recv_frame(frames::settings()).await;
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::settings()).await;
+        srv.recv_frame(frames::settings_ack()).await;
+        srv.send_frame(frames::settings_ack()).await;
+        srv.send_frame(frames::headers(1).response(200).eos()).await;
+    };
+
+    join(srv, get(recorder.wrap(io))).await;
+
+    let transcript = recorder.transcript();
+    assert!(transcript
+        .bytes(Direction::Sent)
+        .starts_with(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"));
+
+    // Saving and loading the transcript keeps all the records
+    let mut saved = vec![];
+    transcript.write_to(&mut saved).unwrap();
+    let loaded = Transcript::read_from(&saved[..]).unwrap();
+    assert_eq!(loaded, transcript);
+
+    // The same client replays it
+    let (io, mut srv) = mock::new();
+    let srv = async move {
+        replay::replay(&mut srv, &loaded).await;
+    };
+
+    join(srv, get(io)).await;
+}
+
+#[tokio::test]
+async fn replay_server_transcript() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    let transcript = replay::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/transcripts/server_get.h2t"
+    ));
+
+    let client = async move {
+        replay::replay(&mut client, &transcript).await;
+    };
+
+    join(client, serve(io)).await;
+}
+
+#[tokio::test]
+async fn replay_frames_sent_in_another_order() {
+    let _ = env_logger::try_init();
+    let (io, mut client) = mock::new();
+
+    // The server now acknowledges the SETTINGS before responding
+    let transcript = replay::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/transcripts/server_get_reordered.h2t"
+    ));
+
+    let client = async move {
+        replay::replay(&mut client, &transcript).await;
+    };
+
+    join(client, serve(io)).await;
+}
+
+#[tokio::test]
+async fn replay_with_timer_moves_the_clock() {
+    let _ = env_logger::try_init();
+    let (io, mut srv) = mock::new();
+    let timer = MockTimer::new();
+
+    let transcript = replay::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/transcripts/client_idle.h2t"
+    ));
+
+    let srv_timer = timer.clone();
+    let srv = async move {
+        replay::replay_with_timer(&mut srv, &transcript, &srv_timer).await;
+    };
+
+    let client = async move {
+        let (client, h2) = client::Builder::new()
+            .timer(timer)
+            .idle_timeout(Duration::from_secs(10))
+            .handshake::<_, Bytes>(io)
+            .await
+            .unwrap();
+
+        h2.await.unwrap();
+        drop(client);
+    };
+
+    join(srv, client).await;
+}
+
+#[tokio::test]
+async fn record_vectored_writes() {
+    use std::io::Cursor;
+    use std::pin::Pin;
+
+    let recorder = Recorder::new();
+    let mut io = recorder.wrap(mock_io::Builder::new().write(b"hello world").build());
+
+    let mut buf = Cursor::new(&b"hello "[..]).chain(Cursor::new(&b"world"[..]));
+    let n = poll_fn(|cx| Pin::new(&mut io).poll_write_buf(cx, &mut buf))
+        .await
+        .unwrap();
+    assert_eq!(n, 11);
+
+    // Both buffers went in one write, recorded as one chunk
+    assert_eq!(io.get_ref().num_writes(), 1);
+
+    let transcript = recorder.transcript();
+    assert_eq!(transcript.records().len(), 1);
+    assert_eq!(&transcript.records()[0].data()[..], b"hello world");
+}
+
+#[test]
+fn read_invalid_transcript() {
+    let invalid: &[&[u8]] = &[
+        b"",
+        b"h2-transcript 2\n",
+        b"h2-transcript 1\n= 0.000001 00\n",
+        b"h2-transcript 1\n> 1 00\n",
+        b"h2-transcript 1\n> 0.000001 0\n",
+        b"h2-transcript 1\n> 0.000001 zz\n",
+    ];
+
+    for src in invalid {
+        let err = Transcript::read_from(*src).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let transcript =
+        Transcript::read_from(&b"h2-transcript 1\n\n# note\n< 1.500000 0102\n"[..]).unwrap();
+    let record = &transcript.records()[0];
+    assert_eq!(record.direction(), Direction::Received);
+    assert_eq!(record.elapsed(), Duration::from_millis(1_500));
+    assert_eq!(&record.data()[..], &[1, 2]);
+}
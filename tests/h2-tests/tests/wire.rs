@@ -0,0 +1,277 @@
+#![deny(warnings)]
+
+use futures::future::join;
+use futures::{SinkExt, StreamExt};
+use h2::wire::{self, Frame, FrameError, RecvError};
+use h2_support::prelude::*;
+use tokio::io::AsyncWriteExt;
+
+fn id(id: u32) -> h2::StreamId {
+    h2::StreamId::from(id)
+}
+
+#[tokio::test]
+async fn wire_client_request() {
+    let _ = env_logger::try_init();
+    let (mut io, mut srv) = mock::new();
+
+    let srv = async move {
+        let settings = srv.assert_client_handshake().await;
+        assert_default_settings!(settings);
+        srv.recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .await;
+        srv.send_frame(frames::headers(1).response(200)).await;
+        srv.send_frame(frames::data(1, "hello").eos()).await;
+    };
+
+    let client = async move {
+        io.write_all(wire::PREFACE).await.unwrap();
+
+        let mut codec = wire::Codec::new(io);
+        codec.send(wire::Settings::new().into()).await.unwrap();
+
+        match codec.next().await.unwrap().unwrap() {
+            Frame::Settings(ref settings) if !settings.is_ack() => {
+                codec.apply_remote_settings(settings);
+                codec.send(wire::Settings::ack().into()).await.unwrap();
+            }
+            frame => panic!("unexpected frame; frame={:?}", frame),
+        }
+
+        match codec.next().await.unwrap().unwrap() {
+            Frame::Settings(ref settings) if settings.is_ack() => {}
+            frame => panic!("unexpected frame; frame={:?}", frame),
+        }
+
+        let pseudo = wire::Pseudo::request(Method::GET, "https://example.com/".parse().unwrap());
+        let mut headers = wire::Headers::new(id(1), pseudo, HeaderMap::new());
+        headers.set_end_stream();
+        codec.send(headers.into()).await.unwrap();
+
+        match codec.next().await.unwrap().unwrap() {
+            Frame::Headers(headers) => {
+                assert_eq!(headers.stream_id(), id(1));
+                assert_eq!(headers.pseudo().status(), Some(StatusCode::OK));
+                assert!(!headers.is_end_stream());
+            }
+            frame => panic!("unexpected frame; frame={:?}", frame),
+        }
+
+        match codec.next().await.unwrap().unwrap() {
+            Frame::Data(data) => {
+                assert_eq!(data.stream_id(), id(1));
+                assert_eq!(data.payload(), &b"hello"[..]);
+                assert!(data.is_end_stream());
+            }
+            frame => panic!("unexpected frame; frame={:?}", frame),
+        }
+    };
+
+    join(srv, client).await;
+}
+
+#[tokio::test]
+async fn wire_send_priority_and_padded_data() {
+    let io = mock_io::Builder::new()
+        // PRIORITY, stream 3 depends exclusively on stream 1
+        .write(&[0, 0, 5, 2, 0, 0, 0, 0, 3, 0x80, 0, 0, 1, 15])
+        // DATA, END_STREAM | PADDED
+        .write(&[0, 0, 5, 0, 9, 0, 0, 0, 1, 2, b'h', b'i', 0, 0])
+        .build();
+
+    let mut codec = wire::Codec::new(io);
+
+    let priority = wire::Priority::new(id(3), id(1), 15, true);
+    codec.send(priority.into()).await.unwrap();
+
+    let mut data = wire::Data::new(id(1), Bytes::from_static(b"hi"));
+    data.set_pad_len(Some(2));
+    data.set_end_stream(true);
+    codec.send(data.into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn wire_recv_priority() {
+    let io = mock_io::Builder::new()
+        .read(&[0, 0, 5, 2, 0, 0, 0, 0, 3, 0x80, 0, 0, 1, 15])
+        .build();
+
+    let mut codec = wire::Codec::new(io);
+
+    match codec.next().await.unwrap().unwrap() {
+        Frame::Priority(priority) => {
+            assert_eq!(priority.stream_id(), id(3));
+            assert_eq!(priority.dependency_id(), id(1));
+            assert_eq!(priority.weight(), 15);
+            assert!(priority.is_exclusive());
+        }
+        frame => panic!("unexpected frame; frame={:?}", frame),
+    }
+
+    assert!(codec.next().await.is_none());
+}
+
+#[tokio::test]
+async fn wire_recv_connection_error_cause() {
+    let io = mock_io::Builder::new()
+        // SETTINGS on stream 1
+        .read(&[0, 0, 0, 4, 0, 0, 0, 0, 1])
+        .build();
+
+    let mut codec = wire::Codec::new(io);
+
+    match codec.next().await.unwrap() {
+        Err(RecvError::Connection { reason, cause }) => {
+            assert_eq!(reason, Reason::PROTOCOL_ERROR);
+            assert_eq!(cause, FrameError::InvalidStreamId);
+        }
+        res => panic!("unexpected result; res={:?}", res),
+    }
+}
+
+#[tokio::test]
+async fn wire_recv_stream_error_cause() {
+    let io = mock_io::Builder::new()
+        // PRIORITY, stream 1 depends on itself
+        .read(&[0, 0, 5, 2, 0, 0, 0, 0, 1, 0, 0, 0, 1, 15])
+        // PING
+        .read(&[0, 0, 8, 6, 0, 0, 0, 0, 0])
+        .read(b"12345678")
+        .build();
+
+    let mut codec = wire::Codec::new(io);
+
+    match codec.next().await.unwrap() {
+        Err(RecvError::Stream {
+            id: stream,
+            reason,
+            cause,
+        }) => {
+            assert_eq!(stream, id(1));
+            assert_eq!(reason, Reason::PROTOCOL_ERROR);
+            assert_eq!(cause, FrameError::InvalidDependencyId);
+        }
+        res => panic!("unexpected result; res={:?}", res),
+    }
+
+    // Stream errors leave the codec usable
+    match codec.next().await.unwrap().unwrap() {
+        Frame::Ping(ping) => {
+            assert!(!ping.is_ack());
+            assert_eq!(&ping.payload(), b"12345678");
+        }
+        frame => panic!("unexpected frame; frame={:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn wire_recv_frame_too_large() {
+    let mut frame = vec![0, 0x40, 0x01, 0, 0, 0, 0, 0, 1];
+    frame.extend_from_slice(&[0; 0x4001]);
+
+    let io = mock_io::Builder::new().read(&frame).build();
+    let mut codec = wire::Codec::new(io);
+
+    let err = codec.next().await.unwrap().unwrap_err();
+    assert_eq!(err.reason(), Some(Reason::FRAME_SIZE_ERROR));
+    assert_eq!(err.cause(), Some(FrameError::TooLarge));
+}
+
+#[tokio::test]
+async fn wire_send_explicit_continuation() {
+    let io = mock_io::Builder::new()
+        // HEADERS, END_STREAM, empty header block
+        .write(&[0, 0, 0, 1, 1, 0, 0, 0, 1])
+        // CONTINUATION, `:method: GET`
+        .write(&[0, 0, 1, 9, 0, 0, 0, 0, 1, 0x82])
+        // CONTINUATION, END_HEADERS, `:scheme: https`
+        .write(&[0, 0, 1, 9, 4, 0, 0, 0, 1, 0x87])
+        .build();
+
+    let mut codec = wire::Codec::new(io);
+
+    let mut headers = wire::Headers::new(id(1), wire::Pseudo::new(), HeaderMap::new());
+    headers.set_end_stream();
+    headers.set_end_headers(false);
+    codec.send(headers.into()).await.unwrap();
+
+    let mut continuation = wire::Continuation::new(id(1), Bytes::from_static(&[0x82]));
+    continuation.set_end_headers(false);
+    codec.send(continuation.into()).await.unwrap();
+
+    let continuation = wire::Continuation::new(id(1), Bytes::from_static(&[0x87]));
+    assert!(continuation.is_end_headers());
+    codec.send(continuation.into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn wire_recv_continuation_merged() {
+    let io = mock_io::Builder::new()
+        .read(&[0, 0, 0, 1, 1, 0, 0, 0, 1])
+        .read(&[0, 0, 1, 9, 0, 0, 0, 0, 1, 0x82])
+        .read(&[0, 0, 1, 9, 4, 0, 0, 0, 1, 0x87])
+        .build();
+
+    let mut codec = wire::Codec::new(io);
+
+    match codec.next().await.unwrap().unwrap() {
+        Frame::Headers(headers) => {
+            assert_eq!(headers.stream_id(), id(1));
+            assert_eq!(headers.pseudo().method(), Some(&Method::GET));
+            assert_eq!(headers.pseudo().scheme(), Some("https"));
+            assert!(headers.is_end_stream());
+            assert!(headers.is_end_headers());
+        }
+        frame => panic!("unexpected frame; frame={:?}", frame),
+    }
+
+    assert!(codec.next().await.is_none());
+}
+
+#[tokio::test]
+async fn wire_send_and_recv_unknown_frame() {
+    let frame = [0, 0, 2, 0xff, 0x21, 0, 0, 0, 3, b'h', b'i'];
+
+    let io = mock_io::Builder::new().write(&frame).read(&frame).build();
+    let mut codec = wire::Codec::new(io);
+
+    let unknown = wire::Unknown::new(0xff, 0x21, id(3), Bytes::from_static(b"hi"));
+    codec.send(unknown.into()).await.unwrap();
+
+    match codec.next().await.unwrap().unwrap() {
+        Frame::Unknown(unknown) => {
+            assert_eq!(unknown.kind(), 0xff);
+            assert_eq!(unknown.flags(), 0x21);
+            assert_eq!(unknown.stream_id(), id(3));
+            assert_eq!(unknown.payload(), &b"hi"[..]);
+        }
+        frame => panic!("unexpected frame; frame={:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn wire_send_and_recv_go_away_debug_data() {
+    let frame = [
+        0, 0, 10, 7, 0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 1, b'h', b'i',
+    ];
+
+    let io = mock_io::Builder::new().write(&frame).read(&frame).build();
+    let mut codec = wire::Codec::new(io);
+
+    let go_away =
+        wire::GoAway::with_debug_data(id(5), Reason::PROTOCOL_ERROR, Bytes::from_static(b"hi"));
+    codec.send(go_away.into()).await.unwrap();
+
+    match codec.next().await.unwrap().unwrap() {
+        Frame::GoAway(go_away) => {
+            assert_eq!(go_away.last_stream_id(), id(5));
+            assert_eq!(go_away.reason(), Reason::PROTOCOL_ERROR);
+            assert_eq!(go_away.debug_data(), &b"hi"[..]);
+        }
+        frame => panic!("unexpected frame; frame={:?}", frame),
+    }
+}
@@ -1,15 +1,191 @@
+use bytes::BytesMut;
+use h2::hpack::{Encoder, Field, Huffman};
+use serde_json::{json, Value};
 use walkdir::WalkDir;
 
 use std::collections::HashMap;
 use std::env;
+use std::fs;
 use std::path::Path;
+use std::process;
+
+mod qifs;
+
+const USAGE: &str = "\
+usage: genfixture [PATH]
+       genfixture encode [PATH]
+       genfixture qifs [PATH]
+
+Prints the `fixture_mod!` listing of the HPACK stories found under PATH,
+usually fixtures/hpack, for src/hpack/test/fixture.rs.
+
+With `encode`, first encodes the stories of PATH/raw-data with our own
+encoder, writing them as the rust-h2 corpora of PATH. These must be
+regenerated whenever the encoder output changes.
+
+With `qifs`, encodes the QIF files of PATH/qifs, usually
+fixtures/qpack/qifs, with our own QPACK encoder instead, writing them to
+PATH/encoded/qpack-06/rust-h2.";
+
+/// A set of stories encoded with the same encoder settings.
+struct Corpus {
+    name: &'static str,
+    description: &'static str,
+    huffman: Huffman,
+    /// Table sizes cycled through, changing every `CHANGE_TABLE_SIZE_EVERY`
+    /// cases. The table keeps the default size if empty.
+    table_sizes: &'static [usize],
+}
+
+const CORPORA: &[Corpus] = &[
+    Corpus {
+        name: "rust-h2",
+        description: "Encoded by h2 in Rust with the default indexing policy. \
+                      Strings are never Huffman encoded.",
+        huffman: Huffman::Never,
+        table_sizes: &[],
+    },
+    Corpus {
+        name: "rust-h2-huffman",
+        description: "Encoded by h2 in Rust with the default indexing policy. \
+                      Strings are always Huffman encoded.",
+        huffman: Huffman::Always,
+        table_sizes: &[],
+    },
+    Corpus {
+        name: "rust-h2-change-table-size",
+        description: "Encoded by h2 in Rust with the default indexing policy. \
+                      Strings are Huffman encoded only when that makes them shorter. \
+                      The header table size changes every other case, \
+                      emptying the table when set to 0.",
+        huffman: Huffman::WhenShorter,
+        table_sizes: &[1024, 0, 16384, 256, 4096],
+    },
+];
+
+const DEFAULT_TABLE_SIZE: usize = 4096;
+
+const CHANGE_TABLE_SIZE_EVERY: usize = 2;
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let (cmd, path) = match &args[..] {
+        [path] => ("", path),
+        [cmd, path] if cmd == "encode" || cmd == "qifs" => (&cmd[..], path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
 
-    let path = args.get(1).expect("usage: genfixture [PATH]");
     let path = Path::new(path);
 
+    if cmd == "qifs" {
+        qifs::write(path);
+        return;
+    }
+
+    if cmd == "encode" {
+        for corpus in CORPORA {
+            corpus.write(path);
+        }
+    }
+
+    print_listing(path);
+}
+
+impl Corpus {
+    /// Encodes every story of `path/raw-data` into `path/<name>`.
+    fn write(&self, path: &Path) {
+        let dir = path.join(self.name);
+        fs::create_dir_all(&dir).unwrap();
+
+        for entry in fs::read_dir(path.join("raw-data")).unwrap() {
+            let entry = entry.unwrap();
+
+            if entry.path().extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+
+            let data = fs::read_to_string(entry.path()).unwrap();
+            let story: Value = serde_json::from_str(&data).unwrap();
+            let story = self.encode(&story);
+
+            let mut data = serde_json::to_string_pretty(&story).unwrap();
+            data.push('\n');
+            fs::write(dir.join(entry.file_name()), data).unwrap();
+        }
+    }
+
+    fn encode(&self, story: &Value) -> Value {
+        let mut encoder = Encoder::new();
+        encoder.set_huffman(self.huffman);
+
+        let cases: Vec<_> = story["cases"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(seqno, case)| {
+                let headers = &case["headers"];
+                let fields = headers.as_array().unwrap().iter().map(|header| {
+                    let (name, value) = header.as_object().unwrap().iter().next().unwrap();
+                    let value = value.as_str().unwrap();
+                    Field::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+                });
+
+                let size = self.header_table_size(seqno);
+
+                if let Some(size) = size {
+                    encoder.update_max_table_size(size);
+                }
+
+                let mut wire = BytesMut::new();
+                encoder.encode(fields, &mut wire);
+
+                let mut case = json!({
+                    "seqno": seqno,
+                    "wire": to_hex(&wire),
+                    "headers": headers,
+                });
+
+                if let Some(size) = size {
+                    case["header_table_size"] = size.into();
+                }
+
+                case
+            })
+            .collect();
+
+        json!({
+            "description": self.description,
+            "cases": cases,
+        })
+    }
+
+    /// The header table size acknowledged just before case `seqno`, if it
+    /// changes.
+    fn header_table_size(&self, seqno: usize) -> Option<usize> {
+        // The first case always records the size, as the format asks for.
+        if seqno == 0 {
+            return Some(DEFAULT_TABLE_SIZE);
+        }
+
+        if self.table_sizes.is_empty() || seqno % CHANGE_TABLE_SIZE_EVERY != 0 {
+            return None;
+        }
+
+        let change = seqno / CHANGE_TABLE_SIZE_EVERY - 1;
+        Some(self.table_sizes[change % self.table_sizes.len()])
+    }
+}
+
+fn to_hex(src: &[u8]) -> String {
+    src.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn print_listing(path: &Path) {
     let mut tests = HashMap::new();
 
     for entry in WalkDir::new(path) {
@@ -38,8 +214,8 @@ fn main() {
 
     let mut one = false;
 
-    for (module, tests) in tests {
-        let module = module.replace("-", "_");
+    for (corpus, tests) in tests {
+        let module = corpus.replace("-", "_");
 
         if one {
             println!("");
@@ -48,7 +224,12 @@ fn main() {
         one = true;
 
         println!("fixture_mod!(");
-        println!("    {} => {{", module);
+
+        // Our own stories are checked against the encoder output
+        match CORPORA.iter().find(|c| c.name == corpus) {
+            Some(c) => println!("    {} ours({:?}) => {{", module, c.huffman),
+            None => println!("    {} => {{", module),
+        }
 
         for test in tests {
             let ident = test.split("/").nth(1).unwrap().split(".").next().unwrap();
@@ -0,0 +1,126 @@
+//! Encodes QIF files in the format of the qifs `encoded` directories.
+
+use bytes::{BufMut, BytesMut};
+use h2::qpack::{Decoder, Encoder, Field};
+
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+/// Table capacity, blocked streams and whether sections are acknowledged
+/// as soon as they are encoded.
+const SETTINGS: &[(usize, usize, bool)] = &[
+    (0, 0, false),
+    (256, 100, false),
+    (4096, 0, false),
+    (4096, 100, true),
+];
+
+/// Encodes every `path/qifs/*.qif` file into `path/encoded/qpack-06/rust-h2`.
+pub fn write(path: &Path) {
+    let dir = path.join("encoded/qpack-06/rust-h2");
+    fs::create_dir_all(&dir).unwrap();
+
+    for entry in fs::read_dir(path.join("qifs")).unwrap() {
+        let entry = entry.unwrap();
+
+        if entry.path().extension().map_or(true, |ext| ext != "qif") {
+            continue;
+        }
+
+        let sections = load(&entry.path());
+        let name = entry
+            .path()
+            .file_stem()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        for &(capacity, blocked, ack) in SETTINGS {
+            let data = encode(&sections, capacity, blocked, ack);
+            let file = format!("{}.out.{}.{}.{}", name, capacity, blocked, ack as u8);
+
+            fs::write(dir.join(file), data).unwrap();
+        }
+    }
+}
+
+/// One `name\tvalue` field per line, with sections separated by empty lines.
+fn load(path: &Path) -> Vec<Vec<Field>> {
+    let data = fs::read_to_string(path).unwrap();
+
+    let mut sections = vec![];
+    let mut fields = vec![];
+
+    for line in data.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if line.is_empty() {
+            if !fields.is_empty() {
+                sections.push(mem::replace(&mut fields, vec![]));
+            }
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '\t');
+        let name = parts.next().unwrap();
+        let value = parts.next().unwrap_or("");
+
+        fields.push(Field::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+    }
+
+    if !fields.is_empty() {
+        sections.push(fields);
+    }
+
+    sections
+}
+
+/// Section N is sent on stream N, and stream 0 carries the encoder stream
+/// instructions each section depends on, written before it.
+fn encode(sections: &[Vec<Field>], capacity: usize, blocked: usize, ack: bool) -> Vec<u8> {
+    let mut encoder = Encoder::with_settings(capacity, blocked);
+    let mut decoder = Decoder::with_settings(capacity, blocked);
+    let mut out = vec![];
+
+    if capacity > 0 {
+        assert!(encoder.set_table_capacity(capacity));
+    }
+
+    for (i, fields) in sections.iter().enumerate() {
+        let stream_id = i as u64 + 1;
+
+        let mut section = BytesMut::new();
+        encoder.encode(stream_id, fields.clone(), &mut section);
+
+        let instructions = encoder.take_instructions();
+
+        if !instructions.is_empty() {
+            write_block(0, &instructions, &mut out);
+        }
+        write_block(stream_id, &section, &mut out);
+
+        if ack {
+            decoder.on_encoder_stream(&instructions).unwrap();
+            decoder.decode(stream_id, &section).unwrap().unwrap();
+            encoder
+                .on_decoder_stream(&decoder.take_instructions())
+                .unwrap();
+        }
+    }
+
+    out
+}
+
+/// A 64 bit stream ID, a 32 bit length and the data.
+fn write_block(stream_id: u64, data: &[u8], dst: &mut Vec<u8>) {
+    let mut head = BytesMut::with_capacity(12);
+    head.put_u64_be(stream_id);
+    head.put_u32_be(data.len() as u32);
+
+    dst.extend_from_slice(&head);
+    dst.extend_from_slice(data);
+}
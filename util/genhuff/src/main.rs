@@ -142,48 +142,122 @@ impl Node {
         }
     }
 
-    fn print(&self) {
-        const MAYBE_EOS: u8 = 1;
-        const DECODED: u8 = 2;
-        const ERROR: u8 = 4;
-
+    /// Collects the 4 bit transitions of every state, indexed by state id.
+    fn collect_transitions(&self, states: &mut Vec<Vec<Transition>>) {
         if self.terminal.is_some() {
             return;
         }
 
-        println!("    // {}", self.id.unwrap());
+        assert_eq!(states.len(), self.id.unwrap());
+        states.push(self.transitions.borrow().clone());
+
+        self.left.as_ref().unwrap().collect_transitions(states);
+        self.right.as_ref().unwrap().collect_transitions(states);
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct Step {
+    target: usize,
+    bytes: [Option<usize>; 2],
+    maybe_eos: bool,
+    error: bool,
+}
+
+/// Combines two 4 bit transitions, so that a whole octet of input is
+/// consumed at once.
+///
+/// Every Huffman code is at least 5 bits long, so a single octet decodes at
+/// most two bytes.
+fn compute_steps(states: &[Vec<Transition>]) -> Vec<Vec<Step>> {
+    let mut steps = vec![];
+
+    for state in 0..states.len() {
+        let mut row = vec![];
+
+        for input in 0..256 {
+            let mut step = Step {
+                target: state,
+                ..Step::default()
+            };
+            let mut decoded = 0;
+
+            for &nibble in &[input >> 4, input & 0xf] {
+                let transition = states[step.target][nibble];
+
+                match transition.target {
+                    Some(target) => step.target = target,
+                    None => {
+                        step = Step {
+                            error: true,
+                            ..Step::default()
+                        };
+                        break;
+                    }
+                }
+
+                if let Some(byte) = transition.byte {
+                    step.bytes[decoded] = Some(byte);
+                    decoded += 1;
+                }
+
+                step.maybe_eos = transition.maybe_eos;
+            }
+
+            row.push(step);
+        }
+
+        steps.push(row);
+    }
+
+    steps
+}
+
+fn print_steps(steps: &[Vec<Step>]) {
+    const MAYBE_EOS: u8 = 1;
+    const DECODED: u8 = 2;
+    const ERROR: u8 = 4;
+    const DECODED_TWO: u8 = 8;
+
+    for (id, row) in steps.iter().enumerate() {
+        println!("    // {}", id);
         println!("    [");
 
-        for transition in self.transitions.borrow().iter() {
-            let mut flags = 0;
-            let mut out = 0;
+        for chunk in row.chunks(8) {
+            let mut line = String::from("       ");
 
-            let target = match transition.target {
-                Some(target) => target,
-                None => {
+            for step in chunk {
+                let mut flags = 0;
+                let mut out = [0; 2];
+
+                if step.error {
                     flags |= ERROR;
-                    0
                 }
-            };
 
-            if let Some(byte) = transition.byte {
-                out = byte;
-                flags |= DECODED;
+                if let Some(byte) = step.bytes[0] {
+                    out[0] = byte;
+                    flags |= DECODED;
+                }
+
+                if let Some(byte) = step.bytes[1] {
+                    out[1] = byte;
+                    flags |= DECODED_TWO;
+                }
 
-                // TODO: Add other flags
-            }
+                if step.maybe_eos {
+                    flags |= MAYBE_EOS;
+                }
 
-            if transition.maybe_eos {
-                flags |= MAYBE_EOS;
+                line.push_str(&format!(
+                    " ({}, {}, {}, 0x{:02x}),",
+                    step.target, out[0], out[1], flags
+                ));
             }
 
-            println!("        ({}, {}, 0x{:02x}),", target, out, flags);
+            println!("{}", line);
         }
 
         println!("    ],");
-
-        self.left.as_ref().unwrap().print();
-        self.right.as_ref().unwrap().print();
     }
 }
 
@@ -237,21 +311,33 @@ fn load_table() -> (Vec<(usize, String)>, Box<Node>) {
 pub fn main() {
     let (encode, decode) = load_table();
 
+    let mut states = vec![];
+    decode.collect_transitions(&mut states);
+
     println!("// !!! DO NOT EDIT !!! Generated by util/genhuff/src/main.rs");
     println!("");
 
     println!("// (num-bits, bits)");
     println!("pub const ENCODE_TABLE: [(usize, u64); 257] = [");
     for (nbits, val) in encode {
-        println!("    ({}, 0x{}),", nbits, val);
+        if val.len() > 5 {
+            let val = format!("{:0>8}", val);
+            println!("    ({}, 0x{}_{}),", nbits, &val[..4], &val[4..]);
+        } else {
+            println!("    ({}, 0x{}),", nbits, val);
+        }
     }
     println!("];");
 
     println!("");
-    println!("// (next-state, byte, flags)");
-    println!("pub const DECODE_TABLE: [[(usize, u8, u8); 16]; 256] = [");
-
-    decode.print();
+    println!("// (next-state, byte, second byte, flags)");
+    println!("#[rustfmt::skip]");
+    println!(
+        "pub const DECODE_TABLE: [[(u8, u8, u8, u8); 256]; {}] = [",
+        states.len()
+    );
+
+    print_steps(&compute_steps(&states));
 
     println!("];");
 }
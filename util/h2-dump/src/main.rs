@@ -0,0 +1,719 @@
+use bytes::Bytes;
+use futures::task::noop_waker_ref;
+use futures::Stream;
+use h2::frame::{self, Frame, Setting};
+use h2::transcript::{Direction, Transcript};
+use h2::{Codec, FrameEvent, FrameKind, FrameObserver, Reason, RecvError};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{cmp, env, fs, mem, process};
+
+const USAGE: &str = "\
+usage: h2-dump [PATH]
+
+Prints the frames of an HTTP/2.0 byte stream read from PATH, or from stdin.
+
+The input is either the bytes sent by one endpoint, with or without the
+client preface, or a transcript saved with `h2::transcript`, which holds
+both directions of a connection.
+
+DATA frames are followed by the flow control window left to their sender,
+WINDOW_UPDATE frames by the window they grant to the peer. Windows start at
+the default size when the peer's SETTINGS frames are not in the input.
+
+Exits with status 1 when the input does not start with a preface, or when a
+frame cannot be decoded.";
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DEFAULT_WINDOW_SIZE: i64 = 65_535;
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let input = match args.len() {
+        0 => {
+            let mut input = vec![];
+            io::stdin().read_to_end(&mut input).map(|_| input)
+        }
+        1 if !args[0].starts_with('-') => fs::read(&args[0]),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let input = input.unwrap_or_else(|e| fail(e));
+
+    match dump(&input) {
+        Ok(true) => {}
+        // The errors have been printed along with the frames
+        Ok(false) => process::exit(1),
+        Err(e) => fail(e),
+    }
+}
+
+fn fail(err: io::Error) -> ! {
+    eprintln!("h2-dump: {}", err);
+    process::exit(1);
+}
+
+/// Prints the frames of `input`, and returns whether they were all decoded
+/// without error.
+fn dump(input: &[u8]) -> io::Result<bool> {
+    if input.starts_with(b"h2-transcript") {
+        let transcript = Transcript::read_from(input)?;
+        let mut dump = Dump::new("send", "recv");
+
+        for record in transcript.records() {
+            let side = match record.direction() {
+                Direction::Sent => 0,
+                Direction::Received => 1,
+            };
+
+            dump.push(side, Some(record.elapsed()), record.data());
+        }
+
+        dump.finish(0);
+        dump.finish(1);
+
+        Ok(!dump.failed)
+    } else {
+        let mut dump = Dump::new("recv", "send");
+        dump.push(0, None, input);
+        dump.finish(0);
+
+        Ok(!dump.failed)
+    }
+}
+
+/// Decodes and prints both directions of a connection
+struct Dump {
+    sides: [Side; 2],
+
+    /// Flow control windows available to the sender of each side
+    windows: [Windows; 2],
+
+    /// Prefix of the lines printed for the current frame
+    prefix: String,
+
+    /// Length of the last frame printed
+    last_len: usize,
+
+    /// Set once an error has been printed
+    failed: bool,
+}
+
+/// One direction of a connection
+struct Side {
+    label: &'static str,
+    codec: Codec<Input, Cursor<Bytes>>,
+    events: Events,
+
+    /// Start of the input, until it is known whether it is the client preface
+    preface: Option<Vec<u8>>,
+
+    /// Set once the first frame, which must be SETTINGS, is decoded
+    settings: bool,
+
+    /// Set once the codec stops yielding frames
+    done: bool,
+}
+
+/// How the input of a side starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Preface {
+    /// With the client preface
+    Client,
+    /// With something close to the client preface, but not quite
+    Invalid,
+    /// Without the client preface, a server only sends a SETTINGS frame
+    None,
+}
+
+/// The bytes to decode, read by the codec
+#[derive(Debug, Default)]
+struct Input {
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+/// Collects the frames seen by a codec
+#[derive(Debug, Clone, Default)]
+struct Events(Arc<Mutex<Vec<Event>>>);
+
+#[derive(Debug)]
+struct Event {
+    kind: FrameKind,
+    flags: u8,
+    stream_id: u32,
+    len: usize,
+    fields: Option<Vec<(String, Vec<u8>)>>,
+}
+
+#[derive(Debug)]
+struct Windows {
+    connection: i64,
+    initial: i64,
+    streams: HashMap<u32, i64>,
+}
+
+// ===== impl Dump =====
+
+impl Dump {
+    fn new(first: &'static str, second: &'static str) -> Dump {
+        Dump {
+            sides: [Side::new(first), Side::new(second)],
+            windows: [Windows::new(), Windows::new()],
+            prefix: String::new(),
+            last_len: 0,
+            failed: false,
+        }
+    }
+
+    /// Decodes and prints the frames completed by `data`.
+    fn push(&mut self, side: usize, elapsed: Option<Duration>, data: &[u8]) {
+        self.prefix = match elapsed {
+            Some(elapsed) => format!("[{:3}.{:03}] ", elapsed.as_secs(), elapsed.subsec_millis()),
+            None => String::new(),
+        };
+
+        let (preface, data) = self.sides[side].check_preface(data);
+
+        match preface {
+            Preface::Client => {
+                println!("{}{} client preface", self.prefix, self.sides[side].label);
+            }
+            Preface::Invalid => {
+                self.preface_error(side, format_args!("invalid client preface"));
+                return;
+            }
+            Preface::None => {}
+        }
+
+        self.sides[side].input().buf.extend_from_slice(&data);
+        self.decode(side);
+    }
+
+    /// Decodes and prints the rest of the input of `side`.
+    fn finish(&mut self, side: usize) {
+        if let Some(data) = self.sides[side].preface.take() {
+            if data.starts_with(&PREFACE[..3]) {
+                self.preface_error(side, format_args!("invalid client preface, truncated"));
+                return;
+            }
+
+            self.sides[side].input().buf.extend_from_slice(&data);
+        }
+
+        self.sides[side].input().eof = true;
+        self.decode(side);
+    }
+
+    fn decode(&mut self, side: usize) {
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        while !self.sides[side].done {
+            let res = Pin::new(&mut self.sides[side].codec).poll_next(&mut cx);
+            let events = self.sides[side].events.take();
+
+            for event in &events {
+                self.print_event(side, event);
+            }
+
+            if !self.sides[side].settings {
+                match res {
+                    Poll::Ready(Some(Ok(Frame::Settings(ref frame)))) if !frame.is_ack() => {
+                        self.sides[side].settings = true;
+                    }
+                    // Ignored, like the endpoints do
+                    Poll::Ready(Some(Ok(Frame::Unknown(_)))) => {}
+                    Poll::Ready(Some(Err(RecvError::Io(_)))) if events.is_empty() => {
+                        self.preface_error(side, format_args!("missing preface, truncated input"));
+                        return;
+                    }
+                    Poll::Ready(Some(_)) if events.is_empty() => {
+                        self.preface_error(side, format_args!("missing preface, invalid frame"));
+                        return;
+                    }
+                    Poll::Ready(Some(_)) => {
+                        self.note(format_args!(
+                            "missing preface, the first frame is not SETTINGS"
+                        ));
+                        self.fail(side);
+                        return;
+                    }
+                    Poll::Ready(None) => {
+                        self.preface_error(side, format_args!("missing preface, empty input"));
+                        return;
+                    }
+                    Poll::Pending => {}
+                }
+            }
+
+            match res {
+                Poll::Ready(Some(Ok(frame))) => self.print_frame(side, frame),
+                Poll::Ready(Some(Err(RecvError::Stream { id, reason }))) => {
+                    let id = u32::from(id);
+                    self.note(format_args!(
+                        "stream error: stream_id={}, {}",
+                        id,
+                        error(reason)
+                    ));
+                    self.failed = true;
+                }
+                Poll::Ready(Some(Err(RecvError::Connection(reason)))) => {
+                    self.note(format_args!("connection error: {}", error(reason)));
+                    self.fail(side);
+                }
+                Poll::Ready(Some(Err(RecvError::Io(_)))) => {
+                    // The input never fails, the codec only errors when it
+                    // ends in the middle of a frame.
+                    self.note(format_args!("truncated frame at the end of the input"));
+                    self.fail(side);
+                }
+                Poll::Ready(None) => {
+                    self.sides[side].done = true;
+                }
+                Poll::Pending => return,
+            }
+        }
+    }
+
+    /// Prints an error about the start of the input of `side`, and stops
+    /// decoding it.
+    fn preface_error(&mut self, side: usize, args: std::fmt::Arguments) {
+        println!("{}{} {}", self.prefix, self.sides[side].label, args);
+        self.fail(side);
+    }
+
+    fn fail(&mut self, side: usize) {
+        self.sides[side].done = true;
+        self.failed = true;
+    }
+
+    fn print_event(&mut self, side: usize, event: &Event) {
+        println!(
+            "{}{} {} frame <length={}, flags=0x{:02x}, stream_id={}>",
+            self.prefix,
+            self.sides[side].label,
+            kind_name(event.kind),
+            event.len,
+            event.flags,
+            event.stream_id,
+        );
+
+        let flags = flag_names(event.kind, event.flags);
+
+        if !flags.is_empty() {
+            self.note(format_args!("{}", flags.join(" | ")));
+        }
+
+        if let FrameKind::Unknown(_) = event.kind {
+            self.note(format_args!("unknown frame type, ignored"));
+        }
+
+        if let Some(ref fields) = event.fields {
+            for (name, value) in fields {
+                self.line(format_args!("{}: {}", name, String::from_utf8_lossy(value)));
+            }
+        }
+
+        self.last_len = event.len;
+    }
+
+    fn print_frame(&mut self, side: usize, frame: Frame) {
+        let peer = 1 - side;
+
+        match frame {
+            Frame::Data(frame) => {
+                let id = u32::from(frame.stream_id());
+                self.windows[side].consume(id, self.last_len as i64);
+                self.print_window(side, id);
+            }
+            Frame::Headers(frame) => {
+                if frame.is_over_size() {
+                    self.note(format_args!("header list over size, fields dropped"));
+                }
+            }
+            Frame::Priority(frame) => {
+                self.line(format_args!("({:?})", frame));
+            }
+            Frame::PushPromise(frame) => {
+                let id = u32::from(frame.promised_id());
+                self.line(format_args!("(promised_stream_id={})", id));
+            }
+            Frame::Settings(frame) => {
+                let mut settings = vec![];
+                frame.for_each(|setting| settings.push(setting));
+
+                if !frame.is_ack() {
+                    self.line(format_args!("(niv={})", settings.len()));
+                }
+
+                for setting in settings {
+                    let (name, id, value) = match setting {
+                        Setting::HeaderTableSize(v) => ("HEADER_TABLE_SIZE", 1, v),
+                        Setting::EnablePush(v) => ("ENABLE_PUSH", 2, v),
+                        Setting::MaxConcurrentStreams(v) => ("MAX_CONCURRENT_STREAMS", 3, v),
+                        Setting::InitialWindowSize(v) => {
+                            // Applies to the streams of the peer
+                            self.windows[peer].set_initial(v as i64);
+                            ("INITIAL_WINDOW_SIZE", 4, v)
+                        }
+                        Setting::MaxFrameSize(v) => ("MAX_FRAME_SIZE", 5, v),
+                        Setting::MaxHeaderListSize(v) => ("MAX_HEADER_LIST_SIZE", 6, v),
+                    };
+
+                    self.line(format_args!("[SETTINGS_{}(0x{:02x}):{}]", name, id, value));
+                }
+            }
+            Frame::Ping(frame) => {
+                let data: Vec<_> = frame
+                    .payload()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                self.line(format_args!("(opaque_data={})", data.concat()));
+            }
+            Frame::GoAway(frame) => {
+                self.line(format_args!(
+                    "(last_stream_id={}, error_code={})",
+                    u32::from(frame.last_stream_id()),
+                    error(frame.reason())
+                ));
+
+                if !frame.debug_data().is_empty() {
+                    let data = String::from_utf8_lossy(frame.debug_data());
+                    self.line(format_args!("(opaque_data={:?})", data));
+                }
+            }
+            Frame::WindowUpdate(frame) => {
+                let id = u32::from(frame.stream_id());
+                let increment = frame.size_increment();
+
+                self.line(format_args!("(window_size_increment={})", increment));
+
+                self.windows[peer].increase(id, increment as i64);
+                self.print_window(peer, id);
+            }
+            Frame::Reset(frame) => {
+                self.line(format_args!("(error_code={})", error(frame.reason())));
+            }
+            // Received CONTINUATION frames are merged into their header
+            // block, and unknown frames are noted with their event.
+            Frame::Continuation(_) | Frame::Unknown(_) => {}
+        }
+    }
+
+    fn print_window(&mut self, side: usize, id: u32) {
+        let connection = self.windows[side].connection;
+
+        if id == 0 {
+            self.note(format_args!("window: connection={}", connection));
+        } else {
+            let stream = *self.windows[side].stream(id);
+            self.note(format_args!(
+                "window: connection={}, stream={}",
+                connection, stream
+            ));
+        }
+    }
+
+    /// Prints an annotation of the current frame
+    fn note(&self, args: std::fmt::Arguments) {
+        self.line(format_args!("; {}", args));
+    }
+
+    /// Prints a detail of the current frame
+    fn line(&self, args: std::fmt::Arguments) {
+        println!(
+            "{:indent$}{}",
+            "",
+            args,
+            indent = cmp::max(self.prefix.len(), 4)
+        );
+    }
+}
+
+fn kind_name(kind: FrameKind) -> String {
+    let name = match kind {
+        FrameKind::Data => "DATA",
+        FrameKind::Headers => "HEADERS",
+        FrameKind::Priority => "PRIORITY",
+        FrameKind::Reset => "RST_STREAM",
+        FrameKind::Settings => "SETTINGS",
+        FrameKind::PushPromise => "PUSH_PROMISE",
+        FrameKind::Ping => "PING",
+        FrameKind::GoAway => "GOAWAY",
+        FrameKind::WindowUpdate => "WINDOW_UPDATE",
+        FrameKind::Continuation => "CONTINUATION",
+        FrameKind::Unknown(kind) => return format!("UNKNOWN(0x{:02x})", kind),
+    };
+
+    name.to_string()
+}
+
+fn flag_names(kind: FrameKind, flags: u8) -> Vec<&'static str> {
+    const END_STREAM: (u8, &str) = (0x1, "END_STREAM");
+    const ACK: (u8, &str) = (0x1, "ACK");
+    const END_HEADERS: (u8, &str) = (0x4, "END_HEADERS");
+    const PADDED: (u8, &str) = (0x8, "PADDED");
+    const PRIORITY: (u8, &str) = (0x20, "PRIORITY");
+
+    let known: &[(u8, &str)] = match kind {
+        FrameKind::Data => &[END_STREAM, PADDED],
+        FrameKind::Headers => &[END_STREAM, END_HEADERS, PADDED, PRIORITY],
+        FrameKind::Settings | FrameKind::Ping => &[ACK],
+        FrameKind::PushPromise => &[END_HEADERS, PADDED],
+        FrameKind::Continuation => &[END_HEADERS],
+        _ => &[],
+    };
+
+    known
+        .iter()
+        .filter(|&&(bit, _)| flags & bit == bit)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+fn error(reason: Reason) -> String {
+    format!("{:?}(0x{:02x})", reason, u32::from(reason))
+}
+
+// ===== impl Side =====
+
+impl Side {
+    fn new(label: &'static str) -> Side {
+        let events = Events::default();
+        let mut codec = Codec::new(Input::default());
+
+        // Be lenient, the settings of the peer may not be in the input
+        codec.set_max_recv_frame_size(frame::MAX_MAX_FRAME_SIZE as usize);
+        codec.set_recv_header_table_size(u32::max_value() as usize);
+        codec.set_frame_observer(Arc::new(events.clone()));
+
+        Side {
+            label,
+            codec,
+            events,
+            preface: Some(vec![]),
+            settings: false,
+            done: false,
+        }
+    }
+
+    fn input(&mut self) -> &mut Input {
+        self.codec.get_mut()
+    }
+
+    /// Returns whether the input starts with the client preface, and the
+    /// input to decode.
+    fn check_preface(&mut self, data: &[u8]) -> (Preface, Vec<u8>) {
+        let mut pending = match self.preface.take() {
+            Some(pending) => pending,
+            None => return (Preface::None, data.to_vec()),
+        };
+
+        pending.extend_from_slice(data);
+
+        if pending.len() < PREFACE.len() && PREFACE.starts_with(&pending) {
+            // Not enough bytes to tell yet
+            self.preface = Some(pending);
+            return (Preface::None, vec![]);
+        }
+
+        if pending.starts_with(PREFACE) {
+            (Preface::Client, pending.split_off(PREFACE.len()))
+        } else if pending.starts_with(&PREFACE[..3]) {
+            // Too long to be the length of a frame
+            (Preface::Invalid, vec![])
+        } else {
+            (Preface::None, pending)
+        }
+    }
+}
+
+// ===== impl Input =====
+
+impl AsyncRead for Input {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        dst: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+
+        if me.pos == me.buf.len() {
+            if me.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            // Polled again once more input is pushed
+            return Poll::Pending;
+        }
+
+        let n = cmp::min(dst.len(), me.buf.len() - me.pos);
+        dst[..n].copy_from_slice(&me.buf[me.pos..me.pos + n]);
+        me.pos += n;
+
+        if me.pos == me.buf.len() {
+            me.buf.clear();
+            me.pos = 0;
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for Input {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        src: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // Nothing is ever sent
+        Poll::Ready(Ok(src.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// ===== impl Events =====
+
+impl Events {
+    fn take(&self) -> Vec<Event> {
+        mem::replace(&mut *self.0.lock().unwrap(), vec![])
+    }
+}
+
+impl FrameObserver for Events {
+    fn frame_received(&self, event: &FrameEvent) {
+        let fields = event.header_fields().map(|fields| {
+            fields
+                .map(|(name, value)| (name.to_string(), value.to_vec()))
+                .collect()
+        });
+
+        self.0.lock().unwrap().push(Event {
+            kind: event.kind(),
+            flags: event.flags(),
+            stream_id: u32::from(event.stream_id()),
+            len: event.len(),
+            fields,
+        });
+    }
+}
+
+// ===== impl Windows =====
+
+impl Windows {
+    fn new() -> Windows {
+        Windows {
+            connection: DEFAULT_WINDOW_SIZE,
+            initial: DEFAULT_WINDOW_SIZE,
+            streams: HashMap::new(),
+        }
+    }
+
+    fn stream(&mut self, id: u32) -> &mut i64 {
+        let initial = self.initial;
+        self.streams.entry(id).or_insert(initial)
+    }
+
+    fn consume(&mut self, id: u32, len: i64) {
+        self.connection -= len;
+        *self.stream(id) -= len;
+    }
+
+    fn increase(&mut self, id: u32, increment: i64) {
+        if id == 0 {
+            self.connection += increment;
+        } else {
+            *self.stream(id) += increment;
+        }
+    }
+
+    /// Applies a new SETTINGS_INITIAL_WINDOW_SIZE to all streams
+    fn set_initial(&mut self, initial: i64) {
+        let delta = initial - self.initial;
+        self.initial = initial;
+
+        for window in self.streams.values_mut() {
+            *window += delta;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &[u8] = &[0, 0, 0, 4, 0, 0, 0, 0, 0];
+
+    fn with_preface(frames: &[u8]) -> Vec<u8> {
+        let mut input = PREFACE.to_vec();
+        input.extend_from_slice(frames);
+        input
+    }
+
+    #[test]
+    fn dump_transcript() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/h2-tests/transcripts/server_get.h2t"
+        );
+        let input = fs::read(path).unwrap();
+
+        assert!(dump(&input).unwrap());
+    }
+
+    #[test]
+    fn dump_with_or_without_client_preface() {
+        assert!(dump(SETTINGS).unwrap());
+        assert!(dump(&with_preface(SETTINGS)).unwrap());
+    }
+
+    #[test]
+    fn invalid_client_preface() {
+        assert!(!dump(b"PRI * HTTP/1.1\r\n\r\nSM\r\n\r\n").unwrap());
+        assert!(!dump(&PREFACE[..10]).unwrap());
+    }
+
+    #[test]
+    fn missing_preface() {
+        assert!(!dump(b"").unwrap());
+        assert!(!dump(b"not an HTTP/2.0 connection").unwrap());
+        assert!(!dump(&[0; 24]).unwrap());
+        assert!(!dump(&with_preface(&[
+            0, 0, 8, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+        ]))
+        .unwrap());
+    }
+
+    #[test]
+    fn decode_errors() {
+        // Truncated PING
+        let mut input = with_preface(SETTINGS);
+        input.extend_from_slice(&[0, 0, 8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(!dump(&input).unwrap());
+
+        // PING with a bad length
+        let mut input = with_preface(SETTINGS);
+        input.extend_from_slice(&[0, 0, 1, 6, 0, 0, 0, 0, 0, 0]);
+        assert!(!dump(&input).unwrap());
+    }
+}